@@ -15,7 +15,7 @@ set iskeyword=a-z,A-Z,-,*,_,!,@
 syntax keyword uklTodos TODO FIXME NOTE
 
 " Language keywords
-syntax keyword uklKeywords let func import
+syntax keyword uklKeywords let func import pub trait impl macro
 
 " Comments
 syntax region uklCommentLine start="//" end="$"          contains=uklTodos
@@ -30,6 +30,9 @@ syntax region uklChar start=/\v'/ skip=/\v\\./ end=/\v'/ contains=uklEscapes
 " Escape literals \n, \r, ....
 syntax match uklEscapes display contained "\\[nrt0\\\"']"
 
+" Attributes, like #[test] or #![allow(unknown_attributes)]
+syntax region uklAttribute start=/\v#!?\[/ end=/\v\]/ contains=uklString
+
 " Function definitions, matches the word 'func' followed by a word
 syntax region uklFuncDef start="func" end=/\v\w+/ contains=uklFuncName
 
@@ -53,6 +56,7 @@ highlight default link uklTypeNames Type
 highlight default link uklChar Character
 highlight default link uklEscapes SpecialChar
 highlight default link uklFuncDef Function
+highlight default link uklAttribute PreProc
 
 let b:current_syntax = "ukl"
 
@@ -0,0 +1,1120 @@
+//! Exporting tokens and syntax trees as JSON, for tools written in other
+//! languages.
+//!
+//! The output follows the schemas in the `schema` directory of this crate,
+//! which are described in `docs/json.md`. Every document has a `version`
+//! field, which is bumped whenever the shape of the output changes.
+
+use std::io::{self, Read, Write};
+
+use serde_json::{json, Value};
+
+use unknown_lang_parser::{
+    ast::{
+        Arm, Attribute, Block, Enum, Expr, ExprKind, Field, FieldInit,
+        FieldPat, Func, Ident, Impl, Item, ItemKind, LambdaParam, Let, Literal,
+        Macro, Module, Param, Pat, PatKind, Path, Stmt, StmtKind, Struct,
+        Trait, TraitMethod, TraitRef, Ty, TyKind, TypeParam, Variant,
+        VariantFields,
+    },
+    lexer::{
+        position::{Span, Spanned},
+        state::Lexer,
+        stream::{StreamError, StreamLexer},
+        tokens::Token,
+    },
+    parser::state::Parser,
+};
+
+/// The version of the JSON schemas that this outputs.
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// Lex the input from `reader` into a document with every token, including
+/// comments, and the error which stopped lexing if there was one. The
+/// document is written to `out` as the tokens are found, so that neither the
+/// input nor its tokens are ever all held in memory. Gives back whether
+/// lexing failed.
+pub fn write_tokens(
+    reader: impl Read,
+    mut out: impl Write,
+) -> io::Result<bool> {
+    let mut lexer = StreamLexer::new(reader);
+
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"version\": {SCHEMA_VERSION},")?;
+    write!(out, "  \"tokens\": [")?;
+
+    let mut errors = vec![];
+    for idx in 0.. {
+        let tok = match lexer.lex_next() {
+            Ok(tok) => tok,
+            Err(StreamError::Lexing(err)) => {
+                errors.push(error(&err.data, err.span));
+                write_end(&mut out, idx)?;
+                break;
+            }
+            Err(StreamError::Io(err)) => return Err(err),
+        };
+        write_element(&mut out, &token(&tok), idx)?;
+
+        if tok.data == Token::Eof {
+            write_end(&mut out, idx + 1)?;
+            break;
+        }
+    }
+
+    let failed = !errors.is_empty();
+    let errors = serde_json::to_string_pretty(&errors)?;
+    writeln!(out, ",\n  \"errors\": {}", errors.replace('\n', "\n  "))?;
+    writeln!(out, "}}")?;
+
+    Ok(failed)
+}
+
+/// Write `value` as the element at `idx` of an array in the document from
+/// [`write_tokens`], indented like the rest of it.
+fn write_element(
+    out: &mut impl Write,
+    value: &Value,
+    idx: usize,
+) -> io::Result<()> {
+    let separator = if idx > 0 { "," } else { "" };
+    let value = serde_json::to_string_pretty(value)?;
+    write!(out, "{separator}\n    {}", value.replace('\n', "\n    "))
+}
+
+/// Close an array in the document from [`write_tokens`] after `len` elements.
+fn write_end(out: &mut impl Write, len: usize) -> io::Result<()> {
+    match len {
+        0 => write!(out, "]"),
+        _ => write!(out, "\n  ]"),
+    }
+}
+
+/// Parse `source` into a document with its syntax tree, and every error
+/// that the parser recovered from.
+pub fn ast(source: &str) -> Value {
+    let mut parser = Parser::new(Lexer::new(source));
+    let module = parser.parse_module();
+
+    let errors: Vec<_> = parser
+        .errors
+        .iter()
+        .map(|err| error(&err.data, err.span))
+        .collect();
+
+    json!({
+        "version": SCHEMA_VERSION,
+        "ast": module_node(&module, source.len()),
+        "errors": errors,
+    })
+}
+
+/// Check if a document has any errors in it.
+pub fn has_errors(doc: &Value) -> bool {
+    doc["errors"]
+        .as_array()
+        .is_some_and(|errors| !errors.is_empty())
+}
+
+fn span(span: Span) -> Value {
+    json!({ "start": span.start.0, "end": span.end.0 })
+}
+
+fn error(err: &impl ToString, at: Span) -> Value {
+    json!({ "message": err.to_string(), "span": span(at) })
+}
+
+/// Create a node of the given kind, with `fields` added to it.
+fn node(kind: &str, at: Span, fields: Value) -> Value {
+    let mut node = json!({ "kind": kind, "span": span(at) });
+
+    if let (Value::Object(node), Value::Object(fields)) = (&mut node, fields) {
+        node.extend(fields);
+    }
+
+    node
+}
+
+fn token(tok: &Spanned<Token>) -> Value {
+    let fields = match &tok.data {
+        Token::Ident(name) | Token::Label(name) => json!({ "name": name }),
+        Token::Number(base, digits) => json!({
+            "base": base,
+            "digits": digits,
+            "value": number_value(*base, digits, false),
+        }),
+        Token::Str(value) => json!({ "value": value }),
+        Token::Char(chr) => json!({ "value": chr.to_string() }),
+        Token::Byte(byte) => json!({ "value": byte }),
+        Token::ByteStr(bytes) => json!({ "value": bytes }),
+        Token::Comment(block, text) => json!({ "block": block, "text": text }),
+        _ => json!({}),
+    };
+
+    node(&format!("{:?}", tok.data.kind()), tok.span, fields)
+}
+
+/// The value of a number in decimal, or `None` if it doesn't fit in 128
+/// bits.
+fn number_value(base: u8, digits: &str, negative: bool) -> Option<String> {
+    let magnitude = digits.chars().try_fold(0u128, |value, chr| {
+        let digit = chr.to_digit(base.into())?;
+        value.checked_mul(base.into())?.checked_add(digit.into())
+    })?;
+
+    Some(match negative {
+        true => format!("-{magnitude}"),
+        false => magnitude.to_string(),
+    })
+}
+
+fn module_node(module: &Module, len: usize) -> Value {
+    let items: Vec<_> = module.items.iter().map(item).collect();
+    node(
+        "Module",
+        Span::new(0, len),
+        json!({ "attrs": attributes(&module.attrs), "items": items }),
+    )
+}
+
+fn attributes(attrs: &[Attribute]) -> Vec<Value> {
+    attrs
+        .iter()
+        .map(|attr| {
+            node(
+                "Attribute",
+                attr.span,
+                json!({
+                    "inner": attr.data.inner,
+                    "name": ident(&attr.data.name),
+                    "args": tokens_of(&attr.data.args),
+                }),
+            )
+        })
+        .collect()
+}
+
+/// Add the outer attributes of an item or statement to its node.
+fn with_attrs(mut node: Value, attrs: &[Attribute]) -> Value {
+    node["attrs"] = attributes(attrs).into();
+    node
+}
+
+fn ident(name: &Ident) -> Value {
+    node("Ident", name.span, json!({ "name": name.data }))
+}
+
+fn path(path: &Path) -> Value {
+    path_node("Path", &path.data, path.span)
+}
+
+/// Create a node for a path, which is either a [`Path`] or the segments of
+/// a path used on its own.
+fn path_node(kind: &str, segments: &[Ident], at: Span) -> Value {
+    let segments: Vec<_> = segments.iter().map(ident).collect();
+    node(kind, at, json!({ "segments": segments }))
+}
+
+fn item(item: &Item) -> Value {
+    let node = match &item.data {
+        ItemKind::Func(func) => func_node(func, item.span),
+        ItemKind::Let(stmt) => let_node(stmt, item.span),
+        ItemKind::Struct(decl) => struct_node(decl, item.span),
+        ItemKind::Enum(decl) => enum_node(decl, item.span),
+        ItemKind::Trait(decl) => trait_node(decl, item.span),
+        ItemKind::Impl(imp) => impl_node(imp, item.span),
+        ItemKind::Macro(decl) => macro_node(decl, item.span),
+        ItemKind::Import(import) => {
+            node("Import", item.span, json!({ "path": path(&import.path) }))
+        }
+        ItemKind::Error => node("Error", item.span, json!({})),
+    };
+
+    with_attrs(node, &item.attrs)
+}
+
+fn func_node(func: &Func, at: Span) -> Value {
+    let params: Vec<_> = func.params.iter().map(param).collect();
+
+    node(
+        "Func",
+        at,
+        json!({
+            "public": func.public,
+            "name": ident(&func.name),
+            "generics": type_params(&func.generics),
+            "params": params,
+            "ret": func.ret.as_ref().map(ty),
+            "body": block(&func.body),
+        }),
+    )
+}
+
+fn type_params(params: &[TypeParam]) -> Vec<Value> {
+    params
+        .iter()
+        .map(|param| {
+            let bounds: Vec<_> =
+                param.data.bounds.iter().map(trait_ref).collect();
+            node(
+                "TypeParam",
+                param.span,
+                json!({ "name": ident(&param.data.name), "bounds": bounds }),
+            )
+        })
+        .collect()
+}
+
+fn param(param: &Param) -> Value {
+    node(
+        "Param",
+        param.span,
+        json!({
+            "name": ident(&param.data.name),
+            "ty": ty(&param.data.ty),
+        }),
+    )
+}
+
+fn struct_node(decl: &Struct, at: Span) -> Value {
+    let fields: Vec<_> = decl.fields.iter().map(field).collect();
+
+    node(
+        "Struct",
+        at,
+        json!({
+            "public": decl.public,
+            "name": ident(&decl.name),
+            "generics": type_params(&decl.generics),
+            "fields": fields,
+        }),
+    )
+}
+
+fn field(field: &Field) -> Value {
+    node(
+        "FieldDecl",
+        field.span,
+        json!({
+            "name": ident(&field.data.name),
+            "ty": ty(&field.data.ty),
+        }),
+    )
+}
+
+fn enum_node(decl: &Enum, at: Span) -> Value {
+    let variants: Vec<_> = decl.variants.iter().map(variant).collect();
+
+    node(
+        "Enum",
+        at,
+        json!({
+            "public": decl.public,
+            "name": ident(&decl.name),
+            "generics": type_params(&decl.generics),
+            "variants": variants,
+        }),
+    )
+}
+
+fn variant(variant: &Variant) -> Value {
+    let (shape, elems, fields) = match &variant.data.fields {
+        VariantFields::Unit => ("unit", vec![], vec![]),
+        VariantFields::Tuple(tys) => {
+            ("tuple", tys.iter().map(ty).collect(), vec![])
+        }
+        VariantFields::Named(fields) => {
+            ("named", vec![], fields.iter().map(field).collect())
+        }
+    };
+
+    node(
+        "Variant",
+        variant.span,
+        json!({
+            "name": ident(&variant.data.name),
+            "shape": shape,
+            "elems": elems,
+            "fields": fields,
+            "discriminant": variant.data.discriminant.as_ref().map(expr),
+        }),
+    )
+}
+
+fn trait_node(decl: &Trait, at: Span) -> Value {
+    let methods: Vec<_> = decl.methods.iter().map(trait_method).collect();
+
+    node(
+        "Trait",
+        at,
+        json!({
+            "public": decl.public,
+            "name": ident(&decl.name),
+            "generics": type_params(&decl.generics),
+            "methods": methods,
+        }),
+    )
+}
+
+fn trait_method(method: &TraitMethod) -> Value {
+    let params: Vec<_> = method.data.params.iter().map(param).collect();
+
+    node(
+        "TraitMethod",
+        method.span,
+        json!({
+            "name": ident(&method.data.name),
+            "generics": type_params(&method.data.generics),
+            "params": params,
+            "ret": method.data.ret.as_ref().map(ty),
+            "default": method.data.default.as_ref().map(block),
+        }),
+    )
+}
+
+fn macro_node(decl: &Macro, at: Span) -> Value {
+    let rules: Vec<_> = decl
+        .rules
+        .iter()
+        .map(|rule| {
+            node(
+                "MacroRule",
+                rule.span,
+                json!({
+                    "matcher": tokens_of(&rule.data.matcher),
+                    "body": tokens_of(&rule.data.body),
+                }),
+            )
+        })
+        .collect();
+
+    node(
+        "Macro",
+        at,
+        json!({ "name": ident(&decl.name), "rules": rules }),
+    )
+}
+
+fn tokens_of(tokens: &[Spanned<Token>]) -> Vec<Value> {
+    tokens.iter().map(token).collect()
+}
+
+fn impl_node(imp: &Impl, at: Span) -> Value {
+    let methods: Vec<_> = imp
+        .methods
+        .iter()
+        .map(|method| func_node(&method.data, method.span))
+        .collect();
+
+    node(
+        "Impl",
+        at,
+        json!({
+            "generics": type_params(&imp.generics),
+            "trait": trait_ref(&imp.trait_ref),
+            "self_ty": ty(&imp.self_ty),
+            "methods": methods,
+        }),
+    )
+}
+
+fn trait_ref(trait_ref: &TraitRef) -> Value {
+    let args: Vec<_> = trait_ref.data.args.iter().map(ty).collect();
+
+    node(
+        "TraitRef",
+        trait_ref.span,
+        json!({ "path": path(&trait_ref.data.path), "args": args }),
+    )
+}
+
+fn ty(ty: &Ty) -> Value {
+    match &ty.data {
+        TyKind::Named(name) => {
+            node("NamedType", ty.span, json!({ "name": name }))
+        }
+        TyKind::Path(segments) => path_node("PathType", segments, ty.span),
+        TyKind::Mut(inner) => {
+            node("MutType", ty.span, json!({ "inner": self::ty(inner) }))
+        }
+        TyKind::Generic { name, args } => {
+            let args: Vec<_> = args.iter().map(self::ty).collect();
+            node(
+                "GenericType",
+                ty.span,
+                json!({ "name": ident(name), "args": args }),
+            )
+        }
+        TyKind::Array { elem, len } => node(
+            "ArrayType",
+            ty.span,
+            json!({ "elem": self::ty(elem), "len": expr(len) }),
+        ),
+        TyKind::Slice(elem) => {
+            node("SliceType", ty.span, json!({ "elem": self::ty(elem) }))
+        }
+        TyKind::Tuple(elems) => {
+            let elems: Vec<_> = elems.iter().map(self::ty).collect();
+            node("TupleType", ty.span, json!({ "elems": elems }))
+        }
+        TyKind::Func { params, ret } => {
+            let params: Vec<_> = params.iter().map(self::ty).collect();
+            node(
+                "FuncType",
+                ty.span,
+                json!({ "params": params, "ret": self::ty(ret) }),
+            )
+        }
+        TyKind::Ref { mutable, ty: inner } => node(
+            "RefType",
+            ty.span,
+            json!({ "mutable": mutable, "inner": self::ty(inner) }),
+        ),
+        TyKind::Paren(inner) => {
+            node("ParenType", ty.span, json!({ "inner": self::ty(inner) }))
+        }
+        TyKind::Error => node("Error", ty.span, json!({})),
+    }
+}
+
+fn block(block: &Block) -> Value {
+    let stmts: Vec<_> = block.data.stmts.iter().map(stmt).collect();
+
+    node(
+        "Block",
+        block.span,
+        json!({
+            "attrs": attributes(&block.data.attrs),
+            "stmts": stmts,
+            "tail": block.data.tail.as_deref().map(expr),
+        }),
+    )
+}
+
+fn stmt(stmt: &Stmt) -> Value {
+    let node = match &stmt.data {
+        StmtKind::Let(let_stmt) => let_node(let_stmt, stmt.span),
+        StmtKind::Return(value) => node(
+            "Return",
+            stmt.span,
+            json!({ "value": value.as_ref().map(expr) }),
+        ),
+        StmtKind::Assign { target, op, value } => node(
+            "Assign",
+            stmt.span,
+            json!({
+                "op": op.as_str(),
+                "target": expr(target),
+                "value": expr(value),
+            }),
+        ),
+        StmtKind::Break { label, value } => node(
+            "Break",
+            stmt.span,
+            json!({
+                "label": label.as_ref().map(ident),
+                "value": value.as_ref().map(expr),
+            }),
+        ),
+        StmtKind::Continue { label } => node(
+            "Continue",
+            stmt.span,
+            json!({ "label": label.as_ref().map(ident) }),
+        ),
+        StmtKind::Expr(value) => {
+            node("ExprStmt", stmt.span, json!({ "expr": expr(value) }))
+        }
+        StmtKind::Error => node("Error", stmt.span, json!({})),
+    };
+
+    with_attrs(node, &stmt.attrs)
+}
+
+fn let_node(stmt: &Let, at: Span) -> Value {
+    node(
+        "Let",
+        at,
+        json!({
+            "name": ident(&stmt.name),
+            "ty": stmt.ty.as_ref().map(ty),
+            "value": expr(&stmt.value),
+        }),
+    )
+}
+
+fn expr(expr: &Expr) -> Value {
+    let at = expr.span;
+
+    match &expr.data {
+        ExprKind::Literal(literal) => literal_node(literal, at),
+        ExprKind::Ident(name) => node("Ident", at, json!({ "name": name })),
+        ExprKind::Path(segments) => path_node("Path", segments, at),
+        ExprKind::Instantiate { path, args } => {
+            let args: Vec<_> = args.iter().map(ty).collect();
+            node(
+                "Instantiate",
+                at,
+                json!({ "path": self::path(path), "args": args }),
+            )
+        }
+        ExprKind::MacroCall { name, args } => node(
+            "MacroCall",
+            at,
+            json!({ "name": ident(name), "args": tokens_of(args) }),
+        ),
+        ExprKind::Unary { op, operand } => node(
+            "Unary",
+            at,
+            json!({ "op": op.as_str(), "operand": self::expr(operand) }),
+        ),
+        ExprKind::Binary { op, lhs, rhs } => node(
+            "Binary",
+            at,
+            json!({
+                "op": op.as_str(),
+                "lhs": self::expr(lhs),
+                "rhs": self::expr(rhs),
+            }),
+        ),
+        ExprKind::Call { callee, args } => {
+            let args: Vec<_> = args.iter().map(self::expr).collect();
+            node(
+                "Call",
+                at,
+                json!({ "callee": self::expr(callee), "args": args }),
+            )
+        }
+        ExprKind::Field { base, field } => node(
+            "Field",
+            at,
+            json!({ "base": self::expr(base), "field": ident(field) }),
+        ),
+        ExprKind::StructLit {
+            path: lit_path,
+            fields,
+            base,
+        } => {
+            let fields: Vec<_> = fields.iter().map(field_init).collect();
+            node(
+                "StructLit",
+                at,
+                json!({
+                    "path": path(lit_path),
+                    "fields": fields,
+                    "base": base.as_deref().map(self::expr),
+                }),
+            )
+        }
+        ExprKind::Paren(inner) => {
+            node("Paren", at, json!({ "inner": self::expr(inner) }))
+        }
+        ExprKind::Block(inner) => block(inner),
+        ExprKind::If {
+            cond,
+            then,
+            else_branch,
+        } => node(
+            "If",
+            at,
+            json!({
+                "cond": self::expr(cond),
+                "then": block(then),
+                "else": else_branch.as_deref().map(self::expr),
+            }),
+        ),
+        ExprKind::While { label, cond, body } => node(
+            "While",
+            at,
+            json!({
+                "label": label.as_ref().map(ident),
+                "cond": self::expr(cond),
+                "body": block(body),
+            }),
+        ),
+        ExprKind::Loop { label, body } => node(
+            "Loop",
+            at,
+            json!({
+                "label": label.as_ref().map(ident),
+                "body": block(body),
+            }),
+        ),
+        ExprKind::For {
+            label,
+            binding,
+            iter,
+            body,
+        } => node(
+            "For",
+            at,
+            json!({
+                "label": label.as_ref().map(ident),
+                "binding": ident(binding),
+                "iter": self::expr(iter),
+                "body": block(body),
+            }),
+        ),
+        ExprKind::Range {
+            start,
+            end,
+            inclusive,
+        } => node(
+            "Range",
+            at,
+            json!({
+                "op": if *inclusive { "..=" } else { ".." },
+                "start": self::expr(start),
+                "end": self::expr(end),
+            }),
+        ),
+        ExprKind::Match { scrutinee, arms } => {
+            let arms: Vec<_> = arms.iter().map(arm).collect();
+            node(
+                "Match",
+                at,
+                json!({ "scrutinee": self::expr(scrutinee), "arms": arms }),
+            )
+        }
+        ExprKind::Lambda { params, ret, body } => {
+            let params: Vec<_> = params.iter().map(lambda_param).collect();
+            node(
+                "Lambda",
+                at,
+                json!({
+                    "params": params,
+                    "ret": ret.as_ref().map(ty),
+                    "body": self::expr(body),
+                }),
+            )
+        }
+        ExprKind::Error => node("Error", at, json!({})),
+    }
+}
+
+fn field_init(init: &FieldInit) -> Value {
+    node(
+        "FieldInit",
+        init.span,
+        json!({
+            "name": ident(&init.data.name),
+            "value": init.data.value.as_ref().map(expr),
+        }),
+    )
+}
+
+fn lambda_param(param: &LambdaParam) -> Value {
+    node(
+        "LambdaParam",
+        param.span,
+        json!({
+            "name": ident(&param.data.name),
+            "ty": param.data.ty.as_ref().map(ty),
+        }),
+    )
+}
+
+fn arm(arm: &Arm) -> Value {
+    node(
+        "MatchArm",
+        arm.span,
+        json!({
+            "pat": pat(&arm.data.pat),
+            "guard": arm.data.guard.as_ref().map(expr),
+            "body": expr(&arm.data.body),
+        }),
+    )
+}
+
+fn pat(pat: &Pat) -> Value {
+    match &pat.data {
+        PatKind::Wild => node("WildcardPat", pat.span, json!({})),
+        PatKind::Binding(name) => {
+            node("BindingPat", pat.span, json!({ "name": name }))
+        }
+        PatKind::Path(segments) => path_node("PathPat", segments, pat.span),
+        PatKind::TupleStruct { path, elems } => {
+            let elems: Vec<_> = elems.iter().map(self::pat).collect();
+            node(
+                "TupleStructPat",
+                pat.span,
+                json!({ "path": self::path(path), "elems": elems }),
+            )
+        }
+        PatKind::Struct { path, fields, rest } => {
+            let fields: Vec<_> = fields.iter().map(field_pat).collect();
+            node(
+                "StructPat",
+                pat.span,
+                json!({
+                    "path": self::path(path),
+                    "fields": fields,
+                    "rest": rest,
+                }),
+            )
+        }
+        PatKind::Literal(literal) => literal_node(literal, pat.span),
+        PatKind::Range {
+            start,
+            end,
+            inclusive,
+        } => node(
+            "RangePat",
+            pat.span,
+            json!({
+                "op": if *inclusive { "..=" } else { ".." },
+                "start": self::pat(start),
+                "end": self::pat(end),
+            }),
+        ),
+        PatKind::Tuple(elems) => {
+            let elems: Vec<_> = elems.iter().map(self::pat).collect();
+            node("TuplePat", pat.span, json!({ "elems": elems }))
+        }
+        PatKind::Paren(inner) => {
+            node("ParenPat", pat.span, json!({ "inner": self::pat(inner) }))
+        }
+        PatKind::Or(alts) => {
+            let alts: Vec<_> = alts.iter().map(self::pat).collect();
+            node("OrPat", pat.span, json!({ "alts": alts }))
+        }
+        PatKind::At { name, pat: inner } => node(
+            "AtPat",
+            pat.span,
+            json!({ "name": ident(name), "pat": self::pat(inner) }),
+        ),
+        PatKind::Error => node("Error", pat.span, json!({})),
+    }
+}
+
+fn field_pat(field: &FieldPat) -> Value {
+    node(
+        "FieldPat",
+        field.span,
+        json!({
+            "name": ident(&field.data.name),
+            "pat": field.data.pat.as_ref().map(pat),
+        }),
+    )
+}
+
+fn literal_node(literal: &Literal, at: Span) -> Value {
+    match literal {
+        Literal::Number {
+            base,
+            digits,
+            negative,
+        } => node(
+            "Number",
+            at,
+            json!({
+                "base": base,
+                "digits": digits,
+                "negative": negative,
+                "value": number_value(*base, digits, *negative),
+            }),
+        ),
+        Literal::Str(value) => node("Str", at, json!({ "value": value })),
+        Literal::Char(chr) => {
+            node("Char", at, json!({ "value": chr.to_string() }))
+        }
+        Literal::Byte(byte) => node("Byte", at, json!({ "value": byte })),
+        Literal::ByteStr(bytes) => {
+            node("ByteStr", at, json!({ "value": bytes }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lex `source` into the document from [`write_tokens`].
+    fn tokens(source: &str) -> Value {
+        let mut out = vec![];
+        write_tokens(source.as_bytes(), &mut out).unwrap();
+
+        serde_json::from_slice(&out).unwrap()
+    }
+
+    #[test]
+    fn test_number_values() {
+        assert_eq!(number_value(16, "15", false).as_deref(), Some("21"));
+        assert_eq!(number_value(2, "101001", true).as_deref(), Some("-41"));
+        assert_eq!(
+            number_value(20, "2312Ji", false).as_deref(),
+            Some("6889198")
+        );
+        assert_eq!(number_value(16, &"f".repeat(33), false), None);
+    }
+
+    #[test]
+    fn test_tokens_document() {
+        let doc = tokens("let a := 0x15");
+
+        assert_eq!(doc["version"], SCHEMA_VERSION);
+        assert_eq!(doc["tokens"][0]["kind"], "Let");
+        assert_eq!(doc["tokens"][0]["name"], "let");
+        assert_eq!(
+            doc["tokens"][4],
+            json!({
+                "kind": "Number",
+                "span": { "start": 9, "end": 13 },
+                "base": 16,
+                "digits": "15",
+                "value": "21",
+            })
+        );
+        assert_eq!(doc["tokens"][5]["kind"], "Eof");
+        assert!(!has_errors(&doc));
+    }
+
+    #[test]
+    fn test_writes_tokens_as_they_are_found() {
+        let mut out = vec![];
+        assert!(!write_tokens("".as_bytes(), &mut out).unwrap());
+        let doc: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(doc["tokens"].as_array().unwrap().len(), 1);
+
+        let mut out = vec![];
+        assert!(write_tokens("\"".as_bytes(), &mut out).unwrap());
+        let doc: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(doc["tokens"], json!([]));
+        assert_eq!(doc["errors"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_lexing_error_is_reported() {
+        let doc = tokens("a \"unclosed");
+
+        assert_eq!(doc["tokens"].as_array().unwrap().len(), 1);
+        assert_eq!(doc["errors"][0]["span"], json!({ "start": 2, "end": 11 }));
+    }
+
+    #[test]
+    fn test_ast_document() {
+        let doc = ast("let a := f ** -0b11");
+        let value = &doc["ast"]["items"][0]["value"];
+
+        assert_eq!(value["kind"], "Binary");
+        assert_eq!(value["op"], "**");
+        assert_eq!(
+            value["lhs"],
+            json!({
+                "kind": "Ident",
+                "span": { "start": 9, "end": 10 },
+                "name": "f",
+            })
+        );
+        assert_eq!(value["rhs"]["kind"], "Number");
+        assert_eq!(value["rhs"]["value"], "-3");
+    }
+
+    #[test]
+    fn test_type_nodes() {
+        let doc = ast("let a : [Option<u8>; 2] = b");
+        let ty = &doc["ast"]["items"][0]["ty"];
+
+        assert_eq!(ty["kind"], "ArrayType");
+        assert_eq!(ty["len"]["value"], "2");
+        assert_eq!(ty["elem"]["kind"], "GenericType");
+        assert_eq!(
+            ty["elem"]["name"],
+            json!({
+                "kind": "Ident",
+                "span": { "start": 9, "end": 15 },
+                "name": "Option",
+            })
+        );
+        assert_eq!(ty["elem"]["args"][0]["kind"], "NamedType");
+    }
+
+    #[test]
+    fn test_match_nodes() {
+        let doc =
+            ast("let a := match b { n @ -1..=0x10 if n > 0 => n, _ => 0 }");
+        let arms = &doc["ast"]["items"][0]["value"]["arms"];
+
+        let first = &arms[0];
+        assert_eq!(first["kind"], "MatchArm");
+        assert_eq!(first["pat"]["kind"], "AtPat");
+        assert_eq!(first["pat"]["pat"]["kind"], "RangePat");
+        assert_eq!(first["pat"]["pat"]["start"]["value"], "-1");
+        assert_eq!(first["pat"]["pat"]["end"]["value"], "16");
+        assert_eq!(first["guard"]["kind"], "Binary");
+
+        assert_eq!(arms[1]["pat"]["kind"], "WildcardPat");
+        assert_eq!(arms[1]["guard"], Value::Null);
+    }
+
+    #[test]
+    fn test_struct_nodes() {
+        let doc = ast("struct P { x: i32 }\nlet a := P { x: b.c, ..d }.x");
+        let items = &doc["ast"]["items"];
+
+        assert_eq!(items[0]["kind"], "Struct");
+        assert_eq!(items[0]["fields"][0]["kind"], "FieldDecl");
+        assert_eq!(items[0]["fields"][0]["ty"]["name"], "i32");
+
+        let access = &items[1]["value"];
+        assert_eq!(access["kind"], "Field");
+        assert_eq!(access["field"]["name"], "x");
+
+        let lit = &access["base"];
+        assert_eq!(lit["kind"], "StructLit");
+        assert_eq!(lit["path"]["segments"][0]["name"], "P");
+        assert_eq!(lit["fields"][0]["value"]["kind"], "Field");
+        assert_eq!(lit["base"]["name"], "d");
+    }
+
+    #[test]
+    fn test_enum_nodes() {
+        let doc = ast(concat!(
+            "enum Shape { Circle(i32), Rect { w: i32 }, Empty = 0b10 }\n",
+            "let a := match Shape::Circle(1) {\n",
+            "    Shape::Circle(r) => r,\n",
+            "    Shape::Rect { w, .. } => w,\n",
+            "    Shape::Empty => 0,\n",
+            "}",
+        ));
+        let items = &doc["ast"]["items"];
+
+        let variants = &items[0]["variants"];
+        assert_eq!(variants[0]["shape"], "tuple");
+        assert_eq!(variants[0]["elems"][0]["name"], "i32");
+        assert_eq!(variants[1]["fields"][0]["kind"], "FieldDecl");
+        assert_eq!(variants[2]["shape"], "unit");
+        assert_eq!(variants[2]["discriminant"]["value"], "2");
+
+        let value = &items[1]["value"];
+        let callee = &value["scrutinee"]["callee"];
+        assert_eq!(callee["kind"], "Path");
+        assert_eq!(callee["segments"][1]["name"], "Circle");
+
+        let arms = &value["arms"];
+        assert_eq!(arms[0]["pat"]["kind"], "TupleStructPat");
+        assert_eq!(arms[1]["pat"]["kind"], "StructPat");
+        assert_eq!(arms[1]["pat"]["rest"], true);
+        assert_eq!(arms[1]["pat"]["fields"][0]["pat"], Value::Null);
+        assert_eq!(arms[2]["pat"]["kind"], "PathPat");
+    }
+
+    #[test]
+    fn test_module_nodes() {
+        let doc = ast(concat!(
+            "import shapes::circle\n",
+            "pub func f(c: circle::Circle) := {}\n",
+            "struct P {}",
+        ));
+        let items = &doc["ast"]["items"];
+
+        assert_eq!(items[0]["kind"], "Import");
+        assert_eq!(items[0]["path"]["kind"], "Path");
+        assert_eq!(items[0]["path"]["segments"][1]["name"], "circle");
+
+        assert_eq!(items[1]["public"], true);
+        let ty = &items[1]["params"][0]["ty"];
+        assert_eq!(ty["kind"], "PathType");
+        assert_eq!(ty["segments"][0]["name"], "circle");
+
+        assert_eq!(items[2]["public"], false);
+    }
+
+    #[test]
+    fn test_lambda_nodes() {
+        let doc = ast("let f := |a, b: u8| -> u8 { a }");
+        let lambda = &doc["ast"]["items"][0]["value"];
+
+        assert_eq!(lambda["kind"], "Lambda");
+        assert_eq!(lambda["params"][0]["kind"], "LambdaParam");
+        assert_eq!(lambda["params"][0]["ty"], Value::Null);
+        assert_eq!(lambda["params"][1]["ty"]["name"], "u8");
+        assert_eq!(lambda["ret"]["name"], "u8");
+        assert_eq!(lambda["body"]["kind"], "Block");
+    }
+
+    #[test]
+    fn test_generic_nodes() {
+        let doc = ast("func id<T>(x: T) : T = { x }\nlet f := id::<u8>");
+        let items = &doc["ast"]["items"];
+
+        let generics = &items[0]["generics"];
+        assert_eq!(generics[0]["kind"], "TypeParam");
+        assert_eq!(generics[0]["name"]["name"], "T");
+
+        let value = &items[1]["value"];
+        assert_eq!(value["kind"], "Instantiate");
+        assert_eq!(value["path"]["segments"][0]["name"], "id");
+        assert_eq!(value["args"][0]["name"], "u8");
+    }
+
+    #[test]
+    fn test_trait_nodes() {
+        let doc = ast(concat!(
+            "pub trait Show { func show(self) : &str }\n",
+            "impl<T: Show> Show for Pair<T> {\n",
+            "    func show(self) : &str = { \"pair\" }\n",
+            "}",
+        ));
+        let items = &doc["ast"]["items"];
+
+        let decl = &items[0];
+        assert_eq!(decl["kind"], "Trait");
+        assert_eq!(decl["public"], true);
+        assert_eq!(decl["methods"][0]["kind"], "TraitMethod");
+        assert_eq!(decl["methods"][0]["params"][0]["ty"]["name"], "Self");
+        assert_eq!(decl["methods"][0]["default"], Value::Null);
+
+        let imp = &items[1];
+        assert_eq!(imp["kind"], "Impl");
+        let bound = &imp["generics"][0]["bounds"][0];
+        assert_eq!(bound["kind"], "TraitRef");
+        assert_eq!(bound["path"]["segments"][0]["name"], "Show");
+        assert_eq!(imp["trait"]["args"], json!([]));
+        assert_eq!(imp["self_ty"]["kind"], "GenericType");
+        assert_eq!(imp["methods"][0]["kind"], "Func");
+    }
+
+    #[test]
+    fn test_macro_nodes() {
+        let doc = ast("macro id { ($a:expr) => { $a } }\nlet a := id!(1)");
+        let items = &doc["ast"]["items"];
+
+        let decl = &items[0];
+        assert_eq!(decl["kind"], "Macro");
+        assert_eq!(decl["name"]["name"], "id");
+        let rule = &decl["rules"][0];
+        assert_eq!(rule["kind"], "MacroRule");
+        assert_eq!(rule["matcher"].as_array().unwrap().len(), 4);
+        assert_eq!(rule["body"][0]["kind"], "Dollar");
+
+        // Calls are left as they are, rather than expanded
+        let call = &items[1]["value"];
+        assert_eq!(call["kind"], "MacroCall");
+        assert_eq!(call["name"]["name"], "id");
+        assert_eq!(call["args"][0]["kind"], "Number");
+    }
+
+    #[test]
+    fn test_attribute_nodes() {
+        let doc = ast(
+            "#![allow(unused)]\n#[inline(always)] func f() := {\n    #[a] let b := 1\n}",
+        );
+        let module = &doc["ast"];
+
+        let inner = &module["attrs"][0];
+        assert_eq!(inner["kind"], "Attribute");
+        assert_eq!(inner["inner"], true);
+        assert_eq!(inner["name"]["name"], "allow");
+        assert_eq!(inner["args"].as_array().unwrap().len(), 3);
+
+        let func = &module["items"][0];
+        assert_eq!(func["attrs"][0]["name"]["name"], "inline");
+        assert_eq!(func["attrs"][0]["inner"], false);
+        assert_eq!(func["attrs"][0]["args"][1]["kind"], "Ident");
+
+        let body = &func["body"];
+        assert_eq!(body["attrs"], serde_json::json!([]));
+        assert_eq!(body["stmts"][0]["attrs"][0]["name"]["name"], "a");
+    }
+}
@@ -0,0 +1,39 @@
+//! Reading the modules of a program from disk.
+
+use std::{convert::Infallible, fs, io, path::PathBuf};
+
+use anyhow::Context;
+use unknown_lang_parser::modules::Loader;
+
+/// Reads modules from the files next to the entry file.
+pub struct FileLoader {
+    /// The directory that the entry file is in
+    pub root: PathBuf,
+}
+
+impl Loader for FileLoader {
+    type Error = anyhow::Error;
+
+    fn read(&mut self, path: &str) -> anyhow::Result<Option<String>> {
+        let file = self.root.join(path);
+
+        match fs::read_to_string(&file) {
+            Ok(source) => Ok(Some(source)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err)
+                .with_context(|| format!("could not read {}", file.display())),
+        }
+    }
+}
+
+/// Reads no modules at all, for a file which isn't next to any others, like
+/// input from stdin.
+pub struct NoFiles;
+
+impl Loader for NoFiles {
+    type Error = Infallible;
+
+    fn read(&mut self, _path: &str) -> Result<Option<String>, Infallible> {
+        Ok(None)
+    }
+}
@@ -1,19 +1,75 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use colored::*;
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
-use unknown_lang_parser::lexer::{state::Lexer, tokens::Token};
-use unknown_lang_parser::state;
+mod json;
+mod loader;
+
+use unknown_lang_parser::{
+    ast::print,
+    attributes,
+    eval::{self, Value},
+    generics,
+    lexer::{
+        self,
+        position::{Span, Spanned},
+        stream::{StreamError, StreamLexer},
+    },
+    modules, resolve, traits,
+};
+
+use crate::loader::{FileLoader, NoFiles};
+
+/// What to output with --emit, instead of compiling the file.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    /// Every token in the file, as JSON
+    TokensJson,
+    /// The syntax tree of the file, as JSON
+    AstJson,
+}
+
+/// How the syntax tree is printed with --print-ast.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum AstFormat {
+    /// An indented tree, with the span of every node
+    Tree,
+    /// A compact S-expression, without spans
+    Sexpr,
+}
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// File to compile. If no file is provided, enter unknown-lang REPL.
+    /// File to compile, along with every module it imports, or `-` to read
+    /// from stdin. If no file is provided, enter unknown-lang REPL.
     #[arg(short, long, value_name = "FILE.ukl")]
     file: Option<PathBuf>,
     /// Toggle to print the tokens of the file. Needs --file to be passed.
     #[arg(short, long, default_value_t = false)]
     print_tokens: bool,
+    /// Print the syntax tree of the file, either as an indented tree or as an
+    /// S-expression. Needs --file to be passed.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        default_missing_value = "tree"
+    )]
+    print_ast: Option<AstFormat>,
+    /// Output the tokens or syntax tree of the file as JSON, instead of
+    /// compiling it. Needs --file to be passed.
+    #[arg(long, value_name = "FORMAT")]
+    emit: Option<Emit>,
+    /// Run the `main` function of the file once it has compiled, and print
+    /// what it returns.
+    #[arg(short, long, default_value_t = false)]
+    run: bool,
     /// Toggle to print diagnostics of compilation, like timings.
     #[arg(short, long, default_value_t = false)]
     diagnostics: bool,
@@ -37,8 +93,46 @@ fn main() -> anyhow::Result<()> {
 
     // We can unwrap since we check for the None case before we get here
     let compile_path = args.file.unwrap();
-    let file_contents = fs::read_to_string(&compile_path)?;
+    let from_stdin = compile_path.as_os_str() == "-";
+
+    // The parser needs the whole file, so it is read in once and everything
+    // after works on that. When only the tokens are needed, they are lexed
+    // as the input is read instead, so that generated code piped in never
+    // has to be held in memory all at once.
+    let read_source = || match from_stdin {
+        true => io::read_to_string(io::stdin()),
+        false => fs::read_to_string(&compile_path),
+    };
+
+    // Only the JSON is written to stdout, so that it can be piped into other
+    // tools
+    if let Some(emit) = args.emit {
+        let failed = match emit {
+            Emit::TokensJson => {
+                let input: Box<dyn Read> = match from_stdin {
+                    true => Box::new(io::stdin().lock()),
+                    false => Box::new(fs::File::open(&compile_path)?),
+                };
+                let mut out = io::BufWriter::new(io::stdout().lock());
+                let failed = json::write_tokens(input, &mut out)?;
+                out.flush()?;
+
+                failed
+            }
+            Emit::AstJson => {
+                let doc = json::ast(&read_source()?);
+                println!("{}", serde_json::to_string_pretty(&doc)?);
 
+                json::has_errors(&doc)
+            }
+        };
+
+        if failed {
+            anyhow::bail!("could not parse {}", compile_path.display());
+        }
+
+        return Ok(());
+    }
     println!(
         "{} {} {}",
         ">".blue().bold(),
@@ -46,16 +140,274 @@ fn main() -> anyhow::Result<()> {
         compile_path.display().to_string().green().bold()
     );
 
-    let lexer = Lexer::new(&file_contents);
-    let mut parser = state::Parser::new(lexer);
+    // Input from stdin is only lexed when nothing but its tokens is printed
+    if from_stdin && args.print_tokens && args.print_ast.is_none() {
+        let lex_start = Instant::now();
+        for tok in StreamLexer::new(io::stdin().lock()) {
+            match tok {
+                Ok(tok) => println!("{}", tok.data),
+                Err(StreamError::Lexing(err)) => {
+                    print_error(&err.data, "<stdin>", err.span);
+                    anyhow::bail!("could not lex {}", compile_path.display());
+                }
+                Err(StreamError::Io(err)) => return Err(err.into()),
+            }
+        }
+
+        if args.diagnostics {
+            println!(
+                "{} {} {:?}",
+                ">".blue().bold(),
+                "Lexing took".white(),
+                lex_start.elapsed()
+            );
+        }
+
+        return Ok(());
+    }
+
+    let source = read_source()?;
+
+    // Imports are found next to the entry file. Input from stdin isn't next
+    // to any files, so it can't import anything.
+    let (root, entry) = if from_stdin {
+        (None, String::from("<stdin>"))
+    } else {
+        let root = compile_path.parent().unwrap_or(Path::new("."));
+        let entry = compile_path.file_name().unwrap_or_default();
+        (
+            Some(root.to_path_buf()),
+            entry.to_string_lossy().into_owned(),
+        )
+    };
+
+    let lex_start = Instant::now();
+    let tokens = match lexer::lex_all(&source) {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            print_error(&err.data, &entry, err.span);
+            anyhow::bail!("could not lex {}", compile_path.display());
+        }
+    };
+
+    if args.print_tokens {
+        for tok in &tokens {
+            println!("{}", tok.data);
+        }
+    }
+
+    if args.diagnostics {
+        println!(
+            "{} {} {:?}",
+            ">".blue().bold(),
+            "Lexing took".white(),
+            lex_start.elapsed()
+        );
+    }
+
+    let load_start = Instant::now();
+    let graph = match root {
+        Some(root) => {
+            let mut loader = FileLoader { root };
+            modules::load_lexed(&entry, source, tokens, &mut loader)?
+        }
+        None => modules::load_lexed(&entry, source, tokens, &mut NoFiles)?,
+    };
+
+    if args.diagnostics {
+        let loading =
+            format!("Loading {} took", plural(graph.modules.len(), "module"));
+        println!(
+            "{} {} {:?}",
+            ">".blue().bold(),
+            loading.white(),
+            load_start.elapsed()
+        );
+    }
+
+    if let Some(format) = args.print_ast {
+        for module in &graph.modules {
+            // The entry file is printed on its own when it imports
+            // nothing, so that its output can be piped elsewhere
+            if graph.modules.len() > 1 {
+                println!(
+                    "{} {} {}",
+                    ">".blue().bold(),
+                    "Module:".white(),
+                    module.path.green().bold()
+                );
+            }
+
+            match format {
+                AstFormat::Tree => print!("{}", print::tree(&module.ast)),
+                AstFormat::Sexpr => {
+                    println!("{}", print::sexpr(&module.ast))
+                }
+            }
+        }
+    }
+
+    for module in &graph.modules {
+        for err in &module.parse_errors {
+            print_error(&err.data, &module.path, err.span);
+            print_trace(err.data.trace(), &module.path);
+        }
+        for err in &module.errors {
+            print_error(&err.data, &module.path, err.span);
+        }
+    }
+
+    if graph.has_errors() {
+        anyhow::bail!("could not load {}", compile_path.display());
+    }
+
+    // Unknown attributes only warn, so they don't stop compilation
+    let mut attributes_failed = false;
+    for module in &graph.modules {
+        let checked = attributes::check(&module.ast);
+        for warning in &checked.warnings {
+            print_warning(&warning.data, &module.path, warning.span);
+        }
+        for err in &checked.errors {
+            print_error(&err.data, &module.path, err.span);
+        }
+        attributes_failed |= !checked.errors.is_empty();
+    }
+
+    if attributes_failed {
+        anyhow::bail!(
+            "could not check the attributes of {}",
+            compile_path.display()
+        );
+    }
+
+    // Paths are only resolved once every module has loaded, since a
+    // broken declaration or import would make every path into it look
+    // unknown
+    let resolution = resolve::resolve_graph(&graph);
+    for (module, resolved) in graph.modules.iter().zip(&resolution.modules) {
+        for err in &resolved.errors {
+            print_error(&err.data, &module.path, err.span);
+        }
+    }
+
+    if resolution.has_errors() {
+        anyhow::bail!("could not resolve {}", compile_path.display());
+    }
+
+    let mut traits_failed = false;
+    for module in &graph.modules {
+        let errors = traits::check(&module.ast);
+        for err in &errors {
+            print_error(&err.data, &module.path, err.span);
+        }
+        traits_failed |= !errors.is_empty();
+    }
 
-    parser.iter_thru_tokens();
+    if traits_failed {
+        anyhow::bail!(
+            "could not check the traits of {}",
+            compile_path.display()
+        );
+    }
+
+    // Each module's generics are instantiated on their own, as calls into
+    // other modules aren't followed yet
+    let generics_start = Instant::now();
+    let mut instances = 0;
+    let mut generics_failed = false;
+    let mut monos = vec![];
+    for module in &graph.modules {
+        let mono = generics::monomorphize(&module.ast);
+        for err in &mono.errors {
+            print_error(&err.data, &module.path, err.span);
+        }
+
+        instances += mono.funcs.len() + mono.types.len();
+        generics_failed |= !mono.errors.is_empty();
+        monos.push(mono);
+    }
+
+    if args.diagnostics {
+        let instantiating =
+            format!("Finding {} took", plural(instances, "instance"));
+        println!(
+            "{} {} {:?}",
+            ">".blue().bold(),
+            instantiating.white(),
+            generics_start.elapsed()
+        );
+    }
 
+    if generics_failed {
+        anyhow::bail!(
+            "could not infer the generics of {}",
+            compile_path.display()
+        );
+    }
     println!(
         "{} {}",
         ">".blue().bold(),
         "Compilation successful!".green(),
     );
 
+    // Only the entry file is run, as calls into other modules aren't
+    // followed yet
+    if args.run {
+        let entry = graph.entry();
+        match eval::run(&entry.ast, &monos[0], "main") {
+            Ok(Value::Unit) => {}
+            Ok(value) => println!("{value}"),
+            Err(err) => {
+                print_error(&err.data, &entry.path, err.span);
+                anyhow::bail!("could not run {}", compile_path.display());
+            }
+        }
+    }
+
     Ok(())
 }
+
+fn print_error(err: &impl std::fmt::Display, path: &str, span: Span) {
+    println!(
+        "{} {} at {}:{}..{}",
+        "error:".red().bold(),
+        err,
+        path,
+        span.start.0,
+        span.end.0
+    );
+}
+
+fn print_warning(warning: &impl std::fmt::Display, path: &str, span: Span) {
+    println!(
+        "{} {} at {}:{}..{}",
+        "warning:".yellow().bold(),
+        warning,
+        path,
+        span.start.0,
+        span.end.0
+    );
+}
+
+/// Print the macros that an error was expanded from, innermost first.
+fn print_trace(trace: &[Spanned<String>], path: &str) {
+    for frame in trace {
+        println!(
+            "  {} `{}!`, declared at {}:{}..{}",
+            "in the expansion of".blue(),
+            frame.data,
+            path,
+            frame.span.start.0,
+            frame.span.end.0
+        );
+    }
+}
+
+/// Write out `count` of `noun`, like `1 module` or `2 modules`.
+fn plural(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("{count} {noun}"),
+        _ => format!("{count} {noun}s"),
+    }
+}
@@ -0,0 +1,195 @@
+#[path = "../../unknown_lang_parser/tests/common/mod.rs"]
+mod common;
+
+use serde_json::Value;
+use std::{fs, path::Path, process::Command};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check `value` against `schema`, which is a part of `root`. This only
+    /// supports the keywords used by the schemas in `schema/`.
+    fn validate(
+        root: &Value,
+        schema: &Value,
+        value: &Value,
+        path: &str,
+    ) -> Result<(), String> {
+        if let Some(target) = schema["$ref"].as_str() {
+            let name = target.trim_start_matches("#/$defs/");
+            return validate(root, &root["$defs"][name], value, path);
+        }
+
+        if let Some(options) = schema["oneOf"].as_array() {
+            let matches = options
+                .iter()
+                .filter(|option| validate(root, option, value, path).is_ok())
+                .count();
+
+            return match matches {
+                1 => Ok(()),
+                _ => Err(format!("{path} matched {matches} options: {value}")),
+            };
+        }
+
+        if let Some(expected) = schema.get("const") {
+            if value != expected {
+                return Err(format!("{path} should be {expected}"));
+            }
+        }
+
+        if let Some(options) = schema["enum"].as_array() {
+            if !options.contains(value) {
+                return Err(format!("{path} is unknown: {value}"));
+            }
+        }
+
+        if let Some(ty) = schema.get("type") {
+            let types: Vec<_> = match ty {
+                Value::Array(types) => types.iter().collect(),
+                ty => vec![ty],
+            };
+
+            if !types.iter().any(|ty| has_type(value, ty.as_str().unwrap())) {
+                return Err(format!("{path} should have type {ty}: {value}"));
+            }
+        }
+
+        if let Some(num) = value.as_u64() {
+            if schema["minimum"].as_u64().is_some_and(|min| num < min)
+                || schema["maximum"].as_u64().is_some_and(|max| num > max)
+            {
+                return Err(format!("{path} is out of range: {num}"));
+            }
+        }
+
+        if let Value::Object(object) = value {
+            for field in schema["required"].as_array().into_iter().flatten() {
+                let field = field.as_str().unwrap();
+
+                if !object.contains_key(field) {
+                    return Err(format!("{path} has no {field}"));
+                }
+            }
+
+            for (field, inner) in object {
+                let field_schema = &schema["properties"][field];
+
+                if field_schema.is_null() {
+                    if schema["additionalProperties"] == Value::Bool(false) {
+                        return Err(format!(
+                            "{path} has unknown field {field}"
+                        ));
+                    }
+                    continue;
+                }
+
+                let path = format!("{path}.{field}");
+                validate(root, field_schema, inner, &path)?;
+            }
+        }
+
+        if let Value::Array(items) = value {
+            if !schema["items"].is_null() {
+                for (i, item) in items.iter().enumerate() {
+                    let path = format!("{path}[{i}]");
+                    validate(root, &schema["items"], item, &path)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn has_type(value: &Value, ty: &str) -> bool {
+        match ty {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "integer" => value.is_u64() || value.is_i64(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            ty => panic!("unsupported type {ty}"),
+        }
+    }
+
+    /// Check that every span is within the source, and doesn't end before it
+    /// starts.
+    fn check_spans(value: &Value, len: u64) {
+        match value {
+            Value::Object(object) => {
+                if let Some(span) = object.get("span") {
+                    let start = span["start"].as_u64().unwrap();
+                    let end = span["end"].as_u64().unwrap();
+                    assert!(start <= end && end <= len, "bad span {span}");
+                }
+
+                object.values().for_each(|inner| check_spans(inner, len));
+            }
+            Value::Array(items) => {
+                items.iter().for_each(|inner| check_spans(inner, len))
+            }
+            _ => {}
+        }
+    }
+
+    fn run_emit(file: &Path, emit: &str) -> Value {
+        let output = Command::new(env!("CARGO_BIN_EXE_unknown_lang_cli"))
+            .arg("--file")
+            .arg(file)
+            .arg(format!("--emit={emit}"))
+            .output()
+            .unwrap();
+
+        serde_json::from_slice(&output.stdout).unwrap()
+    }
+
+    fn read_json(path: &Path) -> Value {
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_examples_match_schemas() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+        for (emit, schema) in [
+            ("tokens-json", "tokens.schema.json"),
+            ("ast-json", "ast.schema.json"),
+        ] {
+            let schema = read_json(&root.join("schema").join(schema));
+
+            for file in common::example_files() {
+                let doc = run_emit(&file, emit);
+                let name = file.file_name().unwrap().to_string_lossy();
+
+                let path = format!("{name}:{emit}");
+                if let Err(err) = validate(&schema, &schema, &doc, &path) {
+                    panic!("{err}");
+                }
+                check_spans(&doc, fs::metadata(&file).unwrap().len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_type_syntax_matches_schema() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let schema = read_json(&root.join("schema/ast.schema.json"));
+
+        let source = "func f(a: &mut [u8; 4], b: [Option<(u8,)>]) : \
+            (&&u8, ()) -> (void) = {}\n";
+        let file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("types.ukl");
+        fs::write(&file, source).unwrap();
+
+        let doc = run_emit(&file, "ast-json");
+        if let Err(err) = validate(&schema, &schema, &doc, "types.ukl") {
+            panic!("{err}");
+        }
+        check_spans(&doc, source.len() as u64);
+
+        let ret = &doc["ast"]["items"][0]["ret"];
+        assert_eq!(ret["kind"], "FuncType");
+        assert_eq!(ret["ret"]["kind"], "ParenType");
+    }
+}
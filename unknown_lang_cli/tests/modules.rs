@@ -0,0 +1,285 @@
+use std::{
+    env, fs,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compile the program starting at `file`, giving back whether it
+    /// succeeded and what it printed.
+    fn compile(file: &Path) -> (bool, String) {
+        let output = Command::new(env!("CARGO_BIN_EXE_unknown_lang_cli"))
+            .arg("--file")
+            .arg(file)
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap();
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        (output.status.success(), stdout)
+    }
+
+    /// Compile `source` piped in from stdin, giving back whether it
+    /// succeeded and what it printed.
+    fn compile_stdin(source: &str) -> (bool, String) {
+        run_stdin(&[], source)
+    }
+
+    /// Run with `args` on `source` piped in from stdin, giving back whether
+    /// it succeeded and what it printed.
+    fn run_stdin(args: &[&str], source: &str) -> (bool, String) {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_unknown_lang_cli"))
+            .args(["--file", "-"])
+            .args(args)
+            .env("NO_COLOR", "1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(source.as_bytes())
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        (output.status.success(), stdout)
+    }
+
+    #[test]
+    fn test_compiles_every_module() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let (success, stdout) =
+            compile(&root.join("../examples/modules/main.ukl"));
+
+        assert!(success, "{stdout}");
+    }
+
+    #[test]
+    fn test_checks_input_from_stdin() {
+        let (success, stdout) = compile_stdin("func main() := {\n    1\n}\n");
+        assert!(success, "{stdout}");
+
+        let (success, stdout) = compile_stdin("func main( := {");
+        assert!(!success);
+        assert!(
+            stdout.contains("error: expected one of `)`, identifier found `:`"),
+            "{stdout}"
+        );
+
+        // There are no files next to stdin to import
+        let (success, stdout) = compile_stdin("import shapes\n");
+        assert!(!success);
+        assert!(stdout.contains("no module named `shapes`"), "{stdout}");
+    }
+
+    #[test]
+    fn test_only_lexes_stdin_for_tokens() {
+        // The tokens are printed without parsing them
+        let (success, stdout) = run_stdin(&["-p"], "func main( := {");
+        assert!(success, "{stdout}");
+        assert!(stdout.ends_with("(\n:\n=\n{\n"), "{stdout}");
+
+        let (success, stdout) = run_stdin(&["-p"], "let a := \"a");
+        assert!(!success);
+        assert!(
+            stdout.contains("error: unclosed string at <stdin>:9..11"),
+            "{stdout}"
+        );
+
+        let source = "let a := 1 // one\nlet b := \"b";
+        let file = env::temp_dir()
+            .join(format!("ukl-stdin-tokens-{}.ukl", std::process::id()));
+        fs::write(&file, source).unwrap();
+        let output = Command::new(env!("CARGO_BIN_EXE_unknown_lang_cli"))
+            .arg("--file")
+            .arg(&file)
+            .args(["--emit", "tokens-json"])
+            .output()
+            .unwrap();
+        fs::remove_file(&file).unwrap();
+
+        let (_, stdout) = run_stdin(&["--emit", "tokens-json"], source);
+        assert_eq!(stdout, String::from_utf8(output.stdout).unwrap());
+    }
+
+    #[test]
+    fn test_reports_import_cycles() {
+        let dir = env::temp_dir()
+            .join(format!("ukl-import-cycle-{}", std::process::id()));
+        fs::create_dir_all(dir.join("shapes")).unwrap();
+        fs::write(dir.join("main.ukl"), "import shapes\n").unwrap();
+        fs::write(dir.join("shapes/mod.ukl"), "import circle\n").unwrap();
+        fs::write(dir.join("shapes/circle.ukl"), "import mod\n").unwrap();
+
+        let (success, stdout) = compile(&dir.join("main.ukl"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!success);
+        assert_eq!(
+            stdout.lines().nth(1),
+            Some(
+                "error: modules import each other in a cycle: \
+                 `shapes/mod.ukl` imports `shapes/circle.ukl`, which imports \
+                 `shapes/mod.ukl` at shapes/circle.ukl:7..10"
+            )
+        );
+    }
+
+    #[test]
+    fn test_reports_uninferred_generics() {
+        let dir = env::temp_dir()
+            .join(format!("ukl-generics-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("main.ukl"),
+            "enum Option<T> { Some(T), None }\n\
+             func none<T>() : Option<T> = { Option::None }\n\
+             let a := none()\n",
+        )
+        .unwrap();
+
+        let (success, stdout) = compile(&dir.join("main.ukl"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!success);
+        assert_eq!(
+            stdout.lines().nth(1),
+            Some(
+                "error: can't infer the type of `T` for `none`, give it with \
+                 `none::<..>` or the type of a variable at main.ukl:88..92"
+            )
+        );
+    }
+
+    #[test]
+    fn test_reports_impls_of_other_modules_traits() {
+        let dir =
+            env::temp_dir().join(format!("ukl-traits-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("shapes.ukl"),
+            "pub trait Draw { func draw(self) }\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("main.ukl"),
+            "import shapes\nimpl shapes::Draw for u8 { func draw(self) := {} }\n",
+        )
+        .unwrap();
+
+        let (success, stdout) = compile(&dir.join("main.ukl"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!success);
+        assert_eq!(
+            stdout.lines().nth(1),
+            Some(
+                "error: `shapes::Draw` is from another module, so it can only \
+                 be implemented here for a type declared here, not `u8` at \
+                 main.ukl:19..38"
+            )
+        );
+    }
+
+    #[test]
+    fn test_reports_errors_inside_of_macros() {
+        let dir =
+            env::temp_dir().join(format!("ukl-macros-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("main.ukl"),
+            "macro inner { () => { (1 +) } }\n\
+             macro outer { () => { inner!() } }\n\
+             let a := outer!()\n",
+        )
+        .unwrap();
+
+        let (success, stdout) = compile(&dir.join("main.ukl"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!success);
+        assert_eq!(
+            stdout.lines().skip(1).take(3).collect::<Vec<_>>(),
+            vec![
+                "error: expected an expression, found `)` at main.ukl:76..84",
+                "  in the expansion of `inner!`, declared at main.ukl:6..11",
+                "  in the expansion of `outer!`, declared at main.ukl:38..43",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_warns_about_unknown_attributes() {
+        let dir = env::temp_dir()
+            .join(format!("ukl-attributes-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("main.ukl"),
+            "#[foo] func f() := {}\n\
+             #[allow(unknown_attributes)] #[bar] func g() := {}\n",
+        )
+        .unwrap();
+
+        let (success, stdout) = compile(&dir.join("main.ukl"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(success, "{stdout}");
+        let warnings: Vec<_> = stdout
+            .lines()
+            .filter(|line| line.starts_with("warning"))
+            .collect();
+        assert_eq!(
+            warnings,
+            vec!["warning: unknown attribute `#[foo]` at main.ukl:0..6"]
+        );
+    }
+
+    #[test]
+    fn test_reports_misused_attributes() {
+        let dir = env::temp_dir()
+            .join(format!("ukl-bad-attributes-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("main.ukl"), "#[test] func f(a: Int) := {}\n")
+            .unwrap();
+
+        let (success, stdout) = compile(&dir.join("main.ukl"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!success);
+        assert!(
+            stdout.contains(
+                "error: `f` is a test, so it can't take parameters or type \
+                 parameters at main.ukl:13..14"
+            ),
+            "{stdout}"
+        );
+    }
+
+    #[test]
+    fn test_runs_main() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let source =
+            fs::read_to_string(root.join("../examples/traits.ukl")).unwrap();
+
+        // `main` returns what the default `describe` of `Pair<u8>` gives
+        let (success, stdout) = run_stdin(&["--run"], &source);
+        assert!(success, "{stdout}");
+        assert_eq!(stdout.lines().last(), Some("\"a byte\""));
+
+        let (success, stdout) =
+            run_stdin(&["--run"], "func main() := {\n    1 / 0\n}\n");
+        assert!(!success);
+        assert!(
+            stdout.contains("error: this divides by zero at <stdin>:21..26"),
+            "{stdout}"
+        );
+    }
+}
@@ -0,0 +1,64 @@
+//! Compares lexing a large input sequentially and in parallel.
+//!
+//! Run with `cargo bench -p unknown_lang_parser --bench parallel_lexing`.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::time::{Duration, Instant};
+
+use unknown_lang_parser::lexer::{lex_all, parallel::lex_parallel};
+
+/// Roughly how many bytes of source to lex.
+const INPUT_SIZE: usize = 16 * 1024 * 1024;
+const RUNS: u32 = 5;
+
+/// Build a large input from the examples, so that it has a realistic mix of
+/// tokens.
+fn generate_input() -> String {
+    let mut sources = String::new();
+
+    for path in common::example_files() {
+        sources.push_str(&std::fs::read_to_string(path).unwrap());
+        sources.push('\n');
+    }
+
+    sources.repeat(INPUT_SIZE / sources.len() + 1)
+}
+
+/// Time the fastest of a few runs of `lex`.
+fn time<T>(lex: impl Fn() -> T) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            std::hint::black_box(lex());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let input = generate_input();
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+    assert_eq!(lex_parallel(&input), lex_all(&input));
+
+    let sequential = time(|| lex_all(&input));
+    let parallel = time(|| lex_parallel(&input));
+
+    let mb = input.len() as f64 / (1024.0 * 1024.0);
+    println!("lexing {mb:.1} MB on {threads} threads");
+    println!(
+        "sequential: {sequential:?} ({:.1} MB/s)",
+        mb / sequential.as_secs_f64()
+    );
+    println!(
+        "parallel:   {parallel:?} ({:.1} MB/s)",
+        mb / parallel.as_secs_f64()
+    );
+    println!(
+        "speedup:    {:.2}x",
+        sequential.as_secs_f64() / parallel.as_secs_f64()
+    );
+}
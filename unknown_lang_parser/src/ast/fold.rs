@@ -0,0 +1,726 @@
+//! Rebuilding the AST from an owned one.
+//!
+//! Where [`VisitorMut`](super::visit_mut::VisitorMut) changes nodes in place,
+//! a [`Fold`] takes each node by value and gives back its replacement, which
+//! can be a different kind of node. The `walk_*` functions fold every child
+//! and put the node back together around them.
+
+use alloc::{boxed::Box, vec::Vec};
+
+use super::{
+    Arm, ArmKind, Attribute, AttributeKind, Block, BlockKind, Enum, Expr,
+    ExprKind, Field, FieldInit, FieldInitKind, FieldKind, FieldPat,
+    FieldPatKind, Func, Ident, Impl, Import, Item, ItemKind, LambdaParam,
+    LambdaParamKind, Let, Literal, Macro, Module, Param, ParamKind, Pat,
+    PatKind, Path, Stmt, StmtKind, Struct, Trait, TraitMethod, TraitMethodKind,
+    TraitRef, TraitRefKind, Ty, TyKind, TypeParam, TypeParamKind, Variant,
+    VariantFields, VariantKind,
+};
+use crate::lexer::position::Spanned;
+
+pub trait Fold {
+    fn fold_module(&mut self, module: Module) -> Module {
+        walk_module(self, module)
+    }
+
+    fn fold_item(&mut self, item: Item) -> Item {
+        walk_item(self, item)
+    }
+
+    fn fold_attribute(&mut self, attr: Attribute) -> Attribute {
+        walk_attribute(self, attr)
+    }
+
+    fn fold_func(&mut self, func: Func) -> Func {
+        walk_func(self, func)
+    }
+
+    fn fold_type_param(&mut self, param: TypeParam) -> TypeParam {
+        walk_type_param(self, param)
+    }
+
+    fn fold_param(&mut self, param: Param) -> Param {
+        walk_param(self, param)
+    }
+
+    fn fold_struct(&mut self, decl: Struct) -> Struct {
+        walk_struct(self, decl)
+    }
+
+    fn fold_field(&mut self, field: Field) -> Field {
+        walk_field(self, field)
+    }
+
+    fn fold_enum(&mut self, decl: Enum) -> Enum {
+        walk_enum(self, decl)
+    }
+
+    fn fold_variant(&mut self, variant: Variant) -> Variant {
+        walk_variant(self, variant)
+    }
+
+    fn fold_trait(&mut self, decl: Trait) -> Trait {
+        walk_trait(self, decl)
+    }
+
+    fn fold_trait_method(&mut self, method: TraitMethod) -> TraitMethod {
+        walk_trait_method(self, method)
+    }
+
+    fn fold_impl(&mut self, decl: Impl) -> Impl {
+        walk_impl(self, decl)
+    }
+
+    fn fold_trait_ref(&mut self, trait_ref: TraitRef) -> TraitRef {
+        walk_trait_ref(self, trait_ref)
+    }
+
+    fn fold_import(&mut self, import: Import) -> Import {
+        walk_import(self, import)
+    }
+
+    fn fold_macro(&mut self, decl: Macro) -> Macro {
+        walk_macro(self, decl)
+    }
+
+    fn fold_ty(&mut self, ty: Ty) -> Ty {
+        walk_ty(self, ty)
+    }
+
+    fn fold_block(&mut self, block: Block) -> Block {
+        walk_block(self, block)
+    }
+
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        walk_stmt(self, stmt)
+    }
+
+    fn fold_let(&mut self, stmt: Let) -> Let {
+        walk_let(self, stmt)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        walk_expr(self, expr)
+    }
+
+    fn fold_field_init(&mut self, init: FieldInit) -> FieldInit {
+        walk_field_init(self, init)
+    }
+
+    fn fold_lambda_param(&mut self, param: LambdaParam) -> LambdaParam {
+        walk_lambda_param(self, param)
+    }
+
+    fn fold_arm(&mut self, arm: Arm) -> Arm {
+        walk_arm(self, arm)
+    }
+
+    fn fold_pat(&mut self, pat: Pat) -> Pat {
+        walk_pat(self, pat)
+    }
+
+    fn fold_field_pat(&mut self, field: FieldPat) -> FieldPat {
+        walk_field_pat(self, field)
+    }
+
+    /// Fold a name being declared, like a function or a parameter. Names
+    /// used in expressions are [`ExprKind::Ident`] instead.
+    fn fold_ident(&mut self, ident: Ident) -> Ident {
+        ident
+    }
+
+    /// Fold the path naming a struct or enum variant in a struct literal or
+    /// pattern, the generic function given type arguments, a trait in a bound
+    /// or impl, or the module of an import. Paths used on their own are
+    /// [`ExprKind::Path`], [`PatKind::Path`] and [`TyKind::Path`] instead.
+    fn fold_path(&mut self, path: Path) -> Path {
+        path
+    }
+
+    fn fold_literal(&mut self, literal: Literal) -> Literal {
+        literal
+    }
+}
+
+pub fn walk_module<F: Fold + ?Sized>(folder: &mut F, module: Module) -> Module {
+    Module {
+        attrs: fold_attributes(folder, module.attrs),
+        items: module
+            .items
+            .into_iter()
+            .map(|item| folder.fold_item(item))
+            .collect(),
+    }
+}
+
+pub fn walk_item<F: Fold + ?Sized>(folder: &mut F, item: Item) -> Item {
+    let kind = match item.data {
+        ItemKind::Func(func) => ItemKind::Func(folder.fold_func(func)),
+        ItemKind::Let(stmt) => ItemKind::Let(folder.fold_let(stmt)),
+        ItemKind::Struct(decl) => ItemKind::Struct(folder.fold_struct(decl)),
+        ItemKind::Enum(decl) => ItemKind::Enum(folder.fold_enum(decl)),
+        ItemKind::Trait(decl) => ItemKind::Trait(folder.fold_trait(decl)),
+        ItemKind::Impl(decl) => ItemKind::Impl(folder.fold_impl(decl)),
+        ItemKind::Import(import) => {
+            ItemKind::Import(folder.fold_import(import))
+        }
+        ItemKind::Macro(decl) => ItemKind::Macro(folder.fold_macro(decl)),
+        ItemKind::Error => ItemKind::Error,
+    };
+
+    Item {
+        attrs: fold_attributes(folder, item.attrs),
+        data: kind,
+        span: item.span,
+    }
+}
+
+pub fn walk_attribute<F: Fold + ?Sized>(
+    folder: &mut F,
+    attr: Attribute,
+) -> Attribute {
+    let kind = AttributeKind {
+        inner: attr.data.inner,
+        name: folder.fold_ident(attr.data.name),
+        args: attr.data.args,
+    };
+
+    Spanned::new(kind, attr.span)
+}
+
+fn fold_attributes<F: Fold + ?Sized>(
+    folder: &mut F,
+    attrs: Vec<Attribute>,
+) -> Vec<Attribute> {
+    attrs
+        .into_iter()
+        .map(|attr| folder.fold_attribute(attr))
+        .collect()
+}
+
+pub fn walk_func<F: Fold + ?Sized>(folder: &mut F, func: Func) -> Func {
+    Func {
+        public: func.public,
+        name: folder.fold_ident(func.name),
+        generics: fold_type_params(folder, func.generics),
+        params: fold_params(folder, func.params),
+        ret: func.ret.map(|ret| folder.fold_ty(ret)),
+        body: folder.fold_block(func.body),
+    }
+}
+
+pub fn walk_type_param<F: Fold + ?Sized>(
+    folder: &mut F,
+    param: TypeParam,
+) -> TypeParam {
+    let kind = TypeParamKind {
+        name: folder.fold_ident(param.data.name),
+        bounds: param
+            .data
+            .bounds
+            .into_iter()
+            .map(|bound| folder.fold_trait_ref(bound))
+            .collect(),
+    };
+
+    Spanned::new(kind, param.span)
+}
+
+fn fold_type_params<F: Fold + ?Sized>(
+    folder: &mut F,
+    params: Vec<TypeParam>,
+) -> Vec<TypeParam> {
+    params
+        .into_iter()
+        .map(|param| folder.fold_type_param(param))
+        .collect()
+}
+
+fn fold_params<F: Fold + ?Sized>(
+    folder: &mut F,
+    params: Vec<Param>,
+) -> Vec<Param> {
+    params
+        .into_iter()
+        .map(|param| folder.fold_param(param))
+        .collect()
+}
+
+pub fn walk_param<F: Fold + ?Sized>(folder: &mut F, param: Param) -> Param {
+    let kind = ParamKind {
+        name: folder.fold_ident(param.data.name),
+        ty: folder.fold_ty(param.data.ty),
+    };
+
+    Spanned::new(kind, param.span)
+}
+
+pub fn walk_struct<F: Fold + ?Sized>(folder: &mut F, decl: Struct) -> Struct {
+    Struct {
+        public: decl.public,
+        name: folder.fold_ident(decl.name),
+        generics: fold_type_params(folder, decl.generics),
+        fields: decl
+            .fields
+            .into_iter()
+            .map(|field| folder.fold_field(field))
+            .collect(),
+    }
+}
+
+pub fn walk_field<F: Fold + ?Sized>(folder: &mut F, field: Field) -> Field {
+    let kind = FieldKind {
+        name: folder.fold_ident(field.data.name),
+        ty: folder.fold_ty(field.data.ty),
+    };
+
+    Spanned::new(kind, field.span)
+}
+
+pub fn walk_enum<F: Fold + ?Sized>(folder: &mut F, decl: Enum) -> Enum {
+    Enum {
+        public: decl.public,
+        name: folder.fold_ident(decl.name),
+        generics: fold_type_params(folder, decl.generics),
+        variants: decl
+            .variants
+            .into_iter()
+            .map(|variant| folder.fold_variant(variant))
+            .collect(),
+    }
+}
+
+pub fn walk_variant<F: Fold + ?Sized>(
+    folder: &mut F,
+    variant: Variant,
+) -> Variant {
+    let fields = match variant.data.fields {
+        VariantFields::Unit => VariantFields::Unit,
+        VariantFields::Tuple(tys) => VariantFields::Tuple(
+            tys.into_iter().map(|ty| folder.fold_ty(ty)).collect(),
+        ),
+        VariantFields::Named(fields) => VariantFields::Named(
+            fields
+                .into_iter()
+                .map(|field| folder.fold_field(field))
+                .collect(),
+        ),
+    };
+
+    let kind = VariantKind {
+        name: folder.fold_ident(variant.data.name),
+        fields,
+        discriminant: variant
+            .data
+            .discriminant
+            .map(|discriminant| folder.fold_expr(discriminant)),
+    };
+
+    Spanned::new(kind, variant.span)
+}
+
+pub fn walk_trait<F: Fold + ?Sized>(folder: &mut F, decl: Trait) -> Trait {
+    Trait {
+        public: decl.public,
+        name: folder.fold_ident(decl.name),
+        generics: fold_type_params(folder, decl.generics),
+        methods: decl
+            .methods
+            .into_iter()
+            .map(|method| folder.fold_trait_method(method))
+            .collect(),
+    }
+}
+
+pub fn walk_trait_method<F: Fold + ?Sized>(
+    folder: &mut F,
+    method: TraitMethod,
+) -> TraitMethod {
+    let kind = TraitMethodKind {
+        name: folder.fold_ident(method.data.name),
+        generics: fold_type_params(folder, method.data.generics),
+        params: fold_params(folder, method.data.params),
+        ret: method.data.ret.map(|ret| folder.fold_ty(ret)),
+        default: method.data.default.map(|body| folder.fold_block(body)),
+    };
+
+    Spanned::new(kind, method.span)
+}
+
+pub fn walk_impl<F: Fold + ?Sized>(folder: &mut F, decl: Impl) -> Impl {
+    Impl {
+        generics: fold_type_params(folder, decl.generics),
+        trait_ref: folder.fold_trait_ref(decl.trait_ref),
+        self_ty: folder.fold_ty(decl.self_ty),
+        methods: decl
+            .methods
+            .into_iter()
+            .map(|method| {
+                Spanned::new(folder.fold_func(method.data), method.span)
+            })
+            .collect(),
+    }
+}
+
+pub fn walk_trait_ref<F: Fold + ?Sized>(
+    folder: &mut F,
+    trait_ref: TraitRef,
+) -> TraitRef {
+    let kind = TraitRefKind {
+        path: folder.fold_path(trait_ref.data.path),
+        args: trait_ref
+            .data
+            .args
+            .into_iter()
+            .map(|arg| folder.fold_ty(arg))
+            .collect(),
+    };
+
+    Spanned::new(kind, trait_ref.span)
+}
+
+pub fn walk_import<F: Fold + ?Sized>(folder: &mut F, import: Import) -> Import {
+    Import {
+        path: folder.fold_path(import.path),
+    }
+}
+
+pub fn walk_macro<F: Fold + ?Sized>(folder: &mut F, decl: Macro) -> Macro {
+    Macro {
+        name: folder.fold_ident(decl.name),
+        rules: decl.rules,
+    }
+}
+
+pub fn walk_ty<F: Fold + ?Sized>(folder: &mut F, ty: Ty) -> Ty {
+    let kind = match ty.data {
+        TyKind::Mut(inner) => TyKind::Mut(Box::new(folder.fold_ty(*inner))),
+        TyKind::Generic { name, args } => TyKind::Generic {
+            name: folder.fold_ident(name),
+            args: args.into_iter().map(|arg| folder.fold_ty(arg)).collect(),
+        },
+        TyKind::Array { elem, len } => TyKind::Array {
+            elem: Box::new(folder.fold_ty(*elem)),
+            len: Box::new(folder.fold_expr(*len)),
+        },
+        TyKind::Slice(elem) => TyKind::Slice(Box::new(folder.fold_ty(*elem))),
+        TyKind::Tuple(elems) => TyKind::Tuple(
+            elems.into_iter().map(|elem| folder.fold_ty(elem)).collect(),
+        ),
+        TyKind::Func { params, ret } => TyKind::Func {
+            params: params
+                .into_iter()
+                .map(|param| folder.fold_ty(param))
+                .collect(),
+            ret: Box::new(folder.fold_ty(*ret)),
+        },
+        TyKind::Ref { mutable, ty } => TyKind::Ref {
+            mutable,
+            ty: Box::new(folder.fold_ty(*ty)),
+        },
+        TyKind::Paren(inner) => TyKind::Paren(Box::new(folder.fold_ty(*inner))),
+        kind @ (TyKind::Named(_) | TyKind::Path(_) | TyKind::Error) => kind,
+    };
+
+    Spanned::new(kind, ty.span)
+}
+
+pub fn walk_block<F: Fold + ?Sized>(folder: &mut F, block: Block) -> Block {
+    let kind = BlockKind {
+        attrs: fold_attributes(folder, block.data.attrs),
+        stmts: block
+            .data
+            .stmts
+            .into_iter()
+            .map(|stmt| folder.fold_stmt(stmt))
+            .collect(),
+        tail: block
+            .data
+            .tail
+            .map(|tail| Box::new(folder.fold_expr(*tail))),
+    };
+
+    Spanned::new(kind, block.span)
+}
+
+pub fn walk_stmt<F: Fold + ?Sized>(folder: &mut F, stmt: Stmt) -> Stmt {
+    let kind = match stmt.data {
+        StmtKind::Let(stmt) => StmtKind::Let(folder.fold_let(stmt)),
+        StmtKind::Return(value) => {
+            StmtKind::Return(value.map(|value| folder.fold_expr(value)))
+        }
+        StmtKind::Assign { target, op, value } => StmtKind::Assign {
+            target: folder.fold_expr(target),
+            op,
+            value: folder.fold_expr(value),
+        },
+        StmtKind::Break { label, value } => StmtKind::Break {
+            label: label.map(|label| folder.fold_ident(label)),
+            value: value.map(|value| folder.fold_expr(value)),
+        },
+        StmtKind::Continue { label } => StmtKind::Continue {
+            label: label.map(|label| folder.fold_ident(label)),
+        },
+        StmtKind::Expr(expr) => StmtKind::Expr(folder.fold_expr(expr)),
+        StmtKind::Error => StmtKind::Error,
+    };
+
+    Stmt {
+        attrs: fold_attributes(folder, stmt.attrs),
+        data: kind,
+        span: stmt.span,
+    }
+}
+
+pub fn walk_let<F: Fold + ?Sized>(folder: &mut F, stmt: Let) -> Let {
+    Let {
+        name: folder.fold_ident(stmt.name),
+        ty: stmt.ty.map(|ty| folder.fold_ty(ty)),
+        value: folder.fold_expr(stmt.value),
+    }
+}
+
+pub fn walk_expr<F: Fold + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    let kind = match expr.data {
+        ExprKind::Literal(literal) => {
+            ExprKind::Literal(folder.fold_literal(literal))
+        }
+        ExprKind::Instantiate { path, args } => ExprKind::Instantiate {
+            path: folder.fold_path(path),
+            args: args.into_iter().map(|arg| folder.fold_ty(arg)).collect(),
+        },
+        ExprKind::Unary { op, operand } => ExprKind::Unary {
+            op,
+            operand: Box::new(folder.fold_expr(*operand)),
+        },
+        ExprKind::Binary { op, lhs, rhs } => ExprKind::Binary {
+            op,
+            lhs: Box::new(folder.fold_expr(*lhs)),
+            rhs: Box::new(folder.fold_expr(*rhs)),
+        },
+        ExprKind::Call { callee, args } => ExprKind::Call {
+            callee: Box::new(folder.fold_expr(*callee)),
+            args: args.into_iter().map(|arg| folder.fold_expr(arg)).collect(),
+        },
+        ExprKind::Field { base, field } => ExprKind::Field {
+            base: Box::new(folder.fold_expr(*base)),
+            field: folder.fold_ident(field),
+        },
+        ExprKind::StructLit { path, fields, base } => ExprKind::StructLit {
+            path: folder.fold_path(path),
+            fields: fields
+                .into_iter()
+                .map(|init| folder.fold_field_init(init))
+                .collect(),
+            base: base.map(|base| Box::new(folder.fold_expr(*base))),
+        },
+        ExprKind::Paren(inner) => {
+            ExprKind::Paren(Box::new(folder.fold_expr(*inner)))
+        }
+        ExprKind::Block(block) => ExprKind::Block(folder.fold_block(block)),
+        ExprKind::If {
+            cond,
+            then,
+            else_branch,
+        } => ExprKind::If {
+            cond: Box::new(folder.fold_expr(*cond)),
+            then: folder.fold_block(then),
+            else_branch: else_branch
+                .map(|else_branch| Box::new(folder.fold_expr(*else_branch))),
+        },
+        ExprKind::While { label, cond, body } => ExprKind::While {
+            label: label.map(|label| folder.fold_ident(label)),
+            cond: Box::new(folder.fold_expr(*cond)),
+            body: folder.fold_block(body),
+        },
+        ExprKind::Loop { label, body } => ExprKind::Loop {
+            label: label.map(|label| folder.fold_ident(label)),
+            body: folder.fold_block(body),
+        },
+        ExprKind::For {
+            label,
+            binding,
+            iter,
+            body,
+        } => ExprKind::For {
+            label: label.map(|label| folder.fold_ident(label)),
+            binding: folder.fold_ident(binding),
+            iter: Box::new(folder.fold_expr(*iter)),
+            body: folder.fold_block(body),
+        },
+        ExprKind::Range {
+            start,
+            end,
+            inclusive,
+        } => ExprKind::Range {
+            start: Box::new(folder.fold_expr(*start)),
+            end: Box::new(folder.fold_expr(*end)),
+            inclusive,
+        },
+        ExprKind::Match { scrutinee, arms } => ExprKind::Match {
+            scrutinee: Box::new(folder.fold_expr(*scrutinee)),
+            arms: arms.into_iter().map(|arm| folder.fold_arm(arm)).collect(),
+        },
+        ExprKind::Lambda { params, ret, body } => ExprKind::Lambda {
+            params: params
+                .into_iter()
+                .map(|param| folder.fold_lambda_param(param))
+                .collect(),
+            ret: ret.map(|ret| folder.fold_ty(ret)),
+            body: Box::new(folder.fold_expr(*body)),
+        },
+        kind @ (ExprKind::Ident(_)
+        | ExprKind::Path(_)
+        | ExprKind::MacroCall { .. }
+        | ExprKind::Error) => kind,
+    };
+
+    Spanned::new(kind, expr.span)
+}
+
+pub fn walk_field_init<F: Fold + ?Sized>(
+    folder: &mut F,
+    init: FieldInit,
+) -> FieldInit {
+    let kind = FieldInitKind {
+        name: folder.fold_ident(init.data.name),
+        value: init.data.value.map(|value| folder.fold_expr(value)),
+    };
+
+    Spanned::new(kind, init.span)
+}
+
+pub fn walk_lambda_param<F: Fold + ?Sized>(
+    folder: &mut F,
+    param: LambdaParam,
+) -> LambdaParam {
+    let kind = LambdaParamKind {
+        name: folder.fold_ident(param.data.name),
+        ty: param.data.ty.map(|ty| folder.fold_ty(ty)),
+    };
+
+    Spanned::new(kind, param.span)
+}
+
+pub fn walk_arm<F: Fold + ?Sized>(folder: &mut F, arm: Arm) -> Arm {
+    let kind = ArmKind {
+        pat: folder.fold_pat(arm.data.pat),
+        guard: arm.data.guard.map(|guard| folder.fold_expr(guard)),
+        body: folder.fold_expr(arm.data.body),
+    };
+
+    Spanned::new(kind, arm.span)
+}
+
+pub fn walk_pat<F: Fold + ?Sized>(folder: &mut F, pat: Pat) -> Pat {
+    let kind = match pat.data {
+        PatKind::Literal(literal) => {
+            PatKind::Literal(folder.fold_literal(literal))
+        }
+        PatKind::Range {
+            start,
+            end,
+            inclusive,
+        } => PatKind::Range {
+            start: Box::new(folder.fold_pat(*start)),
+            end: Box::new(folder.fold_pat(*end)),
+            inclusive,
+        },
+        PatKind::Tuple(pats) => PatKind::Tuple(
+            pats.into_iter().map(|pat| folder.fold_pat(pat)).collect(),
+        ),
+        PatKind::Paren(inner) => {
+            PatKind::Paren(Box::new(folder.fold_pat(*inner)))
+        }
+        PatKind::Or(pats) => PatKind::Or(
+            pats.into_iter().map(|pat| folder.fold_pat(pat)).collect(),
+        ),
+        PatKind::At { name, pat } => PatKind::At {
+            name: folder.fold_ident(name),
+            pat: Box::new(folder.fold_pat(*pat)),
+        },
+        PatKind::TupleStruct { path, elems } => PatKind::TupleStruct {
+            path: folder.fold_path(path),
+            elems: elems
+                .into_iter()
+                .map(|elem| folder.fold_pat(elem))
+                .collect(),
+        },
+        PatKind::Struct { path, fields, rest } => PatKind::Struct {
+            path: folder.fold_path(path),
+            fields: fields
+                .into_iter()
+                .map(|field| folder.fold_field_pat(field))
+                .collect(),
+            rest,
+        },
+        kind @ (PatKind::Wild
+        | PatKind::Binding(_)
+        | PatKind::Path(_)
+        | PatKind::Error) => kind,
+    };
+
+    Spanned::new(kind, pat.span)
+}
+
+pub fn walk_field_pat<F: Fold + ?Sized>(
+    folder: &mut F,
+    field: FieldPat,
+) -> FieldPat {
+    let kind = FieldPatKind {
+        name: folder.fold_ident(field.data.name),
+        pat: field.data.pat.map(|pat| folder.fold_pat(pat)),
+    };
+
+    Spanned::new(kind, field.span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::BinOp, parser::parse};
+
+    /// Drops every set of parenthesis, which the AST only keeps to reproduce
+    /// the source.
+    struct StripParens;
+
+    impl Fold for StripParens {
+        fn fold_expr(&mut self, expr: Expr) -> Expr {
+            match expr.data {
+                ExprKind::Paren(inner) => self.fold_expr(*inner),
+                _ => walk_expr(self, expr),
+            }
+        }
+    }
+
+    #[test]
+    fn test_replaces_nodes() {
+        let (module, _) = parse("let a := ((b ** c))(1)");
+        let module = StripParens.fold_module(module);
+
+        let ItemKind::Let(stmt) = &module.items[0].data else {
+            panic!("expected a let");
+        };
+        let ExprKind::Call { callee, .. } = &stmt.value.data else {
+            panic!("expected a call");
+        };
+        assert!(matches!(
+            callee.data,
+            ExprKind::Binary {
+                op: BinOp::Compose,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_default_fold_is_identity() {
+        struct Identity;
+        impl Fold for Identity {}
+
+        let input = "func f(a: mut usize) : usize = {\n    a += -1\n    a\n}";
+        let (module, _) = parse(input);
+
+        assert_eq!(Identity.fold_module(module.clone()), module);
+    }
+}
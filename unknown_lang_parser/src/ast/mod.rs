@@ -0,0 +1,686 @@
+//! The abstract syntax tree produced by the parser.
+//!
+//! Every node is wrapped in a [`Spanned`], so that later phases can point
+//! back at the source that a node came from. Items and statements are
+//! wrapped in an [`Attributed`] instead, which keeps their attributes as
+//! well. Passes over the tree can be
+//! written with the traits in [`visit`], [`visit_mut`] and [`fold`].
+
+pub mod fold;
+pub mod print;
+pub mod visit;
+pub mod visit_mut;
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+
+use crate::lexer::{
+    position::{Span, Spanned},
+    tokens::Token,
+};
+
+/// A name written in the source, like a variable or a type.
+pub type Ident = Spanned<String>;
+
+/// A name which may be inside of another, like `Shape::Circle`, where each
+/// segment is separated by `::`. A lone name is a path with one segment.
+pub type Path = Spanned<Vec<Ident>>;
+
+/// Join the segments of a path back together with `::`, like `Shape::Circle`.
+pub fn path_to_string(segments: &[Ident]) -> String {
+    let mut out = String::new();
+
+    for (idx, segment) in segments.iter().enumerate() {
+        if idx > 0 {
+            out.push_str("::");
+        }
+        out.push_str(&segment.data);
+    }
+
+    out
+}
+
+/// A whole source file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Module {
+    /// The inner attributes at the start of the file, like
+    /// `#![allow(unknown_attributes)]`
+    pub attrs: Vec<Attribute>,
+    pub items: Vec<Item>,
+}
+
+pub type Attribute = Spanned<AttributeKind>;
+
+/// An attribute, like the `#[test]` of `#[test] func f() := {}`, which gives
+/// the compiler more to know about what it is on. An outer attribute is
+/// written before an item or statement, and an inner one, written with `#!`,
+/// at the start of the file or block it is inside of.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttributeKind {
+    /// Whether the attribute was written with `#!`
+    pub inner: bool,
+    pub name: Ident,
+    /// The tokens after the name, like the `(unused)` of `#[allow(unused)]`
+    pub args: Vec<Spanned<Token>>,
+}
+
+/// A node which can have outer attributes written before it, like an item or
+/// a statement. Its span doesn't include the attributes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Attributed<T> {
+    pub attrs: Vec<Attribute>,
+    pub data: T,
+    pub span: Span,
+}
+
+impl<T> Attributed<T> {
+    /// Wrap `data` without any attributes.
+    pub fn new(data: T, span: Span) -> Self {
+        Self {
+            attrs: Vec::new(),
+            data,
+            span,
+        }
+    }
+}
+
+pub type Item = Attributed<ItemKind>;
+
+/// Something declared at the top level of a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ItemKind {
+    Func(Func),
+    Let(Let),
+    Struct(Struct),
+    Enum(Enum),
+    Trait(Trait),
+    Impl(Impl),
+    Macro(Macro),
+    Import(Import),
+    /// An item which failed to parse
+    Error,
+}
+
+impl ItemKind {
+    /// Checks if this is a declaration which other modules can use, because
+    /// it was declared with `pub`.
+    pub fn is_public(&self) -> bool {
+        match self {
+            ItemKind::Func(func) => func.public,
+            ItemKind::Struct(decl) => decl.public,
+            ItemKind::Enum(decl) => decl.public,
+            ItemKind::Trait(decl) => decl.public,
+            ItemKind::Let(_)
+            | ItemKind::Impl(_)
+            | ItemKind::Macro(_)
+            | ItemKind::Import(_)
+            | ItemKind::Error => false,
+        }
+    }
+}
+
+/// A function declaration, like `func add(a: usize, b: usize) : usize = {}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Func {
+    /// Whether the function was declared with `pub`, so that other modules
+    /// can call it
+    pub public: bool,
+    pub name: Ident,
+    pub generics: Vec<TypeParam>,
+    pub params: Vec<Param>,
+    /// The declared return type, which is left off when declared with `:=`
+    pub ret: Option<Ty>,
+    pub body: Block,
+}
+
+pub type TypeParam = Spanned<TypeParamKind>;
+
+/// A type parameter of a generic function or type, like the `T` of
+/// `func id<T>(x: T) : T`, or the `T: Display + Debug` of
+/// `func show<T: Display + Debug>(x: T)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeParamKind {
+    pub name: Ident,
+    /// The traits that the type argument has to implement
+    pub bounds: Vec<TraitRef>,
+}
+
+pub type TraitRef = Spanned<TraitRefKind>;
+
+/// A trait named in a bound or an impl, along with its type arguments, like
+/// `Add<u8>` or `shapes::Display`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraitRefKind {
+    pub path: Path,
+    pub args: Vec<Ty>,
+}
+
+pub type Param = Spanned<ParamKind>;
+
+/// A single function parameter, like `a : mut usize`. A method can start
+/// with just `self`, which is short for `self: Self`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParamKind {
+    pub name: Ident,
+    pub ty: Ty,
+}
+
+/// A struct declaration, like `struct Point { x: i32, y: i32 }`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Struct {
+    pub public: bool,
+    pub name: Ident,
+    pub generics: Vec<TypeParam>,
+    pub fields: Vec<Field>,
+}
+
+pub type Field = Spanned<FieldKind>;
+
+/// A single field of a struct declaration, like `x: i32`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldKind {
+    pub name: Ident,
+    pub ty: Ty,
+}
+
+/// An enum declaration, like `enum Shape { Circle(f64), Empty }`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Enum {
+    pub public: bool,
+    pub name: Ident,
+    pub generics: Vec<TypeParam>,
+    pub variants: Vec<Variant>,
+}
+
+pub type Variant = Spanned<VariantKind>;
+
+/// A single variant of an enum declaration, like `Rect { w: f64, h: f64 }`
+/// or `Flag = 0b100`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VariantKind {
+    pub name: Ident,
+    pub fields: VariantFields,
+    /// The value given to the variant after `=`, which otherwise follows on
+    /// from the variant before it
+    pub discriminant: Option<Expr>,
+}
+
+/// The values that an enum variant holds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VariantFields {
+    /// No values, like `Empty`
+    Unit,
+    /// Values in order, like `Circle(f64)`
+    Tuple(Vec<Ty>),
+    /// Values by name, like `Rect { w: f64, h: f64 }`
+    Named(Vec<Field>),
+}
+
+/// A trait declaration, like
+/// `trait Display { func show(self) : &str }`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trait {
+    pub public: bool,
+    pub name: Ident,
+    pub generics: Vec<TypeParam>,
+    pub methods: Vec<TraitMethod>,
+}
+
+pub type TraitMethod = Spanned<TraitMethodKind>;
+
+/// A method of a trait, which every impl has to give unless it has a default
+/// body, like `func show(self) : &str` or `func print(self) := { .. }`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraitMethodKind {
+    pub name: Ident,
+    pub generics: Vec<TypeParam>,
+    pub params: Vec<Param>,
+    pub ret: Option<Ty>,
+    /// The body used by impls which don't give the method themselves
+    pub default: Option<Block>,
+}
+
+/// An implementation of a trait for a type, like
+/// `impl<T: Display> Display for Pair<T> { .. }`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Impl {
+    pub generics: Vec<TypeParam>,
+    pub trait_ref: TraitRef,
+    pub self_ty: Ty,
+    pub methods: Vec<Method>,
+}
+
+/// A function given by an impl for one of the methods of its trait.
+pub type Method = Spanned<Func>;
+
+/// A declarative macro, like
+/// `macro max { ($a:expr, $b:expr) => { if $a > $b { $a } else { $b } } }`.
+/// Its calls are expanded before the file is parsed, so the macro is only
+/// kept in the tree as the tokens of its rules.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Macro {
+    pub name: Ident,
+    pub rules: Vec<MacroRule>,
+}
+
+pub type MacroRule = Spanned<MacroRuleKind>;
+
+/// A rule of a macro, which replaces a call matching the tokens of
+/// `matcher` with the tokens of `body`. Neither includes the delimiters
+/// around it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MacroRuleKind {
+    pub matcher: Vec<Spanned<Token>>,
+    pub body: Vec<Spanned<Token>>,
+}
+
+/// An import of another module, like `import shapes::circle`. The module is
+/// named by the last segment of the path in the rest of the file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Import {
+    pub path: Path,
+}
+
+pub type Ty = Spanned<TyKind>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TyKind {
+    /// A type referred to by name, like `usize`
+    Named(String),
+    /// A type inside of another module, like `shapes::Circle`. A type
+    /// without any `::` is [`TyKind::Named`] instead.
+    Path(Vec<Ident>),
+    /// `mut T`, a type which can be changed in place
+    Mut(Box<Ty>),
+    /// A type applied to type arguments, like `Option<T>`
+    Generic { name: Ident, args: Vec<Ty> },
+    /// `[T; N]`, an array with a fixed length
+    Array { elem: Box<Ty>, len: Box<Expr> },
+    /// `[T]`, a view into a run of values
+    Slice(Box<Ty>),
+    /// `(A, B)`, where `()` is the empty tuple and `(A,)` has one element
+    Tuple(Vec<Ty>),
+    /// `(A, B) -> C`, a function type
+    Func { params: Vec<Ty>, ret: Box<Ty> },
+    /// `&T`, or `&mut T` when `mutable` is set
+    Ref { mutable: bool, ty: Box<Ty> },
+    /// A type in parenthesis, kept so that the source can be reproduced
+    /// exactly
+    Paren(Box<Ty>),
+    /// A type which failed to parse
+    Error,
+}
+
+pub type Block = Spanned<BlockKind>;
+
+/// A block of statements, like `{ let a := 1; a + 1 }`. A block evaluates to
+/// its tail expression, if it has one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockKind {
+    /// The inner attributes at the start of the block
+    pub attrs: Vec<Attribute>,
+    pub stmts: Vec<Stmt>,
+    pub tail: Option<Box<Expr>>,
+}
+
+pub type Stmt = Attributed<StmtKind>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StmtKind {
+    Let(Let),
+    /// `return` with an optional value
+    Return(Option<Expr>),
+    /// `target = value` or `target += value`
+    Assign {
+        target: Expr,
+        op: AssignOp,
+        value: Expr,
+    },
+    /// `break`, with an optional label and value, like `break 'outer 1`
+    Break {
+        label: Option<Ident>,
+        value: Option<Expr>,
+    },
+    /// `continue`, with an optional label
+    Continue {
+        label: Option<Ident>,
+    },
+    /// An expression evaluated for its side effects
+    Expr(Expr),
+    /// A statement which failed to parse
+    Error,
+}
+
+/// A variable declaration, either `let a := 1` or `let a : usize = 1`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Let {
+    pub name: Ident,
+    /// The declared type, which is inferred when left off
+    pub ty: Option<Ty>,
+    pub value: Expr,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AssignOp {
+    /// `=`
+    Assign,
+    /// `+=`
+    AddAssign,
+}
+
+pub type Expr = Spanned<ExprKind>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExprKind {
+    Literal(Literal),
+    Ident(String),
+    /// A path with more than one segment, like `Shape::Empty`
+    Path(Vec<Ident>),
+    /// A generic function given its type arguments, like `id::<u8>`
+    Instantiate {
+        path: Path,
+        args: Vec<Ty>,
+    },
+    /// A call to a macro which wasn't expanded, like `max!(a, b)`, with the
+    /// tokens between its delimiters
+    MacroCall {
+        name: Ident,
+        args: Vec<Spanned<Token>>,
+    },
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+    },
+    /// `base.field`
+    Field {
+        base: Box<Expr>,
+        field: Ident,
+    },
+    /// `Point { x: 1, y }`, where any fields which aren't listed are taken
+    /// from `base` when it ends with `..base`. The path names either a struct
+    /// or an enum variant, like `Shape::Rect { w: 1.0, h: 2.0 }`.
+    StructLit {
+        path: Path,
+        fields: Vec<FieldInit>,
+        base: Option<Box<Expr>>,
+    },
+    /// An expression in parenthesis, kept so that the source can be
+    /// reproduced exactly
+    Paren(Box<Expr>),
+    Block(Block),
+    /// `if cond { } else { }`, where the `else` branch is either a block or
+    /// another `if`
+    If {
+        cond: Box<Expr>,
+        then: Block,
+        else_branch: Option<Box<Expr>>,
+    },
+    /// `while cond { }`
+    While {
+        label: Option<Ident>,
+        cond: Box<Expr>,
+        body: Block,
+    },
+    /// `loop { }`, which only stops at a `break`
+    Loop {
+        label: Option<Ident>,
+        body: Block,
+    },
+    /// `for x in a..b { }`
+    For {
+        label: Option<Ident>,
+        binding: Ident,
+        iter: Box<Expr>,
+        body: Block,
+    },
+    /// `a..b`, or `a..=b` when `inclusive` is set
+    Range {
+        start: Box<Expr>,
+        end: Box<Expr>,
+        inclusive: bool,
+    },
+    /// `match value { pattern => result, ... }`
+    Match {
+        scrutinee: Box<Expr>,
+        arms: Vec<Arm>,
+    },
+    /// `|a, b: usize| a + b`, an anonymous function. When the return type is
+    /// given after `->`, the body is always a block, like `|a| -> usize { a
+    /// }`.
+    Lambda {
+        params: Vec<LambdaParam>,
+        ret: Option<Ty>,
+        body: Box<Expr>,
+    },
+    /// An expression which failed to parse
+    Error,
+}
+
+pub type FieldInit = Spanned<FieldInitKind>;
+
+/// A field given a value in a struct literal, like `x: 1`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldInitKind {
+    pub name: Ident,
+    /// The value of the field, which is left off for the shorthand `x`, where
+    /// the value is the variable with the same name as the field
+    pub value: Option<Expr>,
+}
+
+pub type LambdaParam = Spanned<LambdaParamKind>;
+
+/// A single parameter of a lambda, like `a` or `b: mut usize`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LambdaParamKind {
+    pub name: Ident,
+    /// The declared type, which is inferred when left off
+    pub ty: Option<Ty>,
+}
+
+pub type Arm = Spanned<ArmKind>;
+
+/// A single arm of a `match`, like `0 | 1 if small => a`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArmKind {
+    pub pat: Pat,
+    /// The condition after `if`, which also has to hold for the arm to be
+    /// taken
+    pub guard: Option<Expr>,
+    pub body: Expr,
+}
+
+pub type Pat = Spanned<PatKind>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PatKind {
+    /// `_`, which matches anything
+    Wild,
+    /// A name, which matches anything and binds it to the value
+    Binding(String),
+    /// A path with more than one segment, like `Shape::Empty`
+    Path(Vec<Ident>),
+    /// `Shape::Circle(r)`, which matches the values of a variant in order
+    TupleStruct { path: Path, elems: Vec<Pat> },
+    /// `Shape::Rect { w, h: 0.0, .. }`, which matches the fields of a struct
+    /// or variant by name. Fields which aren't listed are only allowed when
+    /// `rest` is set by ending with `..`.
+    Struct {
+        path: Path,
+        fields: Vec<FieldPat>,
+        rest: bool,
+    },
+    /// A literal value, like `0x1f` or `'a'`
+    Literal(Literal),
+    /// `a..b`, or `a..=b` when `inclusive` is set, where both ends are
+    /// literals
+    Range {
+        start: Box<Pat>,
+        end: Box<Pat>,
+        inclusive: bool,
+    },
+    /// `(a, b)`, where `(a,)` has one element
+    Tuple(Vec<Pat>),
+    /// A pattern in parenthesis, kept so that the source can be reproduced
+    /// exactly
+    Paren(Box<Pat>),
+    /// `a | b`, which matches if any of the patterns do
+    Or(Vec<Pat>),
+    /// `name @ pat`, which binds the value to `name` if `pat` matches
+    At { name: Ident, pat: Box<Pat> },
+    /// A pattern which failed to parse
+    Error,
+}
+
+pub type FieldPat = Spanned<FieldPatKind>;
+
+/// A field in a struct pattern, like `h: 0.0`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldPatKind {
+    pub name: Ident,
+    /// The pattern for the field, which is left off for the shorthand `w`,
+    /// where the field is bound to a variable with the same name
+    pub pat: Option<Pat>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Literal {
+    /// A number, with the digits as written in the given base. A `-` written
+    /// right before a number is folded into it.
+    Number {
+        base: u8,
+        digits: String,
+        negative: bool,
+    },
+    Str(String),
+    Char(char),
+    Byte(u8),
+    ByteStr(Vec<u8>),
+}
+
+impl Literal {
+    /// The value of a number, which is `None` for any other literal, or when
+    /// the number doesn't fit in an `i128`.
+    pub fn as_integer(&self) -> Option<i128> {
+        let Literal::Number {
+            base,
+            digits,
+            negative,
+        } = self
+        else {
+            return None;
+        };
+
+        // Digits are checked against the base when lexing, so the only other
+        // character is the `_` separating them
+        let mut value: i128 = 0;
+        for digit in digits.chars().filter_map(|chr| chr.to_digit(36)) {
+            value = value
+                .checked_mul(i128::from(*base))?
+                .checked_add(i128::from(digit))?;
+        }
+
+        Some(if *negative { -value } else { value })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+    /// Function composition, `f ** g` is `f` applied to the result of `g`
+    Compose,
+}
+
+impl BinOp {
+    /// The operator as it is written in the source.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+            BinOp::BitAnd => "&",
+            BinOp::BitOr => "|",
+            BinOp::BitXor => "^",
+            BinOp::Shl => "<<",
+            BinOp::Shr => ">>",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::And => "&&",
+            BinOp::Or => "||",
+            BinOp::Compose => "**",
+        }
+    }
+}
+
+impl core::fmt::Display for BinOp {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// `-`
+    Neg,
+    /// `!`
+    Not,
+    /// `~`
+    BitNot,
+}
+
+impl UnaryOp {
+    /// The operator as it is written in the source.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Not => "!",
+            UnaryOp::BitNot => "~",
+        }
+    }
+}
+
+impl core::fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl AssignOp {
+    /// The operator as it is written in the source.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AssignOp::Assign => "=",
+            AssignOp::AddAssign => "+=",
+        }
+    }
+}
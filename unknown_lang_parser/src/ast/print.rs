@@ -0,0 +1,1431 @@
+//! Dumping the AST in a readable form, for debugging the parser.
+//!
+//! [`tree`] prints every node on its own line, indented under its parent,
+//! with its span and the fields that aren't child nodes. [`sexpr`] prints the
+//! same structure as an S-expression without any spans, which is compact
+//! enough to compare the shape of two trees.
+
+use alloc::{format, string::String};
+use core::fmt::Write;
+
+use super::{
+    path_to_string, Arm, Attribute, Block, Enum, Expr, ExprKind, Field,
+    FieldInit, FieldPat, Func, Ident, Impl, Item, ItemKind, LambdaParam, Let,
+    Literal, Macro, Module, Param, Pat, PatKind, Stmt, StmtKind, Struct, Trait,
+    TraitMethod, TraitRef, Ty, TyKind, TypeParam, Variant, VariantFields,
+};
+use crate::lexer::{
+    position::{Span, Spanned},
+    tokens::Token,
+};
+
+impl Literal {
+    /// Render this literal as source code, in the base it was written in.
+    pub fn to_source(&self) -> String {
+        let tok = match self {
+            Literal::Number {
+                base,
+                digits,
+                negative,
+            } => {
+                let num = Token::Number(*base, digits.clone()).to_source();
+                return if *negative { format!("-{num}") } else { num };
+            }
+            Literal::Str(string) => Token::Str(string.clone()),
+            Literal::Char(chr) => Token::Char(*chr),
+            Literal::Byte(byte) => Token::Byte(*byte),
+            Literal::ByteStr(bytes) => Token::ByteStr(bytes.clone()),
+        };
+
+        tok.to_source()
+    }
+}
+
+/// Print a module as an indented tree, like
+///
+/// ```text
+/// Module 0..12
+///   Let 0..12 name=a
+///     Literal 9..12 0x1
+/// ```
+pub fn tree(module: &Module) -> String {
+    let end = module.items.last().map_or(0, |item| item.span.end.0);
+
+    let mut printer = TreePrinter::default();
+    printer.line("Module", Span::new(0, end), "");
+    printer.nested(|p| {
+        p.attributes(&module.attrs);
+        module.items.iter().for_each(|item| p.item(item));
+    });
+
+    printer.out
+}
+
+/// Print a module as an S-expression, like `(module (let a 0x1))`.
+pub fn sexpr(module: &Module) -> String {
+    let mut out = String::from("(module");
+    sexpr_attributes(&module.attrs, " ", "", &mut out);
+
+    for item in &module.items {
+        out.push(' ');
+        sexpr_item(item, &mut out);
+    }
+
+    out.push(')');
+    out
+}
+
+#[derive(Default)]
+struct TreePrinter {
+    out: String,
+    depth: usize,
+}
+
+impl TreePrinter {
+    /// Print a single node, with `fields` after its span.
+    fn line(&mut self, kind: &str, span: Span, fields: &str) {
+        let _ = write!(
+            self.out,
+            "{:indent$}{kind} {}..{}",
+            "",
+            span.start.0,
+            span.end.0,
+            indent = self.depth * 2
+        );
+
+        if !fields.is_empty() {
+            self.out.push(' ');
+            self.out.push_str(fields);
+        }
+
+        self.out.push('\n');
+    }
+
+    /// Print the children of the last node, one level deeper.
+    fn nested(&mut self, print: impl FnOnce(&mut Self)) {
+        self.depth += 1;
+        print(self);
+        self.depth -= 1;
+    }
+
+    /// Print attributes on lines of their own, before what they are on.
+    fn attributes(&mut self, attrs: &[Attribute]) {
+        for attr in attrs {
+            self.line("Attribute", attr.span, &attr_to_source(attr));
+        }
+    }
+
+    fn item(&mut self, item: &Item) {
+        self.attributes(&item.attrs);
+        match &item.data {
+            ItemKind::Func(func) => self.func(func, item.span),
+            ItemKind::Let(stmt) => self.let_stmt(stmt, item.span),
+            ItemKind::Struct(decl) => self.struct_decl(decl, item.span),
+            ItemKind::Enum(decl) => self.enum_decl(decl, item.span),
+            ItemKind::Trait(decl) => self.trait_decl(decl, item.span),
+            ItemKind::Impl(decl) => self.impl_decl(decl, item.span),
+            ItemKind::Macro(decl) => self.macro_decl(decl, item.span),
+            ItemKind::Import(import) => {
+                let path = path_to_string(&import.path.data);
+                self.line("Import", item.span, &path);
+            }
+            ItemKind::Error => self.line("Error", item.span, ""),
+        }
+    }
+
+    fn func(&mut self, func: &Func, span: Span) {
+        let fields =
+            format!("name={}{}", func.name.data, pub_flag(func.public));
+        self.line("Func", span, &fields);
+        self.nested(|p| {
+            p.type_params(&func.generics);
+            func.params.iter().for_each(|param| p.param(param));
+
+            if let Some(ret) = &func.ret {
+                p.line("Ret", ret.span, "");
+                p.nested(|p| p.ty(ret));
+            }
+
+            p.block(&func.body);
+        });
+    }
+
+    fn type_params(&mut self, params: &[TypeParam]) {
+        for param in params {
+            let fields = format!("name={}", param.data.name.data);
+            self.line("TypeParam", param.span, &fields);
+            self.nested(|p| {
+                let bounds = &param.data.bounds;
+                bounds.iter().for_each(|bound| p.trait_ref(bound));
+            });
+        }
+    }
+
+    fn trait_ref(&mut self, trait_ref: &TraitRef) {
+        let fields = format!(
+            "name={} args={}",
+            path_to_string(&trait_ref.data.path.data),
+            trait_ref.data.args.len()
+        );
+        self.line("TraitRef", trait_ref.span, &fields);
+        self.nested(|p| trait_ref.data.args.iter().for_each(|arg| p.ty(arg)));
+    }
+
+    fn param(&mut self, param: &Param) {
+        let fields = format!("name={}", param.data.name.data);
+        self.line("Param", param.span, &fields);
+        self.nested(|p| p.ty(&param.data.ty));
+    }
+
+    fn struct_decl(&mut self, decl: &Struct, span: Span) {
+        let fields = format!(
+            "name={} fields={}{}",
+            decl.name.data,
+            decl.fields.len(),
+            pub_flag(decl.public)
+        );
+        self.line("Struct", span, &fields);
+        self.nested(|p| {
+            p.type_params(&decl.generics);
+            decl.fields.iter().for_each(|field| p.field(field));
+        });
+    }
+
+    fn field(&mut self, field: &Field) {
+        let fields = format!("name={}", field.data.name.data);
+        self.line("FieldDecl", field.span, &fields);
+        self.nested(|p| p.ty(&field.data.ty));
+    }
+
+    fn enum_decl(&mut self, decl: &Enum, span: Span) {
+        let fields = format!(
+            "name={} variants={}{}",
+            decl.name.data,
+            decl.variants.len(),
+            pub_flag(decl.public)
+        );
+        self.line("Enum", span, &fields);
+        self.nested(|p| {
+            p.type_params(&decl.generics);
+            decl.variants.iter().for_each(|variant| p.variant(variant));
+        });
+    }
+
+    fn trait_decl(&mut self, decl: &Trait, span: Span) {
+        let fields = format!(
+            "name={} methods={}{}",
+            decl.name.data,
+            decl.methods.len(),
+            pub_flag(decl.public)
+        );
+        self.line("Trait", span, &fields);
+        self.nested(|p| {
+            p.type_params(&decl.generics);
+            decl.methods
+                .iter()
+                .for_each(|method| p.trait_method(method));
+        });
+    }
+
+    fn trait_method(&mut self, method: &TraitMethod) {
+        let mut fields = format!("name={}", method.data.name.data);
+        if method.data.default.is_some() {
+            fields.push_str(" default");
+        }
+
+        self.line("TraitMethod", method.span, &fields);
+        self.nested(|p| {
+            p.type_params(&method.data.generics);
+            method.data.params.iter().for_each(|param| p.param(param));
+
+            if let Some(ret) = &method.data.ret {
+                p.line("Ret", ret.span, "");
+                p.nested(|p| p.ty(ret));
+            }
+
+            if let Some(default) = &method.data.default {
+                p.block(default);
+            }
+        });
+    }
+
+    fn impl_decl(&mut self, decl: &Impl, span: Span) {
+        let fields = format!("methods={}", decl.methods.len());
+        self.line("Impl", span, &fields);
+        self.nested(|p| {
+            p.type_params(&decl.generics);
+            p.trait_ref(&decl.trait_ref);
+
+            p.line("SelfTy", decl.self_ty.span, "");
+            p.nested(|p| p.ty(&decl.self_ty));
+
+            for method in &decl.methods {
+                p.func(&method.data, method.span);
+            }
+        });
+    }
+
+    fn macro_decl(&mut self, decl: &Macro, span: Span) {
+        let fields =
+            format!("name={} rules={}", decl.name.data, decl.rules.len());
+        self.line("Macro", span, &fields);
+        self.nested(|p| {
+            for rule in &decl.rules {
+                let fields = format!(
+                    "({}) => {{ {} }}",
+                    tokens_to_source(&rule.data.matcher),
+                    tokens_to_source(&rule.data.body)
+                );
+                p.line("MacroRule", rule.span, &fields);
+            }
+        });
+    }
+
+    fn variant(&mut self, variant: &Variant) {
+        let mut fields = format!("name={}", variant.data.name.data);
+        match &variant.data.fields {
+            VariantFields::Unit => {}
+            VariantFields::Tuple(tys) => {
+                let _ = write!(fields, " elems={}", tys.len());
+            }
+            VariantFields::Named(named) => {
+                let _ = write!(fields, " fields={}", named.len());
+            }
+        }
+
+        self.line("Variant", variant.span, &fields);
+        self.nested(|p| {
+            match &variant.data.fields {
+                VariantFields::Unit => {}
+                VariantFields::Tuple(tys) => tys.iter().for_each(|ty| p.ty(ty)),
+                VariantFields::Named(named) => {
+                    named.iter().for_each(|field| p.field(field));
+                }
+            }
+
+            if let Some(discriminant) = &variant.data.discriminant {
+                p.line("Discriminant", discriminant.span, "");
+                p.nested(|p| p.expr(discriminant));
+            }
+        });
+    }
+
+    fn ty(&mut self, ty: &Ty) {
+        match &ty.data {
+            TyKind::Named(name) => self.line("Named", ty.span, name),
+            TyKind::Path(segments) => {
+                self.line("Path", ty.span, &path_to_string(segments));
+            }
+            TyKind::Mut(inner) => {
+                self.line("Mut", ty.span, "");
+                self.nested(|p| p.ty(inner));
+            }
+            TyKind::Generic { name, args } => {
+                let fields = format!("name={} args={}", name.data, args.len());
+                self.line("Generic", ty.span, &fields);
+                self.nested(|p| args.iter().for_each(|arg| p.ty(arg)));
+            }
+            TyKind::Array { elem, len } => {
+                self.line("Array", ty.span, "");
+                self.nested(|p| {
+                    p.ty(elem);
+                    p.expr(len);
+                });
+            }
+            TyKind::Slice(elem) => {
+                self.line("Slice", ty.span, "");
+                self.nested(|p| p.ty(elem));
+            }
+            TyKind::Tuple(elems) => {
+                let fields = format!("elems={}", elems.len());
+                self.line("Tuple", ty.span, &fields);
+                self.nested(|p| elems.iter().for_each(|elem| p.ty(elem)));
+            }
+            TyKind::Func { params, ret } => {
+                let fields = format!("params={}", params.len());
+                self.line("FuncType", ty.span, &fields);
+                self.nested(|p| {
+                    params.iter().for_each(|param| p.ty(param));
+                    p.line("Ret", ret.span, "");
+                    p.nested(|p| p.ty(ret));
+                });
+            }
+            TyKind::Ref { mutable, ty: inner } => {
+                self.line("Ref", ty.span, if *mutable { "mut" } else { "" });
+                self.nested(|p| p.ty(inner));
+            }
+            TyKind::Paren(inner) => {
+                self.line("Paren", ty.span, "");
+                self.nested(|p| p.ty(inner));
+            }
+            TyKind::Error => self.line("Error", ty.span, ""),
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.line("Block", block.span, "");
+        self.nested(|p| {
+            p.attributes(&block.data.attrs);
+            block.data.stmts.iter().for_each(|stmt| p.stmt(stmt));
+
+            if let Some(tail) = &block.data.tail {
+                p.line("Tail", tail.span, "");
+                p.nested(|p| p.expr(tail));
+            }
+        });
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        self.attributes(&stmt.attrs);
+        match &stmt.data {
+            StmtKind::Let(let_stmt) => self.let_stmt(let_stmt, stmt.span),
+            StmtKind::Return(value) => {
+                self.line("Return", stmt.span, "");
+                self.nested(|p| value.iter().for_each(|value| p.expr(value)));
+            }
+            StmtKind::Assign { target, op, value } => {
+                self.line("Assign", stmt.span, &format!("op={}", op.as_str()));
+                self.nested(|p| {
+                    p.expr(target);
+                    p.expr(value);
+                });
+            }
+            StmtKind::Break { label, value } => {
+                self.line("Break", stmt.span, &label_field(label));
+                self.nested(|p| value.iter().for_each(|value| p.expr(value)));
+            }
+            StmtKind::Continue { label } => {
+                self.line("Continue", stmt.span, &label_field(label));
+            }
+            StmtKind::Expr(expr) => {
+                self.line("ExprStmt", stmt.span, "");
+                self.nested(|p| p.expr(expr));
+            }
+            StmtKind::Error => self.line("Error", stmt.span, ""),
+        }
+    }
+
+    fn let_stmt(&mut self, stmt: &Let, span: Span) {
+        self.line("Let", span, &format!("name={}", stmt.name.data));
+        self.nested(|p| {
+            if let Some(ty) = &stmt.ty {
+                p.ty(ty);
+            }
+
+            p.expr(&stmt.value);
+        });
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        let span = expr.span;
+
+        match &expr.data {
+            ExprKind::Literal(literal) => {
+                self.line("Literal", span, &literal.to_source())
+            }
+            ExprKind::Ident(name) => self.line("Ident", span, name),
+            ExprKind::Path(segments) => {
+                self.line("Path", span, &path_to_string(segments))
+            }
+            ExprKind::Instantiate { path, args } => {
+                let name = path_to_string(&path.data);
+                let info = format!("name={name} args={}", args.len());
+                self.line("Instantiate", span, &info);
+                self.nested(|p| args.iter().for_each(|arg| p.ty(arg)));
+            }
+            ExprKind::MacroCall { name, args } => {
+                let call =
+                    format!("{}!({})", name.data, tokens_to_source(args));
+                self.line("MacroCall", span, &call);
+            }
+            ExprKind::Unary { op, operand } => {
+                self.line("Unary", span, &format!("op={op}"));
+                self.nested(|p| p.expr(operand));
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                self.line("Binary", span, &format!("op={op}"));
+                self.nested(|p| {
+                    p.expr(lhs);
+                    p.expr(rhs);
+                });
+            }
+            ExprKind::Call { callee, args } => {
+                self.line("Call", span, &format!("args={}", args.len()));
+                self.nested(|p| {
+                    p.expr(callee);
+                    args.iter().for_each(|arg| p.expr(arg));
+                });
+            }
+            ExprKind::Field { base, field } => {
+                self.line("Field", span, &format!("name={}", field.data));
+                self.nested(|p| p.expr(base));
+            }
+            ExprKind::StructLit { path, fields, base } => {
+                let name = path_to_string(&path.data);
+                let info = format!("name={name} fields={}", fields.len());
+                self.line("StructLit", span, &info);
+                self.nested(|p| {
+                    fields.iter().for_each(|init| p.field_init(init));
+
+                    if let Some(base) = base {
+                        p.line("Base", base.span, "");
+                        p.nested(|p| p.expr(base));
+                    }
+                });
+            }
+            ExprKind::Paren(inner) => {
+                self.line("Paren", span, "");
+                self.nested(|p| p.expr(inner));
+            }
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::If {
+                cond,
+                then,
+                else_branch,
+            } => {
+                self.line("If", span, "");
+                self.nested(|p| {
+                    p.expr(cond);
+                    p.block(then);
+
+                    if let Some(else_branch) = else_branch {
+                        p.line("Else", else_branch.span, "");
+                        p.nested(|p| p.expr(else_branch));
+                    }
+                });
+            }
+            ExprKind::While { label, cond, body } => {
+                self.line("While", span, &label_field(label));
+                self.nested(|p| {
+                    p.expr(cond);
+                    p.block(body);
+                });
+            }
+            ExprKind::Loop { label, body } => {
+                self.line("Loop", span, &label_field(label));
+                self.nested(|p| p.block(body));
+            }
+            ExprKind::For {
+                label,
+                binding,
+                iter,
+                body,
+            } => {
+                let mut fields = format!("binding={}", binding.data);
+                if label.is_some() {
+                    let _ = write!(fields, " {}", label_field(label));
+                }
+
+                self.line("For", span, &fields);
+                self.nested(|p| {
+                    p.expr(iter);
+                    p.block(body);
+                });
+            }
+            ExprKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let op = if *inclusive { "..=" } else { ".." };
+                self.line("Range", span, &format!("op={op}"));
+                self.nested(|p| {
+                    p.expr(start);
+                    p.expr(end);
+                });
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                self.line("Match", span, &format!("arms={}", arms.len()));
+                self.nested(|p| {
+                    p.expr(scrutinee);
+                    arms.iter().for_each(|arm| p.arm(arm));
+                });
+            }
+            ExprKind::Lambda { params, ret, body } => {
+                self.line("Lambda", span, &format!("params={}", params.len()));
+                self.nested(|p| {
+                    params.iter().for_each(|param| p.lambda_param(param));
+
+                    if let Some(ret) = ret {
+                        p.line("Ret", ret.span, "");
+                        p.nested(|p| p.ty(ret));
+                    }
+
+                    p.expr(body);
+                });
+            }
+            ExprKind::Error => self.line("Error", span, ""),
+        }
+    }
+
+    fn field_init(&mut self, init: &FieldInit) {
+        let fields = format!("name={}", init.data.name.data);
+        self.line("FieldInit", init.span, &fields);
+        self.nested(|p| init.data.value.iter().for_each(|value| p.expr(value)));
+    }
+
+    fn lambda_param(&mut self, param: &LambdaParam) {
+        let fields = format!("name={}", param.data.name.data);
+        self.line("LambdaParam", param.span, &fields);
+        self.nested(|p| param.data.ty.iter().for_each(|ty| p.ty(ty)));
+    }
+
+    fn arm(&mut self, arm: &Arm) {
+        self.line("Arm", arm.span, "");
+        self.nested(|p| {
+            p.pat(&arm.data.pat);
+
+            if let Some(guard) = &arm.data.guard {
+                p.line("Guard", guard.span, "");
+                p.nested(|p| p.expr(guard));
+            }
+
+            p.expr(&arm.data.body);
+        });
+    }
+
+    fn pat(&mut self, pat: &Pat) {
+        let span = pat.span;
+
+        match &pat.data {
+            PatKind::Wild => self.line("Wild", span, ""),
+            PatKind::Binding(name) => self.line("Binding", span, name),
+            PatKind::Path(segments) => {
+                self.line("Path", span, &path_to_string(segments))
+            }
+            PatKind::TupleStruct { path, elems } => {
+                let name = path_to_string(&path.data);
+                let fields = format!("name={name} elems={}", elems.len());
+                self.line("TupleStruct", span, &fields);
+                self.nested(|p| elems.iter().for_each(|elem| p.pat(elem)));
+            }
+            PatKind::Struct { path, fields, rest } => {
+                let name = path_to_string(&path.data);
+                let mut info = format!("name={name} fields={}", fields.len());
+                if *rest {
+                    info.push_str(" rest");
+                }
+
+                self.line("StructPat", span, &info);
+                self.nested(|p| {
+                    fields.iter().for_each(|field| p.field_pat(field));
+                });
+            }
+            PatKind::Literal(literal) => {
+                self.line("Literal", span, &literal.to_source())
+            }
+            PatKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let op = if *inclusive { "..=" } else { ".." };
+                self.line("Range", span, &format!("op={op}"));
+                self.nested(|p| {
+                    p.pat(start);
+                    p.pat(end);
+                });
+            }
+            PatKind::Tuple(elems) => {
+                let fields = format!("elems={}", elems.len());
+                self.line("Tuple", span, &fields);
+                self.nested(|p| elems.iter().for_each(|elem| p.pat(elem)));
+            }
+            PatKind::Paren(inner) => {
+                self.line("Paren", span, "");
+                self.nested(|p| p.pat(inner));
+            }
+            PatKind::Or(alts) => {
+                self.line("Or", span, &format!("alts={}", alts.len()));
+                self.nested(|p| alts.iter().for_each(|alt| p.pat(alt)));
+            }
+            PatKind::At { name, pat } => {
+                self.line("At", span, &format!("name={}", name.data));
+                self.nested(|p| p.pat(pat));
+            }
+            PatKind::Error => self.line("Error", span, ""),
+        }
+    }
+
+    fn field_pat(&mut self, field: &FieldPat) {
+        let fields = format!("name={}", field.data.name.data);
+        self.line("FieldPat", field.span, &fields);
+        self.nested(|p| field.data.pat.iter().for_each(|pat| p.pat(pat)));
+    }
+}
+
+/// The fields for the label of a loop, which are left empty when there
+/// isn't one.
+fn label_field(label: &Option<Ident>) -> String {
+    label
+        .as_ref()
+        .map_or_else(String::new, |label| format!("label={}", label.data))
+}
+
+/// Get ` pub` for a declaration declared with `pub`, to go after its fields.
+fn pub_flag(public: bool) -> &'static str {
+    if public {
+        " pub"
+    } else {
+        ""
+    }
+}
+
+/// Print the label of a loop, with a space before it, if there is one.
+fn sexpr_label(label: &Option<Ident>, out: &mut String) {
+    if let Some(label) = label {
+        let _ = write!(out, " '{}", label.data);
+    }
+}
+
+/// Render the tokens of a macro rule or call on a single line, spaced apart
+/// like they were written, with the line ends between them written as `;`.
+fn tokens_to_source(tokens: &[Spanned<Token>]) -> String {
+    let mut out = String::new();
+    let mut prev_end = None;
+
+    for tok in tokens {
+        if prev_end.is_some_and(|end| end != tok.span.start) {
+            out.push(' ');
+        }
+
+        match tok.data {
+            Token::VirtualSemi => out.push(';'),
+            ref tok => out.push_str(&tok.to_source()),
+        }
+        prev_end = Some(tok.span.end);
+    }
+
+    out
+}
+
+/// Render an attribute as source code, like `#[allow(unused)]`.
+fn attr_to_source(attr: &Attribute) -> String {
+    let bang = if attr.data.inner { "!" } else { "" };
+    let name = &attr.data.name;
+    let args = &attr.data.args;
+    let space = match args.first() {
+        Some(tok) if tok.span.start != name.span.end => " ",
+        _ => "",
+    };
+
+    format!("#{bang}[{}{space}{}]", name.data, tokens_to_source(args))
+}
+
+/// Print attributes as source code, each between `before` and `after`.
+fn sexpr_attributes(
+    attrs: &[Attribute],
+    before: &str,
+    after: &str,
+    out: &mut String,
+) {
+    for attr in attrs {
+        let _ = write!(out, "{before}{}{after}", attr_to_source(attr));
+    }
+}
+
+/// Print an item, wrapped in `(pub item)` when it is declared with `pub`, and
+/// after its attributes.
+fn sexpr_item(item: &Item, out: &mut String) {
+    sexpr_attributes(&item.attrs, "", " ", out);
+    if item.data.is_public() {
+        out.push_str("(pub ");
+    }
+
+    match &item.data {
+        ItemKind::Func(func) => sexpr_func(func, out),
+        ItemKind::Let(stmt) => sexpr_let(stmt, out),
+        ItemKind::Struct(decl) => {
+            let _ = write!(out, "(struct {} ", decl.name.data);
+            sexpr_generics(&decl.generics, out);
+            out.push_str("(fields");
+            for field in &decl.fields {
+                let _ = write!(out, " ({} ", field.data.name.data);
+                sexpr_ty(&field.data.ty, out);
+                out.push(')');
+            }
+            out.push_str("))");
+        }
+        // Variants without any values are printed as just their name
+        ItemKind::Enum(decl) => {
+            let _ = write!(out, "(enum {} ", decl.name.data);
+            sexpr_generics(&decl.generics, out);
+            out.push_str("(variants");
+            for variant in &decl.variants {
+                out.push(' ');
+                sexpr_variant(variant, out);
+            }
+            out.push_str("))");
+        }
+        ItemKind::Trait(decl) => {
+            let _ = write!(out, "(trait {}", decl.name.data);
+            if !decl.generics.is_empty() {
+                out.push(' ');
+                sexpr_generics(&decl.generics, out);
+                out.pop();
+            }
+
+            for method in &decl.methods {
+                let _ = write!(out, " (method {} ", method.data.name.data);
+                sexpr_generics(&method.data.generics, out);
+                sexpr_params(&method.data.params, out);
+
+                if let Some(ret) = &method.data.ret {
+                    out.push(' ');
+                    sexpr_ty(ret, out);
+                }
+                if let Some(default) = &method.data.default {
+                    out.push(' ');
+                    sexpr_block(default, out);
+                }
+                out.push(')');
+            }
+            out.push(')');
+        }
+        ItemKind::Impl(decl) => {
+            out.push_str("(impl ");
+            sexpr_generics(&decl.generics, out);
+            sexpr_trait_ref(&decl.trait_ref, out);
+            out.push(' ');
+            sexpr_ty(&decl.self_ty, out);
+
+            for method in &decl.methods {
+                out.push(' ');
+                sexpr_func(&method.data, out);
+            }
+            out.push(')');
+        }
+        ItemKind::Macro(decl) => {
+            let _ = write!(out, "(macro {}", decl.name.data);
+            for rule in &decl.rules {
+                let _ = write!(
+                    out,
+                    " (rule ({}) {{{}}})",
+                    tokens_to_source(&rule.data.matcher),
+                    tokens_to_source(&rule.data.body)
+                );
+            }
+            out.push(')');
+        }
+        ItemKind::Import(import) => {
+            let path = path_to_string(&import.path.data);
+            let _ = write!(out, "(import {path})");
+        }
+        ItemKind::Error => out.push_str("error"),
+    }
+
+    if item.data.is_public() {
+        out.push(')');
+    }
+}
+
+fn sexpr_func(func: &Func, out: &mut String) {
+    let _ = write!(out, "(func {} ", func.name.data);
+    sexpr_generics(&func.generics, out);
+    sexpr_params(&func.params, out);
+
+    if let Some(ret) = &func.ret {
+        out.push(' ');
+        sexpr_ty(ret, out);
+    }
+
+    out.push(' ');
+    sexpr_block(&func.body, out);
+    out.push(')');
+}
+
+fn sexpr_params(params: &[Param], out: &mut String) {
+    out.push_str("(params");
+    for param in params {
+        let _ = write!(out, " ({} ", param.data.name.data);
+        sexpr_ty(&param.data.ty, out);
+        out.push(')');
+    }
+    out.push(')');
+}
+
+/// Print a trait as just its path, or as `(Add T)` when it is given type
+/// arguments.
+fn sexpr_trait_ref(trait_ref: &TraitRef, out: &mut String) {
+    let path = path_to_string(&trait_ref.data.path.data);
+    if trait_ref.data.args.is_empty() {
+        return out.push_str(&path);
+    }
+
+    let _ = write!(out, "({path}");
+    sexpr_tys(&trait_ref.data.args, out);
+    out.push(')');
+}
+
+/// Print the type parameters of an item as `(generics T U) `, or nothing when
+/// it doesn't have any. A parameter with bounds is printed like
+/// `(T Display (Add T))`.
+fn sexpr_generics(params: &[TypeParam], out: &mut String) {
+    if params.is_empty() {
+        return;
+    }
+
+    out.push_str("(generics");
+    for param in params {
+        let name = &param.data.name.data;
+        if param.data.bounds.is_empty() {
+            let _ = write!(out, " {name}");
+            continue;
+        }
+
+        let _ = write!(out, " ({name}");
+        for bound in &param.data.bounds {
+            out.push(' ');
+            sexpr_trait_ref(bound, out);
+        }
+        out.push(')');
+    }
+    out.push_str(") ");
+}
+
+/// Print a variant, wrapped in `(= variant discriminant)` when it is given a
+/// discriminant.
+fn sexpr_variant(variant: &Variant, out: &mut String) {
+    let discriminant = variant.data.discriminant.as_ref();
+    if discriminant.is_some() {
+        out.push_str("(= ");
+    }
+
+    let name = &variant.data.name.data;
+    match &variant.data.fields {
+        VariantFields::Unit => out.push_str(name),
+        VariantFields::Tuple(tys) => {
+            let _ = write!(out, "({name}");
+            sexpr_tys(tys, out);
+            out.push(')');
+        }
+        VariantFields::Named(fields) => {
+            let _ = write!(out, "({name}");
+            for field in fields {
+                let _ = write!(out, " ({} ", field.data.name.data);
+                sexpr_ty(&field.data.ty, out);
+                out.push(')');
+            }
+            out.push(')');
+        }
+    }
+
+    if let Some(discriminant) = discriminant {
+        out.push(' ');
+        sexpr_expr(discriminant, out);
+        out.push(')');
+    }
+}
+
+fn sexpr_ty(ty: &Ty, out: &mut String) {
+    match &ty.data {
+        TyKind::Named(name) => out.push_str(name),
+        TyKind::Path(segments) => out.push_str(&path_to_string(segments)),
+        TyKind::Mut(inner) => {
+            out.push_str("(mut ");
+            sexpr_ty(inner, out);
+            out.push(')');
+        }
+        TyKind::Generic { name, args } => {
+            let _ = write!(out, "(generic {}", name.data);
+            sexpr_tys(args, out);
+            out.push(')');
+        }
+        TyKind::Array { elem, len } => {
+            out.push_str("(array ");
+            sexpr_ty(elem, out);
+            out.push(' ');
+            sexpr_expr(len, out);
+            out.push(')');
+        }
+        TyKind::Slice(elem) => {
+            out.push_str("(slice ");
+            sexpr_ty(elem, out);
+            out.push(')');
+        }
+        TyKind::Tuple(elems) => {
+            out.push_str("(tuple");
+            sexpr_tys(elems, out);
+            out.push(')');
+        }
+        TyKind::Func { params, ret } => {
+            out.push_str("(fn (params");
+            sexpr_tys(params, out);
+            out.push_str(") ");
+            sexpr_ty(ret, out);
+            out.push(')');
+        }
+        TyKind::Ref { mutable, ty } => {
+            out.push_str(if *mutable { "(ref mut " } else { "(ref " });
+            sexpr_ty(ty, out);
+            out.push(')');
+        }
+        TyKind::Paren(inner) => sexpr_ty(inner, out),
+        TyKind::Error => out.push_str("error"),
+    }
+}
+
+/// Print each of `tys`, with a space before each one.
+fn sexpr_tys(tys: &[Ty], out: &mut String) {
+    for ty in tys {
+        out.push(' ');
+        sexpr_ty(ty, out);
+    }
+}
+
+fn sexpr_block(block: &Block, out: &mut String) {
+    out.push_str("(block");
+    sexpr_attributes(&block.data.attrs, " ", "", out);
+
+    for stmt in &block.data.stmts {
+        out.push(' ');
+        sexpr_stmt(stmt, out);
+    }
+
+    if let Some(tail) = &block.data.tail {
+        out.push(' ');
+        sexpr_expr(tail, out);
+    }
+
+    out.push(')');
+}
+
+fn sexpr_stmt(stmt: &Stmt, out: &mut String) {
+    sexpr_attributes(&stmt.attrs, "", " ", out);
+    match &stmt.data {
+        StmtKind::Let(stmt) => sexpr_let(stmt, out),
+        StmtKind::Return(value) => {
+            out.push_str("(return");
+            if let Some(value) = value {
+                out.push(' ');
+                sexpr_expr(value, out);
+            }
+            out.push(')');
+        }
+        StmtKind::Assign { target, op, value } => {
+            let _ = write!(out, "({} ", op.as_str());
+            sexpr_expr(target, out);
+            out.push(' ');
+            sexpr_expr(value, out);
+            out.push(')');
+        }
+        StmtKind::Break { label, value } => {
+            out.push_str("(break");
+            sexpr_label(label, out);
+            if let Some(value) = value {
+                out.push(' ');
+                sexpr_expr(value, out);
+            }
+            out.push(')');
+        }
+        StmtKind::Continue { label } => {
+            out.push_str("(continue");
+            sexpr_label(label, out);
+            out.push(')');
+        }
+        // Expression statements are marked, so they can be told apart from
+        // the tail of the block
+        StmtKind::Expr(expr) => {
+            out.push_str("(stmt ");
+            sexpr_expr(expr, out);
+            out.push(')');
+        }
+        StmtKind::Error => out.push_str("error"),
+    }
+}
+
+fn sexpr_let(stmt: &Let, out: &mut String) {
+    let _ = write!(out, "(let {}", stmt.name.data);
+
+    if let Some(ty) = &stmt.ty {
+        out.push(' ');
+        sexpr_ty(ty, out);
+    }
+
+    out.push(' ');
+    sexpr_expr(&stmt.value, out);
+    out.push(')');
+}
+
+fn sexpr_expr(expr: &Expr, out: &mut String) {
+    match &expr.data {
+        ExprKind::Literal(literal) => out.push_str(&literal.to_source()),
+        ExprKind::Ident(name) => out.push_str(name),
+        ExprKind::Path(segments) => out.push_str(&path_to_string(segments)),
+        ExprKind::Instantiate { path, args } => {
+            let _ = write!(out, "(inst {}", path_to_string(&path.data));
+            sexpr_tys(args, out);
+            out.push(')');
+        }
+        ExprKind::MacroCall { name, args } => {
+            let _ = write!(out, "({}!", name.data);
+            if !args.is_empty() {
+                let _ = write!(out, " {}", tokens_to_source(args));
+            }
+            out.push(')');
+        }
+        ExprKind::Unary { op, operand } => {
+            let _ = write!(out, "({op} ");
+            sexpr_expr(operand, out);
+            out.push(')');
+        }
+        ExprKind::Binary { op, lhs, rhs } => {
+            let _ = write!(out, "({op} ");
+            sexpr_expr(lhs, out);
+            out.push(' ');
+            sexpr_expr(rhs, out);
+            out.push(')');
+        }
+        ExprKind::Call { callee, args } => {
+            out.push_str("(call ");
+            sexpr_expr(callee, out);
+            for arg in args {
+                out.push(' ');
+                sexpr_expr(arg, out);
+            }
+            out.push(')');
+        }
+        ExprKind::Field { base, field } => {
+            out.push_str("(. ");
+            sexpr_expr(base, out);
+            let _ = write!(out, " {})", field.data);
+        }
+        // Shorthand fields are printed as just their name
+        ExprKind::StructLit { path, fields, base } => {
+            let _ = write!(out, "(new {}", path_to_string(&path.data));
+            for init in fields {
+                match &init.data.value {
+                    Some(value) => {
+                        let _ = write!(out, " ({} ", init.data.name.data);
+                        sexpr_expr(value, out);
+                        out.push(')');
+                    }
+                    None => {
+                        let _ = write!(out, " {}", init.data.name.data);
+                    }
+                }
+            }
+            if let Some(base) = base {
+                out.push_str(" (.. ");
+                sexpr_expr(base, out);
+                out.push(')');
+            }
+            out.push(')');
+        }
+        // Parenthesis only group, which the nesting already shows
+        ExprKind::Paren(inner) => sexpr_expr(inner, out),
+        ExprKind::Block(block) => sexpr_block(block, out),
+        ExprKind::If {
+            cond,
+            then,
+            else_branch,
+        } => {
+            out.push_str("(if ");
+            sexpr_expr(cond, out);
+            out.push(' ');
+            sexpr_block(then, out);
+            if let Some(else_branch) = else_branch {
+                out.push(' ');
+                sexpr_expr(else_branch, out);
+            }
+            out.push(')');
+        }
+        ExprKind::While { label, cond, body } => {
+            out.push_str("(while");
+            sexpr_label(label, out);
+            out.push(' ');
+            sexpr_expr(cond, out);
+            out.push(' ');
+            sexpr_block(body, out);
+            out.push(')');
+        }
+        ExprKind::Loop { label, body } => {
+            out.push_str("(loop");
+            sexpr_label(label, out);
+            out.push(' ');
+            sexpr_block(body, out);
+            out.push(')');
+        }
+        ExprKind::For {
+            label,
+            binding,
+            iter,
+            body,
+        } => {
+            out.push_str("(for");
+            sexpr_label(label, out);
+            let _ = write!(out, " {} ", binding.data);
+            sexpr_expr(iter, out);
+            out.push(' ');
+            sexpr_block(body, out);
+            out.push(')');
+        }
+        ExprKind::Range {
+            start,
+            end,
+            inclusive,
+        } => {
+            out.push_str(if *inclusive { "(..= " } else { "(.. " });
+            sexpr_expr(start, out);
+            out.push(' ');
+            sexpr_expr(end, out);
+            out.push(')');
+        }
+        ExprKind::Match { scrutinee, arms } => {
+            out.push_str("(match ");
+            sexpr_expr(scrutinee, out);
+            for arm in arms {
+                out.push_str(" (arm ");
+                sexpr_pat(&arm.data.pat, out);
+                if let Some(guard) = &arm.data.guard {
+                    out.push_str(" (if ");
+                    sexpr_expr(guard, out);
+                    out.push(')');
+                }
+                out.push(' ');
+                sexpr_expr(&arm.data.body, out);
+                out.push(')');
+            }
+            out.push(')');
+        }
+        // Parameters without a type are printed as just their name
+        ExprKind::Lambda { params, ret, body } => {
+            out.push_str("(lambda (params");
+            for param in params {
+                match &param.data.ty {
+                    Some(ty) => {
+                        let _ = write!(out, " ({} ", param.data.name.data);
+                        sexpr_ty(ty, out);
+                        out.push(')');
+                    }
+                    None => {
+                        let _ = write!(out, " {}", param.data.name.data);
+                    }
+                }
+            }
+            out.push(')');
+
+            if let Some(ret) = ret {
+                out.push(' ');
+                sexpr_ty(ret, out);
+            }
+
+            out.push(' ');
+            sexpr_expr(body, out);
+            out.push(')');
+        }
+        ExprKind::Error => out.push_str("error"),
+    }
+}
+
+fn sexpr_pat(pat: &Pat, out: &mut String) {
+    match &pat.data {
+        PatKind::Wild => out.push('_'),
+        PatKind::Binding(name) => out.push_str(name),
+        PatKind::Path(segments) => out.push_str(&path_to_string(segments)),
+        PatKind::TupleStruct { path, elems } => {
+            let _ = write!(out, "({}", path_to_string(&path.data));
+            sexpr_pats(elems, out);
+            out.push(')');
+        }
+        // The fields are wrapped in braces, to tell them apart from the
+        // values of a tuple variant
+        PatKind::Struct { path, fields, rest } => {
+            let _ = write!(out, "({} {{", path_to_string(&path.data));
+            for (idx, field) in fields.iter().enumerate() {
+                if idx > 0 {
+                    out.push(' ');
+                }
+
+                match &field.data.pat {
+                    Some(pat) => {
+                        let _ = write!(out, "({} ", field.data.name.data);
+                        sexpr_pat(pat, out);
+                        out.push(')');
+                    }
+                    None => out.push_str(&field.data.name.data),
+                }
+            }
+            if *rest {
+                out.push_str(if fields.is_empty() { ".." } else { " .." });
+            }
+            out.push_str("})");
+        }
+        PatKind::Literal(literal) => out.push_str(&literal.to_source()),
+        PatKind::Range {
+            start,
+            end,
+            inclusive,
+        } => {
+            out.push_str(if *inclusive { "(..= " } else { "(.. " });
+            sexpr_pat(start, out);
+            out.push(' ');
+            sexpr_pat(end, out);
+            out.push(')');
+        }
+        PatKind::Tuple(elems) => {
+            out.push_str("(tuple");
+            sexpr_pats(elems, out);
+            out.push(')');
+        }
+        PatKind::Paren(inner) => sexpr_pat(inner, out),
+        PatKind::Or(alts) => {
+            out.push_str("(|");
+            sexpr_pats(alts, out);
+            out.push(')');
+        }
+        PatKind::At { name, pat } => {
+            let _ = write!(out, "(@ {} ", name.data);
+            sexpr_pat(pat, out);
+            out.push(')');
+        }
+        PatKind::Error => out.push_str("error"),
+    }
+}
+
+/// Print each of `pats`, with a space before each one.
+fn sexpr_pats(pats: &[Pat], out: &mut String) {
+    for pat in pats {
+        out.push(' ');
+        sexpr_pat(pat, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_tree() {
+        let (module, _) = parse("func f(a: mut u8) := {\n    g(a ** b)\n}");
+
+        let expected = vec![
+            "Module 0..38",
+            "  Func 0..38 name=f",
+            "    Param 7..16 name=a",
+            "      Mut 10..16",
+            "        Named 14..16 u8",
+            "    Block 21..38",
+            "      Tail 27..36",
+            "        Call 27..36 args=1",
+            "          Ident 27..28 g",
+            "          Binary 29..35 op=**",
+            "            Ident 29..30 a",
+            "            Ident 34..35 b",
+            "",
+        ];
+        assert_eq!(tree(&module), expected.join("\n"));
+    }
+
+    #[test]
+    fn test_sexpr_shows_composition_binding_tighter() {
+        let (module, _) = parse("let res := (addOne ** add (2, 3)) + 1");
+
+        assert_eq!(
+            sexpr(&module),
+            "(module (let res (+ (call (** addOne add) 2 3) 1)))"
+        );
+    }
+
+    #[test]
+    fn test_sexpr_statements() {
+        let (module, _) = parse(
+            "func f(a: usize) : usize = {\n    a += -0x1\n    f(a)\n    return\n}",
+        );
+
+        assert_eq!(
+            sexpr(&module),
+            "(module (func f (params (a usize)) usize \
+            (block (+= a -0x1) (stmt (call f a)) (return))))"
+        );
+    }
+
+    #[test]
+    fn test_literals_keep_their_base() {
+        let (module, _) = parse("let a := 0b101 + 0(3)12 + \"a\\n\" + b'\\0'");
+
+        assert_eq!(
+            sexpr(&module),
+            "(module (let a (+ (+ (+ 0b101 0(3)12) \"a\\n\") b'\\0')))"
+        );
+    }
+
+    #[test]
+    fn test_sexpr_types() {
+        let (module, _) = parse(
+            "func f(a: &mut [u8; 4], b: (Option<u8>,)) : (u8) -> [()] = {}",
+        );
+
+        assert_eq!(
+            sexpr(&module),
+            "(module (func f (params (a (ref mut (array u8 4))) \
+            (b (tuple (generic Option u8)))) (fn (params u8) (slice (tuple))) \
+            (block)))"
+        );
+    }
+
+    #[test]
+    fn test_sexpr_control_flow() {
+        let (module, _) = parse(
+            "func f() := {\n    'a: for i in 0..=n {\n        \
+            if i > 2 { break 'a } else if b { continue }\n    }\n}",
+        );
+
+        assert_eq!(
+            sexpr(&module),
+            "(module (func f (params) (block (for 'a i (..= 0 n) \
+            (block (if (> i 2) (block (break 'a)) \
+            (if b (block (continue)))))))))"
+        );
+    }
+
+    #[test]
+    fn test_sexpr_match() {
+        let (module, _) = parse(
+            "func f() := {\n    match a {\n        (0, _) | 1..=2 => b,\n        \
+            n @ -1 if n < c => { d }\n    }\n}",
+        );
+
+        assert_eq!(
+            sexpr(&module),
+            "(module (func f (params) (block (match a \
+            (arm (| (tuple 0 _) (..= 1 2)) b) \
+            (arm (@ n -1) (if (< n c)) (block d))))))"
+        );
+    }
+
+    #[test]
+    fn test_sexpr_structs() {
+        let (module, _) = parse(
+            "struct P { x: i32, y: i32 }\n\
+            func f(a: P) := { P { x: a.x, y, ..a } }",
+        );
+
+        assert_eq!(
+            sexpr(&module),
+            "(module (struct P (fields (x i32) (y i32))) \
+            (func f (params (a P)) (block (new P (x (. a x)) y (.. a)))))"
+        );
+    }
+
+    #[test]
+    fn test_tree_structs() {
+        let (module, _) = parse("let a := P { x: 1, ..b }.x");
+
+        let expected = vec![
+            "Module 0..26",
+            "  Let 0..26 name=a",
+            "    Field 9..26 name=x",
+            "      StructLit 9..24 name=P fields=1",
+            "        FieldInit 13..17 name=x",
+            "          Literal 16..17 1",
+            "        Base 21..22",
+            "          Ident 21..22 b",
+            "",
+        ];
+        assert_eq!(tree(&module), expected.join("\n"));
+    }
+}
@@ -0,0 +1,633 @@
+//! Walking the AST by reference.
+//!
+//! Every method of [`Visitor`] defaults to calling the matching `walk_*`
+//! function, which visits each child of the node. A pass only overrides the
+//! methods for the nodes it cares about, and calls the `walk_*` function from
+//! them to carry on into the children.
+
+use super::{
+    Arm, Attribute, Block, Enum, Expr, ExprKind, Field, FieldInit, FieldPat,
+    Func, Ident, Impl, Import, Item, ItemKind, LambdaParam, Let, Literal,
+    Macro, Module, Param, Pat, PatKind, Path, Stmt, StmtKind, Struct, Trait,
+    TraitMethod, TraitRef, Ty, TyKind, TypeParam, Variant, VariantFields,
+};
+
+pub trait Visitor {
+    fn visit_module(&mut self, module: &Module) {
+        walk_module(self, module);
+    }
+
+    fn visit_item(&mut self, item: &Item) {
+        walk_item(self, item);
+    }
+
+    fn visit_attribute(&mut self, attr: &Attribute) {
+        walk_attribute(self, attr);
+    }
+
+    fn visit_func(&mut self, func: &Func) {
+        walk_func(self, func);
+    }
+
+    fn visit_type_param(&mut self, param: &TypeParam) {
+        walk_type_param(self, param);
+    }
+
+    fn visit_param(&mut self, param: &Param) {
+        walk_param(self, param);
+    }
+
+    fn visit_struct(&mut self, decl: &Struct) {
+        walk_struct(self, decl);
+    }
+
+    fn visit_field(&mut self, field: &Field) {
+        walk_field(self, field);
+    }
+
+    fn visit_enum(&mut self, decl: &Enum) {
+        walk_enum(self, decl);
+    }
+
+    fn visit_variant(&mut self, variant: &Variant) {
+        walk_variant(self, variant);
+    }
+
+    fn visit_trait(&mut self, decl: &Trait) {
+        walk_trait(self, decl);
+    }
+
+    fn visit_trait_method(&mut self, method: &TraitMethod) {
+        walk_trait_method(self, method);
+    }
+
+    fn visit_impl(&mut self, decl: &Impl) {
+        walk_impl(self, decl);
+    }
+
+    fn visit_trait_ref(&mut self, trait_ref: &TraitRef) {
+        walk_trait_ref(self, trait_ref);
+    }
+
+    fn visit_import(&mut self, import: &Import) {
+        walk_import(self, import);
+    }
+
+    fn visit_macro(&mut self, decl: &Macro) {
+        walk_macro(self, decl);
+    }
+
+    fn visit_ty(&mut self, ty: &Ty) {
+        walk_ty(self, ty);
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        walk_block(self, block);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_let(&mut self, stmt: &Let) {
+        walk_let(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_field_init(&mut self, init: &FieldInit) {
+        walk_field_init(self, init);
+    }
+
+    fn visit_lambda_param(&mut self, param: &LambdaParam) {
+        walk_lambda_param(self, param);
+    }
+
+    fn visit_arm(&mut self, arm: &Arm) {
+        walk_arm(self, arm);
+    }
+
+    fn visit_pat(&mut self, pat: &Pat) {
+        walk_pat(self, pat);
+    }
+
+    fn visit_field_pat(&mut self, field: &FieldPat) {
+        walk_field_pat(self, field);
+    }
+
+    /// Visit a name being declared, like a function or a parameter. Names
+    /// used in expressions are [`ExprKind::Ident`] instead.
+    fn visit_ident(&mut self, _ident: &Ident) {}
+
+    /// Visit the path naming a struct or enum variant in a struct literal or
+    /// pattern, the generic function given type arguments, a trait in a bound
+    /// or impl, or the module of an import. Paths used on their own are
+    /// [`ExprKind::Path`], [`PatKind::Path`] and [`TyKind::Path`] instead.
+    fn visit_path(&mut self, _path: &Path) {}
+
+    fn visit_literal(&mut self, _literal: &Literal) {}
+}
+
+pub fn walk_module<V: Visitor + ?Sized>(visitor: &mut V, module: &Module) {
+    for attr in &module.attrs {
+        visitor.visit_attribute(attr);
+    }
+
+    for item in &module.items {
+        visitor.visit_item(item);
+    }
+}
+
+pub fn walk_item<V: Visitor + ?Sized>(visitor: &mut V, item: &Item) {
+    for attr in &item.attrs {
+        visitor.visit_attribute(attr);
+    }
+
+    match &item.data {
+        ItemKind::Func(func) => visitor.visit_func(func),
+        ItemKind::Let(stmt) => visitor.visit_let(stmt),
+        ItemKind::Struct(decl) => visitor.visit_struct(decl),
+        ItemKind::Enum(decl) => visitor.visit_enum(decl),
+        ItemKind::Trait(decl) => visitor.visit_trait(decl),
+        ItemKind::Impl(decl) => visitor.visit_impl(decl),
+        ItemKind::Import(import) => visitor.visit_import(import),
+        ItemKind::Macro(decl) => visitor.visit_macro(decl),
+        ItemKind::Error => {}
+    }
+}
+
+pub fn walk_attribute<V: Visitor + ?Sized>(visitor: &mut V, attr: &Attribute) {
+    visitor.visit_ident(&attr.data.name);
+}
+
+pub fn walk_func<V: Visitor + ?Sized>(visitor: &mut V, func: &Func) {
+    visitor.visit_ident(&func.name);
+
+    for param in &func.generics {
+        visitor.visit_type_param(param);
+    }
+
+    for param in &func.params {
+        visitor.visit_param(param);
+    }
+
+    if let Some(ret) = &func.ret {
+        visitor.visit_ty(ret);
+    }
+
+    visitor.visit_block(&func.body);
+}
+
+pub fn walk_type_param<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    param: &TypeParam,
+) {
+    visitor.visit_ident(&param.data.name);
+
+    for bound in &param.data.bounds {
+        visitor.visit_trait_ref(bound);
+    }
+}
+
+pub fn walk_param<V: Visitor + ?Sized>(visitor: &mut V, param: &Param) {
+    visitor.visit_ident(&param.data.name);
+    visitor.visit_ty(&param.data.ty);
+}
+
+pub fn walk_struct<V: Visitor + ?Sized>(visitor: &mut V, decl: &Struct) {
+    visitor.visit_ident(&decl.name);
+
+    for param in &decl.generics {
+        visitor.visit_type_param(param);
+    }
+
+    for field in &decl.fields {
+        visitor.visit_field(field);
+    }
+}
+
+pub fn walk_field<V: Visitor + ?Sized>(visitor: &mut V, field: &Field) {
+    visitor.visit_ident(&field.data.name);
+    visitor.visit_ty(&field.data.ty);
+}
+
+pub fn walk_enum<V: Visitor + ?Sized>(visitor: &mut V, decl: &Enum) {
+    visitor.visit_ident(&decl.name);
+
+    for param in &decl.generics {
+        visitor.visit_type_param(param);
+    }
+
+    for variant in &decl.variants {
+        visitor.visit_variant(variant);
+    }
+}
+
+pub fn walk_variant<V: Visitor + ?Sized>(visitor: &mut V, variant: &Variant) {
+    visitor.visit_ident(&variant.data.name);
+
+    match &variant.data.fields {
+        VariantFields::Unit => {}
+        VariantFields::Tuple(tys) => {
+            tys.iter().for_each(|ty| visitor.visit_ty(ty));
+        }
+        VariantFields::Named(fields) => {
+            fields.iter().for_each(|field| visitor.visit_field(field));
+        }
+    }
+
+    if let Some(discriminant) = &variant.data.discriminant {
+        visitor.visit_expr(discriminant);
+    }
+}
+
+pub fn walk_trait<V: Visitor + ?Sized>(visitor: &mut V, decl: &Trait) {
+    visitor.visit_ident(&decl.name);
+
+    for param in &decl.generics {
+        visitor.visit_type_param(param);
+    }
+
+    for method in &decl.methods {
+        visitor.visit_trait_method(method);
+    }
+}
+
+pub fn walk_trait_method<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    method: &TraitMethod,
+) {
+    visitor.visit_ident(&method.data.name);
+
+    for param in &method.data.generics {
+        visitor.visit_type_param(param);
+    }
+
+    for param in &method.data.params {
+        visitor.visit_param(param);
+    }
+
+    if let Some(ret) = &method.data.ret {
+        visitor.visit_ty(ret);
+    }
+
+    if let Some(default) = &method.data.default {
+        visitor.visit_block(default);
+    }
+}
+
+pub fn walk_impl<V: Visitor + ?Sized>(visitor: &mut V, decl: &Impl) {
+    for param in &decl.generics {
+        visitor.visit_type_param(param);
+    }
+
+    visitor.visit_trait_ref(&decl.trait_ref);
+    visitor.visit_ty(&decl.self_ty);
+
+    for method in &decl.methods {
+        visitor.visit_func(&method.data);
+    }
+}
+
+pub fn walk_trait_ref<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    trait_ref: &TraitRef,
+) {
+    visitor.visit_path(&trait_ref.data.path);
+
+    for arg in &trait_ref.data.args {
+        visitor.visit_ty(arg);
+    }
+}
+
+pub fn walk_import<V: Visitor + ?Sized>(visitor: &mut V, import: &Import) {
+    visitor.visit_path(&import.path);
+}
+
+/// Only visits the name of the macro, as its rules are kept as tokens.
+pub fn walk_macro<V: Visitor + ?Sized>(visitor: &mut V, decl: &Macro) {
+    visitor.visit_ident(&decl.name);
+}
+
+pub fn walk_ty<V: Visitor + ?Sized>(visitor: &mut V, ty: &Ty) {
+    match &ty.data {
+        TyKind::Mut(inner)
+        | TyKind::Slice(inner)
+        | TyKind::Ref { ty: inner, .. }
+        | TyKind::Paren(inner) => visitor.visit_ty(inner),
+        TyKind::Generic { name, args } => {
+            visitor.visit_ident(name);
+            args.iter().for_each(|arg| visitor.visit_ty(arg));
+        }
+        TyKind::Array { elem, len } => {
+            visitor.visit_ty(elem);
+            visitor.visit_expr(len);
+        }
+        TyKind::Tuple(elems) => {
+            elems.iter().for_each(|elem| visitor.visit_ty(elem));
+        }
+        TyKind::Func { params, ret } => {
+            params.iter().for_each(|param| visitor.visit_ty(param));
+            visitor.visit_ty(ret);
+        }
+        TyKind::Named(_) | TyKind::Path(_) | TyKind::Error => {}
+    }
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &Block) {
+    for attr in &block.data.attrs {
+        visitor.visit_attribute(attr);
+    }
+
+    for stmt in &block.data.stmts {
+        visitor.visit_stmt(stmt);
+    }
+
+    if let Some(tail) = &block.data.tail {
+        visitor.visit_expr(tail);
+    }
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    for attr in &stmt.attrs {
+        visitor.visit_attribute(attr);
+    }
+
+    match &stmt.data {
+        StmtKind::Let(stmt) => visitor.visit_let(stmt),
+        StmtKind::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+        StmtKind::Assign { target, value, .. } => {
+            visitor.visit_expr(target);
+            visitor.visit_expr(value);
+        }
+        StmtKind::Break { label, value } => {
+            if let Some(label) = label {
+                visitor.visit_ident(label);
+            }
+
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+        StmtKind::Continue { label } => {
+            if let Some(label) = label {
+                visitor.visit_ident(label);
+            }
+        }
+        StmtKind::Expr(expr) => visitor.visit_expr(expr),
+        StmtKind::Error => {}
+    }
+}
+
+pub fn walk_let<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Let) {
+    visitor.visit_ident(&stmt.name);
+
+    if let Some(ty) = &stmt.ty {
+        visitor.visit_ty(ty);
+    }
+
+    visitor.visit_expr(&stmt.value);
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match &expr.data {
+        ExprKind::Literal(literal) => visitor.visit_literal(literal),
+        ExprKind::Instantiate { path, args } => {
+            visitor.visit_path(path);
+            args.iter().for_each(|arg| visitor.visit_ty(arg));
+        }
+        ExprKind::Unary { operand, .. } => visitor.visit_expr(operand),
+        ExprKind::Binary { lhs, rhs, .. } => {
+            visitor.visit_expr(lhs);
+            visitor.visit_expr(rhs);
+        }
+        ExprKind::Call { callee, args } => {
+            visitor.visit_expr(callee);
+
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprKind::Field { base, field } => {
+            visitor.visit_expr(base);
+            visitor.visit_ident(field);
+        }
+        ExprKind::StructLit { path, fields, base } => {
+            visitor.visit_path(path);
+
+            for init in fields {
+                visitor.visit_field_init(init);
+            }
+
+            if let Some(base) = base {
+                visitor.visit_expr(base);
+            }
+        }
+        ExprKind::Paren(inner) => visitor.visit_expr(inner),
+        ExprKind::Block(block) => visitor.visit_block(block),
+        ExprKind::If {
+            cond,
+            then,
+            else_branch,
+        } => {
+            visitor.visit_expr(cond);
+            visitor.visit_block(then);
+
+            if let Some(else_branch) = else_branch {
+                visitor.visit_expr(else_branch);
+            }
+        }
+        ExprKind::While { label, cond, body } => {
+            if let Some(label) = label {
+                visitor.visit_ident(label);
+            }
+
+            visitor.visit_expr(cond);
+            visitor.visit_block(body);
+        }
+        ExprKind::Loop { label, body } => {
+            if let Some(label) = label {
+                visitor.visit_ident(label);
+            }
+
+            visitor.visit_block(body);
+        }
+        ExprKind::For {
+            label,
+            binding,
+            iter,
+            body,
+        } => {
+            if let Some(label) = label {
+                visitor.visit_ident(label);
+            }
+
+            visitor.visit_ident(binding);
+            visitor.visit_expr(iter);
+            visitor.visit_block(body);
+        }
+        ExprKind::Range { start, end, .. } => {
+            visitor.visit_expr(start);
+            visitor.visit_expr(end);
+        }
+        ExprKind::Match { scrutinee, arms } => {
+            visitor.visit_expr(scrutinee);
+
+            for arm in arms {
+                visitor.visit_arm(arm);
+            }
+        }
+        ExprKind::Lambda { params, ret, body } => {
+            for param in params {
+                visitor.visit_lambda_param(param);
+            }
+
+            if let Some(ret) = ret {
+                visitor.visit_ty(ret);
+            }
+
+            visitor.visit_expr(body);
+        }
+        ExprKind::Ident(_)
+        | ExprKind::Path(_)
+        | ExprKind::MacroCall { .. }
+        | ExprKind::Error => {}
+    }
+}
+
+pub fn walk_field_init<V: Visitor + ?Sized>(visitor: &mut V, init: &FieldInit) {
+    visitor.visit_ident(&init.data.name);
+
+    if let Some(value) = &init.data.value {
+        visitor.visit_expr(value);
+    }
+}
+
+pub fn walk_lambda_param<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    param: &LambdaParam,
+) {
+    visitor.visit_ident(&param.data.name);
+
+    if let Some(ty) = &param.data.ty {
+        visitor.visit_ty(ty);
+    }
+}
+
+pub fn walk_arm<V: Visitor + ?Sized>(visitor: &mut V, arm: &Arm) {
+    visitor.visit_pat(&arm.data.pat);
+
+    if let Some(guard) = &arm.data.guard {
+        visitor.visit_expr(guard);
+    }
+
+    visitor.visit_expr(&arm.data.body);
+}
+
+pub fn walk_pat<V: Visitor + ?Sized>(visitor: &mut V, pat: &Pat) {
+    match &pat.data {
+        PatKind::Literal(literal) => visitor.visit_literal(literal),
+        PatKind::Range { start, end, .. } => {
+            visitor.visit_pat(start);
+            visitor.visit_pat(end);
+        }
+        PatKind::Tuple(pats) | PatKind::Or(pats) => {
+            pats.iter().for_each(|pat| visitor.visit_pat(pat));
+        }
+        PatKind::Paren(inner) => visitor.visit_pat(inner),
+        PatKind::At { name, pat } => {
+            visitor.visit_ident(name);
+            visitor.visit_pat(pat);
+        }
+        PatKind::TupleStruct { path, elems } => {
+            visitor.visit_path(path);
+            elems.iter().for_each(|elem| visitor.visit_pat(elem));
+        }
+        PatKind::Struct { path, fields, .. } => {
+            visitor.visit_path(path);
+            fields
+                .iter()
+                .for_each(|field| visitor.visit_field_pat(field));
+        }
+        PatKind::Wild
+        | PatKind::Binding(_)
+        | PatKind::Path(_)
+        | PatKind::Error => {}
+    }
+}
+
+pub fn walk_field_pat<V: Visitor + ?Sized>(visitor: &mut V, field: &FieldPat) {
+    visitor.visit_ident(&field.data.name);
+
+    if let Some(pat) = &field.data.pat {
+        visitor.visit_pat(pat);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec, vec::Vec};
+
+    use super::*;
+    use crate::parser::parse;
+
+    /// Collects every name used in an expression, in the order they appear.
+    #[derive(Default)]
+    struct Names(Vec<String>);
+
+    impl Visitor for Names {
+        fn visit_expr(&mut self, expr: &Expr) {
+            if let ExprKind::Ident(name) = &expr.data {
+                self.0.push(name.clone());
+            }
+
+            walk_expr(self, expr);
+        }
+    }
+
+    #[test]
+    fn test_visits_children_in_order() {
+        let (module, _) = parse(
+            "func f(a: usize) := {\n    let b := g(a, -c)\n    b ** { d }\n}",
+        );
+
+        let mut names = Names::default();
+        names.visit_module(&module);
+
+        assert_eq!(names.0, vec!["g", "a", "c", "b", "d"]);
+    }
+
+    #[test]
+    fn test_not_walking_skips_children() {
+        #[derive(Default)]
+        struct Funcs {
+            names: Vec<String>,
+            exprs: usize,
+        }
+
+        impl Visitor for Funcs {
+            fn visit_func(&mut self, func: &Func) {
+                self.names.push(func.name.data.clone());
+            }
+
+            fn visit_expr(&mut self, expr: &Expr) {
+                self.exprs += 1;
+                walk_expr(self, expr);
+            }
+        }
+
+        let (module, _) = parse("func f() := { a + b }\nlet c := d");
+
+        let mut funcs = Funcs::default();
+        funcs.visit_module(&module);
+
+        assert_eq!(funcs.names, vec!["f"]);
+        assert_eq!(funcs.exprs, 1);
+    }
+}
@@ -0,0 +1,654 @@
+//! Walking the AST by mutable reference, to change it in place.
+//!
+//! This works the same as [`Visitor`](super::visit::Visitor), with every
+//! method and `walk_*` function ending in `_mut`, so that a type can
+//! implement both.
+
+use super::{
+    Arm, Attribute, Block, Enum, Expr, ExprKind, Field, FieldInit, FieldPat,
+    Func, Ident, Impl, Import, Item, ItemKind, LambdaParam, Let, Literal,
+    Macro, Module, Param, Pat, PatKind, Path, Stmt, StmtKind, Struct, Trait,
+    TraitMethod, TraitRef, Ty, TyKind, TypeParam, Variant, VariantFields,
+};
+
+pub trait VisitorMut {
+    fn visit_module_mut(&mut self, module: &mut Module) {
+        walk_module_mut(self, module);
+    }
+
+    fn visit_item_mut(&mut self, item: &mut Item) {
+        walk_item_mut(self, item);
+    }
+
+    fn visit_attribute_mut(&mut self, attr: &mut Attribute) {
+        walk_attribute_mut(self, attr);
+    }
+
+    fn visit_func_mut(&mut self, func: &mut Func) {
+        walk_func_mut(self, func);
+    }
+
+    fn visit_type_param_mut(&mut self, param: &mut TypeParam) {
+        walk_type_param_mut(self, param);
+    }
+
+    fn visit_param_mut(&mut self, param: &mut Param) {
+        walk_param_mut(self, param);
+    }
+
+    fn visit_struct_mut(&mut self, decl: &mut Struct) {
+        walk_struct_mut(self, decl);
+    }
+
+    fn visit_field_mut(&mut self, field: &mut Field) {
+        walk_field_mut(self, field);
+    }
+
+    fn visit_enum_mut(&mut self, decl: &mut Enum) {
+        walk_enum_mut(self, decl);
+    }
+
+    fn visit_variant_mut(&mut self, variant: &mut Variant) {
+        walk_variant_mut(self, variant);
+    }
+
+    fn visit_trait_mut(&mut self, decl: &mut Trait) {
+        walk_trait_mut(self, decl);
+    }
+
+    fn visit_trait_method_mut(&mut self, method: &mut TraitMethod) {
+        walk_trait_method_mut(self, method);
+    }
+
+    fn visit_impl_mut(&mut self, decl: &mut Impl) {
+        walk_impl_mut(self, decl);
+    }
+
+    fn visit_trait_ref_mut(&mut self, trait_ref: &mut TraitRef) {
+        walk_trait_ref_mut(self, trait_ref);
+    }
+
+    fn visit_import_mut(&mut self, import: &mut Import) {
+        walk_import_mut(self, import);
+    }
+
+    fn visit_macro_mut(&mut self, decl: &mut Macro) {
+        walk_macro_mut(self, decl);
+    }
+
+    fn visit_ty_mut(&mut self, ty: &mut Ty) {
+        walk_ty_mut(self, ty);
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        walk_block_mut(self, block);
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt);
+    }
+
+    fn visit_let_mut(&mut self, stmt: &mut Let) {
+        walk_let_mut(self, stmt);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+    }
+
+    fn visit_field_init_mut(&mut self, init: &mut FieldInit) {
+        walk_field_init_mut(self, init);
+    }
+
+    fn visit_lambda_param_mut(&mut self, param: &mut LambdaParam) {
+        walk_lambda_param_mut(self, param);
+    }
+
+    fn visit_arm_mut(&mut self, arm: &mut Arm) {
+        walk_arm_mut(self, arm);
+    }
+
+    fn visit_pat_mut(&mut self, pat: &mut Pat) {
+        walk_pat_mut(self, pat);
+    }
+
+    fn visit_field_pat_mut(&mut self, field: &mut FieldPat) {
+        walk_field_pat_mut(self, field);
+    }
+
+    /// Visit a name being declared, like a function or a parameter. Names
+    /// used in expressions are [`ExprKind::Ident`] instead.
+    fn visit_ident_mut(&mut self, _ident: &mut Ident) {}
+
+    /// Visit the path naming a struct or enum variant in a struct literal or
+    /// pattern, the generic function given type arguments, a trait in a bound
+    /// or impl, or the module of an import. Paths used on their own are
+    /// [`ExprKind::Path`], [`PatKind::Path`] and [`TyKind::Path`] instead.
+    fn visit_path_mut(&mut self, _path: &mut Path) {}
+
+    fn visit_literal_mut(&mut self, _literal: &mut Literal) {}
+}
+
+pub fn walk_module_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    module: &mut Module,
+) {
+    for attr in &mut module.attrs {
+        visitor.visit_attribute_mut(attr);
+    }
+
+    for item in &mut module.items {
+        visitor.visit_item_mut(item);
+    }
+}
+
+pub fn walk_item_mut<V: VisitorMut + ?Sized>(visitor: &mut V, item: &mut Item) {
+    for attr in &mut item.attrs {
+        visitor.visit_attribute_mut(attr);
+    }
+
+    match &mut item.data {
+        ItemKind::Func(func) => visitor.visit_func_mut(func),
+        ItemKind::Let(stmt) => visitor.visit_let_mut(stmt),
+        ItemKind::Struct(decl) => visitor.visit_struct_mut(decl),
+        ItemKind::Enum(decl) => visitor.visit_enum_mut(decl),
+        ItemKind::Trait(decl) => visitor.visit_trait_mut(decl),
+        ItemKind::Impl(decl) => visitor.visit_impl_mut(decl),
+        ItemKind::Import(import) => visitor.visit_import_mut(import),
+        ItemKind::Macro(decl) => visitor.visit_macro_mut(decl),
+        ItemKind::Error => {}
+    }
+}
+
+pub fn walk_attribute_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    attr: &mut Attribute,
+) {
+    visitor.visit_ident_mut(&mut attr.data.name);
+}
+
+pub fn walk_func_mut<V: VisitorMut + ?Sized>(visitor: &mut V, func: &mut Func) {
+    visitor.visit_ident_mut(&mut func.name);
+
+    for param in &mut func.generics {
+        visitor.visit_type_param_mut(param);
+    }
+
+    for param in &mut func.params {
+        visitor.visit_param_mut(param);
+    }
+
+    if let Some(ret) = &mut func.ret {
+        visitor.visit_ty_mut(ret);
+    }
+
+    visitor.visit_block_mut(&mut func.body);
+}
+
+pub fn walk_type_param_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    param: &mut TypeParam,
+) {
+    visitor.visit_ident_mut(&mut param.data.name);
+
+    for bound in &mut param.data.bounds {
+        visitor.visit_trait_ref_mut(bound);
+    }
+}
+
+pub fn walk_param_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    param: &mut Param,
+) {
+    visitor.visit_ident_mut(&mut param.data.name);
+    visitor.visit_ty_mut(&mut param.data.ty);
+}
+
+pub fn walk_struct_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    decl: &mut Struct,
+) {
+    visitor.visit_ident_mut(&mut decl.name);
+
+    for param in &mut decl.generics {
+        visitor.visit_type_param_mut(param);
+    }
+
+    for field in &mut decl.fields {
+        visitor.visit_field_mut(field);
+    }
+}
+
+pub fn walk_field_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    field: &mut Field,
+) {
+    visitor.visit_ident_mut(&mut field.data.name);
+    visitor.visit_ty_mut(&mut field.data.ty);
+}
+
+pub fn walk_enum_mut<V: VisitorMut + ?Sized>(visitor: &mut V, decl: &mut Enum) {
+    visitor.visit_ident_mut(&mut decl.name);
+
+    for param in &mut decl.generics {
+        visitor.visit_type_param_mut(param);
+    }
+
+    for variant in &mut decl.variants {
+        visitor.visit_variant_mut(variant);
+    }
+}
+
+pub fn walk_variant_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    variant: &mut Variant,
+) {
+    visitor.visit_ident_mut(&mut variant.data.name);
+
+    match &mut variant.data.fields {
+        VariantFields::Unit => {}
+        VariantFields::Tuple(tys) => {
+            tys.iter_mut().for_each(|ty| visitor.visit_ty_mut(ty));
+        }
+        VariantFields::Named(fields) => {
+            fields
+                .iter_mut()
+                .for_each(|field| visitor.visit_field_mut(field));
+        }
+    }
+
+    if let Some(discriminant) = &mut variant.data.discriminant {
+        visitor.visit_expr_mut(discriminant);
+    }
+}
+
+pub fn walk_trait_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    decl: &mut Trait,
+) {
+    visitor.visit_ident_mut(&mut decl.name);
+
+    for param in &mut decl.generics {
+        visitor.visit_type_param_mut(param);
+    }
+
+    for method in &mut decl.methods {
+        visitor.visit_trait_method_mut(method);
+    }
+}
+
+pub fn walk_trait_method_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    method: &mut TraitMethod,
+) {
+    visitor.visit_ident_mut(&mut method.data.name);
+
+    for param in &mut method.data.generics {
+        visitor.visit_type_param_mut(param);
+    }
+
+    for param in &mut method.data.params {
+        visitor.visit_param_mut(param);
+    }
+
+    if let Some(ret) = &mut method.data.ret {
+        visitor.visit_ty_mut(ret);
+    }
+
+    if let Some(default) = &mut method.data.default {
+        visitor.visit_block_mut(default);
+    }
+}
+
+pub fn walk_impl_mut<V: VisitorMut + ?Sized>(visitor: &mut V, decl: &mut Impl) {
+    for param in &mut decl.generics {
+        visitor.visit_type_param_mut(param);
+    }
+
+    visitor.visit_trait_ref_mut(&mut decl.trait_ref);
+    visitor.visit_ty_mut(&mut decl.self_ty);
+
+    for method in &mut decl.methods {
+        visitor.visit_func_mut(&mut method.data);
+    }
+}
+
+pub fn walk_trait_ref_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    trait_ref: &mut TraitRef,
+) {
+    visitor.visit_path_mut(&mut trait_ref.data.path);
+
+    for arg in &mut trait_ref.data.args {
+        visitor.visit_ty_mut(arg);
+    }
+}
+
+pub fn walk_macro_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    decl: &mut Macro,
+) {
+    visitor.visit_ident_mut(&mut decl.name);
+}
+
+pub fn walk_import_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    import: &mut Import,
+) {
+    visitor.visit_path_mut(&mut import.path);
+}
+
+pub fn walk_ty_mut<V: VisitorMut + ?Sized>(visitor: &mut V, ty: &mut Ty) {
+    match &mut ty.data {
+        TyKind::Mut(inner)
+        | TyKind::Slice(inner)
+        | TyKind::Ref { ty: inner, .. }
+        | TyKind::Paren(inner) => visitor.visit_ty_mut(inner),
+        TyKind::Generic { name, args } => {
+            visitor.visit_ident_mut(name);
+            args.iter_mut().for_each(|arg| visitor.visit_ty_mut(arg));
+        }
+        TyKind::Array { elem, len } => {
+            visitor.visit_ty_mut(elem);
+            visitor.visit_expr_mut(len);
+        }
+        TyKind::Tuple(elems) => {
+            elems.iter_mut().for_each(|elem| visitor.visit_ty_mut(elem));
+        }
+        TyKind::Func { params, ret } => {
+            params
+                .iter_mut()
+                .for_each(|param| visitor.visit_ty_mut(param));
+            visitor.visit_ty_mut(ret);
+        }
+        TyKind::Named(_) | TyKind::Path(_) | TyKind::Error => {}
+    }
+}
+
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    block: &mut Block,
+) {
+    for attr in &mut block.data.attrs {
+        visitor.visit_attribute_mut(attr);
+    }
+
+    for stmt in &mut block.data.stmts {
+        visitor.visit_stmt_mut(stmt);
+    }
+
+    if let Some(tail) = &mut block.data.tail {
+        visitor.visit_expr_mut(tail);
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut Stmt) {
+    for attr in &mut stmt.attrs {
+        visitor.visit_attribute_mut(attr);
+    }
+
+    match &mut stmt.data {
+        StmtKind::Let(stmt) => visitor.visit_let_mut(stmt),
+        StmtKind::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_expr_mut(value);
+            }
+        }
+        StmtKind::Assign { target, value, .. } => {
+            visitor.visit_expr_mut(target);
+            visitor.visit_expr_mut(value);
+        }
+        StmtKind::Break { label, value } => {
+            if let Some(label) = label {
+                visitor.visit_ident_mut(label);
+            }
+
+            if let Some(value) = value {
+                visitor.visit_expr_mut(value);
+            }
+        }
+        StmtKind::Continue { label } => {
+            if let Some(label) = label {
+                visitor.visit_ident_mut(label);
+            }
+        }
+        StmtKind::Expr(expr) => visitor.visit_expr_mut(expr),
+        StmtKind::Error => {}
+    }
+}
+
+pub fn walk_let_mut<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut Let) {
+    visitor.visit_ident_mut(&mut stmt.name);
+
+    if let Some(ty) = &mut stmt.ty {
+        visitor.visit_ty_mut(ty);
+    }
+
+    visitor.visit_expr_mut(&mut stmt.value);
+}
+
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+    match &mut expr.data {
+        ExprKind::Literal(literal) => visitor.visit_literal_mut(literal),
+        ExprKind::Instantiate { path, args } => {
+            visitor.visit_path_mut(path);
+            args.iter_mut().for_each(|arg| visitor.visit_ty_mut(arg));
+        }
+        ExprKind::Unary { operand, .. } => visitor.visit_expr_mut(operand),
+        ExprKind::Binary { lhs, rhs, .. } => {
+            visitor.visit_expr_mut(lhs);
+            visitor.visit_expr_mut(rhs);
+        }
+        ExprKind::Call { callee, args } => {
+            visitor.visit_expr_mut(callee);
+
+            for arg in args {
+                visitor.visit_expr_mut(arg);
+            }
+        }
+        ExprKind::Field { base, field } => {
+            visitor.visit_expr_mut(base);
+            visitor.visit_ident_mut(field);
+        }
+        ExprKind::StructLit { path, fields, base } => {
+            visitor.visit_path_mut(path);
+
+            for init in fields {
+                visitor.visit_field_init_mut(init);
+            }
+
+            if let Some(base) = base {
+                visitor.visit_expr_mut(base);
+            }
+        }
+        ExprKind::Paren(inner) => visitor.visit_expr_mut(inner),
+        ExprKind::Block(block) => visitor.visit_block_mut(block),
+        ExprKind::If {
+            cond,
+            then,
+            else_branch,
+        } => {
+            visitor.visit_expr_mut(cond);
+            visitor.visit_block_mut(then);
+
+            if let Some(else_branch) = else_branch {
+                visitor.visit_expr_mut(else_branch);
+            }
+        }
+        ExprKind::While { label, cond, body } => {
+            if let Some(label) = label {
+                visitor.visit_ident_mut(label);
+            }
+
+            visitor.visit_expr_mut(cond);
+            visitor.visit_block_mut(body);
+        }
+        ExprKind::Loop { label, body } => {
+            if let Some(label) = label {
+                visitor.visit_ident_mut(label);
+            }
+
+            visitor.visit_block_mut(body);
+        }
+        ExprKind::For {
+            label,
+            binding,
+            iter,
+            body,
+        } => {
+            if let Some(label) = label {
+                visitor.visit_ident_mut(label);
+            }
+
+            visitor.visit_ident_mut(binding);
+            visitor.visit_expr_mut(iter);
+            visitor.visit_block_mut(body);
+        }
+        ExprKind::Range { start, end, .. } => {
+            visitor.visit_expr_mut(start);
+            visitor.visit_expr_mut(end);
+        }
+        ExprKind::Match { scrutinee, arms } => {
+            visitor.visit_expr_mut(scrutinee);
+
+            for arm in arms {
+                visitor.visit_arm_mut(arm);
+            }
+        }
+        ExprKind::Lambda { params, ret, body } => {
+            for param in params {
+                visitor.visit_lambda_param_mut(param);
+            }
+
+            if let Some(ret) = ret {
+                visitor.visit_ty_mut(ret);
+            }
+
+            visitor.visit_expr_mut(body);
+        }
+        ExprKind::Ident(_)
+        | ExprKind::Path(_)
+        | ExprKind::MacroCall { .. }
+        | ExprKind::Error => {}
+    }
+}
+
+pub fn walk_field_init_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    init: &mut FieldInit,
+) {
+    visitor.visit_ident_mut(&mut init.data.name);
+
+    if let Some(value) = &mut init.data.value {
+        visitor.visit_expr_mut(value);
+    }
+}
+
+pub fn walk_lambda_param_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    param: &mut LambdaParam,
+) {
+    visitor.visit_ident_mut(&mut param.data.name);
+
+    if let Some(ty) = &mut param.data.ty {
+        visitor.visit_ty_mut(ty);
+    }
+}
+
+pub fn walk_arm_mut<V: VisitorMut + ?Sized>(visitor: &mut V, arm: &mut Arm) {
+    visitor.visit_pat_mut(&mut arm.data.pat);
+
+    if let Some(guard) = &mut arm.data.guard {
+        visitor.visit_expr_mut(guard);
+    }
+
+    visitor.visit_expr_mut(&mut arm.data.body);
+}
+
+pub fn walk_pat_mut<V: VisitorMut + ?Sized>(visitor: &mut V, pat: &mut Pat) {
+    match &mut pat.data {
+        PatKind::Literal(literal) => visitor.visit_literal_mut(literal),
+        PatKind::Range { start, end, .. } => {
+            visitor.visit_pat_mut(start);
+            visitor.visit_pat_mut(end);
+        }
+        PatKind::Tuple(pats) | PatKind::Or(pats) => {
+            pats.iter_mut().for_each(|pat| visitor.visit_pat_mut(pat));
+        }
+        PatKind::Paren(inner) => visitor.visit_pat_mut(inner),
+        PatKind::At { name, pat } => {
+            visitor.visit_ident_mut(name);
+            visitor.visit_pat_mut(pat);
+        }
+        PatKind::TupleStruct { path, elems } => {
+            visitor.visit_path_mut(path);
+            elems
+                .iter_mut()
+                .for_each(|elem| visitor.visit_pat_mut(elem));
+        }
+        PatKind::Struct { path, fields, .. } => {
+            visitor.visit_path_mut(path);
+            fields
+                .iter_mut()
+                .for_each(|field| visitor.visit_field_pat_mut(field));
+        }
+        PatKind::Wild
+        | PatKind::Binding(_)
+        | PatKind::Path(_)
+        | PatKind::Error => {}
+    }
+}
+
+pub fn walk_field_pat_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    field: &mut FieldPat,
+) {
+    visitor.visit_ident_mut(&mut field.data.name);
+
+    if let Some(pat) = &mut field.data.pat {
+        visitor.visit_pat_mut(pat);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_rename_in_place() {
+        struct Rename;
+
+        impl VisitorMut for Rename {
+            fn visit_ident_mut(&mut self, ident: &mut Ident) {
+                if ident.data == "a" {
+                    ident.data = "b".to_string();
+                }
+            }
+
+            fn visit_expr_mut(&mut self, expr: &mut Expr) {
+                if expr.data == ExprKind::Ident("a".to_string()) {
+                    expr.data = ExprKind::Ident("b".to_string());
+                }
+
+                walk_expr_mut(self, expr);
+            }
+        }
+
+        let (mut module, _) = parse("func f(a: usize) := { a + 1 }");
+        Rename.visit_module_mut(&mut module);
+
+        let ItemKind::Func(func) = &module.items[0].data else {
+            panic!("expected a function");
+        };
+        assert_eq!(func.params[0].data.name.data, "b");
+
+        let tail = func.body.data.tail.as_ref().unwrap();
+        let ExprKind::Binary { lhs, .. } = &tail.data else {
+            panic!("expected a binary expression");
+        };
+        assert_eq!(lhs.data, ExprKind::Ident(String::from("b")));
+    }
+}
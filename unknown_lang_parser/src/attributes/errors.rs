@@ -0,0 +1,67 @@
+use alloc::string::String;
+
+use super::Target;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AttrError {
+    /// An attribute on something it can't be put on, like `#[test]` on a
+    /// struct
+    WrongTarget { name: String, target: Target },
+    /// An attribute given arguments it doesn't take, where `expected`
+    /// describes the ones it does
+    BadArgs { name: String, expected: String },
+    /// An attribute which can only be given once, given more than once on
+    /// the same thing
+    Duplicate(String),
+    /// A `#[test]` function which takes parameters or type parameters, so
+    /// it can't be run on its own
+    TestParams(String),
+}
+
+impl core::fmt::Display for AttrError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::WrongTarget { name, target } => {
+                write!(f, "`#[{name}]` can't be put on {target}")
+            }
+            Self::BadArgs { name, expected } => {
+                write!(f, "`#[{name}]` takes {expected}")
+            }
+            Self::Duplicate(name) => {
+                write!(f, "`#[{name}]` is given more than once")
+            }
+            Self::TestParams(name) => write!(
+                f,
+                "`{name}` is a test, so it can't take parameters or type \
+                 parameters"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AttrError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AttrWarning {
+    /// An attribute which isn't built into the compiler, which is left
+    /// alone
+    Unknown(String),
+}
+
+impl AttrWarning {
+    /// The name which silences this warning when given to `#[allow(..)]`.
+    pub fn lint(&self) -> &'static str {
+        match self {
+            Self::Unknown(_) => "unknown_attributes",
+        }
+    }
+}
+
+impl core::fmt::Display for AttrWarning {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Unknown(name) => write!(f, "unknown attribute `#[{name}]`"),
+        }
+    }
+}
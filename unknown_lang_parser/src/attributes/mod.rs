@@ -0,0 +1,556 @@
+//! Checking the attributes on items and statements.
+//!
+//! Every attribute built into the compiler is in [`BUILTIN`], along with what
+//! it can be put on and which arguments it takes:
+//!
+//! - `#[test]` marks a function without parameters as a test.
+//! - `#[inline]`, `#[inline(always)]` or `#[inline(never)]` on a function.
+//! - `#[deprecated]` or `#[deprecated = "note"]` on a declaration.
+//! - `#[allow(name, ..)]` silences the warnings with those names on what it is
+//!   put on and everything inside of it, and can be put on anything.
+//!
+//! An attribute which isn't built in is left alone, with a warning which
+//! `#[allow(unknown_attributes)]` silences. Inner attributes are on the file
+//! or block they are at the start of.
+
+pub mod errors;
+
+use alloc::{string::String, vec, vec::Vec};
+use core::fmt::Write;
+
+use crate::{
+    ast::{
+        visit::{walk_block, walk_item, walk_module, walk_stmt, Visitor},
+        Attribute, Block, Func, Item, ItemKind, Module, Stmt,
+    },
+    lexer::{
+        kind::TokenKind,
+        position::{Span, Spanned},
+        tokens::Token,
+    },
+};
+
+use self::errors::{AttrError, AttrWarning};
+
+/// What an attribute can be put on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    Func,
+    /// A `let` at the top level of a file
+    Let,
+    Struct,
+    Enum,
+    Trait,
+    Impl,
+    Macro,
+    Import,
+    /// A statement in a block, including a `let`
+    Stmt,
+    /// A whole file, for an inner attribute at its start
+    Module,
+    /// A block, for an inner attribute at its start
+    Block,
+}
+
+impl Target {
+    /// Every kind of declaration, which can be deprecated.
+    const DECLS: &'static [Target] = &[
+        Target::Func,
+        Target::Let,
+        Target::Struct,
+        Target::Enum,
+        Target::Trait,
+        Target::Macro,
+    ];
+
+    const ALL: &'static [Target] = &[
+        Target::Func,
+        Target::Let,
+        Target::Struct,
+        Target::Enum,
+        Target::Trait,
+        Target::Impl,
+        Target::Macro,
+        Target::Import,
+        Target::Stmt,
+        Target::Module,
+        Target::Block,
+    ];
+
+    /// Get the target of the attributes on an item, or `None` for one which
+    /// failed to parse.
+    pub fn of_item(item: &ItemKind) -> Option<Self> {
+        Some(match item {
+            ItemKind::Func(_) => Target::Func,
+            ItemKind::Let(_) => Target::Let,
+            ItemKind::Struct(_) => Target::Struct,
+            ItemKind::Enum(_) => Target::Enum,
+            ItemKind::Trait(_) => Target::Trait,
+            ItemKind::Impl(_) => Target::Impl,
+            ItemKind::Macro(_) => Target::Macro,
+            ItemKind::Import(_) => Target::Import,
+            ItemKind::Error => return None,
+        })
+    }
+}
+
+impl core::fmt::Display for Target {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let target = match self {
+            Target::Func => "a function",
+            Target::Let => "a `let` at the top level",
+            Target::Struct => "a struct",
+            Target::Enum => "an enum",
+            Target::Trait => "a trait",
+            Target::Impl => "an impl",
+            Target::Macro => "a macro",
+            Target::Import => "an import",
+            Target::Stmt => "a statement",
+            Target::Module => "a file",
+            Target::Block => "a block",
+        };
+
+        write!(f, "{target}")
+    }
+}
+
+/// The arguments that an attribute takes after its name.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Args {
+    /// Nothing, like `#[test]`
+    None,
+    /// Nothing, or one of these names in parenthesis, like
+    /// `#[inline(always)]`
+    OneOf(&'static [&'static str]),
+    /// Nothing, or `= "note"`, like `#[deprecated = "use g instead"]`
+    Note,
+    /// At least one name in parenthesis, separated by `,`, like
+    /// `#[allow(unused)]`
+    Names,
+}
+
+impl Args {
+    /// Checks if `args` are what an attribute taking these expects.
+    pub fn matches(self, args: &[Spanned<Token>]) -> bool {
+        let kinds: Vec<_> = args.iter().map(|tok| tok.data.kind()).collect();
+
+        match self {
+            Args::None => args.is_empty(),
+            Args::OneOf(names) => match args {
+                [] => true,
+                [open, name, close] => {
+                    let known = match &name.data {
+                        Token::Ident(name) => names.contains(&name.as_str()),
+                        _ => false,
+                    };
+                    open.data == Token::LPar
+                        && close.data == Token::RPar
+                        && known
+                }
+                _ => false,
+            },
+            Args::Note => {
+                matches!(kinds[..], [] | [TokenKind::Eq, TokenKind::Str])
+            }
+            Args::Names => {
+                let [TokenKind::LPar, inner @ .., TokenKind::RPar] = &kinds[..]
+                else {
+                    return false;
+                };
+
+                // Names and commas take turns, with an optional `,` at the
+                // end
+                !inner.is_empty()
+                    && inner.iter().enumerate().all(|(idx, kind)| {
+                        let expected = match idx % 2 {
+                            0 => TokenKind::Ident,
+                            _ => TokenKind::Comma,
+                        };
+                        *kind == expected
+                    })
+            }
+        }
+    }
+
+    /// Describe these arguments, to go after "takes".
+    fn describe(self) -> String {
+        match self {
+            Args::None => "no arguments".into(),
+            Args::OneOf(names) => {
+                let mut out = String::from("no arguments");
+                for name in names {
+                    let _ = write!(out, " or `({name})`");
+                }
+                out
+            }
+            Args::Note => "no arguments, or a note like `= \"use g\"`".into(),
+            Args::Names => {
+                "names of warnings, like `(unknown_attributes)`".into()
+            }
+        }
+    }
+}
+
+/// An attribute built into the compiler.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Builtin {
+    pub name: &'static str,
+    /// What the attribute can be put on
+    pub targets: &'static [Target],
+    pub args: Args,
+    /// Whether the attribute can be given more than once on the same thing
+    pub repeatable: bool,
+}
+
+/// Every attribute built into the compiler.
+pub const BUILTIN: &[Builtin] = &[
+    Builtin {
+        name: "test",
+        targets: &[Target::Func],
+        args: Args::None,
+        repeatable: false,
+    },
+    Builtin {
+        name: "inline",
+        targets: &[Target::Func],
+        args: Args::OneOf(&["always", "never"]),
+        repeatable: false,
+    },
+    Builtin {
+        name: "deprecated",
+        targets: Target::DECLS,
+        args: Args::Note,
+        repeatable: false,
+    },
+    Builtin {
+        name: "allow",
+        targets: Target::ALL,
+        args: Args::Names,
+        repeatable: true,
+    },
+];
+
+/// Find the attribute built into the compiler with `name`, if there is one.
+pub fn builtin(name: &str) -> Option<&'static Builtin> {
+    BUILTIN.iter().find(|builtin| builtin.name == name)
+}
+
+/// The problems found with the attributes of a module.
+#[derive(Debug, Default)]
+pub struct Checked {
+    pub errors: Vec<Spanned<AttrError>>,
+    /// Warnings which weren't silenced by `#[allow(..)]`
+    pub warnings: Vec<Spanned<AttrWarning>>,
+}
+
+/// Check every attribute in `module`.
+pub fn check(module: &Module) -> Checked {
+    let mut checker = Checker::default();
+    checker.visit_module(module);
+
+    checker.checked
+}
+
+#[derive(Default)]
+struct Checker {
+    /// The names given to every `#[allow(..)]` around the current node
+    allowed: Vec<String>,
+    checked: Checked,
+}
+
+impl Checker {
+    /// Check the attributes on one `target`, giving back the length that
+    /// [`Checker::allowed`] goes back to once the target has been checked.
+    fn enter(&mut self, attrs: &[Attribute], target: Target) -> usize {
+        let scope = self.allowed.len();
+
+        // Every attribute on the target is in the scope of an `allow` next
+        // to it, so they are all added first
+        for attr in attrs {
+            if attr.data.name.data == "allow"
+                && Args::Names.matches(&attr.data.args)
+            {
+                self.allowed.extend(attr.data.args.iter().filter_map(|tok| {
+                    match &tok.data {
+                        Token::Ident(name) => Some(name.clone()),
+                        _ => None,
+                    }
+                }));
+            }
+        }
+
+        let mut seen: Vec<&str> = vec![];
+        for attr in attrs {
+            let name = &attr.data.name.data;
+            let Some(builtin) = builtin(name) else {
+                self.warn(AttrWarning::Unknown(name.clone()), attr.span);
+                continue;
+            };
+
+            if !builtin.targets.contains(&target) {
+                let err = AttrError::WrongTarget {
+                    name: name.clone(),
+                    target,
+                };
+                self.error(err, attr.span);
+            } else if !builtin.args.matches(&attr.data.args) {
+                let err = AttrError::BadArgs {
+                    name: name.clone(),
+                    expected: builtin.args.describe(),
+                };
+                self.error(err, args_span(attr));
+            } else if !builtin.repeatable && seen.contains(&builtin.name) {
+                self.error(AttrError::Duplicate(name.clone()), attr.span);
+            }
+
+            seen.push(builtin.name);
+        }
+
+        scope
+    }
+
+    /// A test is called without any arguments, so it can't take any.
+    fn check_test(&mut self, func: &Func, attrs: &[Attribute]) {
+        let is_test = attrs.iter().any(|attr| attr.data.name.data == "test");
+
+        if is_test && !(func.params.is_empty() && func.generics.is_empty()) {
+            let err = AttrError::TestParams(func.name.data.clone());
+            self.error(err, func.name.span);
+        }
+    }
+
+    fn error(&mut self, err: AttrError, span: Span) {
+        self.checked.errors.push(Spanned::new(err, span));
+    }
+
+    fn warn(&mut self, warning: AttrWarning, span: Span) {
+        if !self.allowed.iter().any(|name| name == warning.lint()) {
+            self.checked.warnings.push(Spanned::new(warning, span));
+        }
+    }
+}
+
+/// Get the span of the arguments of an attribute, or of the whole attribute
+/// when it has none.
+fn args_span(attr: &Attribute) -> Span {
+    match (attr.data.args.first(), attr.data.args.last()) {
+        (Some(first), Some(last)) => first.span.union(last.span),
+        _ => attr.span,
+    }
+}
+
+impl Visitor for Checker {
+    fn visit_module(&mut self, module: &Module) {
+        let scope = self.enter(&module.attrs, Target::Module);
+        walk_module(self, module);
+        self.allowed.truncate(scope);
+    }
+
+    fn visit_item(&mut self, item: &Item) {
+        let Some(target) = Target::of_item(&item.data) else {
+            return walk_item(self, item);
+        };
+
+        let scope = self.enter(&item.attrs, target);
+        if let ItemKind::Func(func) = &item.data {
+            self.check_test(func, &item.attrs);
+        }
+
+        walk_item(self, item);
+        self.allowed.truncate(scope);
+    }
+
+    /// Attributes are checked along with what they are on, which is where
+    /// their target is known.
+    fn visit_attribute(&mut self, _attr: &Attribute) {}
+
+    fn visit_block(&mut self, block: &Block) {
+        let scope = self.enter(&block.data.attrs, Target::Block);
+        walk_block(self, block);
+        self.allowed.truncate(scope);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        let scope = self.enter(&stmt.attrs, Target::Stmt);
+        walk_stmt(self, stmt);
+        self.allowed.truncate(scope);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::parser::parse;
+
+    /// Check the attributes in `input`, giving back the error and warning
+    /// messages.
+    fn check_str(input: &str) -> (Vec<String>, Vec<String>) {
+        let (module, errors) = parse(input);
+        assert_eq!(errors, vec![], "{input}");
+
+        let checked = check(&module);
+        let errors = checked.errors.iter().map(|err| err.data.to_string());
+        let warnings = checked.warnings.iter().map(|w| w.data.to_string());
+
+        (errors.collect(), warnings.collect())
+    }
+
+    #[test]
+    fn test_builtin_attributes() {
+        let (errors, warnings) = check_str(concat!(
+            "#![allow(unused)]\n",
+            "#[test] #[inline(always)] func f() := {\n",
+            "    #![allow(unused,)]\n",
+            "    #[allow(a, b)] let a := 1\n",
+            "}\n",
+            "#[deprecated = \"use f\"] #[inline] func g(a: Int) := { a }\n",
+            "#[deprecated] #[allow(a)] #[allow(b)] struct S {}",
+        ));
+
+        assert_eq!(errors, Vec::<String>::new());
+        assert_eq!(warnings, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_unknown_attributes() {
+        let (module, _) = parse(concat!(
+            "#[derive(Debug)] struct S {}\n",
+            "func f() := {\n",
+            "    #[cold] let a := 1\n",
+            "}",
+        ));
+        let checked = check(&module);
+
+        assert_eq!(checked.errors, vec![]);
+        assert_eq!(
+            checked.warnings,
+            vec![
+                Spanned::new(
+                    AttrWarning::Unknown("derive".into()),
+                    Span::new(0, 16)
+                ),
+                Spanned::new(
+                    AttrWarning::Unknown("cold".into()),
+                    Span::new(47, 54)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_allowed_warnings() {
+        let (errors, warnings) = check_str(concat!(
+            "#[allow(unknown_attributes)] func f() := {\n",
+            "    #[a] let a := 1\n",
+            "}\n",
+            "#[b] #[allow(unknown_attributes)] struct S {}\n",
+            "func g() := {\n",
+            "    #![allow(unknown_attributes)]\n",
+            "    #[c] let c := 1\n",
+            "}\n",
+            "#[d] func h() := {}",
+        ));
+
+        assert_eq!(errors, Vec::<String>::new());
+        // An `allow` only reaches what it is on
+        assert_eq!(warnings, vec!["unknown attribute `#[d]`"]);
+    }
+
+    #[test]
+    fn test_wrong_targets() {
+        let (errors, _) = check_str(concat!(
+            "#![test]\n",
+            "#[test] struct S {}\n",
+            "#[deprecated] impl Show for S {}\n",
+            "func f() := {\n",
+            "    #![inline]\n",
+            "    #[inline] let a := 1\n",
+            "}",
+        ));
+
+        assert_eq!(
+            errors,
+            vec![
+                "`#[test]` can't be put on a file",
+                "`#[test]` can't be put on a struct",
+                "`#[deprecated]` can't be put on an impl",
+                "`#[inline]` can't be put on a block",
+                "`#[inline]` can't be put on a statement",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bad_args() {
+        let (module, _) = parse(concat!(
+            "#[test(a)] func f() := {}\n",
+            "#[inline(sometimes)] func g() := {}\n",
+            "#[deprecated(\"no\")] #[allow] #[allow(a b)] func h() := {}",
+        ));
+        let checked = check(&module);
+        let messages: Vec<_> = checked
+            .errors
+            .iter()
+            .map(|err| err.data.to_string())
+            .collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "`#[test]` takes no arguments",
+                "`#[inline]` takes no arguments or `(always)` or `(never)`",
+                "`#[deprecated]` takes no arguments, or a note like \
+                 `= \"use g\"`",
+                "`#[allow]` takes names of warnings, like \
+                 `(unknown_attributes)`",
+                "`#[allow]` takes names of warnings, like \
+                 `(unknown_attributes)`",
+            ]
+        );
+        // The error is on the arguments, if there are any
+        assert_eq!(checked.errors[0].span, Span::new(6, 9));
+        assert_eq!(checked.errors[3].span, Span::new(82, 90));
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let (errors, _) = check_str(concat!(
+            "#[inline] #[inline(never)] func f() := {}\n",
+            "#[test] #[allow(a)] #[allow(a)] #[test] func g() := {}",
+        ));
+
+        assert_eq!(
+            errors,
+            vec![
+                "`#[inline]` is given more than once",
+                "`#[test]` is given more than once",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tests_without_params() {
+        let (module, _) = parse(concat!(
+            "#[test] func f(a: Int) := {}\n",
+            "#[test] func g<T>() := {}\n",
+            "#[test] func h() := {}",
+        ));
+        let checked = check(&module);
+
+        assert_eq!(
+            checked.errors,
+            vec![
+                Spanned::new(
+                    AttrError::TestParams("f".into()),
+                    Span::new(13, 14)
+                ),
+                Spanned::new(
+                    AttrError::TestParams("g".into()),
+                    Span::new(42, 43)
+                ),
+            ]
+        );
+    }
+}
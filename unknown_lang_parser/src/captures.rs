@@ -0,0 +1,395 @@
+//! Finding the variables that each lambda captures from the code around it.
+//!
+//! A lambda captures every variable which it uses but doesn't declare itself,
+//! as long as the variable was declared in the function or lambda around it.
+//! Functions and top level variables are never captured, since they are
+//! always there to refer to.
+//!
+//! A variable declared with a `mut` type is captured by mutable reference,
+//! so that any change made by the lambda is seen outside of it. Any other
+//! variable is captured by value. A lambda inside of another lambda which
+//! captures a variable from outside of both makes the outer lambda capture it
+//! as well, so that it can be passed on.
+
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::{
+    ast::{
+        visit::{walk_block, walk_expr, walk_field_init, walk_pat, Visitor},
+        Arm, Block, Expr, ExprKind, FieldInit, FieldPat, Func, Let, Module,
+        Pat, PatKind, Ty, TyKind,
+    },
+    lexer::position::{Span, Spanned},
+};
+
+/// How a lambda holds on to a variable from outside of it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CaptureMode {
+    /// A copy of the value, taken when the lambda is created
+    ByValue,
+    /// A mutable reference to a variable declared with a `mut` type
+    ByMutRef,
+}
+
+/// A variable from outside of a lambda which is used inside of it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Capture {
+    pub name: String,
+    /// Where the variable was declared
+    pub binding: Span,
+    pub mode: CaptureMode,
+}
+
+/// Everything that one lambda captures.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LambdaCaptures {
+    /// The span of the whole lambda
+    pub lambda: Span,
+    /// The captured variables in the order they are first used, each with
+    /// the span of that first use
+    pub captures: Vec<Spanned<Capture>>,
+}
+
+/// Find the captures of every lambda in `module`, in the order that the
+/// lambdas start in. A lambda inside of another comes after it.
+pub fn captures(module: &Module) -> Vec<LambdaCaptures> {
+    let mut finder = CaptureFinder {
+        lambdas: vec![],
+        scopes: vec![],
+        active: vec![],
+    };
+    finder.visit_module(module);
+
+    finder.lambdas
+}
+
+/// A variable which is in scope.
+struct Binding {
+    name: String,
+    span: Span,
+    mutable: bool,
+}
+
+struct CaptureFinder {
+    lambdas: Vec<LambdaCaptures>,
+    /// The variables declared in each scope, innermost last. There aren't any
+    /// outside of a function.
+    scopes: Vec<Vec<Binding>>,
+    /// The lambdas which are being visited, by their index in `lambdas`, along
+    /// with the number of scopes that were open when they started
+    active: Vec<(usize, usize)>,
+}
+
+impl CaptureFinder {
+    /// Declare a variable in the innermost scope, where it shadows any other
+    /// variable with the same name.
+    fn declare(&mut self, name: &str, span: Span, ty: Option<&Ty>) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+
+        let mutable = matches!(
+            ty,
+            Some(Spanned {
+                data: TyKind::Mut(_),
+                ..
+            })
+        );
+        scope.push(Binding {
+            name: name.to_string(),
+            span,
+            mutable,
+        });
+    }
+
+    /// Run `visit` in a new scope, which is closed once it is done.
+    fn scoped(&mut self, visit: impl FnOnce(&mut Self)) {
+        self.scopes.push(vec![]);
+        visit(self);
+        self.scopes.pop();
+    }
+
+    /// Record a use of the variable `name` at `span`, which is a capture for
+    /// every lambda that started after the variable was declared.
+    fn use_variable(&mut self, name: &str, span: Span) {
+        let found =
+            self.scopes
+                .iter()
+                .enumerate()
+                .rev()
+                .find_map(|(depth, scope)| {
+                    let binding = scope
+                        .iter()
+                        .rev()
+                        .find(|binding| binding.name == name)?;
+                    Some((depth, binding))
+                });
+        let Some((depth, binding)) = found else {
+            return;
+        };
+
+        let capture = Capture {
+            name: binding.name.clone(),
+            binding: binding.span,
+            mode: match binding.mutable {
+                true => CaptureMode::ByMutRef,
+                false => CaptureMode::ByValue,
+            },
+        };
+
+        for &(lambda, start) in &self.active {
+            let captures = &mut self.lambdas[lambda].captures;
+            if depth < start
+                && !captures.iter().any(|other| other.data == capture)
+            {
+                captures.push(Spanned::new(capture.clone(), span));
+            }
+        }
+    }
+}
+
+impl Visitor for CaptureFinder {
+    fn visit_func(&mut self, func: &Func) {
+        self.scoped(|finder| {
+            for param in &func.params {
+                let param = &param.data;
+                finder.declare(
+                    &param.name.data,
+                    param.name.span,
+                    Some(&param.ty),
+                );
+            }
+
+            finder.visit_block(&func.body);
+        });
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        self.scoped(|finder| walk_block(finder, block));
+    }
+
+    /// The value is visited before the variable is declared, so that
+    /// `let a := a + 1` uses the `a` from before.
+    fn visit_let(&mut self, stmt: &Let) {
+        if let Some(ty) = &stmt.ty {
+            self.visit_ty(ty);
+        }
+        self.visit_expr(&stmt.value);
+
+        self.declare(&stmt.name.data, stmt.name.span, stmt.ty.as_ref());
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match &expr.data {
+            ExprKind::Ident(name) => self.use_variable(name, expr.span),
+            ExprKind::For {
+                binding,
+                iter,
+                body,
+                ..
+            } => {
+                self.visit_expr(iter);
+                self.scoped(|finder| {
+                    finder.declare(&binding.data, binding.span, None);
+                    finder.visit_block(body);
+                });
+            }
+            ExprKind::Lambda { params, ret, body } => {
+                self.active.push((self.lambdas.len(), self.scopes.len()));
+                self.lambdas.push(LambdaCaptures {
+                    lambda: expr.span,
+                    captures: vec![],
+                });
+
+                self.scoped(|finder| {
+                    for param in params {
+                        let param = &param.data;
+                        finder.declare(
+                            &param.name.data,
+                            param.name.span,
+                            param.ty.as_ref(),
+                        );
+                    }
+
+                    if let Some(ret) = ret {
+                        finder.visit_ty(ret);
+                    }
+                    finder.visit_expr(body);
+                });
+
+                self.active.pop();
+            }
+            _ => walk_expr(self, expr),
+        }
+    }
+
+    /// The shorthand `x` uses the variable `x` as the value of the field.
+    fn visit_field_init(&mut self, init: &FieldInit) {
+        match &init.data.value {
+            Some(_) => walk_field_init(self, init),
+            None => self.use_variable(&init.data.name.data, init.span),
+        }
+    }
+
+    /// The variables bound by the pattern are only in scope for the guard
+    /// and body of their own arm.
+    fn visit_arm(&mut self, arm: &Arm) {
+        self.scoped(|finder| {
+            finder.visit_pat(&arm.data.pat);
+
+            if let Some(guard) = &arm.data.guard {
+                finder.visit_expr(guard);
+            }
+            finder.visit_expr(&arm.data.body);
+        });
+    }
+
+    fn visit_pat(&mut self, pat: &Pat) {
+        match &pat.data {
+            PatKind::Binding(name) => self.declare(name, pat.span, None),
+            PatKind::At { name, .. } => {
+                self.declare(&name.data, name.span, None)
+            }
+            _ => {}
+        }
+
+        walk_pat(self, pat);
+    }
+
+    fn visit_field_pat(&mut self, field: &FieldPat) {
+        match &field.data.pat {
+            Some(pat) => self.visit_pat(pat),
+            None => {
+                let name = &field.data.name;
+                self.declare(&name.data, name.span, None);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+
+    use super::*;
+    use crate::parser::parse;
+
+    /// Find the captures of every lambda in `input`, as the name of each
+    /// variable, starting with `&mut` when it is captured by reference.
+    fn captured(input: &str) -> Vec<Vec<String>> {
+        let (module, errors) = parse(input);
+        assert!(errors.is_empty(), "{errors:?}");
+
+        captures(&module)
+            .into_iter()
+            .map(|lambda| {
+                lambda
+                    .captures
+                    .into_iter()
+                    .map(|capture| match capture.data.mode {
+                        CaptureMode::ByValue => capture.data.name,
+                        CaptureMode::ByMutRef => {
+                            format!("&mut {}", capture.data.name)
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_captures() {
+        let input = concat!(
+            "func f(a: usize, total: mut usize) := {\n",
+            "    let b := 2\n",
+            "    let add := |x| x + a * b + a\n",
+            "    let count := || { total += b }\n",
+            "}",
+        );
+
+        assert_eq!(
+            captured(input),
+            vec![vec!["a", "b"], vec!["&mut total", "b"],]
+        );
+    }
+
+    #[test]
+    fn test_spans() {
+        let input = "func f(a: usize) := {\n    |x| a + a\n}";
+        let (module, _) = parse(input);
+
+        let lambdas = captures(&module);
+        assert_eq!(lambdas[0].lambda, Span::new(26, 35));
+        assert_eq!(
+            lambdas[0].captures,
+            vec![Spanned::new(
+                Capture {
+                    name: "a".into(),
+                    binding: Span::new(7, 8),
+                    mode: CaptureMode::ByValue,
+                },
+                Span::new(30, 31)
+            )]
+        );
+    }
+
+    #[test]
+    fn test_own_variables_are_not_captured() {
+        let input = concat!(
+            "let global := 1\n",
+            "func g() := {}\n",
+            "func f(a: usize) := {\n",
+            "    let g := |a: mut usize| { let b := a; b + global + g() }\n",
+            "    let h := |x| match x { Some(a) => a, n @ 0..=1 => n, _ => a }\n",
+            "    let i := |x| for i in 0..a { i + x }\n",
+            "}",
+        );
+
+        assert_eq!(captured(input), vec![vec![], vec!["a"], vec!["a"]]);
+    }
+
+    #[test]
+    fn test_shadowing() {
+        let input = concat!(
+            "func f(a: mut usize) := {\n",
+            "    let g := || a\n",
+            "    let a := 1\n",
+            "    let h := || a\n",
+            "    let a := |a| a\n",
+            "}",
+        );
+
+        assert_eq!(captured(input), vec![vec!["&mut a"], vec!["a"], vec![]]);
+    }
+
+    #[test]
+    fn test_nested_lambdas() {
+        let input = concat!(
+            "func f(a: usize, b: mut usize) := {\n",
+            "    let g := |x| |y| x + y + b\n",
+            "    let h := || { let c := a; || c }\n",
+            "}",
+        );
+
+        assert_eq!(
+            captured(input),
+            vec![
+                // The outer lambda captures `b` to pass it on
+                vec!["&mut b"],
+                vec!["x", "&mut b"],
+                vec!["a"],
+                vec!["c"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_struct_literal_shorthand() {
+        let input = "func f(x: i32) := {\n    let p := || Point { x, y: x }\n}";
+        assert_eq!(captured(input), vec![vec!["x"]]);
+    }
+}
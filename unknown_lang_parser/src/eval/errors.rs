@@ -0,0 +1,120 @@
+use alloc::string::String;
+
+use crate::ast::{BinOp, UnaryOp};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EvalError {
+    /// The function to start running from, like `main`, isn't declared
+    NoEntry(String),
+    /// A name which is neither a variable, a function or a variable declared
+    /// at the top level, like one imported from another module
+    Unknown(String),
+    /// A method called on a value whose type implements no trait with a
+    /// method by that name
+    NoMethod {
+        value: String,
+        method: String,
+    },
+    /// A field read from a value which doesn't have it
+    NoField {
+        value: String,
+        field: String,
+    },
+    /// A call to a value which isn't a function, like `1(2)`
+    NotCallable(String),
+    /// A condition which isn't a `bool`, like the `1` of `if 1 { }`
+    NotBool(String),
+    /// A `for` loop over a value which isn't a range of numbers
+    NotIterable(String),
+    /// A struct literal whose `..base` isn't the same struct
+    WrongBase {
+        name: String,
+        value: String,
+    },
+    /// A function called with the wrong number of arguments
+    WrongArgCount {
+        expected: usize,
+        found: usize,
+    },
+    /// An operator used on values it doesn't work on, like `true + 1`
+    InvalidOperand {
+        op: UnaryOp,
+        value: String,
+    },
+    InvalidOperands {
+        op: BinOp,
+        lhs: String,
+        rhs: String,
+    },
+    /// A number which is too large, or a calculation which overflowed
+    Overflow,
+    /// A division or remainder by zero
+    DivideByZero,
+    /// A value which none of the arms of a `match` matched
+    NoArmMatched(String),
+    /// A `break` or `continue` outside of a loop, or with a label which no
+    /// loop around it has
+    NotInLoop,
+    /// Functions which called each other so deeply that the program most
+    /// likely never stops
+    TooDeep,
+    /// Something that the evaluator can't run yet, like a byte string
+    Unsupported(&'static str),
+}
+
+impl core::fmt::Display for EvalError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoEntry(name) => {
+                write!(f, "there is no function `{name}` to run")
+            }
+            Self::Unknown(name) => write!(f, "`{name}` isn't declared here"),
+            Self::NoMethod { value, method } => {
+                write!(f, "`{value}` has no method `{method}`")
+            }
+            Self::NoField { value, field } => {
+                write!(f, "`{value}` has no field `{field}`")
+            }
+            Self::NotCallable(value) => {
+                write!(f, "`{value}` isn't a function, so it can't be called")
+            }
+            Self::NotBool(value) => write!(f, "`{value}` isn't a `bool`"),
+            Self::NotIterable(value) => {
+                write!(f, "`{value}` isn't a range of numbers to loop over")
+            }
+            Self::WrongBase { name, value } => {
+                write!(f, "`{value}` isn't a `{name}` to take fields from")
+            }
+            Self::WrongArgCount { expected, found } => {
+                let s = if *expected == 1 { "" } else { "s" };
+                write!(
+                    f,
+                    "this takes {expected} argument{s}, but {found} were given"
+                )
+            }
+            Self::InvalidOperand { op, value } => {
+                write!(f, "`{op}` can't be used on `{value}`")
+            }
+            Self::InvalidOperands { op, lhs, rhs } => {
+                write!(f, "`{op}` can't be used on `{lhs}` and `{rhs}`")
+            }
+            Self::Overflow => write!(f, "this number is too large"),
+            Self::DivideByZero => write!(f, "this divides by zero"),
+            Self::NoArmMatched(value) => {
+                write!(f, "no arm of this `match` matches `{value}`")
+            }
+            Self::NotInLoop => {
+                write!(f, "this isn't inside of a loop it can leave")
+            }
+            Self::TooDeep => write!(
+                f,
+                "functions called each other more than {} levels deep",
+                super::MAX_CALLS
+            ),
+            Self::Unsupported(what) => write!(f, "{what} can't be run yet"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EvalError {}
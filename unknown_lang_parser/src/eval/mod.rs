@@ -0,0 +1,1466 @@
+//! Running a program by walking its syntax tree.
+//!
+//! This is the simplest backend there could be, and it runs the code that
+//! [`monomorphize`](crate::generics::monomorphize) gave each instance. A
+//! call to a generic function or to a method runs the body of the instance
+//! it was found to call, so a method called through a bound like
+//! `T: Display` runs the impl for whichever type `T` is, or the trait's
+//! default body when the impl leaves it out. Calls without an instance, like
+//! those in code that generics inference couldn't check, are dispatched by
+//! the value they are called on instead.
+//!
+//! There is no type checker yet, so every number is an `i128`, and only
+//! overflowing that is caught. Values are copied whenever they are passed
+//! around, including into the lambdas that use them, so changing a
+//! parameter doesn't change what was passed to it. Only a single module is
+//! run, so nothing imported from other modules can be used.
+
+pub mod errors;
+
+use alloc::{
+    collections::BTreeMap,
+    rc::Rc,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{cmp::Ordering, fmt};
+
+use crate::{
+    ast::{
+        path_to_string, Arm, AssignOp, BinOp, Block, Expr, ExprKind, FieldInit,
+        Func, Ident, Impl, ItemKind, Let, Literal, Module, Param, Pat, PatKind,
+        Stmt, StmtKind, TraitMethodKind, Ty, TyKind, UnaryOp, VariantFields,
+        VariantKind,
+    },
+    generics::{types::is_integer, Code, Monomorphization, Target},
+    lexer::position::{Span, Spanned},
+};
+
+use self::errors::EvalError;
+
+/// How deeply functions may call each other before the program is taken to
+/// never stop. Every call is run on the stack, so this is kept low enough to
+/// fit on the stack of a thread other than the main one, even in a debug
+/// build.
+pub const MAX_CALLS: usize = 64;
+
+/// A value that the program works with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Unit,
+    Int(i128),
+    Bool(bool),
+    Char(char),
+    Str(String),
+    /// A struct, or an enum variant with named fields, like
+    /// `Shape::Rect { w: 1, h: 2 }`, with its fields in the order they were
+    /// declared
+    Struct {
+        name: String,
+        fields: Vec<(String, Value)>,
+    },
+    /// An enum variant with its values in order, like `Shape::Circle(2)`
+    Variant {
+        name: String,
+        values: Vec<Value>,
+    },
+    /// `start..end`, or `start..=end` when `inclusive` is set
+    Range {
+        start: i128,
+        end: i128,
+        inclusive: bool,
+    },
+    /// A function declared by name, which runs `Code`
+    Func(Code),
+    /// A variant which holds values, used as the function making it
+    Constructor(String),
+    Lambda(Rc<Closure>),
+    /// `f ** g`, which calls `f` with the result of `g`
+    Composed(Rc<(Value, Value)>),
+}
+
+/// A lambda, along with the variables around it that it was made with.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Closure {
+    params: Vec<String>,
+    body: Expr,
+    captured: BTreeMap<String, Value>,
+    /// The code that the lambda was written in, which its calls are looked up
+    /// in
+    code: Code,
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Unit => write!(f, "()"),
+            Value::Int(value) => write!(f, "{value}"),
+            Value::Bool(value) => write!(f, "{value}"),
+            Value::Char(chr) => write!(f, "'{}'", chr.escape_debug()),
+            Value::Str(text) => write!(f, "\"{}\"", text.escape_debug()),
+            Value::Struct { name, fields } => {
+                write!(f, "{name} {{")?;
+                for (idx, (field, value)) in fields.iter().enumerate() {
+                    let sep = if idx > 0 { "," } else { "" };
+                    write!(f, "{sep} {field}: {value}")?;
+                }
+                write!(f, " }}")
+            }
+            Value::Variant { name, values } => {
+                write!(f, "{name}")?;
+                if !values.is_empty() {
+                    write!(f, "(")?;
+                    for (idx, value) in values.iter().enumerate() {
+                        if idx > 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{value}")?;
+                    }
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            Value::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let op = if *inclusive { "..=" } else { ".." };
+                write!(f, "{start}{op}{end}")
+            }
+            Value::Func(code) => match &code.target {
+                Target::Func(name)
+                | Target::Global(name)
+                | Target::Method { name, .. } => write!(f, "{name}"),
+                Target::Default { trait_name, method } => {
+                    write!(f, "{trait_name}::{method}")
+                }
+            },
+            Value::Constructor(name) => write!(f, "{name}"),
+            Value::Lambda(closure) => {
+                write!(f, "|{}| ..", closure.params.join(", "))
+            }
+            Value::Composed(funcs) => write!(f, "{} ** {}", funcs.0, funcs.1),
+        }
+    }
+}
+
+/// Run the function called `entry` in `module`, like `main`, giving back the
+/// value it returns. The calls it makes are looked up in `mono`, which has to
+/// be the monomorphization of `module`.
+pub fn run(
+    module: &Module,
+    mono: &Monomorphization,
+    entry: &str,
+) -> Result<Value, Spanned<EvalError>> {
+    let mut evaluator = Evaluator {
+        module,
+        mono,
+        depth: 0,
+    };
+
+    let Some(func) = evaluator.func(entry) else {
+        let err = EvalError::NoEntry(String::from(entry));
+        return Err(Spanned::new(err, Span::new(0, 0)));
+    };
+
+    let code = Code {
+        target: Target::Func(String::from(entry)),
+        args: vec![],
+    };
+    match evaluator.call_code(&code, vec![], func.name.span) {
+        Ok(value) => Ok(value),
+        Err(Flow::Error(err)) => Err(err),
+        // Any other way of leaving a function is caught as it returns
+        Err(_) => unreachable!(),
+    }
+}
+
+/// How the code being run stopped before getting to its end.
+enum Flow {
+    Break {
+        label: Option<String>,
+        value: Value,
+        span: Span,
+    },
+    Continue {
+        label: Option<String>,
+        span: Span,
+    },
+    Return(Value),
+    Error(Spanned<EvalError>),
+}
+
+impl From<Spanned<EvalError>> for Flow {
+    fn from(err: Spanned<EvalError>) -> Self {
+        Flow::Error(err)
+    }
+}
+
+type Eval<T> = Result<T, Flow>;
+
+fn error<T>(err: EvalError, span: Span) -> Eval<T> {
+    Err(Flow::Error(Spanned::new(err, span)))
+}
+
+/// How a loop is left by the body it runs.
+enum Leave {
+    Break(Value),
+    Continue,
+}
+
+/// A function being run.
+struct Frame {
+    /// What the function is running, which the calls it makes are looked up
+    /// in
+    code: Code,
+    /// The variables declared in each block that is being run, innermost
+    /// last
+    scopes: Vec<BTreeMap<String, Value>>,
+}
+
+impl Frame {
+    fn lookup(&self, name: &str) -> Option<&Value> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn lookup_mut(&mut self, name: &str) -> Option<&mut Value> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+
+    fn declare(&mut self, name: &str, value: Value) {
+        let scope = self.scopes.last_mut().unwrap();
+        scope.insert(String::from(name), value);
+    }
+
+    /// Every variable that can be seen, for a lambda to capture.
+    fn visible(&self) -> BTreeMap<String, Value> {
+        let mut visible = BTreeMap::new();
+        for scope in &self.scopes {
+            visible.extend(scope.clone());
+        }
+        visible
+    }
+}
+
+struct Evaluator<'a> {
+    module: &'a Module,
+    mono: &'a Monomorphization,
+    /// How many functions are being run
+    depth: usize,
+}
+
+impl<'a> Evaluator<'a> {
+    fn func(&self, name: &str) -> Option<&'a Func> {
+        self.module.items.iter().find_map(|item| match &item.data {
+            ItemKind::Func(func) if func.name.data == name => Some(func),
+            _ => None,
+        })
+    }
+
+    fn global(&self, name: &str) -> Option<&'a Let> {
+        self.module.items.iter().find_map(|item| match &item.data {
+            ItemKind::Let(stmt) if stmt.name.data == name => Some(stmt),
+            _ => None,
+        })
+    }
+
+    fn impls(&self) -> impl Iterator<Item = &'a Impl> {
+        self.module
+            .items
+            .iter()
+            .filter_map(|item| match &item.data {
+                ItemKind::Impl(decl) => Some(decl),
+                _ => None,
+            })
+    }
+
+    fn trait_method(
+        &self,
+        trait_name: &str,
+        method: &str,
+    ) -> Option<&'a TraitMethodKind> {
+        self.module.items.iter().find_map(|item| match &item.data {
+            ItemKind::Trait(decl) if decl.name.data == trait_name => decl
+                .methods
+                .iter()
+                .map(|method| &method.data)
+                .find(|other| other.name.data == method),
+            _ => None,
+        })
+    }
+
+    /// Look up an enum variant by its path, like `Shape::Circle`.
+    fn variant(&self, path: &str) -> Option<&'a VariantKind> {
+        let (enum_name, name) = path.rsplit_once("::")?;
+        self.module.items.iter().find_map(|item| match &item.data {
+            ItemKind::Enum(decl) if decl.name.data == enum_name => decl
+                .variants
+                .iter()
+                .map(|variant| &variant.data)
+                .find(|variant| variant.name.data == name),
+            _ => None,
+        })
+    }
+
+    /// The names of the fields of a struct or of an enum variant, in the
+    /// order they were declared.
+    fn field_names(&self, path: &str) -> Option<Vec<&'a str>> {
+        let fields =
+            self.module.items.iter().find_map(|item| match &item.data {
+                ItemKind::Struct(decl) if decl.name.data == path => {
+                    Some(&decl.fields)
+                }
+                _ => None,
+            });
+        let fields = match fields {
+            Some(fields) => fields,
+            None => match &self.variant(path)?.fields {
+                VariantFields::Named(fields) => fields,
+                _ => return None,
+            },
+        };
+
+        Some(fields.iter().map(|field| &*field.data.name.data).collect())
+    }
+
+    /// The parameters and body of a piece of code, or `None` for the value
+    /// of a variable declared at the top level.
+    fn body(&self, code: &Code) -> Option<(&'a [Param], &'a Block)> {
+        match &code.target {
+            Target::Func(name) => {
+                let func = self.func(name)?;
+                Some((&func.params, &func.body))
+            }
+            Target::Method { index, name } => {
+                let decl = self.impls().nth(*index)?;
+                let method = decl
+                    .methods
+                    .iter()
+                    .find(|method| method.data.name.data == *name)?;
+                Some((&method.data.params, &method.data.body))
+            }
+            Target::Default { trait_name, method } => {
+                let method = self.trait_method(trait_name, method)?;
+                Some((&method.params, method.default.as_ref()?))
+            }
+            Target::Global(_) => None,
+        }
+    }
+
+    /// Give back the code that the call at `span` in `frame` was found to
+    /// call when monomorphizing.
+    fn instance(&self, frame: &Frame, span: Span) -> Option<Code> {
+        let instance = self.mono.calls.get(&(frame.code.clone(), span))?;
+        self.mono.code.get(instance).cloned()
+    }
+
+    /// Find the method called `method` for a value with no instance to call,
+    /// from the first impl for its type that has it or whose trait has a
+    /// default body for it.
+    fn dispatch(&self, receiver: &Value, method: &str) -> Option<Code> {
+        for (index, decl) in self.impls().enumerate() {
+            let params: Vec<_> = decl
+                .generics
+                .iter()
+                .map(|param| &param.data.name.data)
+                .collect();
+            if !fits(&decl.self_ty, &params, receiver) {
+                continue;
+            }
+
+            let target = if decl
+                .methods
+                .iter()
+                .any(|other| other.data.name.data == method)
+            {
+                Target::Method {
+                    index,
+                    name: String::from(method),
+                }
+            } else {
+                let trait_name = path_to_string(&decl.trait_ref.data.path.data);
+                match self.trait_method(&trait_name, method) {
+                    Some(decl) if decl.default.is_some() => Target::Default {
+                        trait_name,
+                        method: String::from(method),
+                    },
+                    _ => continue,
+                }
+            };
+
+            return Some(Code {
+                target,
+                args: vec![],
+            });
+        }
+
+        None
+    }
+
+    /// Run `code` with the arguments `args`, from a call at `span`.
+    fn call_code(
+        &mut self,
+        code: &Code,
+        args: Vec<Value>,
+        span: Span,
+    ) -> Eval<Value> {
+        let Some((params, body)) = self.body(code) else {
+            let err =
+                EvalError::NotCallable(Value::Func(code.clone()).to_string());
+            return error(err, span);
+        };
+        if params.len() != args.len() {
+            let err = EvalError::WrongArgCount {
+                expected: params.len(),
+                found: args.len(),
+            };
+            return error(err, span);
+        }
+
+        let names = params.iter().map(|param| param.data.name.data.clone());
+        let mut frame = Frame {
+            code: code.clone(),
+            scopes: vec![names.zip(args).collect()],
+        };
+
+        self.enter(span)?;
+        let result = self.block(&mut frame, body);
+        self.depth -= 1;
+        returned(result)
+    }
+
+    fn call_lambda(
+        &mut self,
+        closure: &Closure,
+        args: Vec<Value>,
+        span: Span,
+    ) -> Eval<Value> {
+        if closure.params.len() != args.len() {
+            let err = EvalError::WrongArgCount {
+                expected: closure.params.len(),
+                found: args.len(),
+            };
+            return error(err, span);
+        }
+
+        let params = closure.params.iter().cloned().zip(args).collect();
+        let mut frame = Frame {
+            code: closure.code.clone(),
+            scopes: vec![closure.captured.clone(), params],
+        };
+
+        self.enter(span)?;
+        let result = self.expr(&mut frame, &closure.body);
+        self.depth -= 1;
+        returned(result)
+    }
+
+    /// Call a value with the arguments `args`, from a call at `span`.
+    fn call_value(
+        &mut self,
+        func: Value,
+        args: Vec<Value>,
+        span: Span,
+    ) -> Eval<Value> {
+        match func {
+            Value::Func(code) => self.call_code(&code, args, span),
+            Value::Constructor(name) => {
+                let expected =
+                    match self.variant(&name).map(|decl| &decl.fields) {
+                        Some(VariantFields::Tuple(fields)) => fields.len(),
+                        _ => args.len(),
+                    };
+                if expected != args.len() {
+                    let err = EvalError::WrongArgCount {
+                        expected,
+                        found: args.len(),
+                    };
+                    return error(err, span);
+                }
+
+                Ok(Value::Variant { name, values: args })
+            }
+            Value::Lambda(closure) => self.call_lambda(&closure, args, span),
+            Value::Composed(funcs) => {
+                let (outer, inner) = &*funcs;
+                let value = self.call_value(inner.clone(), args, span)?;
+                self.call_value(outer.clone(), vec![value], span)
+            }
+            value => error(EvalError::NotCallable(value.to_string()), span),
+        }
+    }
+
+    /// Count another function being run, from a call at `span`.
+    fn enter(&mut self, span: Span) -> Eval<()> {
+        if self.depth >= MAX_CALLS {
+            return error(EvalError::TooDeep, span);
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn block(&mut self, frame: &mut Frame, block: &Block) -> Eval<Value> {
+        frame.scopes.push(BTreeMap::new());
+        let result = self.block_inner(frame, block);
+        frame.scopes.pop();
+        result
+    }
+
+    fn block_inner(&mut self, frame: &mut Frame, block: &Block) -> Eval<Value> {
+        for stmt in &block.data.stmts {
+            self.stmt(frame, stmt)?;
+        }
+
+        match &block.data.tail {
+            Some(tail) => self.expr(frame, tail),
+            None => Ok(Value::Unit),
+        }
+    }
+
+    fn stmt(&mut self, frame: &mut Frame, stmt: &Stmt) -> Eval<()> {
+        match &stmt.data {
+            StmtKind::Let(decl) => {
+                let value = self.expr(frame, &decl.value)?;
+                frame.declare(&decl.name.data, value);
+            }
+            StmtKind::Return(value) => {
+                let value = match value {
+                    Some(value) => self.expr(frame, value)?,
+                    None => Value::Unit,
+                };
+                return Err(Flow::Return(value));
+            }
+            StmtKind::Assign { target, op, value } => {
+                let mut value = self.expr(frame, value)?;
+                if *op == AssignOp::AddAssign {
+                    let current = self.expr(frame, target)?;
+                    value = binary(BinOp::Add, current, value)
+                        .map_err(|err| Spanned::new(err, stmt.span))?;
+                }
+                assign(frame, target, value)?;
+            }
+            StmtKind::Break { label, value } => {
+                let value = match value {
+                    Some(value) => self.expr(frame, value)?,
+                    None => Value::Unit,
+                };
+                return Err(Flow::Break {
+                    label: label.as_ref().map(|label| label.data.clone()),
+                    value,
+                    span: stmt.span,
+                });
+            }
+            StmtKind::Continue { label } => {
+                return Err(Flow::Continue {
+                    label: label.as_ref().map(|label| label.data.clone()),
+                    span: stmt.span,
+                })
+            }
+            StmtKind::Expr(expr) => {
+                self.expr(frame, expr)?;
+            }
+            StmtKind::Error => {
+                return error(EvalError::Unsupported("broken code"), stmt.span)
+            }
+        }
+
+        Ok(())
+    }
+
+    fn expr(&mut self, frame: &mut Frame, expr: &Expr) -> Eval<Value> {
+        let span = expr.span;
+        match &expr.data {
+            ExprKind::Literal(lit) => match literal(lit) {
+                Ok(value) => Ok(value),
+                Err(err) => error(err, span),
+            },
+            ExprKind::Ident(name) => self.ident(frame, name, span),
+            ExprKind::Path(segments) => {
+                self.path(&path_to_string(segments), span)
+            }
+            ExprKind::Instantiate { path, .. } => match path.data.as_slice() {
+                [name] => self.func_value(frame, &name.data, span),
+                segments => self.path(&path_to_string(segments), span),
+            },
+            ExprKind::MacroCall { .. } => {
+                error(EvalError::Unsupported("macro calls"), span)
+            }
+            ExprKind::Unary { op, operand } => {
+                let value = self.expr(frame, operand)?;
+                unary(*op, value).or_else(|err| error(err, span))
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let lhs = self.expr(frame, lhs)?;
+                // `&&` and `||` only look at their right side when they have
+                // to
+                match (op, &lhs) {
+                    (BinOp::And, Value::Bool(false)) => return Ok(lhs),
+                    (BinOp::Or, Value::Bool(true)) => return Ok(lhs),
+                    _ => {}
+                }
+                let rhs = self.expr(frame, rhs)?;
+                binary(*op, lhs, rhs).or_else(|err| error(err, span))
+            }
+            ExprKind::Call { callee, args } => {
+                self.call(frame, callee, args, span)
+            }
+            ExprKind::Field { base, field } => {
+                let value = self.expr(frame, base)?;
+                field_of(value, field)
+            }
+            ExprKind::StructLit { path, fields, base } => {
+                let name = path_to_string(&path.data);
+                self.struct_lit(frame, name, fields, base.as_deref())
+            }
+            ExprKind::Paren(inner) => self.expr(frame, inner),
+            ExprKind::Block(block) => self.block(frame, block),
+            ExprKind::If {
+                cond,
+                then,
+                else_branch,
+            } => {
+                if self.condition(frame, cond)? {
+                    self.block(frame, then)
+                } else if let Some(else_branch) = else_branch {
+                    self.expr(frame, else_branch)
+                } else {
+                    Ok(Value::Unit)
+                }
+            }
+            ExprKind::While { label, cond, body } => {
+                while self.condition(frame, cond)? {
+                    let result = self.block(frame, body);
+                    if let Leave::Break(_) = leave(result, label)? {
+                        break;
+                    }
+                }
+                Ok(Value::Unit)
+            }
+            ExprKind::Loop { label, body } => loop {
+                let result = self.block(frame, body);
+                if let Leave::Break(value) = leave(result, label)? {
+                    return Ok(value);
+                }
+            },
+            ExprKind::For {
+                label,
+                binding,
+                iter,
+                body,
+            } => self.for_loop(frame, label, binding, iter, body),
+            ExprKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let start = self.expr(frame, start)?;
+                let end = self.expr(frame, end)?;
+                match (start, end) {
+                    (Value::Int(start), Value::Int(end)) => Ok(Value::Range {
+                        start,
+                        end,
+                        inclusive: *inclusive,
+                    }),
+                    (Value::Int(_), value) | (value, _) => {
+                        error(EvalError::NotIterable(value.to_string()), span)
+                    }
+                }
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                self.match_expr(frame, scrutinee, arms, span)
+            }
+            ExprKind::Lambda { params, body, .. } => {
+                let closure = Closure {
+                    params: params
+                        .iter()
+                        .map(|param| param.data.name.data.clone())
+                        .collect(),
+                    body: (**body).clone(),
+                    captured: frame.visible(),
+                    code: frame.code.clone(),
+                };
+                Ok(Value::Lambda(Rc::new(closure)))
+            }
+            ExprKind::Error => {
+                error(EvalError::Unsupported("broken code"), span)
+            }
+        }
+    }
+
+    fn for_loop(
+        &mut self,
+        frame: &mut Frame,
+        label: &Option<Ident>,
+        binding: &Ident,
+        iter: &Expr,
+        body: &Block,
+    ) -> Eval<Value> {
+        let (start, last) = match self.expr(frame, iter)? {
+            Value::Range {
+                start,
+                end,
+                inclusive: true,
+            } => (start, end),
+            Value::Range { start, end, .. } if start < end => (start, end - 1),
+            Value::Range { .. } => return Ok(Value::Unit),
+            value => {
+                let err = EvalError::NotIterable(value.to_string());
+                return error(err, iter.span);
+            }
+        };
+
+        let mut next = Some(start);
+        while let Some(idx) = next.filter(|idx| *idx <= last) {
+            next = idx.checked_add(1);
+
+            frame.scopes.push(BTreeMap::new());
+            frame.declare(&binding.data, Value::Int(idx));
+            let result = self.block(frame, body);
+            frame.scopes.pop();
+            if let Leave::Break(_) = leave(result, label)? {
+                break;
+            }
+        }
+
+        Ok(Value::Unit)
+    }
+
+    fn match_expr(
+        &mut self,
+        frame: &mut Frame,
+        scrutinee: &Expr,
+        arms: &[Arm],
+        span: Span,
+    ) -> Eval<Value> {
+        let value = self.expr(frame, scrutinee)?;
+        for arm in arms {
+            let mut bindings = BTreeMap::new();
+            if !matches(&arm.data.pat, &value, &mut bindings)? {
+                continue;
+            }
+
+            frame.scopes.push(bindings);
+            let result = self.arm(frame, &arm.data.guard, &arm.data.body);
+            frame.scopes.pop();
+            if let Some(value) = result? {
+                return Ok(value);
+            }
+        }
+
+        error(EvalError::NoArmMatched(value.to_string()), span)
+    }
+
+    /// Run the body of a `match` arm whose pattern matched, giving back
+    /// `None` when its guard doesn't hold.
+    fn arm(
+        &mut self,
+        frame: &mut Frame,
+        guard: &Option<Expr>,
+        body: &Expr,
+    ) -> Eval<Option<Value>> {
+        if let Some(guard) = guard {
+            if !self.condition(frame, guard)? {
+                return Ok(None);
+            }
+        }
+
+        self.expr(frame, body).map(Some)
+    }
+
+    fn condition(&mut self, frame: &mut Frame, cond: &Expr) -> Eval<bool> {
+        match self.expr(frame, cond)? {
+            Value::Bool(value) => Ok(value),
+            value => error(EvalError::NotBool(value.to_string()), cond.span),
+        }
+    }
+
+    /// The value of a name, which is a variable, then a function and then a
+    /// variable declared at the top level.
+    fn ident(
+        &mut self,
+        frame: &mut Frame,
+        name: &str,
+        span: Span,
+    ) -> Eval<Value> {
+        if let Some(value) = frame.lookup(name) {
+            return Ok(value.clone());
+        }
+        if self.func(name).is_some() {
+            return self.func_value(frame, name, span);
+        }
+
+        let Some(decl) = self.global(name) else {
+            return error(EvalError::Unknown(String::from(name)), span);
+        };
+        let mut frame = Frame {
+            code: Code {
+                target: Target::Global(String::from(name)),
+                args: vec![],
+            },
+            scopes: vec![BTreeMap::new()],
+        };
+
+        // A variable declared with itself would never stop being evaluated
+        self.enter(span)?;
+        let result = self.expr(&mut frame, &decl.value);
+        self.depth -= 1;
+        returned(result)
+    }
+
+    /// The function called `name`, as the instance of it that was found to
+    /// be used at `span`.
+    fn func_value(&self, frame: &Frame, name: &str, span: Span) -> Eval<Value> {
+        let code = self.instance(frame, span).unwrap_or_else(|| Code {
+            target: Target::Func(String::from(name)),
+            args: vec![],
+        });
+        Ok(Value::Func(code))
+    }
+
+    /// The value of a path, which is an enum variant.
+    fn path(&self, path: &str, span: Span) -> Eval<Value> {
+        match self.variant(path).map(|decl| &decl.fields) {
+            Some(VariantFields::Unit) => Ok(Value::Variant {
+                name: String::from(path),
+                values: vec![],
+            }),
+            Some(VariantFields::Tuple(_)) => {
+                Ok(Value::Constructor(String::from(path)))
+            }
+            _ => error(EvalError::Unknown(String::from(path)), span),
+        }
+    }
+
+    /// Run a call, where a call to something that isn't a field, like
+    /// `x.show()`, is a call to a method.
+    fn call(
+        &mut self,
+        frame: &mut Frame,
+        callee: &Expr,
+        args: &[Expr],
+        span: Span,
+    ) -> Eval<Value> {
+        let func = match &callee.data {
+            ExprKind::Field { base, field } => {
+                let receiver = self.expr(frame, base)?;
+                if has_field(&receiver, &field.data) {
+                    field_of(receiver, field)?
+                } else {
+                    let code = match self.instance(frame, callee.span) {
+                        Some(code) => code,
+                        None => match self.dispatch(&receiver, &field.data) {
+                            Some(code) => code,
+                            None => {
+                                let err = EvalError::NoMethod {
+                                    value: receiver.to_string(),
+                                    method: field.data.clone(),
+                                };
+                                return error(err, field.span);
+                            }
+                        },
+                    };
+
+                    // The receiver is passed as the first argument
+                    let mut values = vec![receiver];
+                    for arg in args {
+                        values.push(self.expr(frame, arg)?);
+                    }
+                    return self.call_code(&code, values, span);
+                }
+            }
+            _ => self.expr(frame, callee)?,
+        };
+
+        let mut values = vec![];
+        for arg in args {
+            values.push(self.expr(frame, arg)?);
+        }
+        self.call_value(func, values, span)
+    }
+
+    fn struct_lit(
+        &mut self,
+        frame: &mut Frame,
+        name: String,
+        inits: &[FieldInit],
+        base: Option<&Expr>,
+    ) -> Eval<Value> {
+        let mut fields = vec![];
+        for init in inits {
+            let field = &init.data.name;
+            let value = match &init.data.value {
+                Some(value) => self.expr(frame, value)?,
+                None => self.ident(frame, &field.data, field.span)?,
+            };
+            fields.push((field.data.clone(), value));
+        }
+
+        if let Some(base) = base {
+            match self.expr(frame, base)? {
+                Value::Struct {
+                    name: other,
+                    fields: rest,
+                } if other == name => {
+                    for (field, value) in rest {
+                        if !fields.iter().any(|(other, _)| *other == field) {
+                            fields.push((field, value));
+                        }
+                    }
+                }
+                value => {
+                    let err = EvalError::WrongBase {
+                        name,
+                        value: value.to_string(),
+                    };
+                    return error(err, base.span);
+                }
+            }
+        }
+
+        if let Some(names) = self.field_names(&name) {
+            fields.sort_by_key(|(field, _)| {
+                names.iter().position(|other| other == field)
+            });
+        }
+
+        Ok(Value::Struct { name, fields })
+    }
+}
+
+/// Give back what a function returned, once it has stopped running.
+fn returned(result: Eval<Value>) -> Eval<Value> {
+    match result {
+        Ok(value) | Err(Flow::Return(value)) => Ok(value),
+        Err(Flow::Break { span, .. } | Flow::Continue { span, .. }) => {
+            error(EvalError::NotInLoop, span)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Decide what a loop does after its body has run, where `label` is the
+/// label of the loop. A `break` or `continue` for a loop around it is passed
+/// on.
+fn leave(result: Eval<Value>, label: &Option<Ident>) -> Eval<Leave> {
+    let is_own = |other: &Option<String>| match (other, label) {
+        (None, _) => true,
+        (Some(other), Some(label)) => *other == label.data,
+        (Some(_), None) => false,
+    };
+
+    match result {
+        Ok(_) => Ok(Leave::Continue),
+        Err(Flow::Break { label, value, .. }) if is_own(&label) => {
+            Ok(Leave::Break(value))
+        }
+        Err(Flow::Continue { label, .. }) if is_own(&label) => {
+            Ok(Leave::Continue)
+        }
+        Err(flow) => Err(flow),
+    }
+}
+
+/// Change the variable or field that `target` names to `value`.
+fn assign(frame: &mut Frame, target: &Expr, value: Value) -> Eval<()> {
+    // The fields to go through, from the variable to the one being changed
+    let mut fields = vec![];
+    let mut root = target;
+    loop {
+        match &root.data {
+            ExprKind::Field { base, field } => {
+                fields.push(field);
+                root = base;
+            }
+            ExprKind::Paren(inner) => root = inner,
+            _ => break,
+        }
+    }
+
+    let ExprKind::Ident(name) = &root.data else {
+        return error(EvalError::Unsupported("assigning to this"), target.span);
+    };
+    let Some(mut place) = frame.lookup_mut(name) else {
+        return error(EvalError::Unknown(name.clone()), root.span);
+    };
+
+    for field in fields.into_iter().rev() {
+        if !has_field(place, &field.data) {
+            let err = EvalError::NoField {
+                value: place.to_string(),
+                field: field.data.clone(),
+            };
+            return error(err, field.span);
+        }
+        if let Value::Struct { fields, .. } = place {
+            let found =
+                fields.iter_mut().find(|(other, _)| *other == field.data);
+            place = &mut found.unwrap().1;
+        }
+    }
+
+    *place = value;
+    Ok(())
+}
+
+fn has_field(value: &Value, name: &str) -> bool {
+    match value {
+        Value::Struct { fields, .. } => {
+            fields.iter().any(|(field, _)| field == name)
+        }
+        _ => false,
+    }
+}
+
+fn field_of(value: Value, field: &Ident) -> Eval<Value> {
+    if let Value::Struct { fields, .. } = &value {
+        if let Some((_, value)) =
+            fields.iter().find(|(name, _)| *name == field.data)
+        {
+            return Ok(value.clone());
+        }
+    }
+
+    let err = EvalError::NoField {
+        value: value.to_string(),
+        field: field.data.clone(),
+    };
+    error(err, field.span)
+}
+
+/// Checks if a value could be of the type `ty` that an impl is for, where
+/// `params` are the type parameters of the impl.
+fn fits(ty: &Ty, params: &[&String], value: &Value) -> bool {
+    let name = match &ty.data {
+        TyKind::Named(name) => name,
+        TyKind::Generic { name, .. } => &name.data,
+        TyKind::Mut(ty) | TyKind::Paren(ty) => return fits(ty, params, value),
+        TyKind::Ref { ty, .. } => {
+            return matches!(
+                (&ty.data, value),
+                (TyKind::Named(name), Value::Str(_)) if name == "str"
+            )
+        }
+        _ => return false,
+    };
+    if params.contains(&name) {
+        return true;
+    }
+
+    match value {
+        Value::Int(_) => is_integer(name),
+        Value::Bool(_) => name == "bool",
+        Value::Char(_) => name == "char",
+        // A variant is of the type of its enum
+        Value::Struct { name: path, .. }
+        | Value::Variant { name: path, .. } => {
+            let ty_name = path.rsplit_once("::").map_or(&**path, |(ty, _)| ty);
+            ty_name == name
+        }
+        _ => false,
+    }
+}
+
+fn literal(lit: &Literal) -> Result<Value, EvalError> {
+    match lit {
+        Literal::Number { .. } => {
+            lit.as_integer().map(Value::Int).ok_or(EvalError::Overflow)
+        }
+        Literal::Str(text) => Ok(Value::Str(text.clone())),
+        Literal::Char(chr) => Ok(Value::Char(*chr)),
+        Literal::Byte(byte) => Ok(Value::Int(i128::from(*byte))),
+        Literal::ByteStr(_) => Err(EvalError::Unsupported("byte strings")),
+    }
+}
+
+fn unary(op: UnaryOp, value: Value) -> Result<Value, EvalError> {
+    match (op, value) {
+        (UnaryOp::Neg, Value::Int(value)) => checked(value.checked_neg()),
+        (UnaryOp::Not, Value::Bool(value)) => Ok(Value::Bool(!value)),
+        (UnaryOp::Not | UnaryOp::BitNot, Value::Int(value)) => {
+            Ok(Value::Int(!value))
+        }
+        (op, value) => Err(EvalError::InvalidOperand {
+            op,
+            value: value.to_string(),
+        }),
+    }
+}
+
+fn binary(op: BinOp, lhs: Value, rhs: Value) -> Result<Value, EvalError> {
+    use Value::{Bool, Int};
+
+    let ord = compare(&lhs, &rhs);
+    match (op, lhs, rhs, ord) {
+        (BinOp::Eq, lhs, rhs, _) => Ok(Bool(lhs == rhs)),
+        (BinOp::Ne, lhs, rhs, _) => Ok(Bool(lhs != rhs)),
+        (BinOp::Lt, .., Some(ord)) => Ok(Bool(ord.is_lt())),
+        (BinOp::Le, .., Some(ord)) => Ok(Bool(ord.is_le())),
+        (BinOp::Gt, .., Some(ord)) => Ok(Bool(ord.is_gt())),
+        (BinOp::Ge, .., Some(ord)) => Ok(Bool(ord.is_ge())),
+        (BinOp::And, Bool(a), Bool(b), _) => Ok(Bool(a && b)),
+        (BinOp::Or, Bool(a), Bool(b), _) => Ok(Bool(a || b)),
+        (BinOp::BitAnd, Bool(a), Bool(b), _) => Ok(Bool(a & b)),
+        (BinOp::BitOr, Bool(a), Bool(b), _) => Ok(Bool(a | b)),
+        (BinOp::BitXor, Bool(a), Bool(b), _) => Ok(Bool(a ^ b)),
+        (BinOp::Compose, outer, inner, _) => {
+            Ok(Value::Composed(Rc::new((outer, inner))))
+        }
+        (BinOp::Div | BinOp::Rem, Int(_), Int(0), _) => {
+            Err(EvalError::DivideByZero)
+        }
+        (BinOp::Add, Int(a), Int(b), _) => checked(a.checked_add(b)),
+        (BinOp::Sub, Int(a), Int(b), _) => checked(a.checked_sub(b)),
+        (BinOp::Mul, Int(a), Int(b), _) => checked(a.checked_mul(b)),
+        (BinOp::Div, Int(a), Int(b), _) => checked(a.checked_div(b)),
+        (BinOp::Rem, Int(a), Int(b), _) => checked(a.checked_rem(b)),
+        (BinOp::BitAnd, Int(a), Int(b), _) => Ok(Int(a & b)),
+        (BinOp::BitOr, Int(a), Int(b), _) => Ok(Int(a | b)),
+        (BinOp::BitXor, Int(a), Int(b), _) => Ok(Int(a ^ b)),
+        (BinOp::Shl, Int(a), Int(b), _) => {
+            checked(u32::try_from(b).ok().and_then(|b| a.checked_shl(b)))
+        }
+        (BinOp::Shr, Int(a), Int(b), _) => {
+            checked(u32::try_from(b).ok().and_then(|b| a.checked_shr(b)))
+        }
+        (op, lhs, rhs, _) => Err(EvalError::InvalidOperands {
+            op,
+            lhs: lhs.to_string(),
+            rhs: rhs.to_string(),
+        }),
+    }
+}
+
+fn checked(value: Option<i128>) -> Result<Value, EvalError> {
+    value.map(Value::Int).ok_or(EvalError::Overflow)
+}
+
+/// Compare two values with `<`, which only works on values of the same type
+/// which have an order.
+fn compare(lhs: &Value, rhs: &Value) -> Option<Ordering> {
+    match (lhs, rhs) {
+        (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+        (Value::Char(a), Value::Char(b)) => Some(a.cmp(b)),
+        (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// Checks if `value` matches `pat`, adding the variables it binds to
+/// `bindings`.
+fn matches(
+    pat: &Pat,
+    value: &Value,
+    bindings: &mut BTreeMap<String, Value>,
+) -> Eval<bool> {
+    let matched = match (&pat.data, value) {
+        (PatKind::Wild, _) => true,
+        (PatKind::Binding(name), _) => {
+            bindings.insert(name.clone(), value.clone());
+            true
+        }
+        (PatKind::Path(segments), Value::Variant { name, values }) => {
+            *name == path_to_string(segments) && values.is_empty()
+        }
+        (
+            PatKind::TupleStruct { path, elems },
+            Value::Variant { name, values },
+        ) => {
+            if *name != path_to_string(&path.data)
+                || values.len() != elems.len()
+            {
+                return Ok(false);
+            }
+            for (elem, value) in elems.iter().zip(values) {
+                if !matches(elem, value, bindings)? {
+                    return Ok(false);
+                }
+            }
+            true
+        }
+        (
+            PatKind::Struct { path, fields, .. },
+            Value::Struct {
+                name,
+                fields: values,
+            },
+        ) => {
+            if *name != path_to_string(&path.data) {
+                return Ok(false);
+            }
+            for field in fields {
+                let name = &field.data.name.data;
+                let Some((_, value)) =
+                    values.iter().find(|(other, _)| other == name)
+                else {
+                    return Ok(false);
+                };
+                let matched = match &field.data.pat {
+                    Some(pat) => matches(pat, value, bindings)?,
+                    None => {
+                        bindings.insert(name.clone(), value.clone());
+                        true
+                    }
+                };
+                if !matched {
+                    return Ok(false);
+                }
+            }
+            true
+        }
+        (PatKind::Literal(lit), _) => match literal(lit) {
+            Ok(lit) => lit == *value,
+            Err(err) => return error(err, pat.span),
+        },
+        (
+            PatKind::Range {
+                start,
+                end,
+                inclusive,
+            },
+            _,
+        ) => {
+            let (PatKind::Literal(start), PatKind::Literal(end)) =
+                (&start.data, &end.data)
+            else {
+                return error(EvalError::Unsupported("this range"), pat.span);
+            };
+            let start = literal(start).or_else(|err| error(err, pat.span))?;
+            let end = literal(end).or_else(|err| error(err, pat.span))?;
+            let above = compare(value, &start).is_some_and(|ord| ord.is_ge());
+            let below =
+                compare(value, &end).is_some_and(|ord| match inclusive {
+                    true => ord.is_le(),
+                    false => ord.is_lt(),
+                });
+            above && below
+        }
+        (PatKind::Tuple(_), _) => {
+            return error(EvalError::Unsupported("tuples"), pat.span)
+        }
+        (PatKind::Paren(inner), _) => matches(inner, value, bindings)?,
+        (PatKind::Or(pats), _) => {
+            for pat in pats {
+                let mut inner = BTreeMap::new();
+                if matches(pat, value, &mut inner)? {
+                    bindings.extend(inner);
+                    return Ok(true);
+                }
+            }
+            false
+        }
+        (PatKind::At { name, pat }, _) => {
+            if !matches(pat, value, bindings)? {
+                return Ok(false);
+            }
+            bindings.insert(name.data.clone(), value.clone());
+            true
+        }
+        (PatKind::Error, _) => {
+            return error(EvalError::Unsupported("broken code"), pat.span)
+        }
+        _ => false,
+    };
+
+    Ok(matched)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::{generics::monomorphize, parser::parse};
+
+    /// Run `main` in `input`, failing if it didn't parse or instantiate.
+    fn run_main(input: &str) -> Result<Value, Spanned<EvalError>> {
+        let (module, errors) = parse(input);
+        assert_eq!(errors, vec![], "{input}");
+        let mono = monomorphize(&module);
+        assert_eq!(mono.errors, vec![], "{input}");
+
+        run(&module, &mono, "main")
+    }
+
+    fn output(input: &str) -> String {
+        match run_main(input) {
+            Ok(value) => value.to_string(),
+            Err(err) => panic!("{}: {input}", err.data),
+        }
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let input = concat!(
+            "func sum_to(n: usize, limit: usize) : usize = {\n",
+            "    let sum : mut usize = 0\n",
+            "    'outer: for i in 1..=n {\n",
+            "        if i % 3 == 0 { continue }\n",
+            "        while i > 0 {\n",
+            "            if sum > limit { break 'outer }\n",
+            "            break\n",
+            "        }\n",
+            "        sum += i\n",
+            "    }\n",
+            "    return sum\n",
+            "}\n",
+            "func collatz(n: mut usize) : usize = {\n",
+            "    let steps : mut usize = 0\n",
+            "    while n != 1 {\n",
+            "        n = if n % 2 == 0 { n / 2 } else { 3 * n + 1 }\n",
+            "        steps += 1\n",
+            "    }\n",
+            "    steps\n",
+            "}\n",
+            "func main() := {\n",
+            "    let first := loop { break sum_to(10, 20) }\n",
+            "    first * 1000 + collatz(27)\n",
+            "}",
+        );
+
+        assert_eq!(output(input), "27111");
+    }
+
+    #[test]
+    fn test_default_methods_through_bounds() {
+        let input = concat!(
+            "trait Describe {\n",
+            "    func name(self) : &str\n",
+            "    func describe(self) : &str = { self.name() }\n",
+            "}\n",
+            "struct Pair<T> { a: T, b: T }\n",
+            "impl Describe for u8 { func name(self) : &str = { \"a byte\" } }\n",
+            "impl<T: Describe> Describe for Pair<T> {\n",
+            "    func name(self) : &str = { self.a.name() }\n",
+            "}\n",
+            "func describe_all<T: Describe>(first: T, second: T) : &str = {\n",
+            "    first.describe()\n",
+            "    second.describe()\n",
+            "}\n",
+            "func main() := {\n",
+            "    let byte : u8 = 1\n",
+            "    let pair := Pair { a: byte, b: 2 }\n",
+            "    describe_all(pair, pair)\n",
+            "}",
+        );
+
+        assert_eq!(output(input), "\"a byte\"");
+    }
+
+    #[test]
+    fn test_methods_run_the_impl_of_their_instance() {
+        // Both impls are for integers, so only the type that `x` was
+        // inferred as tells them apart
+        let input = concat!(
+            "trait Size { func size(self) : usize }\n",
+            "impl Size for u8 { func size(self) : usize = { 1 } }\n",
+            "impl Size for u16 { func size(self) : usize = { 2 } }\n",
+            "func size_of<T: Size>(x: T) : usize = { x.size() }\n",
+            "func main() := {\n",
+            "    let a : u8 = 1\n",
+            "    let b : u16 = 1\n",
+            "    size_of(a) * 10 + b.size()\n",
+            "}",
+        );
+
+        assert_eq!(output(input), "12");
+    }
+
+    #[test]
+    fn test_lambdas_and_composition() {
+        let input = concat!(
+            "func add(a: usize, b: usize) : usize = { a + b }\n",
+            "func main() := {\n",
+            "    let offset := 10\n",
+            "    let shifted := (|x: usize| -> usize { x + offset }) ** add\n",
+            "    offset = 0\n",
+            "    shifted(-5, 2)\n",
+            "}",
+        );
+
+        assert_eq!(output(input), "7");
+    }
+
+    #[test]
+    fn test_structs_and_enums() {
+        let input = concat!(
+            "struct Point { x: i32, y: i32 }\n",
+            "enum Shape { Circle(i32), Rect { w: i32, h: i32 }, Empty }\n",
+            "func area(shape: Shape) : i32 = {\n",
+            "    match shape {\n",
+            "        Shape::Circle(r) => 3 * r * r,\n",
+            "        Shape::Rect { w, h } => w * h,\n",
+            "        Shape::Empty => 0,\n",
+            "    }\n",
+            "}\n",
+            "func main() := {\n",
+            "    let p := Point { y: 2, x: 1 }\n",
+            "    p.x += area(Shape::Rect { h: 2, w: 4 }) + area(Shape::Empty)\n",
+            "    Point { y: area(Shape::Circle(1)), ..p }\n",
+            "}",
+        );
+
+        assert_eq!(output(input), "Point { x: 9, y: 3 }");
+    }
+
+    #[test]
+    fn test_patterns() {
+        let input = concat!(
+            "func kind(c: char) : usize = {\n",
+            "    match c {\n",
+            "        '0'..='9' => 0,\n",
+            "        'a'..='z' | 'A'..='Z' => 1,\n",
+            "        _ => 2,\n",
+            "    }\n",
+            "}\n",
+            "func bucket(n: i32) : i32 = {\n",
+            "    match n {\n",
+            "        0 => 0\n",
+            "        x if x < 0 => -1\n",
+            "        n @ 1..0b100 => n * 2\n",
+            "        _ => 0o17\n",
+            "    }\n",
+            "}\n",
+            "func main() := {\n",
+            "    kind('7') + kind('q') * 10 + kind('!') * 100\n",
+            "        + bucket(3) * 1000 + bucket(-4) + bucket(9)\n",
+            "}",
+        );
+
+        assert_eq!(output(input), "6224");
+    }
+
+    #[test]
+    fn test_runtime_errors() {
+        let err = run_main("func main() := { let a := 0; 1 / a }");
+        assert_eq!(
+            err,
+            Err(Spanned::new_span(EvalError::DivideByZero, 29, 34))
+        );
+
+        let err = run_main(concat!(
+            "func main() := {\n",
+            "    let a := 170141183460469231731687303715884105727\n",
+            "    a + 1\n",
+            "}",
+        ));
+        assert_eq!(err.unwrap_err().data, EvalError::Overflow);
+
+        let err = run_main(
+            "func f(n: usize) : usize = { f(n + 1) }\nfunc main() := { f(0) }",
+        );
+        assert_eq!(err.unwrap_err().data, EvalError::TooDeep);
+
+        let (module, _) = parse("func start() := {}");
+        let err = run(&module, &Monomorphization::default(), "main");
+        assert_eq!(
+            err.unwrap_err().data,
+            EvalError::NoEntry("main".to_string())
+        );
+    }
+}
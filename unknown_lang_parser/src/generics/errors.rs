@@ -0,0 +1,107 @@
+use alloc::{string::String, vec::Vec};
+
+use super::types::Type;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GenericsError {
+    /// A type parameter of a generic function or struct which nothing
+    /// decides, like the `T` of `func none<T>() : Option<T>` called as
+    /// `none()`
+    CannotInfer { name: String, param: String },
+    /// A value given to a generic function or struct whose type doesn't fit
+    /// with what its type parameters were already inferred as, like the
+    /// `'a'` of `pair(1, 'a')` for `func pair<T>(a: T, b: T)`
+    Mismatch {
+        name: String,
+        expected: Type,
+        found: Type,
+    },
+    /// A generic function or type given the wrong number of type arguments,
+    /// like `id::<u8, u8>`
+    WrongArgCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A generic function which calls itself with ever larger type
+    /// arguments, so that it would never stop being instantiated
+    TooDeep(String),
+    /// A generic function which needs so many instances that it is most
+    /// likely making new ones forever
+    TooManyInstances(String),
+    /// A method called on a type which implements no trait with a method by
+    /// that name, like `1.show()` without an `impl Display for i32`
+    NoMethod { ty: Type, method: String },
+    /// A method called on a type which implements more than one trait with
+    /// a method by that name
+    AmbiguousMethod { method: String, traits: Vec<String> },
+    /// A type argument which doesn't implement a bound of its type
+    /// parameter, like the `u8` of `show(1u8)` for
+    /// `func show<T: Display>(x: T)` without an `impl Display for u8`
+    Unsatisfied { name: String, ty: Type, bound: Type },
+}
+
+impl core::fmt::Display for GenericsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::CannotInfer { name, param } => write!(
+                f,
+                "can't infer the type of `{param}` for `{name}`, give it \
+                 with `{name}::<..>` or the type of a variable"
+            ),
+            Self::Mismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{name}` expected `{expected}` here, but found `{found}`"
+            ),
+            Self::WrongArgCount {
+                name,
+                expected,
+                found,
+            } => {
+                let s = if *expected == 1 { "" } else { "s" };
+                write!(
+                    f,
+                    "`{name}` takes {expected} type argument{s}, but \
+                     {found} were given"
+                )
+            }
+            Self::TooDeep(name) => write!(
+                f,
+                "`{name}` calls itself with ever larger type arguments, so it \
+                 can't be instantiated"
+            ),
+            Self::TooManyInstances(name) => write!(
+                f,
+                "`{name}` would need more than {} instances of generic \
+                 functions and types, so it can't be instantiated",
+                super::MAX_INSTANCES
+            ),
+            Self::NoMethod { ty, method } => write!(
+                f,
+                "`{ty}` has no method `{method}`, since none of the traits \
+                 it implements have one"
+            ),
+            Self::AmbiguousMethod { method, traits } => {
+                write!(f, "`{method}` is a method of more than one trait: ")?;
+                for (idx, name) in traits.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "`{name}`")?;
+                }
+                Ok(())
+            }
+            Self::Unsatisfied { name, ty, bound } => write!(
+                f,
+                "`{name}` needs `{ty}` to implement `{bound}`, but it doesn't"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for GenericsError {}
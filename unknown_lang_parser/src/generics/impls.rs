@@ -0,0 +1,205 @@
+//! Finding the impl of a trait that a type uses, like the
+//! `impl<T: Display> Display for Pair<T>` used by a `Pair<u8>`.
+//!
+//! An impl applies to a type when the type it is for can be made the same as
+//! it, by filling in the type parameters of the impl. Those type arguments
+//! then have to implement the bounds of the impl too, which is found the same
+//! way. A type parameter implements the traits it is bounded by.
+
+use alloc::vec::Vec;
+
+use super::{
+    infer::{Bound, Decls},
+    types::{Type, Vars},
+};
+
+/// How deeply the bounds of impls are followed before giving up, for impls
+/// like `impl<T: Tr> Tr for T` which would need themselves.
+const MAX_DEPTH: usize = 16;
+
+/// How a type implements a trait.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Found {
+    /// It is a type parameter bounded by the trait
+    Bound,
+    /// By the impl at `index` in [`Decls::impls`], with `args` as the type
+    /// arguments of the impl
+    Impl { index: usize, args: Vec<Type> },
+}
+
+/// Find how `ty` implements the trait `trait_name` with the type arguments
+/// `args`, where `bounds` are the bounds of the type parameters in scope.
+/// Neither `ty` nor `args` can have any [`Type::Var`] in them.
+pub fn find_impl(
+    decls: &Decls,
+    bounds: &[Bound],
+    ty: &Type,
+    trait_name: &str,
+    args: &[Type],
+) -> Option<Found> {
+    find_at(decls, bounds, ty, trait_name, args, 0)
+}
+
+fn find_at(
+    decls: &Decls,
+    bounds: &[Bound],
+    ty: &Type,
+    trait_name: &str,
+    args: &[Type],
+    depth: usize,
+) -> Option<Found> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+
+    if let Type::Param(param) = ty {
+        let bounded = bounds.iter().any(|bound| {
+            bound.param == *param
+                && bound.trait_name == trait_name
+                && Vars::default().unify_all(&bound.args, args)
+        });
+        if bounded {
+            return Some(Found::Bound);
+        }
+    }
+
+    for (index, decl) in decls.impls.iter().enumerate() {
+        if decl.trait_name != trait_name {
+            continue;
+        }
+
+        let mut vars = Vars::default();
+        let fresh: Vec<_> =
+            decl.generics.iter().map(|_| vars.fresh()).collect();
+        let self_ty = decl.self_ty.subst(&decl.generics, &fresh);
+        let trait_args: Vec<_> = decl
+            .trait_args
+            .iter()
+            .map(|arg| arg.subst(&decl.generics, &fresh))
+            .collect();
+
+        if !vars.unify(&self_ty, ty) || !vars.unify_all(&trait_args, args) {
+            continue;
+        }
+
+        // A type parameter of the impl which the type doesn't decide can't
+        // be checked against its bounds
+        let impl_args: Vec<_> =
+            fresh.iter().map(|var| vars.resolve(var)).collect();
+        let holds = decl.bounds.iter().all(|bound| {
+            let Some(idx) =
+                decl.generics.iter().position(|param| *param == bound.param)
+            else {
+                return true;
+            };
+            let ty = &impl_args[idx];
+            let args: Vec<_> = bound
+                .args
+                .iter()
+                .map(|arg| arg.subst(&decl.generics, &impl_args))
+                .collect();
+            if ty.is_undecided() || args.iter().any(Type::is_undecided) {
+                return true;
+            }
+
+            let found =
+                find_at(decls, bounds, ty, &bound.trait_name, &args, depth + 1);
+            found.is_some()
+        });
+
+        if holds {
+            return Some(Found::Impl {
+                index,
+                args: impl_args,
+            });
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec};
+
+    use super::*;
+    use crate::{
+        generics::{declare, types::Type},
+        parser::parse,
+    };
+
+    fn decls(input: &str) -> Decls {
+        let (module, errors) = parse(input);
+        assert_eq!(errors, vec![], "{input}");
+
+        declare(&module)
+    }
+
+    fn pair(arg: Type) -> Type {
+        Type::Named {
+            name: "Pair".into(),
+            args: vec![arg],
+        }
+    }
+
+    #[test]
+    fn test_find_impl() {
+        let decls = decls(concat!(
+            "impl Display for u8 {}\n",
+            "impl<T: Display> Display for Pair<T> {}\n",
+            "impl Add<u8> for u8 {}\n",
+        ));
+        let u8 = Type::named("u8");
+
+        assert_eq!(
+            find_impl(&decls, &[], &u8, "Display", &[]),
+            Some(Found::Impl {
+                index: 0,
+                args: vec![]
+            })
+        );
+        assert_eq!(
+            find_impl(&decls, &[], &pair(pair(u8.clone())), "Display", &[]),
+            Some(Found::Impl {
+                index: 1,
+                args: vec![pair(u8.clone())]
+            })
+        );
+        assert_eq!(
+            find_impl(&decls, &[], &pair(Type::named("char")), "Display", &[]),
+            None
+        );
+        assert_eq!(
+            find_impl(&decls, &[], &u8, "Add", core::slice::from_ref(&u8)),
+            Some(Found::Impl {
+                index: 2,
+                args: vec![]
+            })
+        );
+        assert_eq!(
+            find_impl(&decls, &[], &u8, "Add", &[Type::named("char")]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_type_params_implement_their_bounds() {
+        let decls = decls("impl<T: Display> Display for T {}");
+        let bounds = [Bound {
+            param: String::from("T"),
+            trait_name: String::from("Display"),
+            args: vec![],
+        }];
+        let param = Type::Param(String::from("T"));
+
+        assert_eq!(
+            find_impl(&decls, &bounds, &param, "Display", &[]),
+            Some(Found::Bound)
+        );
+        // The impl needs itself, so it is given up on
+        assert_eq!(
+            find_impl(&decls, &[], &Type::named("u8"), "Display", &[]),
+            None
+        );
+    }
+}
@@ -0,0 +1,1035 @@
+//! Inferring the type arguments of every generic function called, and every
+//! generic struct built, in the body of a function.
+//!
+//! Each call gives the type parameters of the function it calls a fresh
+//! [`Type::Var`], which the types of the arguments are then unified with.
+//! There is no type checker, so only the types that are easy to see are
+//! worked out: literals, variables, and what functions and fields are
+//! declared to be. Everything else is [`Type::Unknown`], which fits with any
+//! type, so that a call is never blamed for something that wasn't checked.
+//!
+//! A method call like `x.show()` is a call to the method of whichever trait
+//! the type of `x` implements with a method called `show`, either by its
+//! bounds when it is a type parameter, or by an impl. The type arguments of a
+//! call then have to implement the bounds of what was called.
+
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+
+use super::{
+    errors::GenericsError,
+    impls::find_impl,
+    types::{Type, Vars},
+};
+use crate::{
+    ast::{
+        path_to_string, BinOp, Block, Expr, ExprKind, FieldInit, Func, Ident,
+        Let, Literal, Param, Pat, PatKind, Path, Stmt, StmtKind, Ty,
+    },
+    lexer::position::{Span, Spanned},
+};
+
+/// The signature of a function, or of an enum variant which is built like
+/// one.
+#[derive(Clone, Debug)]
+pub struct Sig {
+    pub generics: Vec<String>,
+    pub bounds: Vec<Bound>,
+    pub params: Vec<Type>,
+    pub ret: Type,
+    /// Whether this is a variant without any values, which is a value on
+    /// its own rather than something to call
+    pub unit: bool,
+}
+
+/// A struct or enum declaration.
+#[derive(Clone, Debug)]
+pub struct TypeDecl {
+    pub generics: Vec<String>,
+    pub bounds: Vec<Bound>,
+    pub is_struct: bool,
+    /// The fields of a struct, or the values of every variant of an enum
+    pub fields: Vec<(String, Type)>,
+}
+
+/// A trait that a type parameter has to implement, like the `T: Add<T>` of
+/// `func sum<T: Add<T>>(a: T, b: T) : T`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bound {
+    pub param: String,
+    /// The trait by its whole path, like `Display` or `shapes::Display`
+    pub trait_name: String,
+    pub args: Vec<Type>,
+}
+
+/// A trait declaration.
+#[derive(Clone, Debug)]
+pub struct TraitDecl {
+    pub generics: Vec<String>,
+    pub methods: BTreeMap<String, Method>,
+}
+
+/// A method of a trait. The type parameters of its signature are `Self`,
+/// then those of the trait, then those of the method, and it is bounded by
+/// `Self` implementing the trait.
+#[derive(Clone, Debug)]
+pub struct Method {
+    pub sig: Sig,
+    /// Whether the first parameter is `self`, so that it can be called like
+    /// `x.show()`
+    pub receiver: bool,
+}
+
+/// An impl of a trait for a type.
+#[derive(Clone, Debug)]
+pub struct ImplDecl {
+    pub generics: Vec<String>,
+    pub bounds: Vec<Bound>,
+    pub trait_name: String,
+    pub trait_args: Vec<Type>,
+    pub self_ty: Type,
+}
+
+/// Everything declared at the top level of a module, by name.
+#[derive(Clone, Debug, Default)]
+pub struct Decls {
+    pub funcs: BTreeMap<String, Sig>,
+    /// The variants of every enum which can be used without a struct
+    /// literal, by their path like `Option::Some`
+    pub variants: BTreeMap<String, Sig>,
+    pub types: BTreeMap<String, TypeDecl>,
+    /// The declared types of the variables at the top level
+    pub globals: BTreeMap<String, Type>,
+    pub traits: BTreeMap<String, TraitDecl>,
+    /// Every impl, in the order they were declared
+    pub impls: Vec<ImplDecl>,
+}
+
+/// A call to a generic function, with the type arguments it was inferred to
+/// be called with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Call {
+    pub name: String,
+    /// The type arguments, which may use the type parameters of the function
+    /// that the call is in
+    pub args: Vec<Type>,
+}
+
+/// What was found in the body of a function, or in a variable at the top
+/// level.
+#[derive(Clone, Debug, Default)]
+pub struct Body {
+    pub calls: Vec<Spanned<Call>>,
+    /// Every type which was written out or inferred, to find the instances of
+    /// generic structs and enums in
+    pub types: Vec<Type>,
+    pub errors: Vec<Spanned<GenericsError>>,
+}
+
+/// A generic function or type whose type arguments are being inferred.
+struct Use {
+    name: String,
+    params: Vec<String>,
+    bounds: Vec<Bound>,
+    args: Vec<Type>,
+    span: Span,
+    kind: UseKind,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum UseKind {
+    Func,
+    Struct,
+    /// A variant of a generic enum, which can't be blamed when nothing
+    /// decides its type arguments, since they are often decided by code that
+    /// isn't checked, like the other arms of a `match`
+    Variant,
+}
+
+/// A reference to a function or variant, with its type arguments filled in.
+struct Instantiated {
+    name: String,
+    /// The parameter types as declared, before filling in type arguments
+    declared: Vec<Type>,
+    params: Vec<Type>,
+    ret: Type,
+    unit: bool,
+}
+
+pub struct Checker<'a> {
+    decls: &'a Decls,
+    /// The type parameters of the function being checked
+    generics: &'a [String],
+    bounds: &'a [Bound],
+    /// The type that `Self` is in the methods of an impl
+    self_ty: Option<Type>,
+    vars: Vars,
+    /// The variables declared in each scope, innermost last
+    scopes: Vec<Vec<(String, Type)>>,
+    /// The return type of the function being checked
+    ret: Type,
+    uses: Vec<Use>,
+    types: Vec<Type>,
+    errors: Vec<Spanned<GenericsError>>,
+}
+
+impl<'a> Checker<'a> {
+    pub fn new(
+        decls: &'a Decls,
+        generics: &'a [String],
+        bounds: &'a [Bound],
+    ) -> Self {
+        Self {
+            decls,
+            generics,
+            bounds,
+            self_ty: None,
+            vars: Vars::default(),
+            scopes: vec![vec![]],
+            ret: Type::Unknown,
+            uses: vec![],
+            types: vec![],
+            errors: vec![],
+        }
+    }
+
+    /// Check the methods of an impl, where `Self` is `self_ty`.
+    pub fn with_self(mut self, self_ty: Type) -> Self {
+        self.self_ty = Some(self_ty);
+        self
+    }
+
+    /// Check the body of a function.
+    pub fn func(self, func: &Func) -> Body {
+        self.body(&func.params, func.ret.as_ref(), &func.body)
+    }
+
+    /// Check a function body with its parameters and return type, which is
+    /// either a function or the default body of a trait method.
+    pub fn body(
+        mut self,
+        params: &[Param],
+        ret: Option<&Ty>,
+        body: &Block,
+    ) -> Body {
+        for param in params {
+            let ty = self.lower(&param.data.ty);
+            self.declare(&param.data.name.data, ty);
+        }
+        if let Some(ret) = ret {
+            self.ret = self.lower(ret);
+        }
+
+        let ret = self.ret.clone();
+        let body = self.block(body);
+        self.vars.unify(&ret, &body);
+
+        self.finish()
+    }
+
+    /// Check the value of a variable at the top level.
+    pub fn global(mut self, stmt: &Let) -> Body {
+        self.let_stmt(stmt);
+        self.finish()
+    }
+
+    /// Work out the type arguments of everything used, now that every
+    /// constraint on them is known.
+    fn finish(mut self) -> Body {
+        let mut calls = vec![];
+
+        for used in core::mem::take(&mut self.uses) {
+            let args: Vec<_> =
+                used.args.iter().map(|arg| self.vars.finish(arg)).collect();
+
+            let undecided = args
+                .iter()
+                .position(|arg| arg.any(&|ty| matches!(ty, Type::Var(_))));
+            if let Some(idx) = undecided {
+                if used.kind != UseKind::Variant {
+                    let err = GenericsError::CannotInfer {
+                        name: used.name.clone(),
+                        param: used.params[idx].clone(),
+                    };
+                    self.errors.push(Spanned::new(err, used.span));
+                }
+                continue;
+            }
+
+            // Something that the type arguments depend on wasn't checked
+            if args.iter().any(|arg| arg.any(&|ty| *ty == Type::Unknown)) {
+                continue;
+            }
+
+            self.check_bounds(&used, &args);
+
+            let call = Call {
+                name: used.name.clone(),
+                args,
+            };
+            match used.kind {
+                UseKind::Func => calls.push(Spanned::new(call, used.span)),
+                UseKind::Struct | UseKind::Variant => {
+                    self.types.push(Type::Named {
+                        name: call.name,
+                        args: call.args,
+                    })
+                }
+            }
+        }
+
+        let types = self
+            .types
+            .iter()
+            .map(|ty| self.vars.finish(ty))
+            .filter(|ty| !ty.is_undecided())
+            .collect();
+
+        Body {
+            calls,
+            types,
+            errors: self.errors,
+        }
+    }
+
+    /// Check that the type arguments that something generic was used with
+    /// implement its bounds. Only the traits of this module are checked,
+    /// since the impls of a trait from another module may be in that module.
+    fn check_bounds(&mut self, used: &Use, args: &[Type]) {
+        for bound in &used.bounds {
+            if !self.decls.traits.contains_key(&bound.trait_name) {
+                continue;
+            }
+            let Some(idx) = used.params.iter().position(|p| *p == bound.param)
+            else {
+                continue;
+            };
+
+            let ty = &args[idx];
+            let trait_args: Vec<_> = bound
+                .args
+                .iter()
+                .map(|arg| arg.subst(&used.params, args))
+                .collect();
+            let found = find_impl(
+                self.decls,
+                self.bounds,
+                ty,
+                &bound.trait_name,
+                &trait_args,
+            );
+
+            if found.is_none() {
+                let err = GenericsError::Unsatisfied {
+                    name: used.name.clone(),
+                    ty: ty.clone(),
+                    bound: Type::Named {
+                        name: bound.trait_name.clone(),
+                        args: trait_args,
+                    },
+                };
+                self.error(err, used.span);
+            }
+        }
+    }
+
+    fn error(&mut self, err: GenericsError, span: Span) {
+        self.errors.push(Spanned::new(err, span));
+    }
+
+    /// Lower a type written in the source, keeping it to look for instances
+    /// of generic types in.
+    fn lower(&mut self, ty: &Ty) -> Type {
+        let mut ty = Type::from_ty(ty, self.generics);
+        if let Some(self_ty) = &self.self_ty {
+            ty = ty.with_self(self_ty);
+        }
+
+        self.types.push(ty.clone());
+        ty
+    }
+
+    fn declare(&mut self, name: &str, ty: Type) {
+        self.scopes.last_mut().unwrap().push((name.into(), ty));
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Type> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|(other, _)| other == name)
+            .map(|(_, ty)| ty)
+    }
+
+    /// Run `check` in a new scope, which is closed once it is done.
+    fn scoped<T>(&mut self, check: impl FnOnce(&mut Self) -> T) -> T {
+        self.scopes.push(vec![]);
+        let out = check(self);
+        self.scopes.pop();
+        out
+    }
+
+    /// Declare every variable that a pattern binds. Patterns aren't checked
+    /// against the value they match, so their types aren't known.
+    fn declare_pat(&mut self, pat: &Pat) {
+        match &pat.data {
+            PatKind::Binding(name) => self.declare(name, Type::Unknown),
+            PatKind::At { name, pat } => {
+                self.declare(&name.data, Type::Unknown);
+                self.declare_pat(pat);
+            }
+            PatKind::TupleStruct { elems: pats, .. }
+            | PatKind::Tuple(pats)
+            | PatKind::Or(pats) => {
+                pats.iter().for_each(|pat| self.declare_pat(pat))
+            }
+            PatKind::Struct { fields, .. } => {
+                for field in fields {
+                    match &field.data.pat {
+                        Some(pat) => self.declare_pat(pat),
+                        None => {
+                            self.declare(&field.data.name.data, Type::Unknown)
+                        }
+                    }
+                }
+            }
+            PatKind::Paren(inner) => self.declare_pat(inner),
+            PatKind::Wild
+            | PatKind::Path(_)
+            | PatKind::Literal(_)
+            | PatKind::Range { .. }
+            | PatKind::Error => {}
+        }
+    }
+
+    /// Look up the function or variant that `expr` refers to, giving each of
+    /// its type parameters either a fresh variable or the type argument
+    /// given with `::<..>`.
+    fn instantiate(&mut self, expr: &Expr) -> Option<Instantiated> {
+        let decls = self.decls;
+        let (name, sig, explicit, kind) = match &expr.data {
+            // A variable hides the function with the same name
+            ExprKind::Ident(name) if self.lookup(name).is_none() => {
+                (name.clone(), decls.funcs.get(name)?, None, UseKind::Func)
+            }
+            ExprKind::Path(segments) => {
+                let name = path_to_string(segments);
+                let sig = decls.variants.get(&name)?;
+                (name, sig, None, UseKind::Variant)
+            }
+            ExprKind::Instantiate { path, args } => {
+                let name = path_to_string(&path.data);
+                let (sig, kind) = match path.data.len() {
+                    1 => (decls.funcs.get(&name)?, UseKind::Func),
+                    _ => (decls.variants.get(&name)?, UseKind::Variant),
+                };
+                (name, sig, Some(args), kind)
+            }
+            _ => return None,
+        };
+
+        let args: Vec<_> = match explicit {
+            Some(args) if args.len() == sig.generics.len() => {
+                args.iter().map(|arg| self.lower(arg)).collect()
+            }
+            Some(args) => {
+                let err = GenericsError::WrongArgCount {
+                    name: name.clone(),
+                    expected: sig.generics.len(),
+                    found: args.len(),
+                };
+                self.error(err, expr.span);
+                sig.generics.iter().map(|_| Type::Unknown).collect()
+            }
+            None => sig.generics.iter().map(|_| self.vars.fresh()).collect(),
+        };
+
+        let subst = |ty: &Type| ty.subst(&sig.generics, &args);
+        let instantiated = Instantiated {
+            name: name.clone(),
+            declared: sig.params.clone(),
+            params: sig.params.iter().map(subst).collect(),
+            ret: subst(&sig.ret),
+            unit: sig.unit,
+        };
+
+        if !sig.generics.is_empty() {
+            // The instance of a variant is the instance of its enum
+            let name = match kind {
+                UseKind::Variant => {
+                    String::from(name.rsplit_once("::").unwrap().0)
+                }
+                _ => name,
+            };
+            self.uses.push(Use {
+                name,
+                params: sig.generics.clone(),
+                bounds: sig.bounds.clone(),
+                args,
+                span: expr.span,
+                kind,
+            });
+        }
+
+        Some(instantiated)
+    }
+
+    fn block(&mut self, block: &Block) -> Type {
+        self.scoped(|checker| {
+            block.data.stmts.iter().for_each(|stmt| checker.stmt(stmt));
+
+            match &block.data.tail {
+                Some(tail) => checker.expr(tail),
+                None => Type::unit(),
+            }
+        })
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.data {
+            StmtKind::Let(stmt) => self.let_stmt(stmt),
+            StmtKind::Return(value) => {
+                let ty = match value {
+                    Some(value) => self.expr(value),
+                    None => Type::unit(),
+                };
+                let ret = self.ret.clone();
+                self.vars.unify(&ret, &ty);
+            }
+            StmtKind::Assign { target, value, .. } => {
+                let target = self.expr(target);
+                let value = self.expr(value);
+                self.vars.unify(&target, &value);
+            }
+            StmtKind::Break {
+                value: Some(value), ..
+            } => {
+                self.expr(value);
+            }
+            StmtKind::Expr(expr) => {
+                self.expr(expr);
+            }
+            StmtKind::Break { value: None, .. }
+            | StmtKind::Continue { .. }
+            | StmtKind::Error => {}
+        }
+    }
+
+    /// The value is checked before the variable is declared, so that
+    /// `let a := a + 1` uses the `a` from before.
+    fn let_stmt(&mut self, stmt: &Let) {
+        let value = self.expr(&stmt.value);
+
+        let ty = match &stmt.ty {
+            Some(ty) => {
+                let ty = self.lower(ty);
+                self.vars.unify(&ty, &value);
+                ty
+            }
+            None => value,
+        };
+        self.declare(&stmt.name.data, ty);
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Type {
+        match &expr.data {
+            ExprKind::Literal(literal) => self.literal(literal),
+            ExprKind::Ident(name) => {
+                if let Some(ty) = self.lookup(name) {
+                    return ty.clone();
+                }
+                if let Some(func) = self.instantiate(expr) {
+                    return Type::Func {
+                        params: func.params,
+                        ret: func.ret.into(),
+                    };
+                }
+                let global = self.decls.globals.get(name);
+                global.cloned().unwrap_or(Type::Unknown)
+            }
+            ExprKind::Path(_) | ExprKind::Instantiate { .. } => {
+                match self.instantiate(expr) {
+                    Some(func) if func.unit => func.ret,
+                    Some(func) => Type::Func {
+                        params: func.params,
+                        ret: func.ret.into(),
+                    },
+                    None => Type::Unknown,
+                }
+            }
+            ExprKind::Call { callee, args } => self.call(callee, args),
+            ExprKind::Unary { operand, .. } => self.expr(operand),
+            ExprKind::Binary { op, lhs, rhs } => {
+                let lhs = self.expr(lhs);
+                let rhs = self.expr(rhs);
+
+                match op {
+                    BinOp::Eq
+                    | BinOp::Ne
+                    | BinOp::Lt
+                    | BinOp::Le
+                    | BinOp::Gt
+                    | BinOp::Ge => {
+                        self.vars.unify(&lhs, &rhs);
+                        Type::named("bool")
+                    }
+                    BinOp::And | BinOp::Or => Type::named("bool"),
+                    // Shifting doesn't need both sides to be the same type
+                    BinOp::Shl | BinOp::Shr => lhs,
+                    BinOp::Compose => Type::Unknown,
+                    _ => {
+                        self.vars.unify(&lhs, &rhs);
+                        lhs
+                    }
+                }
+            }
+            ExprKind::Field { base, field } => {
+                let base = self.expr(base);
+                self.field(&base, &field.data).unwrap_or(Type::Unknown)
+            }
+            ExprKind::StructLit { path, fields, base } => {
+                self.struct_lit(expr.span, path, fields, base.as_deref())
+            }
+            ExprKind::Paren(inner) => self.expr(inner),
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::If {
+                cond,
+                then,
+                else_branch,
+            } => {
+                self.expr(cond);
+                let then = self.block(then);
+
+                match else_branch {
+                    Some(else_branch) => {
+                        let other = self.expr(else_branch);
+                        self.vars.unify(&then, &other);
+                        then
+                    }
+                    None => Type::unit(),
+                }
+            }
+            ExprKind::While { cond, body, .. } => {
+                self.expr(cond);
+                self.block(body);
+                Type::unit()
+            }
+            ExprKind::Loop { body, .. } => {
+                self.block(body);
+                Type::Unknown
+            }
+            ExprKind::For {
+                binding,
+                iter,
+                body,
+                ..
+            } => {
+                // Only a range is known to give values of its own type
+                let elem = match &iter.data {
+                    ExprKind::Range { .. } => self.expr(iter),
+                    _ => {
+                        self.expr(iter);
+                        Type::Unknown
+                    }
+                };
+
+                self.scoped(|checker| {
+                    checker.declare(&binding.data, elem);
+                    checker.block(body);
+                });
+                Type::unit()
+            }
+            // The type of a range is only known to be the type of its values
+            // when looping over it
+            ExprKind::Range { start, end, .. } => {
+                let start = self.expr(start);
+                let end = self.expr(end);
+                self.vars.unify(&start, &end);
+                start
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                self.expr(scrutinee);
+
+                let mut result: Option<Type> = None;
+                for arm in arms {
+                    let ty = self.scoped(|checker| {
+                        checker.declare_pat(&arm.data.pat);
+                        if let Some(guard) = &arm.data.guard {
+                            checker.expr(guard);
+                        }
+                        checker.expr(&arm.data.body)
+                    });
+
+                    match &result {
+                        Some(result) => {
+                            self.vars.unify(result, &ty);
+                        }
+                        None => result = Some(ty),
+                    }
+                }
+
+                result.unwrap_or(Type::Unknown)
+            }
+            ExprKind::Lambda { params, ret, body } => {
+                self.scoped(|checker| {
+                    let params: Vec<_> = params
+                        .iter()
+                        .map(|param| {
+                            let ty = match &param.data.ty {
+                                Some(ty) => checker.lower(ty),
+                                None => Type::Unknown,
+                            };
+                            checker.declare(&param.data.name.data, ty.clone());
+                            ty
+                        })
+                        .collect();
+
+                    // A `return` in the lambda returns from the lambda
+                    let ret = match ret {
+                        Some(ret) => checker.lower(ret),
+                        None => checker.vars.fresh(),
+                    };
+                    let outer = core::mem::replace(&mut checker.ret, ret);
+
+                    let body = checker.expr(body);
+                    let ret = core::mem::replace(&mut checker.ret, outer);
+                    checker.vars.unify(&ret, &body);
+
+                    Type::Func {
+                        params,
+                        ret: ret.into(),
+                    }
+                })
+            }
+            ExprKind::MacroCall { .. } | ExprKind::Error => Type::Unknown,
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal) -> Type {
+        let reference = |inner: Type| Type::Ref {
+            mutable: false,
+            inner: inner.into(),
+        };
+
+        match literal {
+            Literal::Number { .. } => self.vars.fresh_integer(),
+            Literal::Str(_) => reference(Type::named("str")),
+            Literal::Char(_) => Type::named("char"),
+            Literal::Byte(_) => Type::named("u8"),
+            Literal::ByteStr(_) => {
+                reference(Type::Slice(Type::named("u8").into()))
+            }
+        }
+    }
+
+    /// Get the type of the field called `name` of a struct, if `base` is a
+    /// struct with that field.
+    fn field(&self, base: &Type, name: &str) -> Option<Type> {
+        let Type::Named { name: ty, args } = self.vars.shallow(base) else {
+            return None;
+        };
+
+        let decl = self.decls.types.get(&ty).filter(|decl| decl.is_struct)?;
+        let (_, field) = decl.fields.iter().find(|(other, _)| other == name)?;
+        Some(field.subst(&decl.generics, &args))
+    }
+
+    /// Check a call, unifying the type of each argument with the parameter
+    /// that it is passed to. A call to a field which isn't a field of a
+    /// struct, like `x.show()`, is a call to a method.
+    fn call(&mut self, callee: &Expr, args: &[Expr]) -> Type {
+        let func = match &callee.data {
+            ExprKind::Ident(_)
+            | ExprKind::Path(_)
+            | ExprKind::Instantiate { .. } => self.instantiate(callee),
+            ExprKind::Field { base, field } => {
+                let receiver = self.expr(base);
+                if let Some(ty) = self.field(&receiver, &field.data) {
+                    return self.call_value(ty, args);
+                }
+
+                match self.method(&receiver, field, callee.span) {
+                    Some(method) => return self.call_func(method, args),
+                    None => return self.call_value(Type::Unknown, args),
+                }
+            }
+            _ => None,
+        };
+
+        match func.filter(|func| !func.unit) {
+            Some(func) => self.call_func(func, args),
+            None => {
+                let callee = self.expr(callee);
+                self.call_value(callee, args)
+            }
+        }
+    }
+
+    /// Look up the method called `name` that a value of type `receiver` is
+    /// called with, like the `show` of `x.show()`. It is the method of the
+    /// one trait that has a method with that name taking `self`, and which
+    /// the receiver implements.
+    fn method(
+        &mut self,
+        receiver: &Type,
+        name: &Ident,
+        span: Span,
+    ) -> Option<Instantiated> {
+        let decls = self.decls;
+        let ty = self.vars.resolve(receiver).without_vars();
+        if ty == Type::Unknown {
+            return None;
+        }
+
+        // The traits whose impls or bounds could give the receiver a method
+        // which can't be seen, since the trait is in another module
+        let mut hidden = false;
+        let mut candidates = vec![];
+        for bound in self.bounds {
+            if Type::Param(bound.param.clone()) != ty {
+                continue;
+            }
+            match decls.traits.get(&bound.trait_name) {
+                Some(decl) if has_method(decl, &name.data) => {
+                    candidates.push((&bound.trait_name, Some(&bound.args)));
+                }
+                Some(_) => {}
+                None => hidden = true,
+            }
+        }
+
+        let mut matching: Vec<&ImplDecl> = vec![];
+        for decl in &decls.impls {
+            let mut vars = Vars::default();
+            let fresh: Vec<_> =
+                decl.generics.iter().map(|_| vars.fresh()).collect();
+            if !vars.unify(&decl.self_ty.subst(&decl.generics, &fresh), &ty) {
+                continue;
+            }
+
+            match decls.traits.get(&decl.trait_name) {
+                Some(trait_decl) if has_method(trait_decl, &name.data) => {
+                    if !matching
+                        .iter()
+                        .any(|other| other.trait_name == decl.trait_name)
+                    {
+                        candidates.push((&decl.trait_name, None));
+                    }
+                    matching.push(decl);
+                }
+                Some(_) => {}
+                None => hidden = true,
+            }
+        }
+
+        let (trait_name, bound_args) = match candidates.as_slice() {
+            [found] => *found,
+            [] => {
+                let foreign = matches!(
+                    &ty,
+                    Type::Named { name, .. } if name.contains("::")
+                );
+                if !hidden && !foreign {
+                    let err = GenericsError::NoMethod {
+                        ty,
+                        method: name.data.clone(),
+                    };
+                    self.error(err, name.span);
+                }
+                return None;
+            }
+            _ => {
+                let err = GenericsError::AmbiguousMethod {
+                    method: name.data.clone(),
+                    traits: candidates
+                        .iter()
+                        .map(|(name, _)| (*name).clone())
+                        .collect(),
+                };
+                self.error(err, name.span);
+                return None;
+            }
+        };
+
+        let trait_decl = &decls.traits[trait_name];
+        let method = &trait_decl.methods[&name.data];
+
+        // A single impl decides the type arguments of the trait, while with
+        // more than one they are left for the call to decide
+        let impls: Vec<_> = matching
+            .iter()
+            .filter(|decl| decl.trait_name == *trait_name)
+            .collect();
+        let trait_args: Vec<_> = match (bound_args, impls.as_slice()) {
+            (Some(args), _) => args.clone(),
+            (None, [decl]) => {
+                let fresh: Vec<_> =
+                    decl.generics.iter().map(|_| self.vars.fresh()).collect();
+                let self_ty = decl.self_ty.subst(&decl.generics, &fresh);
+                self.vars.unify(&self_ty, receiver);
+                decl.trait_args
+                    .iter()
+                    .map(|arg| arg.subst(&decl.generics, &fresh))
+                    .collect()
+            }
+            (None, _) => trait_decl
+                .generics
+                .iter()
+                .map(|_| self.vars.fresh())
+                .collect(),
+        };
+
+        let sig = &method.sig;
+        let mut args = vec![receiver.clone()];
+        args.extend(trait_args);
+        while args.len() < sig.generics.len() {
+            args.push(self.vars.fresh());
+        }
+
+        let name = [trait_name, "::", &name.data].concat();
+        self.uses.push(Use {
+            name: name.clone(),
+            params: sig.generics.clone(),
+            bounds: sig.bounds.clone(),
+            args: args.clone(),
+            span,
+            kind: UseKind::Func,
+        });
+
+        // The receiver is passed as the first argument, before the call's
+        let subst = |ty: &Type| ty.subst(&sig.generics, &args);
+        Some(Instantiated {
+            name,
+            declared: sig.params[1..].to_vec(),
+            params: sig.params[1..].iter().map(subst).collect(),
+            ret: subst(&sig.ret),
+            unit: false,
+        })
+    }
+
+    /// Check a call to a value of type `callee`, which isn't a function or
+    /// method declared by name.
+    fn call_value(&mut self, callee: Type, args: &[Expr]) -> Type {
+        let args: Vec<_> = args.iter().map(|arg| self.expr(arg)).collect();
+
+        match self.vars.shallow(&callee) {
+            Type::Func { params, ret } => {
+                let _ =
+                    self.vars.unify(&Type::Tuple(params), &Type::Tuple(args));
+                *ret
+            }
+            _ => Type::Unknown,
+        }
+    }
+
+    /// Check a call to a function or method declared by name. A mismatch is
+    /// only reported for a parameter of a generic function whose type uses a
+    /// type parameter, since any other mismatch is for a type checker to
+    /// find.
+    fn call_func(&mut self, func: Instantiated, args: &[Expr]) -> Type {
+        for (idx, arg) in args.iter().enumerate() {
+            let ty = self.expr(arg);
+            let (Some(param), Some(declared)) =
+                (func.params.get(idx), func.declared.get(idx))
+            else {
+                continue;
+            };
+
+            if !self.vars.unify(param, &ty) && declared.has_params() {
+                let err = GenericsError::Mismatch {
+                    name: func.name.clone(),
+                    expected: self.vars.finish(param),
+                    found: self.vars.finish(&ty),
+                };
+                self.error(err, arg.span);
+            }
+        }
+
+        func.ret
+    }
+
+    /// Check a struct literal, inferring the type arguments of a generic
+    /// struct from the values given to its fields.
+    fn struct_lit(
+        &mut self,
+        span: Span,
+        path: &Path,
+        fields: &[FieldInit],
+        base: Option<&Expr>,
+    ) -> Type {
+        let name = path_to_string(&path.data);
+        let decl = self
+            .decls
+            .types
+            .get(&name)
+            .filter(|decl| decl.is_struct && path.data.len() == 1);
+
+        let args: Vec<_> = match decl {
+            Some(decl) => {
+                decl.generics.iter().map(|_| self.vars.fresh()).collect()
+            }
+            None => vec![],
+        };
+
+        for init in fields {
+            let ty = match &init.data.value {
+                Some(value) => self.expr(value),
+                None => {
+                    let name = &init.data.name.data;
+                    self.lookup(name).cloned().unwrap_or(Type::Unknown)
+                }
+            };
+
+            let Some(decl) = decl else {
+                continue;
+            };
+            let declared = decl
+                .fields
+                .iter()
+                .find(|(field, _)| *field == init.data.name.data);
+            let Some((_, declared)) = declared else {
+                continue;
+            };
+
+            let field = declared.subst(&decl.generics, &args);
+            if !self.vars.unify(&field, &ty) && declared.has_params() {
+                let err = GenericsError::Mismatch {
+                    name: name.clone(),
+                    expected: self.vars.finish(&field),
+                    found: self.vars.finish(&ty),
+                };
+                self.error(err, init.span);
+            }
+        }
+
+        let Some(decl) = decl else {
+            if let Some(base) = base {
+                self.expr(base);
+            }
+            return Type::Unknown;
+        };
+
+        if !decl.generics.is_empty() {
+            self.uses.push(Use {
+                name: name.clone(),
+                params: decl.generics.clone(),
+                bounds: decl.bounds.clone(),
+                args: args.clone(),
+                span,
+                kind: UseKind::Struct,
+            });
+        }
+
+        let ty = Type::Named { name, args };
+        if let Some(base) = base {
+            let base = self.expr(base);
+            self.vars.unify(&ty, &base);
+        }
+        ty
+    }
+}
+
+/// Checks if a trait has a method called `name` which takes `self`.
+fn has_method(decl: &TraitDecl, name: &str) -> bool {
+    decl.methods.get(name).is_some_and(|method| method.receiver)
+}
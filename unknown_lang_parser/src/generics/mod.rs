@@ -0,0 +1,1169 @@
+//! Inferring the type arguments of generic functions and types, and finding
+//! every instance of them that a program uses.
+//!
+//! Generic code is compiled by monomorphization: every generic function is
+//! copied once for each set of type arguments it is called with, and every
+//! generic struct or enum once for each set it is used with. Starting from
+//! the functions and variables which aren't generic, each call to a generic
+//! function gives an [`Instance`] of it. The calls in the body of that
+//! instance are then followed with its type arguments filled in, until no
+//! new instances turn up.
+//!
+//! A method call like `x.show()` is an instance of the trait's method, with
+//! the type of `x` as its first type argument. Following it finds the impl
+//! of the trait for that type, which is the same dispatch a backend would
+//! do, and falls back to the trait's default body when the impl doesn't give
+//! the method.
+//!
+//! Each instance is given the [`Code`] it runs, which is the body it was
+//! found to dispatch to along with the types of its type parameters, and
+//! each call in a piece of code is given the instance it calls. This is all
+//! that a backend needs to run or emit the program, which is how
+//! [`eval`](crate::eval) runs it. A generic function which is never called
+//! from code that isn't generic has no instances. Calls into other modules,
+//! like `shapes::id(1)`, aren't followed, since each module is instantiated
+//! on its own.
+
+pub mod errors;
+pub mod impls;
+pub mod infer;
+pub mod types;
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::String,
+    vec,
+    vec::Vec,
+};
+use core::fmt;
+
+use crate::{
+    ast::{
+        path_to_string,
+        visit::{walk_ty, Visitor},
+        ItemKind, Module, Param, Ty, TyKind, TypeParam, VariantFields,
+    },
+    lexer::position::{Span, Spanned},
+};
+
+use self::{
+    errors::GenericsError,
+    impls::{find_impl, Found},
+    infer::{
+        Body, Bound, Call, Checker, Decls, ImplDecl, Method, Sig, TraitDecl,
+        TypeDecl,
+    },
+    types::Type,
+};
+
+/// How deeply type arguments may be nested before a generic function is
+/// taken to be calling itself with ever larger types.
+const MAX_DEPTH: usize = 32;
+
+/// How many types a type argument may be made of. A function calling itself
+/// with `(T, T)` doubles the size of its type argument each time, without
+/// nesting it much deeper.
+const MAX_SIZE: usize = 256;
+
+/// How many instances a module may need before it is taken to be making new
+/// ones forever, like a function calling itself with both `(T, u8)` and
+/// `(T, u16)`.
+const MAX_INSTANCES: usize = 4096;
+
+/// A generic function or type, along with the type arguments it is used
+/// with.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Instance {
+    pub name: String,
+    pub args: Vec<Type>,
+}
+
+impl fmt::Display for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ty = Type::Named {
+            name: self.name.clone(),
+            args: self.args.clone(),
+        };
+        write!(f, "{ty}")
+    }
+}
+
+/// A function body, by where it is declared.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Target {
+    /// A function declared at the top level
+    Func(String),
+    /// The value of a variable declared at the top level
+    Global(String),
+    /// A method given by an impl, where `index` counts only the impls of the
+    /// module
+    Method { index: usize, name: String },
+    /// The default body of a method of a trait
+    Default { trait_name: String, method: String },
+}
+
+/// A function body along with the types given to its type parameters, which
+/// is what an instance of a function compiles to. Code which isn't generic
+/// has no type arguments.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Code {
+    pub target: Target,
+    pub args: Vec<Type>,
+}
+
+impl Code {
+    fn new(target: Target, args: &[Type]) -> Self {
+        Self {
+            target,
+            args: args.to_vec(),
+        }
+    }
+}
+
+/// Every instance of the generic functions and types in a module.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Monomorphization {
+    /// The instances of generic functions, in the order they were found
+    pub funcs: Vec<Instance>,
+    /// The instances of generic structs and enums, in the order they were
+    /// found
+    pub types: Vec<Instance>,
+    /// The code that each instance of a function runs
+    pub code: BTreeMap<Instance, Code>,
+    /// The instance that each call in a piece of code calls, by the span of
+    /// what is called. Calls which share a span, like those that a macro
+    /// expanded into, can't be told apart, and are left out when they call
+    /// different instances.
+    pub calls: BTreeMap<(Code, Span), Instance>,
+    /// Every type argument which couldn't be inferred, or which was given
+    /// wrong
+    pub errors: Vec<Spanned<GenericsError>>,
+}
+
+/// Infer the type arguments of everything generic used in `module`, and find
+/// the instances of them that it needs.
+pub fn monomorphize(module: &Module) -> Monomorphization {
+    let decls = declare(module);
+
+    let mut checker = ArgCounts {
+        decls: &decls,
+        errors: vec![],
+    };
+    checker.visit_module(module);
+
+    let mut mono = Monomorphization {
+        errors: checker.errors,
+        ..Default::default()
+    };
+    let mut roots = vec![];
+    let mut generic = BTreeMap::new();
+    // The bodies of the methods of each impl by its index and the name of
+    // the method, along with their type parameters
+    let mut methods = BTreeMap::new();
+    let mut defaults = BTreeMap::new();
+    let mut impls = 0;
+
+    for item in &module.items {
+        let body = match &item.data {
+            ItemKind::Func(func) => {
+                let sig = &decls.funcs[&func.name.data];
+                Checker::new(&decls, &sig.generics, &sig.bounds).func(func)
+            }
+            ItemKind::Let(stmt) => Checker::new(&decls, &[], &[]).global(stmt),
+            ItemKind::Trait(decl) => {
+                let trait_decl = &decls.traits[&decl.name.data];
+                for method in &decl.methods {
+                    let Some(default) = &method.data.default else {
+                        continue;
+                    };
+
+                    let name = &method.data.name.data;
+                    let sig = &trait_decl.methods[name].sig;
+                    let body = Checker::new(&decls, &sig.generics, &sig.bounds)
+                        .body(
+                            &method.data.params,
+                            method.data.ret.as_ref(),
+                            default,
+                        );
+                    mono.errors.extend(body.errors.iter().cloned());
+
+                    let name = [&decl.name.data, "::", name].concat();
+                    defaults.entry(name).or_insert(body);
+                }
+                continue;
+            }
+            ItemKind::Impl(imp) => {
+                let decl = &decls.impls[impls];
+                for method in &imp.methods {
+                    let func = &method.data;
+                    let (generics, bounds) =
+                        type_params(&func.generics, decl.generics.clone());
+                    let bounds = [decl.bounds.clone(), bounds].concat();
+
+                    let body = Checker::new(&decls, &generics, &bounds)
+                        .with_self(decl.self_ty.clone())
+                        .func(func);
+                    mono.errors.extend(body.errors.iter().cloned());
+
+                    if generics.is_empty() {
+                        let target = Target::Method {
+                            index: impls,
+                            name: func.name.data.clone(),
+                        };
+                        roots.push((Code::new(target, &[]), body.clone()));
+                    }
+                    let key = (impls, func.name.data.clone());
+                    methods.entry(key).or_insert((generics, body));
+                }
+
+                impls += 1;
+                continue;
+            }
+            _ => continue,
+        };
+        mono.errors.extend(body.errors.iter().cloned());
+
+        let target = match &item.data {
+            ItemKind::Func(func) if !func.generics.is_empty() => {
+                generic.entry(func.name.data.clone()).or_insert(body);
+                continue;
+            }
+            ItemKind::Func(func) => Target::Func(func.name.data.clone()),
+            ItemKind::Let(stmt) => Target::Global(stmt.name.data.clone()),
+            _ => continue,
+        };
+        roots.push((Code::new(target, &[]), body));
+    }
+
+    let mut instantiator = Instantiator {
+        decls: &decls,
+        mono,
+        found: BTreeSet::new(),
+        shared: BTreeSet::new(),
+    };
+    for (code, body) in &roots {
+        instantiator.add_body(code, body, &[]);
+    }
+
+    // Following the calls in an instance may add more instances to follow
+    let mut next = 0;
+    while let Some(instance) = instantiator.mono.funcs.get(next).cloned() {
+        next += 1;
+
+        if let Some(body) = generic.get(&instance.name) {
+            let target = Target::Func(instance.name.clone());
+            let code = Code::new(target, &instance.args);
+            let params = &decls.funcs[&instance.name].generics;
+            instantiator.add_code(instance, code, body, params);
+            continue;
+        }
+
+        let Some((trait_name, method)) = instance.name.rsplit_once("::") else {
+            continue;
+        };
+        let Some(trait_decl) = decls.traits.get(trait_name) else {
+            continue;
+        };
+
+        // The type arguments of a method are the type it is called on, then
+        // those of the trait, then those of the method
+        let self_ty = &instance.args[0];
+        let (trait_args, method_args) =
+            instance.args[1..].split_at(trait_decl.generics.len());
+        let found = find_impl(&decls, &[], self_ty, trait_name, trait_args);
+        let Some(Found::Impl { index, args }) = found else {
+            continue;
+        };
+
+        match methods.get(&(index, String::from(method))) {
+            Some((params, body)) => {
+                let args = [args, method_args.to_vec()].concat();
+                let target = Target::Method {
+                    index,
+                    name: String::from(method),
+                };
+                let code = Code::new(target, &args);
+                instantiator.add_code(instance, code, body, params);
+            }
+            None => {
+                if let Some(body) = defaults.get(&instance.name) {
+                    let target = Target::Default {
+                        trait_name: String::from(trait_name),
+                        method: String::from(method),
+                    };
+                    let code = Code::new(target, &instance.args);
+                    let params = &trait_decl.methods[method].sig.generics;
+                    instantiator.add_code(instance, code, body, params);
+                }
+            }
+        }
+    }
+
+    let mut mono = instantiator.mono;
+    for key in &instantiator.shared {
+        mono.calls.remove(key);
+    }
+
+    mono
+}
+
+/// Collect the signature of every function and the declaration of every type
+/// in `module`. The first declaration with a name is the one it refers to.
+fn declare(module: &Module) -> Decls {
+    let mut decls = Decls::default();
+
+    for item in &module.items {
+        match &item.data {
+            ItemKind::Func(func) => {
+                let (generics, bounds) = type_params(&func.generics, vec![]);
+                let sig =
+                    sig(&func.params, func.ret.as_ref(), generics, bounds);
+                decls.funcs.entry(func.name.data.clone()).or_insert(sig);
+            }
+            ItemKind::Struct(decl) => {
+                let name = &decl.name.data;
+                let (generics, bounds) = type_params(&decl.generics, vec![]);
+                let fields = decl
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        let ty = Type::from_ty(&field.data.ty, &generics);
+                        (field.data.name.data.clone(), ty)
+                    })
+                    .collect();
+
+                let decl = TypeDecl {
+                    generics,
+                    bounds,
+                    is_struct: true,
+                    fields,
+                };
+                decls.types.entry(name.clone()).or_insert(decl);
+            }
+            ItemKind::Enum(decl) => {
+                let enum_name = &decl.name.data;
+                let (generics, bounds) = type_params(&decl.generics, vec![]);
+                let ret = Type::Named {
+                    name: enum_name.clone(),
+                    args: generics.iter().cloned().map(Type::Param).collect(),
+                };
+
+                let mut fields = vec![];
+                for variant in &decl.variants {
+                    let name = &variant.data.name.data;
+                    let lower = |ty: &Ty| Type::from_ty(ty, &generics);
+
+                    let (params, unit) = match &variant.data.fields {
+                        VariantFields::Unit => (vec![], true),
+                        VariantFields::Tuple(tys) => {
+                            (tys.iter().map(lower).collect(), false)
+                        }
+                        // Variants with values by name are built with a
+                        // struct literal instead
+                        VariantFields::Named(named) => {
+                            for field in named {
+                                let ty = lower(&field.data.ty);
+                                fields.push((field.data.name.data.clone(), ty));
+                            }
+                            continue;
+                        }
+                    };
+
+                    fields.extend(
+                        params.iter().map(|ty| (name.clone(), ty.clone())),
+                    );
+                    let sig = Sig {
+                        generics: generics.clone(),
+                        bounds: bounds.clone(),
+                        params,
+                        ret: ret.clone(),
+                        unit,
+                    };
+                    let path = [enum_name, "::", name].concat();
+                    decls.variants.entry(path).or_insert(sig);
+                }
+
+                let decl = TypeDecl {
+                    generics,
+                    bounds,
+                    is_struct: false,
+                    fields,
+                };
+                decls.types.entry(enum_name.clone()).or_insert(decl);
+            }
+            ItemKind::Let(stmt) => {
+                let ty = match &stmt.ty {
+                    Some(ty) => Type::from_ty(ty, &[]),
+                    None => Type::Unknown,
+                };
+                decls.globals.entry(stmt.name.data.clone()).or_insert(ty);
+            }
+            ItemKind::Trait(decl) => {
+                let name = &decl.name.data;
+                let (generics, bounds) =
+                    type_params(&decl.generics, vec![String::from("Self")]);
+
+                // Every method needs `Self` to implement the trait
+                let this = Bound {
+                    param: String::from("Self"),
+                    trait_name: name.clone(),
+                    args: generics[1..]
+                        .iter()
+                        .cloned()
+                        .map(Type::Param)
+                        .collect(),
+                };
+                let mut methods = BTreeMap::new();
+                for method in &decl.methods {
+                    let method = &method.data;
+                    let (generics, own) =
+                        type_params(&method.generics, generics.clone());
+                    let bounds =
+                        [vec![this.clone()], bounds.clone(), own].concat();
+
+                    let receiver = method
+                        .params
+                        .first()
+                        .is_some_and(|param| param.data.name.data == "self");
+                    let sig = sig(
+                        &method.params,
+                        method.ret.as_ref(),
+                        generics,
+                        bounds,
+                    );
+                    methods
+                        .entry(method.name.data.clone())
+                        .or_insert(Method { sig, receiver });
+                }
+
+                let decl = TraitDecl {
+                    generics: generics[1..].to_vec(),
+                    methods,
+                };
+                decls.traits.entry(name.clone()).or_insert(decl);
+            }
+            ItemKind::Impl(imp) => {
+                let (generics, bounds) = type_params(&imp.generics, vec![]);
+                let trait_ref = &imp.trait_ref.data;
+                let trait_args = trait_ref
+                    .args
+                    .iter()
+                    .map(|arg| Type::from_ty(arg, &generics))
+                    .collect();
+
+                decls.impls.push(ImplDecl {
+                    trait_name: path_to_string(&trait_ref.path.data),
+                    trait_args,
+                    self_ty: Type::from_ty(&imp.self_ty, &generics),
+                    generics,
+                    bounds,
+                });
+            }
+            ItemKind::Import(_) | ItemKind::Macro(_) | ItemKind::Error => {}
+        }
+    }
+
+    decls
+}
+
+/// Get the names of the type parameters in `generics` after those in
+/// `outer`, along with their bounds.
+fn type_params(
+    generics: &[TypeParam],
+    outer: Vec<String>,
+) -> (Vec<String>, Vec<Bound>) {
+    let mut names = outer;
+    names.extend(generics.iter().map(|param| param.data.name.data.clone()));
+
+    let mut bounds = vec![];
+    for param in generics {
+        for bound in &param.data.bounds {
+            bounds.push(Bound {
+                param: param.data.name.data.clone(),
+                trait_name: path_to_string(&bound.data.path.data),
+                args: bound
+                    .data
+                    .args
+                    .iter()
+                    .map(|arg| Type::from_ty(arg, &names))
+                    .collect(),
+            });
+        }
+    }
+
+    (names, bounds)
+}
+
+fn sig(
+    params: &[Param],
+    ret: Option<&Ty>,
+    generics: Vec<String>,
+    bounds: Vec<Bound>,
+) -> Sig {
+    let params = params
+        .iter()
+        .map(|param| Type::from_ty(&param.data.ty, &generics))
+        .collect();
+    let ret = match ret {
+        Some(ret) => Type::from_ty(ret, &generics),
+        None => Type::Unknown,
+    };
+
+    Sig {
+        generics,
+        bounds,
+        params,
+        ret,
+        unit: false,
+    }
+}
+
+/// Checks that every generic struct and enum written as a type is given as
+/// many type arguments as it has type parameters.
+struct ArgCounts<'a> {
+    decls: &'a Decls,
+    errors: Vec<Spanned<GenericsError>>,
+}
+
+impl Visitor for ArgCounts<'_> {
+    fn visit_ty(&mut self, ty: &Ty) {
+        let (name, found) = match &ty.data {
+            TyKind::Named(name) => (name, 0),
+            TyKind::Generic { name, args } => (&name.data, args.len()),
+            _ => return walk_ty(self, ty),
+        };
+
+        if let Some(decl) = self.decls.types.get(name) {
+            if decl.generics.len() != found {
+                let err = GenericsError::WrongArgCount {
+                    name: name.clone(),
+                    expected: decl.generics.len(),
+                    found,
+                };
+                self.errors.push(Spanned::new(err, ty.span));
+            }
+        }
+
+        walk_ty(self, ty);
+    }
+}
+
+struct Instantiator<'a> {
+    decls: &'a Decls,
+    mono: Monomorphization,
+    /// Every instance of a function or type found so far, to quickly tell
+    /// if one is new
+    found: BTreeSet<Instance>,
+    /// The spans in each piece of code which have calls to more than one
+    /// instance
+    shared: BTreeSet<(Code, Span)>,
+}
+
+/// Checks if a type argument is too large to be instantiated with.
+fn is_too_large(ty: &Type) -> bool {
+    ty.depth() > MAX_DEPTH || ty.size() > MAX_SIZE
+}
+
+impl Instantiator<'_> {
+    /// Give an instance of a function the `code` it runs, which is `body`
+    /// with the type parameters in `params`, and add the instances it uses.
+    fn add_code(
+        &mut self,
+        instance: Instance,
+        code: Code,
+        body: &Body,
+        params: &[String],
+    ) {
+        self.add_body(&code, body, params);
+        self.mono.code.insert(instance, code);
+    }
+
+    /// Add the instances used by a function body, where the type parameters
+    /// in `params` are given the types of `code`.
+    fn add_body(&mut self, code: &Code, body: &Body, params: &[String]) {
+        let args = &code.args;
+        for call in &body.calls {
+            let callee = call.span;
+            let args = call.data.args.iter().map(|ty| ty.subst(params, args));
+            let call = Call {
+                name: call.data.name.clone(),
+                args: args.collect(),
+            };
+
+            let span = |name: &str| {
+                let call =
+                    body.calls.iter().find(|call| call.data.name == name);
+                call.unwrap().span
+            };
+
+            if call.args.iter().any(is_too_large) {
+                let span = span(&call.name);
+                self.error(GenericsError::TooDeep(call.name), span);
+                continue;
+            }
+
+            let instance = Instance {
+                name: call.name,
+                args: call.args,
+            };
+            let key = (code.clone(), callee);
+            match self.mono.calls.get(&key) {
+                Some(other) if *other != instance => {
+                    self.shared.insert(key);
+                }
+                Some(_) => {}
+                None => {
+                    self.mono.calls.insert(key, instance.clone());
+                }
+            }
+            self.add_type_args(&instance.args);
+            if self.found.contains(&instance) {
+                continue;
+            }
+
+            if self.is_full() {
+                let span = span(&instance.name);
+                self.error(
+                    GenericsError::TooManyInstances(instance.name),
+                    span,
+                );
+                return;
+            }
+            self.found.insert(instance.clone());
+            self.mono.funcs.push(instance);
+        }
+
+        for ty in &body.types {
+            self.add_type(&ty.subst(params, args));
+        }
+    }
+
+    /// Checks if there are as many instances as there can be.
+    fn is_full(&self) -> bool {
+        self.found.len() >= MAX_INSTANCES
+    }
+
+    /// Report an error, unless it has already been reported.
+    fn error(&mut self, err: GenericsError, span: Span) {
+        if !self.mono.errors.iter().any(|other| other.data == err) {
+            self.mono.errors.push(Spanned::new(err, span));
+        }
+    }
+
+    fn add_type_args(&mut self, args: &[Type]) {
+        args.iter().for_each(|arg| self.add_type(arg));
+    }
+
+    /// Add every instance of a generic struct or enum inside of `ty`, along
+    /// with the instances used by their fields.
+    fn add_type(&mut self, ty: &Type) {
+        // The function using a type this large is reported instead
+        if is_too_large(ty) || self.is_full() {
+            return;
+        }
+
+        match ty {
+            Type::Named { name, args } => {
+                self.add_type_args(args);
+
+                let Some(decl) = self.decls.types.get(name) else {
+                    return;
+                };
+                if decl.generics.is_empty() || decl.generics.len() != args.len()
+                {
+                    return;
+                }
+
+                let instance = Instance {
+                    name: name.clone(),
+                    args: args.clone(),
+                };
+                if !self.found.insert(instance.clone()) {
+                    return;
+                }
+                self.mono.types.push(instance);
+
+                for (_, field) in &decl.fields {
+                    self.add_type(&field.subst(&decl.generics, args));
+                }
+            }
+            Type::Ref { inner, .. }
+            | Type::Array(inner)
+            | Type::Slice(inner) => self.add_type(inner),
+            Type::Tuple(elems) => self.add_type_args(elems),
+            Type::Func { params, ret } => {
+                self.add_type_args(params);
+                self.add_type(ret);
+            }
+            Type::Param(_) | Type::Var(_) | Type::Unknown => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, string::ToString};
+
+    use super::*;
+    use crate::parser::parse;
+
+    /// Monomorphize `input`, failing if it didn't parse.
+    fn mono(input: &str) -> Monomorphization {
+        let (module, errors) = parse(input);
+        assert_eq!(errors, vec![], "{input}");
+
+        monomorphize(&module)
+    }
+
+    fn names(instances: &[Instance]) -> Vec<String> {
+        instances
+            .iter()
+            .map(|instance| instance.to_string())
+            .collect()
+    }
+
+    fn names_of(tys: &[Type]) -> Vec<String> {
+        tys.iter().map(|ty| ty.to_string()).collect()
+    }
+
+    fn messages(mono: &Monomorphization) -> Vec<String> {
+        mono.errors.iter().map(|err| err.data.to_string()).collect()
+    }
+
+    #[test]
+    fn test_inferred_from_arguments() {
+        let mono = mono(concat!(
+            "func id<T>(x: T) : T = { x }\n",
+            "func add<T>(a: T, b: T) : T = { a + b }\n",
+            "func main() := {\n",
+            "    let a : u8 = 1\n",
+            "    let b := id(a)\n",
+            "    let c := add(b, 2)\n",
+            "    let d := add(1, 2)\n",
+            "    let e := id('e')\n",
+            "    let f := id(id(\"f\"))\n",
+            "}",
+        ));
+
+        assert_eq!(mono.errors, vec![]);
+        assert_eq!(
+            names(&mono.funcs),
+            vec!["id<u8>", "add<u8>", "add<i32>", "id<char>", "id<&str>"]
+        );
+    }
+
+    #[test]
+    fn test_inferred_from_the_expected_type() {
+        let mono = mono(concat!(
+            "enum Option<T> { Some(T), None }\n",
+            "func none<T>() : Option<T> = { Option::None }\n",
+            "func main() := {\n",
+            "    let a : Option<usize> = none()\n",
+            "    let b := none::<u8>()\n",
+            "}",
+        ));
+
+        assert_eq!(mono.errors, vec![]);
+        assert_eq!(names(&mono.funcs), vec!["none<usize>", "none<u8>"]);
+        assert_eq!(names(&mono.types), vec!["Option<usize>", "Option<u8>"]);
+    }
+
+    #[test]
+    fn test_instances_of_instances() {
+        let mono = mono(concat!(
+            "struct Pair<A, B> { a: A, b: B }\n",
+            "func pair<A, B>(a: A, b: B) : Pair<A, B> = { Pair { a, b } }\n",
+            "func twice<T>(x: T) : Pair<T, T> = { pair(x, x) }\n",
+            "func unused<T>(x: T) : T = { twice(x); x }\n",
+            "let p := twice(b'p')\n",
+            "let q := pair(1, twice('q'))",
+        ));
+
+        assert_eq!(mono.errors, vec![]);
+        assert_eq!(
+            names(&mono.funcs),
+            vec![
+                "twice<u8>",
+                "pair<i32, Pair<char, char>>",
+                "twice<char>",
+                "pair<u8, u8>",
+                "pair<char, char>",
+            ]
+        );
+        assert_eq!(
+            names(&mono.types),
+            vec![
+                "Pair<char, char>",
+                "Pair<u8, u8>",
+                "Pair<i32, Pair<char, char>>",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generic_struct_literals_and_fields() {
+        let mono = mono(concat!(
+            "struct Wrapper<T> { value: T }\n",
+            "func unwrap<T>(w: Wrapper<T>) : T = { w.value }\n",
+            "func main() := {\n",
+            "    let w := Wrapper { value: 'w' }\n",
+            "    let v := unwrap(Wrapper { value: w })\n",
+            "    let c := unwrap(v)\n",
+            "}",
+        ));
+
+        assert_eq!(mono.errors, vec![]);
+        assert_eq!(
+            names(&mono.funcs),
+            vec!["unwrap<Wrapper<char>>", "unwrap<char>"]
+        );
+        assert_eq!(
+            names(&mono.types),
+            vec!["Wrapper<char>", "Wrapper<Wrapper<char>>"]
+        );
+    }
+
+    #[test]
+    fn test_cannot_infer() {
+        let input = concat!(
+            "struct Empty<T> {}\n",
+            "func none<T>() : T = { none() }\n",
+            "func main() := {\n",
+            "    let a := none()\n",
+            "    let b := Empty {}\n",
+            "    let c := none::<u8>()\n",
+            "}",
+        );
+        let mono = mono(input);
+
+        assert_eq!(
+            messages(&mono),
+            vec![
+                "can't infer the type of `T` for `none`, give it with \
+                 `none::<..>` or the type of a variable",
+                "can't infer the type of `T` for `Empty`, give it with \
+                 `Empty::<..>` or the type of a variable",
+            ]
+        );
+        let none = input.find("none()\n    let b").unwrap();
+        assert_eq!(mono.errors[0].span.start.0, none);
+        assert_eq!(names(&mono.funcs), vec!["none<u8>"]);
+    }
+
+    #[test]
+    fn test_mismatched_type_arguments() {
+        let mono = mono(concat!(
+            "struct Pair<T> { a: T, b: T }\n",
+            "func add<T>(a: T, b: T) : T = { a + b }\n",
+            "func takes_u8(a: u8) : u8 = { a }\n",
+            "func main() := {\n",
+            "    let a : u8 = 1\n",
+            "    let b := add(a, 'b')\n",
+            "    let c := Pair { a: \"c\", b: 1 }\n",
+            // Not blamed, since it isn't a generic function
+            "    let d := takes_u8('d')\n",
+            "}",
+        ));
+
+        assert_eq!(
+            messages(&mono),
+            vec![
+                "`add` expected `u8` here, but found `char`",
+                "`Pair` expected `&str` here, but found `i32`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wrong_number_of_type_arguments() {
+        let mono = mono(concat!(
+            "struct Pair<A, B> { a: A, b: B }\n",
+            "func id<T>(x: T) : T = { x }\n",
+            "func f(p: Pair<u8>, q: Pair) : Pair<u8, u8> = {\n",
+            "    let a := id::<u8, u8>(1)\n",
+            "    let b := f::<u8>\n",
+            "}",
+        ));
+
+        assert_eq!(
+            messages(&mono),
+            vec![
+                "`Pair` takes 2 type arguments, but 1 were given",
+                "`Pair` takes 2 type arguments, but 0 were given",
+                "`id` takes 1 type argument, but 2 were given",
+                "`f` takes 0 type arguments, but 1 were given",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unchecked_code_is_not_blamed() {
+        let mono = mono(concat!(
+            "func id<T>(x: T) : T = { x }\n",
+            "func unknown() := { 1 }\n",
+            "func main(s: Shape) := {\n",
+            "    let a := id(unknown())\n",
+            "    let b := id(match s { _ => 1 } + unknown())\n",
+            "    let c := id(|x| x)\n",
+            "}",
+        ));
+
+        // Without knowing the types, there's nothing to instantiate either
+        assert_eq!(mono.errors, vec![]);
+        assert_eq!(mono.funcs, vec![]);
+    }
+
+    #[test]
+    fn test_ever_larger_instances() {
+        let mono = mono(concat!(
+            "struct Box<T> { value: T }\n",
+            "func grow<T>(x: T) : T = { grow(Box { value: x }); x }\n",
+            "let a := grow(1)",
+        ));
+
+        assert_eq!(
+            messages(&mono),
+            vec![
+                "`grow` calls itself with ever larger type arguments, so it \
+                 can't be instantiated"
+            ]
+        );
+        assert_eq!(mono.funcs.len(), MAX_DEPTH);
+    }
+
+    #[test]
+    fn test_ever_wider_instances() {
+        let mono = mono(concat!(
+            "func f<T>(n: u8) : u8 = { f::<(T, T)>(n) }\n",
+            "let a := f::<u8>(1)",
+        ));
+
+        assert_eq!(
+            messages(&mono),
+            vec![
+                "`f` calls itself with ever larger type arguments, so it \
+                 can't be instantiated"
+            ]
+        );
+        assert!(mono.funcs.len() < 10);
+    }
+
+    #[test]
+    fn test_too_many_instances() {
+        let mono = mono(concat!(
+            "func f<T>(n: u8) : u8 = {\n",
+            "    f::<(T, u8)>(n)\n",
+            "    f::<(T, u16)>(n)\n",
+            "    n\n",
+            "}\n",
+            "let a := f::<u8>(1)",
+        ));
+
+        assert_eq!(
+            messages(&mono),
+            vec![format!(
+                "`f` would need more than {MAX_INSTANCES} instances of \
+                 generic functions and types, so it can't be instantiated"
+            )]
+        );
+        assert_eq!(mono.funcs.len(), MAX_INSTANCES);
+    }
+
+    #[test]
+    fn test_method_calls_are_dispatched_to_impls() {
+        let mono = mono(concat!(
+            "trait Display {\n",
+            "    func show(self) : &str\n",
+            "    func print(self) := { self.show() }\n",
+            "}\n",
+            "struct Pair<T> { a: T, b: T }\n",
+            "func id<T>(x: T) : T = { x }\n",
+            "impl Display for u8 { func show(self) : &str = { \"u8\" } }\n",
+            "impl<T: Display> Display for Pair<T> {\n",
+            "    func show(self) : &str = { id(self.a).show() }\n",
+            "}\n",
+            "func main() := {\n",
+            "    let a : u8 = 1\n",
+            "    a.show()\n",
+            "    Pair { a, b: a }.print()\n",
+            "}",
+        ));
+
+        assert_eq!(mono.errors, vec![]);
+        assert_eq!(
+            names(&mono.funcs),
+            vec![
+                "Display::show<u8>",
+                "Display::print<Pair<u8>>",
+                "Display::show<Pair<u8>>",
+                "id<u8>",
+            ]
+        );
+        assert_eq!(names(&mono.types), vec!["Pair<u8>"]);
+    }
+
+    #[test]
+    fn test_instances_are_given_their_code() {
+        let input = concat!(
+            "trait Display {\n",
+            "    func show(self) : &str\n",
+            "    func print(self) := { self.show() }\n",
+            "}\n",
+            "struct Pair<T> { a: T, b: T }\n",
+            "impl Display for u8 { func show(self) : &str = { \"u8\" } }\n",
+            "impl<T: Display> Display for Pair<T> {\n",
+            "    func show(self) : &str = { self.a.show() }\n",
+            "}\n",
+            "func main() := {\n",
+            "    let a : u8 = 1\n",
+            "    Pair { a, b: a }.print()\n",
+            "}",
+        );
+        let mono = mono(input);
+        assert_eq!(mono.errors, vec![]);
+
+        let code: Vec<_> = mono
+            .code
+            .iter()
+            .map(|(instance, code)| {
+                let args: Vec<_> = names_of(&code.args);
+                (instance.to_string(), code.target.clone(), args)
+            })
+            .collect();
+        let method = |index, name: &str| Target::Method {
+            index,
+            name: name.to_string(),
+        };
+        let print = Target::Default {
+            trait_name: "Display".to_string(),
+            method: "print".to_string(),
+        };
+        assert_eq!(
+            code,
+            vec![
+                (
+                    "Display::print<Pair<u8>>".to_string(),
+                    print.clone(),
+                    vec!["Pair<u8>".to_string()]
+                ),
+                (
+                    "Display::show<Pair<u8>>".to_string(),
+                    method(1, "show"),
+                    vec!["u8".to_string()]
+                ),
+                ("Display::show<u8>".to_string(), method(0, "show"), vec![]),
+            ]
+        );
+
+        // A call in a default body is found through the instance of the
+        // default that it is in
+        let span = |text: &str| {
+            let start = input.find(text).unwrap();
+            Span::new(start, start + text.len())
+        };
+        let called: Vec<_> = mono
+            .calls
+            .iter()
+            .map(|((code, callee), instance)| {
+                (code.target.clone(), *callee, instance.to_string())
+            })
+            .collect();
+        assert_eq!(
+            called,
+            vec![
+                (
+                    Target::Func("main".to_string()),
+                    span("Pair { a, b: a }.print"),
+                    "Display::print<Pair<u8>>".to_string()
+                ),
+                (
+                    method(1, "show"),
+                    span("self.a.show"),
+                    "Display::show<u8>".to_string()
+                ),
+                (
+                    print,
+                    span("self.show"),
+                    "Display::show<Pair<u8>>".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_methods_through_bounds() {
+        let mono = mono(concat!(
+            "trait Add<Rhs> { func add(self, other: Rhs) : Self }\n",
+            "impl Add<u8> for u8 { func add(self, other: u8) : u8 = { 0 } }\n",
+            "func sum<T: Add<T>>(a: T, b: T) : T = { a.add(b) }\n",
+            "func main() := {\n",
+            "    let a : u8 = 1\n",
+            "    let b := sum(a, 2)\n",
+            "    let c := a.add(b)\n",
+            "}",
+        ));
+
+        assert_eq!(mono.errors, vec![]);
+        assert_eq!(names(&mono.funcs), vec!["sum<u8>", "Add::add<u8, u8>"]);
+    }
+
+    #[test]
+    fn test_unsatisfied_bounds() {
+        let input = concat!(
+            "trait Display { func show(self) : &str }\n",
+            "impl Display for u8 { func show(self) : &str = { \"u8\" } }\n",
+            "struct Shown<T: Display> { value: T }\n",
+            "func show<T: Display>(x: T) : &str = { x.show() }\n",
+            "func main() := {\n",
+            "    let a := show('a')\n",
+            "    let b := Shown { value: \"b\" }\n",
+            "    let c := show(Shown { value: b'c' })\n",
+            "}",
+        );
+        let mono = mono(input);
+
+        assert_eq!(
+            messages(&mono),
+            vec![
+                "`show` needs `char` to implement `Display`, but it doesn't",
+                "`Shown` needs `&str` to implement `Display`, but it doesn't",
+                "`show` needs `Shown<u8>` to implement `Display`, but it \
+                 doesn't",
+            ]
+        );
+        let show = input.find("show('a')").unwrap();
+        assert_eq!(mono.errors[0].span.start.0, show);
+    }
+
+    #[test]
+    fn test_missing_and_ambiguous_methods() {
+        let input = concat!(
+            "trait Display { func show(self) : &str }\n",
+            "trait Debug { func show(self) : &str }\n",
+            "impl Display for u8 { func show(self) : &str = { \"u8\" } }\n",
+            "impl Debug for u8 { func show(self) : &str = { \"1u8\" } }\n",
+            "impl shapes::Draw for char {}\n",
+            "func main(p: shapes::Point) := {\n",
+            "    let a : u8 = 1\n",
+            "    a.show()\n",
+            "    \"s\".show()\n",
+            // Might be a method of a trait from another module
+            "    'c'.draw()\n",
+            "    p.draw()\n",
+            "}",
+        );
+        let mono = mono(input);
+
+        assert_eq!(
+            messages(&mono),
+            vec![
+                "`show` is a method of more than one trait: `Display`, \
+                 `Debug`",
+                "`&str` has no method `show`, since none of the traits it \
+                 implements have one",
+            ]
+        );
+        let show = input.find("show()\n    \"s\"").unwrap();
+        assert_eq!(mono.errors[0].span.start.0, show);
+    }
+}
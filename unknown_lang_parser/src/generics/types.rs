@@ -0,0 +1,519 @@
+//! The types that type arguments are inferred as, and the type variables
+//! that stand in for them until they are known.
+
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+use core::fmt;
+
+use crate::ast::{path_to_string, Ty, TyKind};
+
+/// The type that an integer literal is when nothing else decides it.
+const DEFAULT_INTEGER: &str = "i32";
+
+/// The types that an integer literal can be.
+const INTEGERS: &[&str] = &[
+    "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64",
+    "u128", "usize",
+];
+
+/// The types other than integers which every module has without declaring
+/// them.
+const BUILTINS: &[&str] = &["bool", "char", "str", "f32", "f64"];
+
+/// Checks if `name` is a type which every module has without declaring it,
+/// like `u8` or `bool`.
+pub fn is_builtin(name: &str) -> bool {
+    INTEGERS.contains(&name) || BUILTINS.contains(&name)
+}
+
+/// Checks if `name` is one of the integer types, like `u8` or `isize`.
+pub fn is_integer(name: &str) -> bool {
+    INTEGERS.contains(&name)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Type {
+    /// A type referred to by name with the type arguments it is given, like
+    /// `usize` or `Option<u8>`. A type inside of another module is named by
+    /// its whole path.
+    Named { name: String, args: Vec<Type> },
+    /// A type parameter of the function being looked at, like the `T` of
+    /// `func id<T>(x: T) : T`
+    Param(String),
+    /// `&T`, or `&mut T` when `mutable` is set
+    Ref { mutable: bool, inner: Box<Type> },
+    /// `[T; N]`, where the length isn't kept
+    Array(Box<Type>),
+    /// `[T]`
+    Slice(Box<Type>),
+    /// `(A, B)`
+    Tuple(Vec<Type>),
+    /// `(A, B) -> C`
+    Func { params: Vec<Type>, ret: Box<Type> },
+    /// A type which is still being inferred, by its index in [`Vars`]
+    Var(usize),
+    /// A type which can't be worked out, since there is no type checker to
+    /// work out the type of every expression. It fits with any other type.
+    Unknown,
+}
+
+impl Type {
+    /// Create a type with a name and no type arguments, like `u8`.
+    pub fn named(name: &str) -> Self {
+        Type::Named {
+            name: name.into(),
+            args: vec![],
+        }
+    }
+
+    /// The empty tuple, which is what a block without a tail evaluates to.
+    pub fn unit() -> Self {
+        Type::Tuple(vec![])
+    }
+
+    /// Lower a type written in the source, where the names in `params` are
+    /// type parameters. `mut` is left off, since a `mut u8` uses the same
+    /// instances as a `u8`.
+    pub fn from_ty(ty: &Ty, params: &[String]) -> Self {
+        let lower = |ty: &Ty| Box::new(Type::from_ty(ty, params));
+
+        match &ty.data {
+            TyKind::Named(name) if params.contains(name) => {
+                Type::Param(name.clone())
+            }
+            TyKind::Named(name) => Type::named(name),
+            TyKind::Path(segments) => Type::named(&path_to_string(segments)),
+            TyKind::Generic { name, args } => Type::Named {
+                name: name.data.clone(),
+                args: args
+                    .iter()
+                    .map(|arg| Type::from_ty(arg, params))
+                    .collect(),
+            },
+            TyKind::Mut(inner) | TyKind::Paren(inner) => {
+                Type::from_ty(inner, params)
+            }
+            TyKind::Array { elem, .. } => Type::Array(lower(elem)),
+            TyKind::Slice(elem) => Type::Slice(lower(elem)),
+            TyKind::Tuple(elems) => Type::Tuple(
+                elems
+                    .iter()
+                    .map(|elem| Type::from_ty(elem, params))
+                    .collect(),
+            ),
+            TyKind::Func { params: args, ret } => Type::Func {
+                params: args
+                    .iter()
+                    .map(|arg| Type::from_ty(arg, params))
+                    .collect(),
+                ret: lower(ret),
+            },
+            TyKind::Ref { mutable, ty } => Type::Ref {
+                mutable: *mutable,
+                inner: lower(ty),
+            },
+            TyKind::Error => Type::Unknown,
+        }
+    }
+
+    /// Replace every type parameter in `params` with the type argument at the
+    /// same position in `args`.
+    pub fn subst(&self, params: &[String], args: &[Type]) -> Self {
+        self.map(&mut |ty| match ty {
+            Type::Param(name) => {
+                let idx = params.iter().position(|param| param == name)?;
+                args.get(idx).cloned()
+            }
+            _ => None,
+        })
+    }
+
+    /// Rebuild the type, replacing every part that `f` gives a replacement
+    /// for.
+    fn map(&self, f: &mut impl FnMut(&Type) -> Option<Type>) -> Self {
+        if let Some(ty) = f(self) {
+            return ty;
+        }
+
+        let all = |tys: &[Type], f: &mut _| -> Vec<Type> {
+            tys.iter().map(|ty| ty.map(f)).collect()
+        };
+
+        match self {
+            Type::Named { name, args } => Type::Named {
+                name: name.clone(),
+                args: all(args, f),
+            },
+            Type::Ref { mutable, inner } => Type::Ref {
+                mutable: *mutable,
+                inner: Box::new(inner.map(f)),
+            },
+            Type::Array(elem) => Type::Array(Box::new(elem.map(f))),
+            Type::Slice(elem) => Type::Slice(Box::new(elem.map(f))),
+            Type::Tuple(elems) => Type::Tuple(all(elems, f)),
+            Type::Func { params, ret } => Type::Func {
+                params: all(params, f),
+                ret: Box::new(ret.map(f)),
+            },
+            Type::Param(_) | Type::Var(_) | Type::Unknown => self.clone(),
+        }
+    }
+
+    /// Replace every `Self` in a type written inside of an impl with the type
+    /// that the impl is for.
+    pub fn with_self(&self, self_ty: &Type) -> Self {
+        self.map(&mut |ty| match ty {
+            Type::Named { name, args } if name == "Self" && args.is_empty() => {
+                Some(self_ty.clone())
+            }
+            _ => None,
+        })
+    }
+
+    /// Replace every type variable with [`Type::Unknown`], for a type that
+    /// is looked at outside of the [`Vars`] it was inferred with.
+    pub fn without_vars(&self) -> Self {
+        self.map(&mut |ty| match ty {
+            Type::Var(_) => Some(Type::Unknown),
+            _ => None,
+        })
+    }
+
+    /// Checks if any part of this type is still being inferred, or can't be
+    /// worked out at all.
+    pub fn is_undecided(&self) -> bool {
+        self.any(&|ty| matches!(ty, Type::Var(_) | Type::Unknown))
+    }
+
+    /// Checks if `f` holds for this type or any type inside of it.
+    pub fn any(&self, f: &impl Fn(&Type) -> bool) -> bool {
+        if f(self) {
+            return true;
+        }
+
+        match self {
+            Type::Named { args: tys, .. } | Type::Tuple(tys) => {
+                tys.iter().any(|ty| ty.any(f))
+            }
+            Type::Func { params, ret } => {
+                params.iter().any(|ty| ty.any(f)) || ret.any(f)
+            }
+            Type::Ref { inner, .. }
+            | Type::Array(inner)
+            | Type::Slice(inner) => inner.any(f),
+            Type::Param(_) | Type::Var(_) | Type::Unknown => false,
+        }
+    }
+
+    /// Checks if a type parameter is used anywhere in this type.
+    pub fn has_params(&self) -> bool {
+        self.any(&|ty| matches!(ty, Type::Param(_)))
+    }
+
+    /// The number of types in this type, counting itself, where `(u8, u8)`
+    /// is 3 and `Option<(u8, u8)>` is 4.
+    pub fn size(&self) -> usize {
+        let sum = |tys: &[Type]| tys.iter().map(Type::size).sum::<usize>();
+
+        let inner = match self {
+            Type::Named { args, .. } => sum(args),
+            Type::Tuple(elems) => sum(elems),
+            Type::Func { params, ret } => sum(params) + ret.size(),
+            Type::Ref { inner, .. }
+            | Type::Array(inner)
+            | Type::Slice(inner) => inner.size(),
+            Type::Param(_) | Type::Var(_) | Type::Unknown => 0,
+        };
+
+        inner + 1
+    }
+
+    /// The number of types nested inside of each other, where `u8` is 1 and
+    /// `Option<u8>` is 2.
+    pub fn depth(&self) -> usize {
+        let deepest = |tys: &[Type]| tys.iter().map(Type::depth).max();
+
+        let inner = match self {
+            Type::Named { args, .. } => deepest(args),
+            Type::Tuple(elems) => deepest(elems),
+            Type::Func { params, ret } => {
+                deepest(params).max(Some(ret.depth()))
+            }
+            Type::Ref { inner, .. }
+            | Type::Array(inner)
+            | Type::Slice(inner) => Some(inner.depth()),
+            Type::Param(_) | Type::Var(_) | Type::Unknown => None,
+        };
+
+        inner.unwrap_or(0) + 1
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list(f: &mut fmt::Formatter<'_>, tys: &[Type]) -> fmt::Result {
+            for (idx, ty) in tys.iter().enumerate() {
+                if idx > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{ty}")?;
+            }
+            Ok(())
+        }
+
+        match self {
+            Type::Named { name, args } if args.is_empty() => {
+                write!(f, "{name}")
+            }
+            Type::Named { name, args } => {
+                write!(f, "{name}<")?;
+                list(f, args)?;
+                write!(f, ">")
+            }
+            Type::Param(name) => write!(f, "{name}"),
+            Type::Ref { mutable, inner } => match mutable {
+                true => write!(f, "&mut {inner}"),
+                false => write!(f, "&{inner}"),
+            },
+            Type::Array(elem) => write!(f, "[{elem}; _]"),
+            Type::Slice(elem) => write!(f, "[{elem}]"),
+            Type::Tuple(elems) => {
+                write!(f, "(")?;
+                list(f, elems)?;
+                match elems.len() {
+                    1 => write!(f, ",)"),
+                    _ => write!(f, ")"),
+                }
+            }
+            Type::Func { params, ret } => {
+                write!(f, "(")?;
+                list(f, params)?;
+                write!(f, ") -> {ret}")
+            }
+            Type::Var(_) | Type::Unknown => write!(f, "_"),
+        }
+    }
+}
+
+/// The type variables of one function, and what each of them was inferred
+/// as so far.
+#[derive(Clone, Debug, Default)]
+pub struct Vars {
+    bound: Vec<Option<Type>>,
+    /// Whether each variable is the type of an integer literal, which is an
+    /// `i32` when nothing else decides it
+    integer: Vec<bool>,
+}
+
+impl Vars {
+    /// Create a variable for a type which isn't known yet.
+    pub fn fresh(&mut self) -> Type {
+        self.bound.push(None);
+        self.integer.push(false);
+        Type::Var(self.bound.len() - 1)
+    }
+
+    /// Create a variable for the type of an integer literal.
+    pub fn fresh_integer(&mut self) -> Type {
+        let var = self.fresh();
+        *self.integer.last_mut().unwrap() = true;
+        var
+    }
+
+    /// Follow the variables that `ty` is bound to, until reaching either a
+    /// type which isn't a variable or a variable which isn't bound yet.
+    pub fn shallow(&self, ty: &Type) -> Type {
+        let mut ty = ty;
+        while let Type::Var(var) = ty {
+            match &self.bound[*var] {
+                Some(bound) => ty = bound,
+                None => break,
+            }
+        }
+        ty.clone()
+    }
+
+    /// Replace every bound variable in `ty` with what it is bound to.
+    pub fn resolve(&self, ty: &Type) -> Type {
+        ty.map(&mut |ty| match ty {
+            Type::Var(_) => match self.shallow(ty) {
+                var @ Type::Var(_) => Some(var),
+                bound => Some(self.resolve(&bound)),
+            },
+            _ => None,
+        })
+    }
+
+    /// Resolve `ty` once every constraint on it is known, giving integer
+    /// literals which nothing else decided their default type. Any variable
+    /// which is left wasn't decided by anything.
+    pub fn finish(&self, ty: &Type) -> Type {
+        self.resolve(ty).map(&mut |ty| match ty {
+            Type::Var(var) if self.integer[*var] => {
+                Some(Type::named(DEFAULT_INTEGER))
+            }
+            _ => None,
+        })
+    }
+
+    /// Make `a` and `b` the same type, binding the variables in either one.
+    /// Gives back `false` when they can't be the same, in which case some of
+    /// the variables may already have been bound.
+    ///
+    /// A variable unified with [`Type::Unknown`] is bound to it, so that it
+    /// isn't taken to be undecided when it was decided by something which
+    /// wasn't checked.
+    pub fn unify(&mut self, a: &Type, b: &Type) -> bool {
+        let (a, b) = (self.shallow(a), self.shallow(b));
+
+        match (&a, &b) {
+            (Type::Var(a), Type::Var(b)) if a == b => true,
+            (&Type::Var(var), ty) | (ty, &Type::Var(var)) => {
+                if self.resolve(ty).any(&|ty| *ty == Type::Var(var)) {
+                    return false;
+                }
+                if self.integer[var] && !fits_integer(ty) {
+                    return false;
+                }
+
+                // An integer literal passed on stays an integer literal
+                if let Type::Var(other) = ty {
+                    self.integer[*other] |= self.integer[var];
+                }
+                self.bound[var] = Some(ty.clone());
+                true
+            }
+            (Type::Unknown, _) | (_, Type::Unknown) => true,
+            (
+                Type::Named { name, args },
+                Type::Named {
+                    name: other,
+                    args: other_args,
+                },
+            ) => name == other && self.unify_all(args, other_args),
+            (Type::Param(a), Type::Param(b)) => a == b,
+            // `&mut T` can be used as a `&T`, so only what they refer to
+            // needs to match
+            (Type::Ref { inner: a, .. }, Type::Ref { inner: b, .. })
+            | (Type::Array(a), Type::Array(b))
+            | (Type::Slice(a), Type::Slice(b)) => self.unify(a, b),
+            (Type::Tuple(a), Type::Tuple(b)) => self.unify_all(a, b),
+            (
+                Type::Func { params, ret },
+                Type::Func {
+                    params: other_params,
+                    ret: other_ret,
+                },
+            ) => {
+                self.unify_all(params, other_params)
+                    && self.unify(ret, other_ret)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn unify_all(&mut self, a: &[Type], b: &[Type]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| self.unify(a, b))
+    }
+}
+
+/// Checks if an integer literal could be of type `ty`, which is either one
+/// of the integer types or something that isn't known yet.
+fn fits_integer(ty: &Type) -> bool {
+    match ty {
+        Type::Named { name, args } => {
+            args.is_empty() && INTEGERS.contains(&name.as_str())
+        }
+        Type::Param(_) | Type::Var(_) | Type::Unknown => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::parser::parse;
+
+    /// Parse the type of `let a : <ty> = 0`, with `T` as a type parameter.
+    fn ty(input: &str) -> Type {
+        let (module, errors) = parse(&["let a : ", input, " = 0"].concat());
+        assert_eq!(errors, vec![], "{input}");
+
+        let crate::ast::ItemKind::Let(stmt) = &module.items[0].data else {
+            panic!("expected a variable");
+        };
+        Type::from_ty(stmt.ty.as_ref().unwrap(), &["T".into()])
+    }
+
+    #[test]
+    fn test_lowering_and_display() {
+        for (input, expected) in [
+            ("mut u8", "u8"),
+            ("Option<Option<T>>", "Option<Option<T>>"),
+            ("shapes::Shape", "shapes::Shape"),
+            ("(&mut T, [u8; 4], [u8])", "(&mut T, [u8; _], [u8])"),
+            ("(T,)", "(T,)"),
+            ("((T) -> u8)", "(T) -> u8"),
+        ] {
+            assert_eq!(ty(input).to_string(), expected, "{input}");
+        }
+
+        assert!(ty("Option<&T>").has_params());
+        assert!(!ty("Option<&u8>").has_params());
+        assert_eq!(ty("Option<&T>").depth(), 3);
+    }
+
+    #[test]
+    fn test_subst() {
+        let params = ["T".into()];
+        let ty = ty("(T) -> Option<T>").subst(&params, &[Type::named("u8")]);
+        assert_eq!(ty.to_string(), "(u8) -> Option<u8>");
+    }
+
+    #[test]
+    fn test_unify() {
+        let mut vars = Vars::default();
+        let (a, b) = (vars.fresh(), vars.fresh());
+
+        let pair = |a: &Type, b: &Type| Type::Named {
+            name: "Pair".into(),
+            args: vec![a.clone(), b.clone()],
+        };
+        let u8 = Type::named("u8");
+        let option = |ty: Type| Type::Named {
+            name: "Option".into(),
+            args: vec![ty],
+        };
+
+        assert!(vars.unify(&pair(&a, &b), &pair(&option(b.clone()), &u8)));
+        assert_eq!(vars.resolve(&a), option(u8.clone()));
+
+        // `a` is already an `Option<u8>`
+        assert!(!vars.unify(&a, &u8));
+        // A type can't contain itself
+        let c = vars.fresh();
+        assert!(!vars.unify(&c, &option(c.clone())));
+        // Anything fits with an unknown type
+        assert!(vars.unify(&Type::Unknown, &u8));
+        assert!(vars.unify(&c, &Type::Unknown));
+        assert_eq!(vars.resolve(&c), Type::Unknown);
+    }
+
+    #[test]
+    fn test_integer_literals_default_to_i32() {
+        let mut vars = Vars::default();
+        let (literal, other) = (vars.fresh_integer(), vars.fresh());
+
+        assert!(vars.unify(&literal, &other));
+        assert_eq!(vars.finish(&other), Type::named("i32"));
+
+        let decided = vars.fresh_integer();
+        assert!(vars.unify(&decided, &Type::named("u8")));
+        assert_eq!(vars.finish(&decided), Type::named("u8"));
+
+        let literal = vars.fresh_integer();
+        assert!(!vars.unify(&Type::named("char"), &literal));
+        assert!(!vars.unify(&Type::unit(), &literal));
+    }
+}
@@ -0,0 +1,365 @@
+//! Automatic semicolon insertion.
+//!
+//! Semicolons are optional at the end of a line. When the lexer reaches a
+//! newline it asks the [`AsiState`] whether the statement on that line could
+//! be finished, and if so it emits a [`Token::VirtualSemi`] in place of the
+//! newline. The full set of rules lives in `grammar.ebnf`, but in short a
+//! virtual semicolon is inserted when:
+//!
+//! 1. the previous token can end a statement (identifiers, literals, labels,
+//!    and the closing delimiters `)`, `]`, `}` and the `>` of `id::<u8>`),
+//! 2. we are not nested inside of a `(` or `[` pair, or the type arguments
+//!    after `::<`, and
+//! 3. the next line does not continue the current one, meaning it does not
+//!    start with an operator, `.`, `,`, `:`, a closing delimiter or `else`.
+//!
+//! Comments are ignored when deciding any of this, and no virtual semicolon
+//! is inserted before the end of the input.
+//!
+//! Insertion can also be done after lexing with [`insert_semicolons`], for
+//! tokens which were lexed with it turned off.
+
+use alloc::vec::Vec;
+
+use super::{is_valid_id, position::Spanned, tokens::Token};
+
+/// The state needed to decide whether a newline should turn into a
+/// [`Token::VirtualSemi`]. This is updated with every token that the lexer
+/// produces.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AsiState {
+    /// Whether the last meaningful token could end a statement.
+    pub can_end_statement: bool,
+    /// The currently open delimiters, innermost last. The `<` of type
+    /// arguments given in an expression, like `id::<u8>`, counts as one.
+    pub delimiters: Vec<char>,
+    /// Whether the last meaningful token was `::`, so that a `<` after it
+    /// opens a list of type arguments rather than comparing.
+    pub after_path_sep: bool,
+    /// Turns off insertion completely.
+    pub disabled: bool,
+}
+
+impl AsiState {
+    /// Update the state with a token that was just lexed. Comments and
+    /// whitespace are skipped, so that a trailing comment does not hide the
+    /// token before it.
+    pub fn observe(&mut self, token: &Token) {
+        let in_type_args = self.delimiters.last() == Some(&'<');
+        let mut closes_type_args = false;
+
+        match token {
+            Token::Comment(_, _) | Token::Whitespace(_) => return,
+            Token::LPar => self.delimiters.push('('),
+            Token::LBracket => self.delimiters.push('['),
+            Token::LBrace => self.delimiters.push('{'),
+            Token::RPar | Token::RBracket | Token::RBrace => {
+                self.delimiters.pop();
+            }
+            // Within type arguments every `<` opens the arguments of another
+            // type, like the `Option<` of `id::<Option<u8>>`
+            Token::Less if self.after_path_sep || in_type_args => {
+                self.delimiters.push('<')
+            }
+            Token::Greater if in_type_args => {
+                self.delimiters.pop();
+                closes_type_args = true;
+            }
+            // `>>` closes two lists of type arguments at once
+            Token::GreaterGreater if in_type_args => {
+                self.delimiters.pop();
+                if self.delimiters.last() == Some(&'<') {
+                    self.delimiters.pop();
+                }
+                closes_type_args = true;
+            }
+            _ => {}
+        }
+
+        self.after_path_sep = *token == Token::ColonColon;
+        self.can_end_statement = token.can_end_statement() || closes_type_args;
+    }
+
+    /// Checks if a newline seen in this state would end the statement, not
+    /// taking into account what the next line starts with.
+    pub fn should_insert(&self) -> bool {
+        !self.disabled
+            && self.can_end_statement
+            && !matches!(self.delimiters.last(), Some('(' | '[' | '<'))
+    }
+}
+
+impl Token {
+    /// Checks if a given token is allowed to be the last token of a
+    /// statement.
+    pub fn can_end_statement(&self) -> bool {
+        matches!(
+            self,
+            Token::Ident(_)
+                | Token::Number(_, _)
+                | Token::Str(_)
+                | Token::Char(_)
+                | Token::Byte(_)
+                | Token::ByteStr(_)
+                | Token::Label(_)
+                | Token::RPar
+                | Token::RBracket
+                | Token::RBrace
+        )
+    }
+}
+
+/// Insert virtual semicolons into `tokens`, which were lexed from `input` with
+/// insertion disabled. This gives the same tokens as lexing with insertion
+/// turned on. Comments need to be kept in `tokens`, so that the only things
+/// between two tokens are whitespace and newlines.
+pub fn insert_semicolons(
+    input: &str,
+    tokens: Vec<Spanned<Token>>,
+) -> Vec<Spanned<Token>> {
+    let mut state = AsiState::default();
+    let mut out = Vec::with_capacity(tokens.len());
+    let mut prev_end = 0;
+
+    for tok in tokens {
+        // The lexer makes the same decision at every newline between two
+        // tokens, so only the first one needs checking.
+        let gap = &input[prev_end..tok.span.start.0];
+        let newline = gap.find('\n').map(|idx| prev_end + idx);
+
+        if let Some(newline) = newline {
+            if state.should_insert()
+                && continues_line(&input[newline..]) == Some(false)
+            {
+                let semi =
+                    Spanned::new_span(Token::VirtualSemi, newline, newline + 1);
+
+                state.observe(&semi.data);
+                out.push(semi);
+            }
+        }
+
+        state.observe(&tok.data);
+        prev_end = tok.span.end.0;
+        out.push(tok);
+    }
+
+    out
+}
+
+/// Look past whitespace, newlines and comments at the start of `rest` and
+/// check if the first meaningful character continues the previous line.
+/// Returns `None` if there is nothing meaningful left in the input.
+pub fn continues_line(rest: &str) -> Option<bool> {
+    let idx = skip_trivia(rest)?;
+    let bytes = rest.as_bytes();
+
+    match bytes[idx] {
+        // '!' on its own is a prefix operator and starts a new statement,
+        // while '!=' can only be continuing an expression.
+        b'!' => Some(bytes.get(idx + 1) == Some(&b'=')),
+        // `else` carries on the `if` from the line before
+        b'e' if starts_with_word(&rest[idx..], "else") => Some(true),
+        chr => Some(is_continuation_start(chr)),
+    }
+}
+
+/// Checks if the answer from [`continues_line`] could change once there is
+/// more input. This happens when `rest` ends part way through an `else`, or
+/// right after it, or right after a `!` which could still become `!=`.
+pub fn is_incomplete(rest: &str) -> bool {
+    skip_trivia(rest).is_some_and(|idx| {
+        let start = &rest[idx..];
+        start == "!" || "else".starts_with(start)
+    })
+}
+
+/// Find the first character in `rest` which isn't whitespace or part of a
+/// comment, if there is one.
+fn skip_trivia(rest: &str) -> Option<usize> {
+    let bytes = rest.as_bytes();
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b' ' | b'\t' | b'\r' | b'\n' => idx += 1,
+            b'/' if bytes.get(idx + 1) == Some(&b'/') => {
+                // Skip to the end of the line, the newline itself is handled
+                // on the next iteration.
+                while idx < bytes.len() && bytes[idx] != b'\n' {
+                    idx += 1;
+                }
+            }
+            // This could still turn out to be the start of a comment
+            b'/' if idx + 1 == bytes.len() => return None,
+            b'/' if bytes.get(idx + 1) == Some(&b'*') => {
+                idx += 2;
+                loop {
+                    match bytes.get(idx) {
+                        // Unclosed multi-line comments run to the end
+                        None => return None,
+                        Some(b'*') if bytes.get(idx + 1) == Some(&b'/') => {
+                            idx += 2;
+                            break;
+                        }
+                        Some(_) => idx += 1,
+                    }
+                }
+            }
+            _ => return Some(idx),
+        }
+    }
+
+    None
+}
+
+/// Checks if `text` starts with `word`, and not a longer identifier that
+/// `word` is the start of.
+fn starts_with_word(text: &str, word: &str) -> bool {
+    text.strip_prefix(word)
+        .is_some_and(|rest| !rest.chars().next().is_some_and(is_valid_id))
+}
+
+/// Checks if a line starting with the given character joins onto the line
+/// before it.
+fn is_continuation_start(chr: u8) -> bool {
+    matches!(
+        chr,
+        b'.' | b','
+            | b':'
+            | b'='
+            | b'+'
+            | b'-'
+            | b'*'
+            | b'/'
+            | b'%'
+            | b'&'
+            | b'|'
+            | b'^'
+            | b'<'
+            | b'>'
+            | b')'
+            | b']'
+            | b'}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{lex_all, state::Lexer},
+        *,
+    };
+
+    #[test]
+    fn test_continues_line_on_operators() {
+        assert_eq!(continues_line("\n    + 1"), Some(true));
+        assert_eq!(continues_line("\n.method()"), Some(true));
+        assert_eq!(continues_line("\n    != b"), Some(true));
+        assert_eq!(continues_line("\n}"), Some(true));
+    }
+
+    #[test]
+    fn test_continues_line_on_else() {
+        assert_eq!(continues_line("\n} else {"), Some(true));
+        assert_eq!(continues_line("\n    else {"), Some(true));
+        assert_eq!(continues_line("\nelsewhere()"), Some(false));
+    }
+
+    #[test]
+    fn test_is_incomplete() {
+        assert!(is_incomplete("\n    el"));
+        assert!(is_incomplete("\n    // c\n    else"));
+        assert!(!is_incomplete("\n    else {"));
+        assert!(!is_incomplete("\n    elf"));
+        assert!(!is_incomplete("\n"));
+        assert!(is_incomplete("\n    !"));
+        assert!(!is_incomplete("\n    !="));
+        assert!(!is_incomplete("\n    !a"));
+    }
+
+    #[test]
+    fn test_new_statement_on_next_line() {
+        assert_eq!(continues_line("\nlet a := 5;"), Some(false));
+        assert_eq!(continues_line("\n!a"), Some(false));
+        assert_eq!(continues_line("\n(a)"), Some(false));
+    }
+
+    #[test]
+    fn test_continues_line_skips_comments() {
+        assert_eq!(continues_line("\n// comment\n  + 1"), Some(true));
+        assert_eq!(continues_line("\n/* a\n * b */ let"), Some(false));
+    }
+
+    #[test]
+    fn test_continues_line_at_end() {
+        assert_eq!(continues_line(""), None);
+        assert_eq!(continues_line("\n\n   // comment"), None);
+        assert_eq!(continues_line("\n/* unclosed"), None);
+        // A '/' at the end could still be the start of a comment
+        assert_eq!(continues_line("\n// a\n/"), None);
+    }
+
+    #[test]
+    fn test_no_insertion_in_parens() {
+        let mut state = AsiState::default();
+        state.observe(&Token::LPar);
+        state.observe(&Token::Ident("a".into()));
+        assert!(!state.should_insert());
+
+        state.observe(&Token::RPar);
+        assert!(state.should_insert());
+    }
+
+    #[test]
+    fn test_type_arguments_are_delimiters() {
+        let mut state = AsiState::default();
+        state.observe(&Token::Ident("a".into()));
+        state.observe(&Token::Less);
+        assert!(!state.should_insert());
+        state.observe(&Token::Ident("b".into()));
+        state.observe(&Token::Greater);
+        assert!(!state.should_insert());
+
+        let mut state = AsiState::default();
+        state.observe(&Token::Ident("id".into()));
+        state.observe(&Token::ColonColon);
+        state.observe(&Token::Less);
+        state.observe(&Token::Ident("Option".into()));
+        state.observe(&Token::Less);
+        state.observe(&Token::Ident("u8".into()));
+        assert!(!state.should_insert());
+
+        state.observe(&Token::GreaterGreater);
+        assert!(state.delimiters.is_empty());
+        assert!(state.should_insert());
+    }
+
+    #[test]
+    fn test_insertion_in_braces() {
+        let mut state = AsiState::default();
+        state.observe(&Token::LPar);
+        state.observe(&Token::LBrace);
+        state.observe(&Token::Number(10, "1".into()));
+        assert!(state.should_insert());
+    }
+
+    #[test]
+    fn test_insert_semicolons_after_lexing() {
+        let input = "a\n  + b\n// comment\nc(\nd\n)\n";
+        let mut lexer = Lexer::new(input);
+        lexer.asi.disabled = true;
+
+        let mut toks = Vec::new();
+        loop {
+            let tok = lexer.lex_spanned().unwrap();
+            if tok.data == Token::Eof {
+                break;
+            }
+            toks.push(tok);
+        }
+
+        assert!(!toks.iter().any(|tok| tok.data == Token::VirtualSemi));
+        assert_eq!(insert_semicolons(input, toks), lex_all(input).unwrap());
+    }
+}
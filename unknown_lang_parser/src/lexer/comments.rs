@@ -1,5 +1,7 @@
 //! Handles lexing single and multiline comments.
 
+use alloc::string::ToString;
+
 use crate::lexer::{state::Lexer, tokens::Token};
 
 use super::{errors::LexingError, TokenResult};
@@ -26,35 +28,27 @@ impl<'a> Lexer<'a> {
         };
 
         while let Some(&chr) = self.lookahead.peek() {
-            if chr == '*' {
-                advance(self, chr);
-                if let Some('/') = self.lookahead.peek() {
-                    closed = true;
-                    advance(self, chr);
-                    // break from the loop since we found the end
-                    break;
-                }
-            }
             advance(self, chr);
+            if chr == '*' && self.lookahead.peek() == Some(&'/') {
+                closed = true;
+                advance(self, '/');
+                // break from the loop since we found the end
+                break;
+            }
         }
 
         self.pos += size;
+        let (comment, rest) = self.input.split_at(size);
+        // An unclosed comment runs to the end of the input, so it is consumed
+        // either way
+        self.input = rest;
 
         if !closed {
             return Err(LexingError::UnclosedMutlilineComment);
         }
 
         // subtract 2 from the size since we don't want to include the last '*/'
-        let comment = &self.input[..size - 2];
-        self.input = &self.input[size..];
-        Ok(Token::Comment(true, comment.to_string()))
-    }
-}
-
-impl Token {
-    #[allow(dead_code)]
-    fn is_multiline_comment(&self) -> bool {
-        matches!(self, Token::Comment(true, _))
+        Ok(Token::Comment(true, comment[..size - 2].to_string()))
     }
 }
 
@@ -112,6 +106,18 @@ mod tests {
         assert_eq!(tok, Ok(Token::Eof));
     }
 
+    #[test]
+    fn test_multi_line_comment_ending_in_stars() {
+        let input = "/* stars **/+";
+        let mut lexer = Lexer::new(input);
+
+        let tok = lexer.lex_next();
+        assert_eq!(tok, Ok(Token::Comment(true, " stars *".to_string())));
+
+        let tok = lexer.lex_next();
+        assert_eq!(tok, Ok(Token::Plus));
+    }
+
     #[test]
     fn test_unclosed_multiline() {
         let input = r"/* wow!
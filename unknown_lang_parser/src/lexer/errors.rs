@@ -1,7 +1,14 @@
-#[derive(Debug, PartialEq)]
+use alloc::string::String;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum LexingError {
     NoNextCharacter,
     UnclosedString,
+    UnclosedChar,
+    EmptyChar,
+    NonAsciiByte(char),
+    InvalidHexEscape(String),
+    HexEscapeOutOfRange(u8),
     UnclosedMutlilineComment,
     UnusedEscape,
     UnknownBase(String),
@@ -11,11 +18,25 @@ pub enum LexingError {
     UnknownCharacter(char),
 }
 
-impl std::fmt::Display for LexingError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for LexingError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::NoNextCharacter => write!(f, "no next character"),
             Self::UnclosedString => write!(f, "unclosed string"),
+            Self::UnclosedChar => write!(f, "unclosed character literal"),
+            Self::EmptyChar => write!(f, "empty character literal"),
+            Self::NonAsciiByte(chr) => {
+                write!(f, "non-ASCII character in byte literal: {chr}")
+            }
+            Self::InvalidHexEscape(digits) => {
+                write!(f, "invalid hex escape: \\x{digits}")
+            }
+            Self::HexEscapeOutOfRange(byte) => {
+                write!(
+                    f,
+                    "hex escape out of range for a character: {byte:#04x}"
+                )
+            }
             Self::UnclosedMutlilineComment => {
                 write!(f, "unclosed multi-line comment")
             }
@@ -33,4 +54,5 @@ impl std::fmt::Display for LexingError {
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for LexingError {}
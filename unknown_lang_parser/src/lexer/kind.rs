@@ -0,0 +1,609 @@
+//! Payload-free token kinds, and queries for classifying tokens.
+//!
+//! A [`Token`] carries its data along with it, which makes it awkward to talk
+//! about tokens in general, like when reporting that one of a set of tokens
+//! was expected. [`TokenKind`] is the same set of tokens without the data,
+//! with keywords split out from identifiers.
+
+use super::tokens::Token;
+
+/// The kind of a [`Token`], without any of its data.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TokenKind {
+    LPar,
+    RPar,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Eq,
+    EqEq,
+    Colon,
+    ColonColon,
+    Semi,
+    VirtualSemi,
+    FatArrow,
+    Dollar,
+    Comma,
+    RightArrow,
+    Dot,
+    DotDot,
+    DotDotEq,
+    Tilde,
+    At,
+    Hash,
+
+    Char,
+    Str,
+    Byte,
+    ByteStr,
+    Ident,
+    Label,
+    Comment,
+    Whitespace,
+    Number,
+
+    Plus,
+    Minus,
+    Star,
+    StarStar,
+    Slash,
+    Percent,
+    Ampersand,
+    AmpAmp,
+    Bar,
+    BarBar,
+    Hat,
+    Greater,
+    GreaterEq,
+    GreaterGreater,
+    Less,
+    LessEq,
+    LessLess,
+    Bang,
+    BangEq,
+    PlusEq,
+
+    // Keywords, which are lexed as identifiers
+    Let,
+    Func,
+    Return,
+    Mut,
+    Import,
+    If,
+    Else,
+    While,
+    Loop,
+    For,
+    In,
+    Break,
+    Continue,
+    Match,
+    Struct,
+    Enum,
+    Pub,
+    Trait,
+    Impl,
+    Macro,
+
+    Eof,
+}
+
+/// Which way operators of the same precedence group together.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Assoc {
+    /// `a - b - c` is `(a - b) - c`
+    Left,
+    /// `f ** g ** h` is `f ** (g ** h)`
+    Right,
+    /// `a == b == c` is an error
+    None,
+}
+
+/// The precedence and associativity of a binary operator. Operators with a
+/// higher level bind tighter.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Precedence {
+    pub level: u8,
+    pub assoc: Assoc,
+}
+
+impl Precedence {
+    /// The level of prefix operators, like `-a`.
+    pub const PREFIX: u8 = 10;
+    /// The level of calls, like `f(x)`.
+    pub const CALL: u8 = 11;
+    /// The level of field access, like `a.b`.
+    pub const FIELD: u8 = 13;
+
+    const fn new(level: u8, assoc: Assoc) -> Self {
+        Self { level, assoc }
+    }
+}
+
+impl TokenKind {
+    /// Get the keyword with the given name, if there is one.
+    pub fn keyword(name: &str) -> Option<Self> {
+        match name {
+            "let" => Some(TokenKind::Let),
+            "func" => Some(TokenKind::Func),
+            "return" => Some(TokenKind::Return),
+            "mut" => Some(TokenKind::Mut),
+            "import" => Some(TokenKind::Import),
+            "if" => Some(TokenKind::If),
+            "else" => Some(TokenKind::Else),
+            "while" => Some(TokenKind::While),
+            "loop" => Some(TokenKind::Loop),
+            "for" => Some(TokenKind::For),
+            "in" => Some(TokenKind::In),
+            "break" => Some(TokenKind::Break),
+            "continue" => Some(TokenKind::Continue),
+            "match" => Some(TokenKind::Match),
+            "struct" => Some(TokenKind::Struct),
+            "enum" => Some(TokenKind::Enum),
+            "pub" => Some(TokenKind::Pub),
+            "trait" => Some(TokenKind::Trait),
+            "impl" => Some(TokenKind::Impl),
+            "macro" => Some(TokenKind::Macro),
+            _ => None,
+        }
+    }
+
+    /// Checks if this is a literal, like a number or a string.
+    pub fn is_literal(self) -> bool {
+        matches!(
+            self,
+            TokenKind::Number
+                | TokenKind::Str
+                | TokenKind::Char
+                | TokenKind::Byte
+                | TokenKind::ByteStr
+        )
+    }
+
+    /// Checks if this is a keyword.
+    pub fn is_keyword(self) -> bool {
+        matches!(
+            self,
+            TokenKind::Let
+                | TokenKind::Func
+                | TokenKind::Return
+                | TokenKind::Mut
+                | TokenKind::Import
+                | TokenKind::If
+                | TokenKind::Else
+                | TokenKind::While
+                | TokenKind::Loop
+                | TokenKind::For
+                | TokenKind::In
+                | TokenKind::Break
+                | TokenKind::Continue
+                | TokenKind::Match
+                | TokenKind::Struct
+                | TokenKind::Enum
+                | TokenKind::Pub
+                | TokenKind::Trait
+                | TokenKind::Impl
+                | TokenKind::Macro
+        )
+    }
+
+    /// Checks if this is an operator. This includes prefix operators like
+    /// `!`, and assignment operators like `+=`.
+    pub fn is_operator(self) -> bool {
+        self.is_prefix_operator()
+            || self.is_assignment_operator()
+            || self.binary_precedence().is_some()
+    }
+
+    /// Checks if this assigns to a variable, like `a += 1`. Assignments are
+    /// statements, so these aren't binary operators.
+    pub fn is_assignment_operator(self) -> bool {
+        matches!(self, TokenKind::Eq | TokenKind::PlusEq)
+    }
+
+    /// Checks if this can be used as a prefix operator, like `-a`.
+    pub fn is_prefix_operator(self) -> bool {
+        matches!(self, TokenKind::Minus | TokenKind::Bang | TokenKind::Tilde)
+    }
+
+    /// Checks if an expression is allowed to start with this token.
+    pub fn can_start_expression(self) -> bool {
+        self.is_literal()
+            || self.is_prefix_operator()
+            || matches!(
+                self,
+                TokenKind::Ident
+                    | TokenKind::LPar
+                    | TokenKind::LBracket
+                    | TokenKind::LBrace
+                    | TokenKind::If
+                    | TokenKind::While
+                    | TokenKind::Loop
+                    | TokenKind::For
+                    | TokenKind::Label
+                    | TokenKind::Match
+                    | TokenKind::Bar
+                    | TokenKind::BarBar
+            )
+    }
+
+    /// Checks if this ends a statement, either written or inserted at the end
+    /// of a line.
+    pub fn is_terminator(self) -> bool {
+        matches!(self, TokenKind::Semi | TokenKind::VirtualSemi)
+    }
+
+    /// Get the token which closes a group opened by this one, like the `)`
+    /// for a `(`, or `None` if this doesn't open a group.
+    pub fn closing_delimiter(self) -> Option<TokenKind> {
+        match self {
+            TokenKind::LPar => Some(TokenKind::RPar),
+            TokenKind::LBracket => Some(TokenKind::RBracket),
+            TokenKind::LBrace => Some(TokenKind::RBrace),
+            _ => None,
+        }
+    }
+
+    /// Checks if this closes a group, see
+    /// [`closing_delimiter`](Self::closing_delimiter).
+    pub fn is_closing_delimiter(self) -> bool {
+        matches!(
+            self,
+            TokenKind::RPar | TokenKind::RBracket | TokenKind::RBrace
+        )
+    }
+
+    /// Get the precedence of this token when used as a binary operator, or
+    /// `None` if it isn't one. From loosest to tightest, including the
+    /// operators that aren't binary:
+    ///
+    /// | level | operators                   | associativity |
+    /// |-------|-----------------------------|---------------|
+    /// | 1     | `\|\|`                      | left          |
+    /// | 2     | `&&`                        | left          |
+    /// | 3     | `==` `!=` `<` `<=` `>` `>=` | none          |
+    /// | 4     | `\|`                        | left          |
+    /// | 5     | `^`                         | left          |
+    /// | 6     | `&`                         | left          |
+    /// | 7     | `<<` `>>`                   | left          |
+    /// | 8     | `+` `-`                     | left          |
+    /// | 9     | `*` `/` `%`                 | left          |
+    /// | 10    | prefix `-` `!` `~`          |               |
+    /// | 11    | calls `f(x)`                |               |
+    /// | 12    | `**`                        | right         |
+    /// | 13    | field access `a.b`          |               |
+    ///
+    /// `=` and `+=` aren't in the table, since an assignment is a statement
+    /// rather than an expression.
+    ///
+    /// Function composition binds tighter than calls, so that
+    /// `addOne ** add (2, 3)` calls the composed function. Field access binds
+    /// tighter than both, so `a.f ** b.g` composes the two fields.
+    pub fn binary_precedence(self) -> Option<Precedence> {
+        let prec = match self {
+            TokenKind::BarBar => Precedence::new(1, Assoc::Left),
+            TokenKind::AmpAmp => Precedence::new(2, Assoc::Left),
+            TokenKind::EqEq
+            | TokenKind::BangEq
+            | TokenKind::Less
+            | TokenKind::LessEq
+            | TokenKind::Greater
+            | TokenKind::GreaterEq => Precedence::new(3, Assoc::None),
+            TokenKind::Bar => Precedence::new(4, Assoc::Left),
+            TokenKind::Hat => Precedence::new(5, Assoc::Left),
+            TokenKind::Ampersand => Precedence::new(6, Assoc::Left),
+            TokenKind::LessLess | TokenKind::GreaterGreater => {
+                Precedence::new(7, Assoc::Left)
+            }
+            TokenKind::Plus | TokenKind::Minus => {
+                Precedence::new(8, Assoc::Left)
+            }
+            TokenKind::Star | TokenKind::Slash | TokenKind::Percent => {
+                Precedence::new(9, Assoc::Left)
+            }
+            TokenKind::StarStar => Precedence::new(12, Assoc::Right),
+            _ => return None,
+        };
+
+        Some(prec)
+    }
+
+    /// Get the precedence of this token when used as a prefix operator, or
+    /// `None` if it isn't one. See [`TokenKind::binary_precedence`] for how
+    /// this compares to the other operators.
+    pub fn prefix_precedence(self) -> Option<Precedence> {
+        self.is_prefix_operator()
+            .then_some(Precedence::new(Precedence::PREFIX, Assoc::Right))
+    }
+}
+
+impl core::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let name = match self {
+            TokenKind::LPar => "`(`",
+            TokenKind::RPar => "`)`",
+            TokenKind::LBracket => "`[`",
+            TokenKind::RBracket => "`]`",
+            TokenKind::LBrace => "`{`",
+            TokenKind::RBrace => "`}`",
+            TokenKind::Eq => "`=`",
+            TokenKind::EqEq => "`==`",
+            TokenKind::Colon => "`:`",
+            TokenKind::ColonColon => "`::`",
+            TokenKind::Semi => "`;`",
+            TokenKind::VirtualSemi => "newline",
+            TokenKind::FatArrow => "`=>`",
+            TokenKind::Dollar => "`$`",
+            TokenKind::Comma => "`,`",
+            TokenKind::RightArrow => "`->`",
+            TokenKind::Dot => "`.`",
+            TokenKind::DotDot => "`..`",
+            TokenKind::DotDotEq => "`..=`",
+            TokenKind::Tilde => "`~`",
+            TokenKind::At => "`@`",
+            TokenKind::Hash => "`#`",
+
+            TokenKind::Char => "character literal",
+            TokenKind::Str => "string literal",
+            TokenKind::Byte => "byte literal",
+            TokenKind::ByteStr => "byte string literal",
+            TokenKind::Ident => "identifier",
+            TokenKind::Label => "label",
+            TokenKind::Comment => "comment",
+            TokenKind::Whitespace => "whitespace",
+            TokenKind::Number => "number",
+
+            TokenKind::Plus => "`+`",
+            TokenKind::Minus => "`-`",
+            TokenKind::Star => "`*`",
+            TokenKind::StarStar => "`**`",
+            TokenKind::Slash => "`/`",
+            TokenKind::Percent => "`%`",
+            TokenKind::Ampersand => "`&`",
+            TokenKind::AmpAmp => "`&&`",
+            TokenKind::Bar => "`|`",
+            TokenKind::BarBar => "`||`",
+            TokenKind::Hat => "`^`",
+            TokenKind::Greater => "`>`",
+            TokenKind::GreaterEq => "`>=`",
+            TokenKind::GreaterGreater => "`>>`",
+            TokenKind::Less => "`<`",
+            TokenKind::LessEq => "`<=`",
+            TokenKind::LessLess => "`<<`",
+            TokenKind::Bang => "`!`",
+            TokenKind::BangEq => "`!=`",
+            TokenKind::PlusEq => "`+=`",
+
+            TokenKind::Let => "`let`",
+            TokenKind::Func => "`func`",
+            TokenKind::Return => "`return`",
+            TokenKind::Mut => "`mut`",
+            TokenKind::Import => "`import`",
+            TokenKind::If => "`if`",
+            TokenKind::Else => "`else`",
+            TokenKind::While => "`while`",
+            TokenKind::Loop => "`loop`",
+            TokenKind::For => "`for`",
+            TokenKind::In => "`in`",
+            TokenKind::Break => "`break`",
+            TokenKind::Continue => "`continue`",
+            TokenKind::Match => "`match`",
+            TokenKind::Struct => "`struct`",
+            TokenKind::Enum => "`enum`",
+            TokenKind::Pub => "`pub`",
+            TokenKind::Trait => "`trait`",
+            TokenKind::Impl => "`impl`",
+            TokenKind::Macro => "`macro`",
+
+            TokenKind::Eof => "end of file",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+impl Token {
+    /// Get the [`TokenKind`] of this token. Identifiers which are keywords
+    /// get the kind of that keyword.
+    pub fn kind(&self) -> TokenKind {
+        match self {
+            Token::LPar => TokenKind::LPar,
+            Token::RPar => TokenKind::RPar,
+            Token::LBracket => TokenKind::LBracket,
+            Token::RBracket => TokenKind::RBracket,
+            Token::LBrace => TokenKind::LBrace,
+            Token::RBrace => TokenKind::RBrace,
+            Token::Eq => TokenKind::Eq,
+            Token::EqEq => TokenKind::EqEq,
+            Token::Colon => TokenKind::Colon,
+            Token::ColonColon => TokenKind::ColonColon,
+            Token::Semi => TokenKind::Semi,
+            Token::VirtualSemi => TokenKind::VirtualSemi,
+            Token::FatArrow => TokenKind::FatArrow,
+            Token::Dollar => TokenKind::Dollar,
+            Token::Comma => TokenKind::Comma,
+            Token::RightArrow => TokenKind::RightArrow,
+            Token::Dot => TokenKind::Dot,
+            Token::DotDot => TokenKind::DotDot,
+            Token::DotDotEq => TokenKind::DotDotEq,
+            Token::Tilde => TokenKind::Tilde,
+            Token::At => TokenKind::At,
+            Token::Hash => TokenKind::Hash,
+
+            Token::Char(_) => TokenKind::Char,
+            Token::Str(_) => TokenKind::Str,
+            Token::Byte(_) => TokenKind::Byte,
+            Token::ByteStr(_) => TokenKind::ByteStr,
+            Token::Ident(ident) => {
+                TokenKind::keyword(ident).unwrap_or(TokenKind::Ident)
+            }
+            Token::Label(_) => TokenKind::Label,
+            Token::Comment(_, _) => TokenKind::Comment,
+            Token::Whitespace(_) => TokenKind::Whitespace,
+            Token::Number(_, _) => TokenKind::Number,
+
+            Token::Plus => TokenKind::Plus,
+            Token::Minus => TokenKind::Minus,
+            Token::Star => TokenKind::Star,
+            Token::StarStar => TokenKind::StarStar,
+            Token::Slash => TokenKind::Slash,
+            Token::Percent => TokenKind::Percent,
+            Token::Ampersand => TokenKind::Ampersand,
+            Token::AmpAmp => TokenKind::AmpAmp,
+            Token::Bar => TokenKind::Bar,
+            Token::BarBar => TokenKind::BarBar,
+            Token::Hat => TokenKind::Hat,
+            Token::Greater => TokenKind::Greater,
+            Token::GreaterEq => TokenKind::GreaterEq,
+            Token::GreaterGreater => TokenKind::GreaterGreater,
+            Token::Less => TokenKind::Less,
+            Token::LessEq => TokenKind::LessEq,
+            Token::LessLess => TokenKind::LessLess,
+            Token::Bang => TokenKind::Bang,
+            Token::BangEq => TokenKind::BangEq,
+            Token::PlusEq => TokenKind::PlusEq,
+
+            Token::Eof => TokenKind::Eof,
+        }
+    }
+
+    /// Checks if a given token is a literal, see [`TokenKind::is_literal`].
+    pub fn is_literal(&self) -> bool {
+        self.kind().is_literal()
+    }
+
+    /// Checks if a given token is a keyword, see [`TokenKind::is_keyword`].
+    pub fn is_keyword(&self) -> bool {
+        self.kind().is_keyword()
+    }
+
+    /// Checks if a given token is an operator, see
+    /// [`TokenKind::is_operator`].
+    pub fn is_operator(&self) -> bool {
+        self.kind().is_operator()
+    }
+
+    /// Checks if an expression can start with a given token, see
+    /// [`TokenKind::can_start_expression`].
+    pub fn can_start_expression(&self) -> bool {
+        self.kind().can_start_expression()
+    }
+
+    /// Get the base of a number token, or `None` if this isn't a number.
+    pub fn base(&self) -> Option<u8> {
+        match self {
+            Token::Number(base, _) => Some(*base),
+            _ => None,
+        }
+    }
+
+    /// Checks if a given token is a number in the given base
+    pub fn is_base(&self, radix: u8) -> bool {
+        self.base() == Some(radix)
+    }
+
+    /// Checks if a given token is a binary number
+    pub fn is_bin(&self) -> bool {
+        self.is_base(2)
+    }
+
+    /// Checks if a given token is a octal number
+    pub fn is_oct(&self) -> bool {
+        self.is_base(8)
+    }
+
+    /// Checks if a given token is a decimal number
+    pub fn is_dec(&self) -> bool {
+        self.is_base(10)
+    }
+
+    /// Checks if a given token is a hexadecimal number
+    pub fn is_hex(&self) -> bool {
+        self.is_base(16)
+    }
+
+    /// Checks if a given token is a multi-line (`/* */`) comment
+    pub fn is_multiline_comment(&self) -> bool {
+        matches!(self, Token::Comment(true, _))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec};
+
+    use super::*;
+
+    #[test]
+    fn test_keywords_have_their_own_kind() {
+        assert_eq!(Token::Ident("let".to_string()).kind(), TokenKind::Let);
+        assert_eq!(Token::Ident("lets".to_string()).kind(), TokenKind::Ident);
+        assert!(Token::Ident("func".to_string()).is_keyword());
+    }
+
+    #[test]
+    fn test_kind_names() {
+        assert_eq!(TokenKind::RPar.to_string(), "`)`");
+        assert_eq!(TokenKind::Ident.to_string(), "identifier");
+        assert_eq!(TokenKind::Return.to_string(), "`return`");
+    }
+
+    #[test]
+    fn test_classification() {
+        assert!(Token::Number(16, "FF".to_string()).is_literal());
+        assert!(Token::ByteStr(vec![]).is_literal());
+        assert!(!Token::Ident("a".to_string()).is_literal());
+
+        assert!(Token::Plus.is_operator());
+        assert!(Token::Bang.is_operator());
+        assert!(!Token::Comma.is_operator());
+
+        assert!(Token::Minus.can_start_expression());
+        assert!(Token::LPar.can_start_expression());
+        assert!(!Token::RPar.can_start_expression());
+        assert!(Token::BarBar.can_start_expression());
+        assert!(!Token::Ident("let".to_string()).can_start_expression());
+    }
+
+    #[test]
+    fn test_bases() {
+        let tok = Token::Number(16, "FF".to_string());
+        assert!(tok.is_hex());
+        assert!(!tok.is_bin());
+        assert!(tok.is_base(16));
+        assert!(!tok.is_base(17));
+        assert_eq!(Token::Plus.base(), None);
+    }
+
+    #[test]
+    fn test_precedence() {
+        let mul = TokenKind::Star.binary_precedence().unwrap();
+        let add = TokenKind::Plus.binary_precedence().unwrap();
+
+        assert!(mul.level > add.level);
+        assert_eq!(add.assoc, Assoc::Left);
+        assert_eq!(
+            TokenKind::EqEq.binary_precedence().unwrap().assoc,
+            Assoc::None
+        );
+        assert_eq!(TokenKind::Bang.binary_precedence(), None);
+        // Assignments are statements, so they have no precedence
+        assert_eq!(TokenKind::Eq.binary_precedence(), None);
+        assert!(TokenKind::PlusEq.is_operator());
+    }
+
+    #[test]
+    fn test_composition_binds_tightest() {
+        let compose = TokenKind::StarStar.binary_precedence().unwrap();
+        let mul = TokenKind::Star.binary_precedence().unwrap();
+        let neg = TokenKind::Minus.prefix_precedence().unwrap();
+
+        assert!(Precedence::FIELD > compose.level);
+        assert!(compose.level > Precedence::CALL);
+        assert!(Precedence::CALL > neg.level);
+        assert!(neg.level > mul.level);
+        assert_eq!(compose.assoc, Assoc::Right);
+        assert_eq!(TokenKind::Plus.prefix_precedence(), None);
+    }
+}
@@ -1,40 +1,17 @@
-use super::{errors::LexingError, Lexer, Token, TokenResult};
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
 
-// TODO: Evaluate if these are actually needed
-#[allow(dead_code)]
-impl Token {
-    /// Checks if a given token is a binary number
-    fn is_bin(&self) -> bool {
-        matches!(self, Token::Number(2, _))
-    }
-
-    /// Checks if a given token is a octal number
-    fn is_oct(&self) -> bool {
-        matches!(self, Token::Number(8, _))
-    }
-
-    /// Checks if a given token is a decimal number
-    fn is_dec(&self) -> bool {
-        matches!(self, Token::Number(10, _))
-    }
-
-    /// Checks if a given token is a hexadecimal number
-    fn is_hex(&self) -> bool {
-        matches!(self, Token::Number(16, _))
-    }
-
-    /// Checks if a given token is a hexadecimal number
-    fn is_base(&self, _radix: u8) -> bool {
-        matches!(self, Token::Number(_radix, _))
-    }
-}
+use super::{
+    errors::LexingError, is_valid_id, is_valid_id_start, Lexer, Token,
+    TokenResult,
+};
 
 impl<'a> Lexer<'a> {
     pub fn lex_string(&mut self) -> TokenResult {
         let mut string = String::new();
 
-        println!("initial capacity: {}", string.capacity());
-
         // Consume the initial quote (")
         self.next_char();
 
@@ -49,7 +26,7 @@ impl<'a> Lexer<'a> {
                     self.next_char();
                     // lex the escaped character and push the result to the
                     // string of it is an `Ok` type.
-                    string.push(self.lex_escaped_char()?);
+                    string.push(self.lex_text_escape()?);
                     continue;
                 }
                 chr => string.push(chr),
@@ -68,10 +45,120 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// Lex a character literal, like `'a'` or `'\n'`.
+    pub fn lex_char(&mut self) -> TokenResult {
+        // Consume the opening quote (')
+        self.next_char();
+
+        let chr = match self.next_char() {
+            Some('\\') => self.lex_text_escape()?,
+            Some('\'') => return Err(LexingError::EmptyChar),
+            Some(chr) => chr,
+            None => return Err(LexingError::UnclosedChar),
+        };
+
+        if let Some('\'') = self.next_char() {
+            Ok(Token::Char(chr))
+        } else {
+            Err(LexingError::UnclosedChar)
+        }
+    }
+
+    /// Checks if the `'` at the current position starts a label like
+    /// `'outer`, rather than a character literal like `'o'`. A name followed
+    /// by another `'`, like `'ab'`, is a character literal with too much in
+    /// it.
+    pub fn at_label(&self) -> bool {
+        let rest = &self.input[1..];
+        let name_len = rest.find(|chr| !is_valid_id(chr)).unwrap_or(rest.len());
+
+        rest.starts_with(is_valid_id_start)
+            && !rest[name_len..].starts_with('\'')
+    }
+
+    /// Lex a label, like `'outer`.
+    pub fn lex_label(&mut self) -> TokenResult {
+        // Consume the quote (')
+        self.next_char();
+
+        Ok(Token::Label(
+            self.accumulate_while(&is_valid_id).to_string(),
+        ))
+    }
+
+    /// Lex a byte string, like `b"bytes\xFF"`. Byte strings may only contain
+    /// ASCII characters, anything else has to be written with a `\x` escape.
+    pub fn lex_byte_string(&mut self) -> TokenResult {
+        let mut bytes = Vec::new();
+
+        // Consume the prefix and the initial quote (b")
+        self.next_chars(2);
+
+        while let Some(&chr) = self.lookahead.peek() {
+            match chr {
+                '"' => break,
+                '\\' => {
+                    // consume the backslash
+                    self.next_char();
+                    bytes.push(self.lex_escaped_char()? as u8);
+                    continue;
+                }
+                chr if chr.is_ascii() => bytes.push(chr as u8),
+                chr => return Err(LexingError::NonAsciiByte(chr)),
+            }
+
+            self.next_char();
+        }
+
+        if let Some('"') = self.next_char() {
+            Ok(Token::ByteStr(bytes))
+        } else {
+            Err(LexingError::UnclosedString)
+        }
+    }
+
+    /// Lex a byte character, like `b'a'` or `b'\x7F'`. The same rules as byte
+    /// strings apply to what can be written between the quotes.
+    pub fn lex_byte_char(&mut self) -> TokenResult {
+        // Consume the prefix and the opening quote (b')
+        self.next_chars(2);
+
+        let byte = match self.next_char() {
+            Some('\\') => self.lex_escaped_char()? as u8,
+            Some('\'') => return Err(LexingError::EmptyChar),
+            Some(chr) if chr.is_ascii() => chr as u8,
+            Some(chr) => return Err(LexingError::NonAsciiByte(chr)),
+            None => return Err(LexingError::UnclosedChar),
+        };
+
+        if let Some('\'') = self.next_char() {
+            Ok(Token::Byte(byte))
+        } else {
+            Err(LexingError::UnclosedChar)
+        }
+    }
+
+    /// Lex an escape within a string or character literal. These share their
+    /// escapes with byte literals, but a `\x` escape may only go up to `\x7F`
+    /// since anything above that isn't a character on its own.
+    fn lex_text_escape(&mut self) -> Result<char, LexingError> {
+        let chr = self.lex_escaped_char()?;
+
+        if !chr.is_ascii() {
+            return Err(LexingError::HexEscapeOutOfRange(chr as u8));
+        }
+
+        Ok(chr)
+    }
+
     /// If we encounter a backslash, we want to peek ahead to the next
     /// character. If the character would make it a valid escape character,
     /// return the actual escaped character, rather than just the back slash
     /// and escape code independently.
+    ///
+    /// A `\x` escape is followed by two hex digits, and can produce any
+    /// value from `\x00` to `\xFF`. It is up to the caller to check that
+    /// the value is allowed in the literal being lexed.
     fn lex_escaped_char(&mut self) -> Result<char, LexingError> {
         if let Some(&chr) = self.lookahead.peek() {
             // now that we know there is something next, consume the forward
@@ -86,6 +173,7 @@ impl<'a> Lexer<'a> {
                 't' => Ok('\t'),
                 '0' => Ok('\0'),
                 '\\' => Ok('\\'),
+                'x' => self.lex_hex_escape(),
                 _ => Err(LexingError::UnknownEscapedCharacter(chr)),
             };
         }
@@ -93,6 +181,30 @@ impl<'a> Lexer<'a> {
         Err(LexingError::UnusedEscape)
     }
 
+    /// Lex the two hex digits following a `\x` escape.
+    fn lex_hex_escape(&mut self) -> Result<char, LexingError> {
+        let mut digits = String::new();
+
+        while let Some(&chr) = self.lookahead.peek() {
+            if digits.len() == 2 || !chr.is_ascii_hexdigit() {
+                break;
+            }
+
+            digits.push(chr);
+            self.next_char();
+        }
+
+        if digits.len() != 2 {
+            return Err(LexingError::InvalidHexEscape(digits));
+        }
+
+        // Two hex digits always fit into a byte, so this can't fail
+        let byte = u8::from_str_radix(&digits, 16)
+            .map_err(|_| LexingError::InvalidHexEscape(digits))?;
+
+        Ok(char::from(byte))
+    }
+
     /// Lex a number. This handles cases where the base is specified, like
     /// `0b110101` would translate to a binary number rather than a base 10
     /// number. Currently supports bin, oct, dec, and hex, but would be easy
@@ -175,7 +287,12 @@ impl<'a> Lexer<'a> {
                     return Err(LexingError::BaseTooLarge(radix as u8));
                 }
 
-                let num = self.accumulate_while(&|x| x.is_digit(radix));
+                // `.is_digit()` panics for radicies below 2, so compare against
+                // the base 36 digit value instead. This lets the nullary (and
+                // unary) bases through without special casing them here.
+                let num = self.accumulate_while(&|x| {
+                    x.to_digit(36).is_some_and(|digit| digit < radix)
+                });
 
                 return Ok(Token::Number(radix as u8, num.to_string()));
             }
@@ -189,6 +306,8 @@ impl<'a> Lexer<'a> {
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec;
+
     use super::*;
 
     #[test]
@@ -264,6 +383,107 @@ mod tests {
         assert_eq!(tok, Err(LexingError::UnknownEscapedCharacter('p')));
     }
 
+    #[test]
+    fn test_lexes_chars() {
+        let input = r"'a' '\'' '\x41'";
+        let mut lexer = Lexer::new(input);
+
+        assert_eq!(lexer.lex_next(), Ok(Token::Char('a')));
+        assert_eq!(lexer.lex_next(), Ok(Token::Char('\'')));
+        assert_eq!(lexer.lex_next(), Ok(Token::Char('A')));
+        assert_eq!(lexer.lex_next(), Ok(Token::Eof));
+    }
+
+    #[test]
+    fn test_lexes_labels() {
+        let input = "'outer: 'a 'b' '_1";
+        let mut lexer = Lexer::new(input);
+
+        assert_eq!(lexer.lex_next(), Ok(Token::Label("outer".to_string())));
+        assert_eq!(lexer.lex_next(), Ok(Token::Colon));
+        assert_eq!(lexer.lex_next(), Ok(Token::Label("a".to_string())));
+        assert_eq!(lexer.lex_next(), Ok(Token::Char('b')));
+        assert_eq!(lexer.lex_next(), Ok(Token::Label("_1".to_string())));
+        assert_eq!(lexer.lex_next(), Ok(Token::Eof));
+    }
+
+    #[test]
+    fn test_unclosed_char() {
+        let input = "'ab'";
+        let mut lexer = Lexer::new(input);
+
+        let tok = lexer.lex_next();
+        assert_eq!(tok, Err(LexingError::UnclosedChar));
+    }
+
+    #[test]
+    fn test_string_hex_escape_must_be_ascii() {
+        let input = r#""\x7F\xFF""#;
+        let mut lexer = Lexer::new(input);
+
+        let tok = lexer.lex_next();
+        assert_eq!(tok, Err(LexingError::HexEscapeOutOfRange(0xFF)));
+    }
+
+    #[test]
+    fn test_lexes_byte_string() {
+        let input = r#"b"ab\x00\xFF\n""#;
+        let mut lexer = Lexer::new(input);
+
+        let tok = lexer.lex_next();
+        assert_eq!(tok, Ok(Token::ByteStr(vec![b'a', b'b', 0, 0xFF, b'\n'])));
+
+        let tok = lexer.lex_next();
+        assert_eq!(tok, Ok(Token::Eof));
+    }
+
+    #[test]
+    fn test_lexes_byte_chars() {
+        let input = r"b'a' b'\xFF' b'\''";
+        let mut lexer = Lexer::new(input);
+
+        assert_eq!(lexer.lex_next(), Ok(Token::Byte(b'a')));
+        assert_eq!(lexer.lex_next(), Ok(Token::Byte(0xFF)));
+        assert_eq!(lexer.lex_next(), Ok(Token::Byte(b'\'')));
+        assert_eq!(lexer.lex_next(), Ok(Token::Eof));
+    }
+
+    #[test]
+    fn test_byte_literals_reject_non_ascii() {
+        let mut lexer = Lexer::new("b\"héllo\"");
+        assert_eq!(lexer.lex_next(), Err(LexingError::NonAsciiByte('é')));
+
+        let mut lexer = Lexer::new("b'é'");
+        assert_eq!(lexer.lex_next(), Err(LexingError::NonAsciiByte('é')));
+    }
+
+    #[test]
+    fn test_invalid_hex_escape() {
+        let input = r#"b"\xG0""#;
+        let mut lexer = Lexer::new(input);
+
+        let tok = lexer.lex_next();
+        assert_eq!(tok, Err(LexingError::InvalidHexEscape("".to_string())));
+    }
+
+    #[test]
+    fn test_b_is_still_an_identifier() {
+        let input = "b bytes";
+        let mut lexer = Lexer::new(input);
+
+        assert_eq!(lexer.lex_next(), Ok(Token::Ident("b".to_string())));
+        assert_eq!(lexer.lex_next(), Ok(Token::Ident("bytes".to_string())));
+    }
+
+    #[test]
+    fn test_display_byte_literals() {
+        let tok = Token::ByteStr(vec![b'h', b'i', 0, 0xFF]);
+        assert_eq!(tok.to_string(), r#"b"hi\x00\xff""#);
+
+        let tok = Token::Byte(b'\n');
+        assert_eq!(tok.to_string(), r"b'\n'");
+    }
+
     #[test]
     fn test_lexing_numbers() {
         let input = "123";
@@ -345,9 +565,9 @@ mod tests {
         let tok = lexer.lex_next();
         assert_eq!(tok, Err(LexingError::UnknownBase("-1".to_string())));
         let tok = lexer.lex_next();
-        assert_eq!(tok, Ok(Token::Star));
+        assert_eq!(tok, Ok(Token::StarStar));
         let tok = lexer.lex_next();
-        assert_eq!(tok, Ok(Token::Star));
+        assert_eq!(tok, Ok(Token::Eof));
     }
 
     #[test]
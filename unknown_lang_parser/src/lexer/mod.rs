@@ -1,11 +1,26 @@
+pub mod asi;
 pub mod comments;
 pub mod errors;
+pub mod kind;
 pub mod literals;
+#[cfg(feature = "std")]
+pub mod parallel;
+pub mod position;
+pub mod render;
 pub mod state;
+#[cfg(feature = "std")]
+pub mod stream;
 pub mod tokens;
-pub mod position;
 
-use self::{errors::LexingError, state::Lexer, tokens::Token};
+use alloc::{string::ToString, vec::Vec};
+
+use self::{
+    asi::{continues_line, is_incomplete},
+    errors::LexingError,
+    position::Spanned,
+    state::Lexer,
+    tokens::Token,
+};
 
 type TokenResult = Result<Token, LexingError>;
 
@@ -31,6 +46,24 @@ fn is_valid_id_start(chr: char) -> bool {
     chr.is_alphabetic() || matches!(chr, '_')
 }
 
+/// Lex all of the tokens in `input`, including comments, along with their
+/// spans. This stops at the end of the input or at the first error.
+pub fn lex_all(
+    input: &str,
+) -> Result<Vec<Spanned<Token>>, Spanned<LexingError>> {
+    let mut lexer = Lexer::new(input);
+    let mut toks = Vec::new();
+
+    loop {
+        let tok = lexer.lex_spanned()?;
+        if tok.data == Token::Eof {
+            return Ok(toks);
+        }
+
+        toks.push(tok);
+    }
+}
+
 impl<'a> Lexer<'a> {
     /// Get the next *meaningful* [`Token`] from the lexer. Meaningful here
     /// means any token which is not a comment token.
@@ -54,23 +87,79 @@ impl<'a> Lexer<'a> {
         token
     }
 
-    /// Get the next token from the lexer.
+    /// Get the next token from the lexer, keeping track of it for automatic
+    /// semicolon insertion.
     fn lex_token(&mut self) -> TokenResult {
-        // Check if there is a character to move to
-        if let Some(&chr) = self.lookahead.peek() {
-            match chr {
+        let token = self.scan_token()?;
+        self.asi.observe(&token);
+
+        Ok(token)
+    }
+
+    /// Scan the next token from the input.
+    fn scan_token(&mut self) -> TokenResult {
+        // Skipped whitespace goes around the loop, rather than calling this
+        // again, so that any number of blank lines can be skipped
+        loop {
+            self.token_start = self.pos;
+
+            // If there is no character to move to, return an EOF Token
+            let Some(&chr) = self.lookahead.peek() else {
+                return Ok(Token::Eof);
+            };
+
+            return match chr {
                 c if is_whitespace(c) => {
-                    self.accumulate_while(&is_whitespace);
-                    self.lex_token()
+                    let trivia = self.trivia;
+                    let ws = self.accumulate_while(&is_whitespace);
+
+                    match trivia {
+                        true => Ok(Token::Whitespace(ws.to_string())),
+                        false => continue,
+                    }
                 }
                 '\n' => {
-                    self.accumulate_while(&|x| matches!(x, '\n' | '\r'));
-                    self.lex_token()
+                    // A newline may end the current statement, see `asi.rs`
+                    // for the rules on when that happens.
+                    if self.asi.should_insert() {
+                        // The next line might start with an `else` or `!=`
+                        // which hasn't been read in full yet
+                        if is_incomplete(self.input) {
+                            self.needs_input = true;
+                        }
+
+                        match continues_line(self.input) {
+                            Some(false) => {
+                                return Ok(self.single_token(Token::VirtualSemi))
+                            }
+                            Some(true) => {}
+                            // Only comments and whitespace are left, if there
+                            // is more input it could change the answer
+                            None => self.needs_input = true,
+                        }
+                    }
+
+                    let trivia = self.trivia;
+                    let ws =
+                        self.accumulate_while(&|x| matches!(x, '\n' | '\r'));
+
+                    match trivia {
+                        true => Ok(Token::Whitespace(ws.to_string())),
+                        false => continue,
+                    }
                 }
                 '.' => {
                     self.next_char();
                     match self.lookahead.peek() {
-                        Some('.') => Ok(self.single_token(Token::DotDot)),
+                        Some('.') => {
+                            self.next_char();
+                            match self.lookahead.peek() {
+                                Some('=') => {
+                                    Ok(self.single_token(Token::DotDotEq))
+                                }
+                                _ => Ok(Token::DotDot),
+                            }
+                        }
                         _ => Ok(Token::Dot),
                     }
                 }
@@ -106,6 +195,8 @@ impl<'a> Lexer<'a> {
                     }
                 }
                 '~' => Ok(self.single_token(Token::Tilde)),
+                '@' => Ok(self.single_token(Token::At)),
+                '#' => Ok(self.single_token(Token::Hash)),
                 '+' => {
                     self.next_char();
                     match self.lookahead.peek() {
@@ -113,7 +204,13 @@ impl<'a> Lexer<'a> {
                         _ => Ok(Token::Plus),
                     }
                 }
-                '*' => Ok(self.single_token(Token::Star)),
+                '*' => {
+                    self.next_char();
+                    match self.lookahead.peek() {
+                        Some('*') => Ok(self.single_token(Token::StarStar)),
+                        _ => Ok(Token::Star),
+                    }
+                }
                 '/' => {
                     self.next_char();
                     match self.lookahead.peek() {
@@ -125,8 +222,20 @@ impl<'a> Lexer<'a> {
                     }
                 }
                 '%' => Ok(self.single_token(Token::Percent)),
-                '&' => Ok(self.single_token(Token::Ampersand)),
-                '|' => Ok(self.single_token(Token::Bar)),
+                '&' => {
+                    self.next_char();
+                    match self.lookahead.peek() {
+                        Some('&') => Ok(self.single_token(Token::AmpAmp)),
+                        _ => Ok(Token::Ampersand),
+                    }
+                }
+                '|' => {
+                    self.next_char();
+                    match self.lookahead.peek() {
+                        Some('|') => Ok(self.single_token(Token::BarBar)),
+                        _ => Ok(Token::Bar),
+                    }
+                }
                 '^' => Ok(self.single_token(Token::Hat)),
                 '>' => {
                     self.next_char();
@@ -154,15 +263,21 @@ impl<'a> Lexer<'a> {
                     }
                 }
                 '"' => self.lex_string(),
+                '\'' if self.at_label() => self.lex_label(),
+                '\'' => self.lex_char(),
+                'b' if self.input.starts_with("b\"") => self.lex_byte_string(),
+                'b' if self.input.starts_with("b'") => self.lex_byte_char(),
                 c if c.is_ascii_digit() => self.lex_number(),
                 c if is_valid_id_start(c) => Ok(Token::Ident(
                     self.accumulate_while(&is_valid_id).to_string(),
                 )),
-                c => Err(LexingError::UnknownCharacter(c)),
-            }
-        } else {
-            // If there is no character to move to, return an EOF Token
-            Ok(Token::Eof)
+                c => {
+                    // Skip over the character so lexing can carry on after
+                    // the error
+                    self.next_char();
+                    Err(LexingError::UnknownCharacter(c))
+                }
+            };
         }
     }
 }
@@ -188,4 +303,85 @@ mod tests {
         let tok = lexer.get_next_token();
         assert_eq!(tok, Ok(Token::Plus));
     }
+
+    #[test]
+    fn test_inserts_semicolon_at_line_end() {
+        let input = "a\nb";
+        let mut lexer = Lexer::new(input);
+
+        assert_eq!(lexer.lex_next(), Ok(Token::Ident("a".to_string())));
+        assert_eq!(lexer.lex_next(), Ok(Token::VirtualSemi));
+        assert_eq!(lexer.lex_next(), Ok(Token::Ident("b".to_string())));
+        assert_eq!(lexer.lex_next(), Ok(Token::Eof));
+    }
+
+    #[test]
+    fn test_many_blank_lines() {
+        let input = "a\n".to_string() + &" \n".repeat(300_000) + "b";
+        let tokens = lex_all(&input).unwrap();
+
+        assert_eq!(
+            tokens.into_iter().map(|tok| tok.data).collect::<Vec<_>>(),
+            [
+                Token::Ident("a".to_string()),
+                Token::VirtualSemi,
+                Token::Ident("b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_semicolon_after_explicit_one() {
+        let input = "a;\nb";
+        let mut lexer = Lexer::new(input);
+
+        assert_eq!(lexer.lex_next(), Ok(Token::Ident("a".to_string())));
+        assert_eq!(lexer.lex_next(), Ok(Token::Semi));
+        assert_eq!(lexer.lex_next(), Ok(Token::Ident("b".to_string())));
+    }
+
+    #[test]
+    fn test_no_semicolon_before_continuation() {
+        let input = "a\n    .b\n    + c";
+        let mut lexer = Lexer::new(input);
+
+        assert_eq!(lexer.lex_next(), Ok(Token::Ident("a".to_string())));
+        assert_eq!(lexer.lex_next(), Ok(Token::Dot));
+        assert_eq!(lexer.lex_next(), Ok(Token::Ident("b".to_string())));
+        assert_eq!(lexer.lex_next(), Ok(Token::Plus));
+        assert_eq!(lexer.lex_next(), Ok(Token::Ident("c".to_string())));
+        assert_eq!(lexer.lex_next(), Ok(Token::Eof));
+    }
+
+    #[test]
+    fn test_no_semicolon_at_end_of_input() {
+        let input = "a\n\n";
+        let mut lexer = Lexer::new(input);
+
+        assert_eq!(lexer.lex_next(), Ok(Token::Ident("a".to_string())));
+        assert_eq!(lexer.lex_next(), Ok(Token::Eof));
+    }
+
+    #[test]
+    fn test_ranges() {
+        let input = "0..10 a..=b";
+        let mut lexer = Lexer::new(input);
+
+        assert_eq!(lexer.lex_next(), Ok(Token::Number(10, "0".to_string())));
+        assert_eq!(lexer.lex_next(), Ok(Token::DotDot));
+        assert_eq!(lexer.lex_next(), Ok(Token::Number(10, "10".to_string())));
+        assert_eq!(lexer.lex_next(), Ok(Token::Ident("a".to_string())));
+        assert_eq!(lexer.lex_next(), Ok(Token::DotDotEq));
+        assert_eq!(lexer.lex_next(), Ok(Token::Ident("b".to_string())));
+    }
+
+    #[test]
+    fn test_semicolon_after_trailing_comment() {
+        let input = "a // comment\nb";
+        let mut lexer = Lexer::new(input);
+
+        assert_eq!(lexer.get_next_token(), Ok(Token::Ident("a".to_string())));
+        assert_eq!(lexer.get_next_token(), Ok(Token::VirtualSemi));
+        assert_eq!(lexer.get_next_token(), Ok(Token::Ident("b".to_string())));
+    }
 }
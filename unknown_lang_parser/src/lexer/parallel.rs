@@ -0,0 +1,412 @@
+//! Lexing large inputs on multiple threads.
+//!
+//! The input is split into chunks at *safe boundaries*, which are newlines
+//! that aren't inside of any token. Finding these takes a single pass over
+//! the bytes of the input, stepping over whole words, numbers, strings,
+//! characters and comments the way the lexer would, so that it always knows
+//! where a token starts. Each chunk is
+//! then lexed on its own thread, and the token vectors are stitched back
+//! together with their spans moved to where the chunk starts.
+//!
+//! Whether a newline becomes a virtual semicolon depends on tokens from
+//! before it, which may be in an earlier chunk. So chunks are lexed with
+//! semicolon insertion turned off, and it is done afterwards over the whole
+//! token stream with [`insert_semicolons`], which is cheap compared to
+//! lexing. This gives the exact same tokens as [`lex_all`].
+
+use std::thread;
+
+use alloc::{vec, vec::Vec};
+
+use super::{
+    asi::insert_semicolons, errors::LexingError, is_valid_id,
+    is_valid_id_start, lex_all, position::Spanned, state::Lexer, tokens::Token,
+};
+
+/// Inputs smaller than this are not worth splitting up.
+const MIN_CHUNK_SIZE: usize = 64 * 1024;
+
+type LexResult = Result<Vec<Spanned<Token>>, Spanned<LexingError>>;
+
+/// Lex all of the tokens in `input` across as many threads as are available.
+/// This gives the same result as [`lex_all`], including stopping at the first
+/// error.
+pub fn lex_parallel(input: &str) -> LexResult {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunks = (input.len() / MIN_CHUNK_SIZE).clamp(1, threads);
+
+    lex_parallel_with(input, chunks)
+}
+
+/// Lex all of the tokens in `input`, split into about `chunks` pieces which
+/// each get their own thread. There might be less chunks than asked for, if
+/// there aren't enough safe places to split the input.
+pub fn lex_parallel_with(input: &str, chunks: usize) -> LexResult {
+    let bounds = safe_boundaries(input, chunks);
+
+    if bounds.len() == 2 {
+        return lex_all(input);
+    }
+
+    let results: Vec<LexResult> = thread::scope(|scope| {
+        let handles: Vec<_> = bounds
+            .windows(2)
+            .map(|pair| {
+                let (start, end) = (pair[0], pair[1]);
+                scope.spawn(move || lex_chunk(&input[start..end], start))
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("lexing thread panicked"))
+            .collect()
+    });
+
+    // Anything lexed after the first error would never have been reached by
+    // the sequential lexer, so stop there too.
+    let mut tokens = Vec::new();
+    for result in results {
+        tokens.extend(result?);
+    }
+
+    Ok(insert_semicolons(input, tokens))
+}
+
+/// Lex a single chunk which starts at `offset` in the whole input, without
+/// inserting any semicolons.
+fn lex_chunk(chunk: &str, offset: usize) -> LexResult {
+    let mut lexer = Lexer::new(chunk);
+    lexer.asi.disabled = true;
+    let mut tokens = Vec::new();
+
+    loop {
+        match lexer.lex_spanned() {
+            Ok(tok) if tok.data == Token::Eof => return Ok(tokens),
+            Ok(tok) => {
+                tokens.push(Spanned::new(tok.data, tok.span.shift(offset)))
+            }
+            Err(err) => {
+                return Err(Spanned::new(err.data, err.span.shift(offset)))
+            }
+        }
+    }
+}
+
+/// Find where to split `input` into about `chunks` pieces. The result starts
+/// with 0 and ends with the length of the input, and every position in
+/// between is a newline outside of any token, so that every token falls
+/// entirely within one chunk.
+pub fn safe_boundaries(input: &str, chunks: usize) -> Vec<usize> {
+    let bytes = input.as_bytes();
+    let target = bytes.len() / chunks.max(1);
+    let mut bounds = vec![0];
+    let mut idx = 0;
+
+    // Every time around, `idx` is where the lexer would start a token, so
+    // words and numbers are skipped as a whole. Otherwise a `b'` or `0(` in
+    // the middle of one would be taken for the start of a literal.
+    while idx < bytes.len() {
+        let rest = &input[idx..];
+
+        idx = match bytes[idx] {
+            b'\n' => {
+                if idx >= bounds[bounds.len() - 1] + target
+                    && bounds.len() < chunks
+                {
+                    bounds.push(idx);
+                }
+                idx + 1
+            }
+            b'"' => skip_string(bytes, idx + 1),
+            b'\'' => match label_len(&rest[1..]) {
+                Some(len) => idx + 1 + len,
+                None => skip_char(bytes, idx + 1),
+            },
+            b'b' if rest.starts_with("b\"") => skip_string(bytes, idx + 2),
+            b'b' if rest.starts_with("b'") => skip_char(bytes, idx + 2),
+            b'/' if rest.starts_with("//") => {
+                // The newline ending the comment is left for the next loop
+                find_byte(bytes, idx + 2, b'\n').unwrap_or(bytes.len())
+            }
+            b'/' if rest.starts_with("/*") => find_comment_end(bytes, idx + 2),
+            byte if byte.is_ascii_digit() => idx + number_len(rest),
+            _ if rest.starts_with(is_valid_id_start) => {
+                idx + rest.find(|chr| !is_valid_id(chr)).unwrap_or(rest.len())
+            }
+            byte => idx + utf8_len(byte),
+        };
+    }
+
+    bounds.push(bytes.len());
+    bounds
+}
+
+/// Get the length of the number at the start of `rest`, following
+/// [`Lexer::lex_number`]. An arbitrary base specifier, like `0(16)`, runs
+/// until the closing parenthesis whatever is inside of it.
+fn number_len(rest: &str) -> usize {
+    let digits = |start: usize, is_digit: &dyn Fn(char) -> bool| {
+        let len = rest[start..].find(|chr| !is_digit(chr));
+        start + len.unwrap_or(rest.len() - start)
+    };
+    let in_base = |base: u32| move |chr: char| chr.is_digit(base) || chr == '_';
+
+    if !rest.starts_with('0') {
+        return digits(0, &in_base(10));
+    }
+
+    match rest.as_bytes().get(1) {
+        Some(b'x' | b'X') => digits(2, &in_base(16)),
+        Some(b'b' | b'B') => digits(2, &in_base(2)),
+        Some(b'o' | b'O') => digits(2, &in_base(8)),
+        Some(b'(') => {
+            let Some(close) = rest.find(')') else {
+                return rest.len();
+            };
+
+            // A base which isn't a number, or is too large, is an error
+            match rest[2..close].parse::<u32>() {
+                Ok(radix) if radix <= 36 => digits(close + 1, &|chr| {
+                    chr.to_digit(36).is_some_and(|digit| digit < radix)
+                }),
+                _ => close + 1,
+            }
+        }
+        _ => digits(1, &in_base(10)),
+    }
+}
+
+/// Skip to just past the closing quote of a string, starting just after the
+/// opening one.
+fn skip_string(bytes: &[u8], mut idx: usize) -> usize {
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'"' => return idx + 1,
+            // Whatever is escaped can't close the string
+            b'\\' => idx += 2,
+            _ => idx += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+/// Get the length of the name of a label, like `'outer`, if the `'` just
+/// before `rest` starts one. This follows [`Lexer::at_label`].
+fn label_len(rest: &str) -> Option<usize> {
+    let name_len = rest.find(|chr| !is_valid_id(chr)).unwrap_or(rest.len());
+
+    (rest.starts_with(is_valid_id_start) && !rest[name_len..].starts_with('\''))
+        .then_some(name_len)
+}
+
+/// Skip past a character literal, starting just after the opening quote. The
+/// lexer takes exactly one character (or escape) and then one more character
+/// whatever it is, which is the closing quote unless the literal is unclosed.
+/// A newline taken that way must stay in the same chunk, so that the error
+/// has the same span.
+fn skip_char(bytes: &[u8], mut idx: usize) -> usize {
+    match bytes.get(idx) {
+        Some(b'\\') => {
+            let escaped = bytes.get(idx + 1).copied();
+            idx += 1 + escaped.map_or(0, utf8_len);
+
+            // A hex escape takes up to two digits
+            if escaped == Some(b'x') {
+                for _ in 0..2 {
+                    if bytes.get(idx).is_some_and(u8::is_ascii_hexdigit) {
+                        idx += 1;
+                    }
+                }
+            }
+        }
+        Some(&byte) => idx += utf8_len(byte),
+        None => return bytes.len(),
+    }
+
+    if let Some(&byte) = bytes.get(idx) {
+        idx += utf8_len(byte);
+    }
+
+    idx.min(bytes.len())
+}
+
+/// Skip to just past the `*/` of a multi-line comment.
+fn find_comment_end(bytes: &[u8], mut idx: usize) -> usize {
+    while idx + 1 < bytes.len() {
+        if bytes[idx] == b'*' && bytes[idx + 1] == b'/' {
+            return idx + 2;
+        }
+        idx += 1;
+    }
+
+    bytes.len()
+}
+
+fn find_byte(bytes: &[u8], from: usize, needle: u8) -> Option<usize> {
+    let from = from.min(bytes.len());
+    bytes[from..]
+        .iter()
+        .position(|&byte| byte == needle)
+        .map(|idx| from + idx)
+}
+
+/// Get the length of a UTF-8 character from its first byte.
+fn utf8_len(byte: u8) -> usize {
+    match byte {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        _ => 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, string::String};
+
+    use super::*;
+
+    fn assert_same_as_lexer(input: &str) {
+        let expected = lex_all(input);
+
+        for chunks in 1..=16 {
+            let result = lex_parallel_with(input, chunks);
+            assert_eq!(result, expected, "{chunks} chunks for {input:?}");
+        }
+    }
+
+    /// Build a large input that exercises every token that can contain a
+    /// newline, and every automatic semicolon rule.
+    fn generated_input(lines: usize) -> String {
+        let mut input = String::new();
+
+        for i in 0..lines {
+            let line = match i % 9 {
+                0 => format!("let a{i} := add(0x{i:X}, 0b101)\n"),
+                1 => format!("let s{i} := \"multi\nline // not a comment\"\n"),
+                2 => format!("/* block\n comment {i} \"*/ let c := '\"'\n"),
+                3 => format!("call(\n    {i},\n    0(9)88\n)\n"),
+                4 => format!("value\n    .field{i}\n    + 1 // trailing\n"),
+                5 => format!("let b{i} := b\"bytes\\\"\\n\" + b'\\''\n"),
+                6 => format!("{{\n    a{i} + b\n}}\n\n"),
+                7 => format!("let c{i} := '\\x41' // ∘ unicode\n"),
+                _ => format!("res = addOne**add({i},4);\n"),
+            };
+            input.push_str(&line);
+        }
+
+        input
+    }
+
+    #[test]
+    fn test_boundaries_are_newlines() {
+        let input = generated_input(200);
+        let bounds = safe_boundaries(&input, 8);
+
+        assert_eq!(bounds[0], 0);
+        assert_eq!(bounds[bounds.len() - 1], input.len());
+        assert!(bounds.len() > 2);
+
+        for &bound in &bounds[1..bounds.len() - 1] {
+            assert_eq!(input.as_bytes()[bound], b'\n');
+        }
+    }
+
+    #[test]
+    fn test_boundaries_skip_tokens() {
+        let input = "\"a\nb\" /*\n*/ '\n' 0(\n)";
+        assert_eq!(safe_boundaries(input, input.len()), vec![0, input.len()]);
+    }
+
+    #[test]
+    fn test_same_as_lexer_on_generated_input() {
+        let input = generated_input(500);
+        assert!(lex_all(&input).is_ok());
+        assert_same_as_lexer(&input);
+    }
+
+    #[test]
+    fn test_semicolons_across_chunks() {
+        assert_same_as_lexer("a\n\nb\n\n(c\n\nd)\n\ne\n\n  .f\n\n{g\n\nh}\n\n");
+    }
+
+    #[test]
+    fn test_unclosed_chars_across_chunks() {
+        let inputs = [
+            "let a := 1\nlet b := '+\nlet d := 2\n",
+            "let a := 1\nlet b := b'c\nlet d := 2\n",
+            "let a := 1\nlet b := '\\\nlet d := 2\n",
+            "let a := 1\nlet b := '\\x4\nlet d := 2\n",
+            "let a := 1\nlet b := '∘\nlet d := 2\n",
+        ];
+
+        for input in inputs {
+            assert!(lex_all(input).is_err(), "{input:?}");
+            assert_same_as_lexer(input);
+        }
+    }
+
+    #[test]
+    fn test_labels_across_chunks() {
+        assert_same_as_lexer("'outer: loop {\n    break 'outer\n}\n'a'\n");
+    }
+
+    #[test]
+    fn test_literals_after_words_and_numbers() {
+        let inputs = [
+            "b'b'0(\n<*\n)\nlet a := 1\n",
+            "01b'_\ne/}<'[\nlet a := 1\n",
+            "ab'c'\n0x1fb'd'\n0(16)ab'e'\n0(99)b'f'\n",
+            "'a \n'outer\n0b1b'\n",
+        ];
+
+        for input in inputs {
+            assert_same_as_lexer(input);
+        }
+    }
+
+    /// Compare against the lexer on many short random inputs, made of the
+    /// pieces that decide where tokens start and end.
+    #[test]
+    fn test_same_as_lexer_on_random_input() {
+        const PIECES: &[&str] = &[
+            "\n", "\n", " ", "b", "'", "\"", "0", "1", "(", ")", "_", "x", "e",
+            "/", "*", "\\", "<", "}", "[", "∘", "!", "b'", "0(",
+        ];
+
+        // A fixed xorshift generator, so that any failure can be repeated
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize % bound
+        };
+
+        for _ in 0..20_000 {
+            let len = 1 + next(24);
+            let input: String =
+                (0..len).map(|_| PIECES[next(PIECES.len())]).collect();
+
+            let expected = lex_all(&input);
+            for chunks in [2, 3, 8] {
+                let result = lex_parallel_with(&input, chunks);
+                assert_eq!(result, expected, "{chunks} chunks for {input:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_same_first_error() {
+        let mut input = generated_input(100);
+        input.push_str("let bad := \"\\q\"\n");
+        input.push_str(&generated_input(100));
+        input.push_str("let worse := §\n");
+
+        let expected = lex_all(&input);
+        assert!(expected.is_err());
+        assert_same_as_lexer(&input);
+    }
+}
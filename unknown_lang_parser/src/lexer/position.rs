@@ -1,20 +1,20 @@
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct BytePos(pub u32);
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BytePos(pub usize);
 
 impl BytePos {
     pub fn advance(self, ch: char) -> Self {
-        Self(self.0 + ch.len_utf8() as u32)
+        Self(self.0 + ch.len_utf8())
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Span {
     pub start: BytePos,
     pub end: BytePos,
 }
 
 impl Span {
-    pub fn new(start: u32, end: u32) -> Self {
+    pub fn new(start: usize, end: usize) -> Self {
         Self {
             start: BytePos(start),
             end: BytePos(end),
@@ -44,8 +44,8 @@ impl Span {
     /// ```
     pub fn union_span(span_a: Self, span_b: Self) -> Self {
         Self {
-            start: std::cmp::min(span_a.start, span_b.start),
-            end: std::cmp::max(span_a.end, span_b.end),
+            start: core::cmp::min(span_a.start, span_b.start),
+            end: core::cmp::max(span_a.end, span_b.end),
         }
     }
 
@@ -65,12 +65,31 @@ impl Span {
     /// ```
     pub fn union(self, other: Self) -> Self {
         Self {
-            start: std::cmp::min(self.start, other.start),
-            end: std::cmp::max(self.end, other.end),
+            start: core::cmp::min(self.start, other.start),
+            end: core::cmp::max(self.end, other.end),
         }
     }
 }
 
+impl Span {
+    /// Moving a [`Span`] later in the input by `offset` bytes. This is used
+    /// when lexing part of a larger input, to turn positions within the part
+    /// into positions within the whole input.
+    pub fn shift(self, offset: usize) -> Self {
+        Self::new(self.start.0 + offset, self.end.0 + offset)
+    }
+
+    /// The number of bytes covered by this [`Span`].
+    pub fn len(self) -> usize {
+        self.end.0 - self.start.0
+    }
+
+    /// Checks if this [`Span`] covers no bytes.
+    pub fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+}
+
 impl<T> From<Spanned<T>> for Span {
     fn from(spanned: Spanned<T>) -> Span {
         spanned.span
@@ -83,6 +102,7 @@ impl<T> From<&Spanned<T>> for Span {
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Spanned<T> {
     pub data: T,
     pub span: Span,
@@ -100,7 +120,7 @@ impl<T> Spanned<T> {
         }
     }
 
-    pub fn new_span(data: T, start: u32, end: u32) -> Self {
+    pub fn new_span(data: T, start: usize, end: usize) -> Self {
         Self {
             data,
             span: Span::new(start, end),
@@ -0,0 +1,253 @@
+//! Rendering tokens back into source code.
+//!
+//! The [`Display`](core::fmt::Display) implementation for [`Token`] is meant
+//! for debugging, so it can't be lexed again. [`Token::to_source`] renders a
+//! token exactly how it could have been written, and [`unparse`] renders a
+//! whole token stream, only putting whitespace between tokens when they would
+//! otherwise lex differently.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+
+use super::{state::Lexer, tokens::Token};
+
+impl Token {
+    /// Render this token as source code. Lexing the output gives back the
+    /// same token. Virtual semicolons are rendered as the newline they came
+    /// from, and the end of file token renders as nothing.
+    pub fn to_source(&self) -> String {
+        match self {
+            Token::Char(chr) => {
+                let mut out = String::from('\'');
+                escape_char(*chr, '\'', &mut out);
+                out.push('\'');
+                out
+            }
+            Token::Str(string) => {
+                let mut out = String::from('"');
+                string
+                    .chars()
+                    .for_each(|chr| escape_char(chr, '"', &mut out));
+                out.push('"');
+                out
+            }
+            Token::Byte(byte) => {
+                let mut out = String::from("b'");
+                escape_byte(*byte, '\'', &mut out);
+                out.push('\'');
+                out
+            }
+            Token::ByteStr(bytes) => {
+                let mut out = String::from("b\"");
+                bytes
+                    .iter()
+                    .for_each(|byte| escape_byte(*byte, '"', &mut out));
+                out.push('"');
+                out
+            }
+            Token::Ident(ident) => ident.clone(),
+            Token::Label(label) => format!("'{label}"),
+            Token::Comment(false, cmt) => format!("//{cmt}"),
+            Token::Comment(true, cmt) => format!("/*{cmt}*/"),
+            Token::Whitespace(ws) => ws.clone(),
+            Token::Number(base, num) => match base {
+                2 => format!("0b{num}"),
+                8 => format!("0o{num}"),
+                // The lexer eats the first `0` of a number, so it has to be
+                // put back when there was more after it, like in `007`
+                10 if num != "0" && num.starts_with(['0', '_']) => {
+                    format!("0{num}")
+                }
+                10 => num.clone(),
+                16 => format!("0x{num}"),
+                base => format!("0({base}){num}"),
+            },
+            Token::VirtualSemi => "\n".to_string(),
+            Token::Eof => String::new(),
+            // Everything else is punctuation, which displays as written
+            tok => tok.to_string(),
+        }
+    }
+}
+
+/// Escape a character within a string or character literal, where `quote` is
+/// the quote surrounding the literal.
+fn escape_char(chr: char, quote: char, out: &mut String) {
+    match chr {
+        '\n' => out.push_str("\\n"),
+        '\r' => out.push_str("\\r"),
+        '\t' => out.push_str("\\t"),
+        '\0' => out.push_str("\\0"),
+        '\\' => out.push_str("\\\\"),
+        chr if chr == quote => {
+            out.push('\\');
+            out.push(chr);
+        }
+        chr if chr.is_ascii_control() => {
+            out.push_str(&format!("\\x{:02X}", chr as u8))
+        }
+        chr => out.push(chr),
+    }
+}
+
+/// Escape a byte within a byte string or byte literal. Anything that isn't
+/// printable ASCII is written as a hex escape.
+fn escape_byte(byte: u8, quote: char, out: &mut String) {
+    if byte.is_ascii() {
+        escape_char(byte as char, quote, out);
+    } else {
+        out.push_str(&format!("\\x{byte:02X}"));
+    }
+}
+
+/// Render a stream of tokens back into source code, with as little
+/// whitespace as possible. Lexing the output gives back the same tokens.
+/// Rendering stops at the first [`Token::Eof`].
+pub fn unparse(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    let mut prev: Option<&Token> = None;
+
+    for tok in tokens.iter().take_while(|tok| **tok != Token::Eof) {
+        if let Some(prev) = prev {
+            out.push_str(separator(prev, tok));
+        }
+
+        out.push_str(&tok.to_source());
+        prev = Some(tok);
+    }
+
+    out
+}
+
+/// Get the whitespace needed between two adjacent tokens.
+fn separator(prev: &Token, next: &Token) -> &'static str {
+    match (prev, next) {
+        // Virtual semicolons already render as a newline, which keeps tokens
+        // apart and ends any line comment before it.
+        (Token::VirtualSemi, _) | (_, Token::VirtualSemi) => "",
+        // Line comments run until the end of the line
+        (Token::Comment(false, _), _) => "\n",
+        (prev, next) if lexes_apart(prev, next) => "",
+        _ => " ",
+    }
+}
+
+/// Checks if two tokens written right next to each other still lex as the
+/// same two tokens, for example `a+` does, while `a b` and `- >` do not.
+fn lexes_apart(prev: &Token, next: &Token) -> bool {
+    let joined = prev.to_source() + &next.to_source();
+    let mut lexer = Lexer::new(&joined);
+
+    lexer.lex_next().as_ref() == Ok(prev)
+        && lexer.lex_next().as_ref() == Ok(next)
+        && lexer.lex_next() == Ok(Token::Eof)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use super::*;
+
+    // Lex all of the tokens, including comments, in the input
+    fn lex(input: &str) -> Vec<Token> {
+        let mut lexer = Lexer::new(input);
+        let mut toks = vec![];
+
+        loop {
+            match lexer.lex_next() {
+                Ok(Token::Eof) => break,
+                Ok(tok) => toks.push(tok),
+                Err(err) => panic!("failed to lex {input:?}: {err}"),
+            }
+        }
+
+        toks
+    }
+
+    #[test]
+    fn test_renders_numbers_with_base() {
+        assert_eq!(Token::Number(10, "12".to_string()).to_source(), "12");
+        assert_eq!(Token::Number(2, "101".to_string()).to_source(), "0b101");
+        assert_eq!(Token::Number(8, "17".to_string()).to_source(), "0o17");
+        assert_eq!(Token::Number(16, "FF".to_string()).to_source(), "0xFF");
+        assert_eq!(Token::Number(9, "88".to_string()).to_source(), "0(9)88");
+    }
+
+    #[test]
+    fn test_numbers_keep_leading_zeros() {
+        for input in ["007", "0_1", "00", "0", "0__", "10_0"] {
+            let toks = lex(input);
+            assert_eq!(lex(&unparse(&toks)), toks, "{input}");
+        }
+
+        assert_eq!(lex("007")[0].to_source(), "007");
+        assert_eq!(lex("0_1")[0].to_source(), "0_1");
+    }
+
+    #[test]
+    fn test_renders_escapes() {
+        let tok = Token::Str("a\"b\\c\n\t\0\x01é".to_string());
+        assert_eq!(tok.to_source(), r#""a\"b\\c\n\t\0\x01é""#);
+
+        assert_eq!(Token::Char('\'').to_source(), r"'\''");
+        assert_eq!(Token::Char('"').to_source(), "'\"'");
+        assert_eq!(Token::Byte(0xFF).to_source(), r"b'\xFF'");
+        assert_eq!(
+            Token::ByteStr(vec![b'"', b'a', 0x80]).to_source(),
+            r#"b"\"a\x80""#
+        );
+    }
+
+    #[test]
+    fn test_renders_comments() {
+        let tok = Token::Comment(true, " block ".to_string());
+        assert_eq!(tok.to_source(), "/* block */");
+
+        let tok = Token::Comment(false, " line".to_string());
+        assert_eq!(tok.to_source(), "// line");
+    }
+
+    #[test]
+    fn test_tokens_relex_to_themselves() {
+        let toks = vec![
+            Token::Str("tab\there \"quoted\"".to_string()),
+            Token::Char('\\'),
+            Token::Byte(b'\n'),
+            Token::ByteStr(vec![0, 0x7F, 0xFF]),
+            Token::Number(36, "zz".to_string()),
+            Token::Comment(true, "\n * multi\n ".to_string()),
+        ];
+
+        for tok in toks {
+            assert_eq!(lex(&tok.to_source()), vec![tok]);
+        }
+    }
+
+    #[test]
+    fn test_unparse_minimal_spacing() {
+        let toks = lex("let a := add( 1 , b ) ;");
+        assert_eq!(unparse(&toks), "let a:=add(1,b);");
+    }
+
+    #[test]
+    fn test_unparse_keeps_tokens_apart() {
+        let input = "a - > b * * c . . . d 0 x1 b \"s\" / / e";
+        let toks = lex(input);
+        let output = unparse(&toks);
+
+        assert_eq!(output, "a- >b* *c. . .d 0 x1 b \"s\"/ /e");
+        assert_eq!(lex(&output), toks);
+    }
+
+    #[test]
+    fn test_unparse_ends_line_comments() {
+        let toks = lex("a // comment\nb\n    + c");
+        let output = unparse(&toks);
+
+        assert_eq!(output, "a// comment\nb+c");
+        assert_eq!(lex(&output), toks);
+    }
+}
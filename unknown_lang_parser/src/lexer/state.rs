@@ -1,12 +1,29 @@
-use std::{iter::Peekable, str::Chars};
+use core::{iter::Peekable, str::Chars};
 
-use super::TokenResult;
+use super::{
+    asi::AsiState,
+    errors::LexingError,
+    position::{Span, Spanned},
+    tokens::Token,
+    TokenResult,
+};
 
 #[derive(Debug)]
 pub struct Lexer<'a> {
     pub input: &'a str,
     pub lookahead: Peekable<Chars<'a>>,
     pub pos: usize,
+    pub asi: AsiState,
+    /// Where the last token started
+    pub token_start: usize,
+    /// Set when the lexer had to make a decision without knowing what comes
+    /// after the end of the input. If more input could still show up, like in
+    /// a [`StreamLexer`](super::stream::StreamLexer), the last token should be
+    /// lexed again once it has.
+    pub needs_input: bool,
+    /// Whether whitespace is lexed into [`Token::Whitespace`] instead of
+    /// being skipped, so that the tokens cover every byte of the input.
+    pub trivia: bool,
 }
 
 impl<'a> Lexer<'a> {
@@ -15,6 +32,20 @@ impl<'a> Lexer<'a> {
             input,
             lookahead: input.chars().peekable(),
             pos: 0,
+            asi: AsiState::default(),
+            token_start: 0,
+            needs_input: false,
+            trivia: false,
+        }
+    }
+
+    /// Create a lexer which keeps whitespace as tokens. Along with comments,
+    /// the tokens then cover every byte of the input, apart from anything
+    /// that fails to lex.
+    pub fn with_trivia(input: &'a str) -> Self {
+        Self {
+            trivia: true,
+            ..Self::new(input)
         }
     }
 
@@ -32,10 +63,6 @@ impl<'a> Lexer<'a> {
             return Some(chr);
         }
 
-        // If we are at the end, the index should be the length of the
-        // input
-        self.pos = self.input.len();
-
         None
     }
 
@@ -74,12 +101,9 @@ impl<'a> Lexer<'a> {
         // Check to see that the next n characters exist. If any return None,
         // we return None sunce we can't return the next n chars.
         for _ in 0..size {
-            if let Some(chr) = self.lookahead.peek() {
-                self.pos += chr.len_utf8();
-                self.lookahead.next();
-            } else {
-                return None;
-            }
+            let chr = self.lookahead.peek()?;
+            self.pos += chr.len_utf8();
+            self.lookahead.next();
         }
 
         let str = &self.input[..size];
@@ -91,12 +115,24 @@ impl<'a> Lexer<'a> {
     pub fn lex_next(&mut self) -> TokenResult {
         self.lex_token()
     }
+
+    /// Get the next token along with the [`Span`] of input it came from. An
+    /// error is spanned over whatever was consumed before it was found.
+    pub fn lex_spanned(
+        &mut self,
+    ) -> Result<Spanned<Token>, Spanned<LexingError>> {
+        let result = self.lex_token();
+        let span = Span::new(self.token_start, self.pos);
+
+        match result {
+            Ok(tok) => Ok(Spanned::new(tok, span)),
+            Err(err) => Err(Spanned::new(err, span)),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::lexer::tokens::Token;
-
     use super::*;
 
     #[test]
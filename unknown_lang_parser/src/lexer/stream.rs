@@ -0,0 +1,365 @@
+//! Lexing from any [`Read`], without holding all of the input in memory.
+//!
+//! The [`StreamLexer`] keeps a window of decoded input, and lexes tokens out
+//! of it with a regular [`Lexer`]. Whenever a token runs into the end of the
+//! window, for example a string which continues past the last read, more
+//! input is read and the token is lexed again. Consumed input is dropped from
+//! the front of the window, so memory use is bounded by the read size and the
+//! largest token rather than the size of the input.
+
+use std::{
+    fmt,
+    io::{self, Read},
+};
+
+use alloc::{string::String, vec::Vec};
+
+use super::{
+    asi::AsiState, errors::LexingError, position::Spanned, state::Lexer,
+    tokens::Token,
+};
+
+/// How many bytes to read at once by default.
+const DEFAULT_READ_SIZE: usize = 64 * 1024;
+
+/// An error found while lexing from a stream.
+#[derive(Debug)]
+pub enum StreamError {
+    /// Reading from the stream failed, or it wasn't valid UTF-8.
+    Io(io::Error),
+    /// The input failed to lex.
+    Lexing(Spanned<LexingError>),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read input: {err}"),
+            Self::Lexing(err) => write!(
+                f,
+                "{} at bytes {}..{}",
+                err.data, err.span.start.0, err.span.end.0
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {}
+
+impl From<io::Error> for StreamError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// A lexer over a [`Read`]. This produces the same tokens and spans as
+/// running a [`Lexer`] over the whole input at once.
+pub struct StreamLexer<R> {
+    reader: R,
+    /// Decoded input, from `start` onwards is yet to be lexed
+    window: String,
+    start: usize,
+    /// Bytes at the end of the last read that don't make up a full UTF-8
+    /// character yet
+    partial: Vec<u8>,
+    /// Position in the whole input of the start of `window`
+    offset: usize,
+    asi: AsiState,
+    read_size: usize,
+    eof: bool,
+}
+
+impl<R: Read> StreamLexer<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_read_size(reader, DEFAULT_READ_SIZE)
+    }
+
+    /// Create a lexer which reads `read_size` bytes at a time. Smaller reads
+    /// use less memory, but need more calls to the reader.
+    pub fn with_read_size(reader: R, read_size: usize) -> Self {
+        Self {
+            reader,
+            window: String::new(),
+            start: 0,
+            partial: Vec::new(),
+            offset: 0,
+            asi: AsiState::default(),
+            read_size: read_size.max(1),
+            eof: false,
+        }
+    }
+
+    /// Get the next token from the stream, along with its position in the
+    /// whole input. Once the input runs out this keeps returning
+    /// [`Token::Eof`].
+    pub fn lex_next(&mut self) -> Result<Spanned<Token>, StreamError> {
+        loop {
+            let mut lexer = Lexer::new(&self.window[self.start..]);
+            lexer.asi = self.asi.clone();
+
+            let result = lexer.lex_spanned();
+
+            // If the lexer reached the end of the window, the token might
+            // carry on into input we haven't read yet.
+            if !self.eof && (lexer.input.is_empty() || lexer.needs_input) {
+                let remaining = self.window.len() - self.start;
+                self.fill(remaining)?;
+                continue;
+            }
+
+            let base = self.offset + self.start;
+            self.start += lexer.pos;
+            self.asi = lexer.asi;
+            self.compact();
+
+            return match result {
+                Ok(tok) => Ok(Spanned::new(tok.data, tok.span.shift(base))),
+                Err(err) => Err(StreamError::Lexing(Spanned::new(
+                    err.data,
+                    err.span.shift(base),
+                ))),
+            };
+        }
+    }
+
+    /// Read more of the input into the window. To avoid lexing a large token
+    /// over and over again, this reads at least as much as is already left
+    /// in the window.
+    fn fill(&mut self, remaining: usize) -> io::Result<()> {
+        let size = self.read_size.max(remaining);
+        let mut buf = core::mem::take(&mut self.partial);
+        let old_len = buf.len();
+        buf.resize(old_len + size, 0);
+
+        let read = loop {
+            match self.reader.read(&mut buf[old_len..]) {
+                Ok(read) => break read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        };
+        buf.truncate(old_len + read);
+
+        if read == 0 {
+            self.eof = true;
+
+            if !buf.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "stream ended part way through a UTF-8 character",
+                ));
+            }
+
+            return Ok(());
+        }
+
+        // Only decode the complete characters, anything left at the end is
+        // kept around for the next read.
+        let valid = match core::str::from_utf8(&buf) {
+            Ok(text) => text.len(),
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(err) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, err))
+            }
+        };
+        self.partial = buf.split_off(valid);
+
+        let text = String::from_utf8(buf)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        self.window.push_str(&text);
+
+        Ok(())
+    }
+
+    /// Drop input that has already been lexed from the front of the window,
+    /// once it makes up most of the window.
+    fn compact(&mut self) {
+        if self.start > self.read_size && self.start * 2 > self.window.len() {
+            self.window.drain(..self.start);
+            self.offset += self.start;
+            self.start = 0;
+        }
+    }
+}
+
+impl<R: Read> Iterator for StreamLexer<R> {
+    type Item = Result<Spanned<Token>, StreamError>;
+
+    /// Iterate over the tokens in the stream, stopping at the end of input.
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.lex_next() {
+            Ok(Spanned {
+                data: Token::Eof, ..
+            }) => None,
+            result => Some(result),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{lex_all, position::Span},
+        *,
+    };
+
+    /// A reader which hands out at most `size` bytes per read, to split up
+    /// the input in awkward places.
+    struct Trickle<'a> {
+        input: &'a [u8],
+        size: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.size.min(buf.len()).min(self.input.len());
+            buf[..len].copy_from_slice(&self.input[..len]);
+            self.input = &self.input[len..];
+            Ok(len)
+        }
+    }
+
+    fn stream_all(
+        input: &str,
+        size: usize,
+    ) -> Result<Vec<Spanned<Token>>, StreamError> {
+        let reader = Trickle {
+            input: input.as_bytes(),
+            size,
+        };
+
+        StreamLexer::with_read_size(reader, size).collect()
+    }
+
+    fn assert_same_as_lexer(input: &str) {
+        let expected = lex_all(input).unwrap();
+
+        for size in 1..=8 {
+            let toks = stream_all(input, size).unwrap();
+            assert_eq!(toks, expected, "read size {size} for {input:?}");
+        }
+    }
+
+    #[test]
+    fn test_streams_tokens_with_spans() {
+        let toks = stream_all("let a := 5;", 64).unwrap();
+
+        assert_eq!(
+            toks[0],
+            Spanned::new_span(Token::Ident("let".into()), 0, 3)
+        );
+        assert_eq!(toks[1], Spanned::new_span(Token::Ident("a".into()), 4, 5));
+        assert_eq!(
+            toks[4],
+            Spanned::new_span(Token::Number(10, "5".into()), 9, 10)
+        );
+    }
+
+    #[test]
+    fn test_tokens_across_reads() {
+        assert_same_as_lexer("identifier 0x1234ABCD 0(36)zzz <<= >>= -> ..");
+    }
+
+    #[test]
+    fn test_strings_and_comments_across_reads() {
+        assert_same_as_lexer(
+            "\"a long string \\n with escapes\" /* a multi-line\n comment */\n\
+             b\"bytes\\xFF\" 'c' // line comment\nend",
+        );
+    }
+
+    #[test]
+    fn test_utf8_across_reads() {
+        assert_same_as_lexer("\"∘ → é\" // (f ∘ g)(x)\nnaïve");
+    }
+
+    #[test]
+    fn test_semicolons_across_reads() {
+        assert_same_as_lexer("a\n// comment\n\n   + b\nc\n\n/* c */ d\n");
+    }
+
+    #[test]
+    fn test_every_split_point() {
+        let inputs = [
+            "a\n!= b",
+            "a\n!b",
+            "if a {\n} else {\n}",
+            "a\n// c\n!= b\n!c",
+        ];
+
+        for input in inputs {
+            let expected = lex_all(input).unwrap();
+
+            for idx in 0..=input.len() {
+                let (first, second) = input.as_bytes().split_at(idx);
+                let toks: Result<Vec<_>, _> =
+                    StreamLexer::new(first.chain(second)).collect();
+
+                assert_eq!(toks.unwrap(), expected, "split at {idx} {input:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let err = stream_all("let a := 5;\n\"unclosed", 3).unwrap_err();
+
+        match err {
+            StreamError::Lexing(err) => {
+                assert_eq!(err.data, LexingError::UnclosedString);
+                assert_eq!(err.span, Span::new(12, 21));
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let reader = Trickle {
+            input: b"abc \xFF",
+            size: 2,
+        };
+        let result: Result<Vec<_>, _> = StreamLexer::new(reader).collect();
+
+        assert!(matches!(result, Err(StreamError::Io(_))));
+    }
+
+    #[test]
+    fn test_window_stays_bounded() {
+        let line = "let value := add(0x15, 0b101001) // comment\n";
+        let input = line.repeat(2000);
+
+        let mut lexer = StreamLexer::with_read_size(
+            Trickle {
+                input: input.as_bytes(),
+                size: 256,
+            },
+            256,
+        );
+
+        let mut count = 0;
+        while let Some(tok) = lexer.next() {
+            tok.unwrap();
+            count += 1;
+            assert!(lexer.window.len() <= 1024, "{}", lexer.window.len());
+        }
+
+        assert_eq!(count, lex_all(&input).unwrap().len());
+        assert_eq!(lexer.offset + lexer.start, input.len());
+    }
+
+    #[test]
+    fn test_eof_repeats() {
+        let mut lexer = StreamLexer::new("a".as_bytes());
+
+        assert_eq!(lexer.lex_next().unwrap().data, Token::Ident("a".into()));
+        assert_eq!(
+            lexer.lex_next().unwrap(),
+            Spanned::new_span(Token::Eof, 1, 1)
+        );
+        assert_eq!(
+            lexer.lex_next().unwrap(),
+            Spanned::new_span(Token::Eof, 1, 1)
+        );
+    }
+}
@@ -1,5 +1,7 @@
 //! Tokens for unknown-lang parser
-#[derive(Debug, PartialEq, Eq)]
+use alloc::{string::String, vec::Vec};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Token {
     /// (
     LPar,
@@ -23,6 +25,8 @@ pub enum Token {
     ColonColon,
     /// ;
     Semi,
+    /// A semicolon inserted at the end of a line, see `asi.rs`
+    VirtualSemi,
     /// =>
     FatArrow,
     /// $
@@ -35,13 +39,28 @@ pub enum Token {
     Dot,
     /// ..
     DotDot,
+    /// ..=
+    DotDotEq,
     /// ~
     Tilde,
+    /// @
+    At,
+    /// #
+    Hash,
 
     Char(char),
     Str(String),
+    /// b'x'
+    Byte(u8),
+    /// b"..."
+    ByteStr(Vec<u8>),
     Ident(String),
+    /// A label on a loop, like `'outer`, without the `'`
+    Label(String),
     Comment(bool, String),
+    /// Spaces, tabs and newlines, which are only kept by a lexer created with
+    /// [`Lexer::with_trivia`](super::state::Lexer::with_trivia)
+    Whitespace(String),
     // Currently all numbers (including floats) will go into this number
     // token, and later will be split into different typed numbers.
     Number(u8, String),
@@ -53,14 +72,20 @@ pub enum Token {
     Minus,
     /// *
     Star,
+    /// **
+    StarStar,
     /// /
     Slash,
     /// %
     Percent,
     /// &
     Ampersand,
+    /// &&
+    AmpAmp,
     /// |
     Bar,
+    /// ||
+    BarBar,
     /// ^
     Hat,
     /// >
@@ -86,8 +111,8 @@ pub enum Token {
     Eof,
 }
 
-impl std::fmt::Display for Token {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl core::fmt::Display for Token {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         match self {
             Token::LPar => write!(f, "("),
             Token::RPar => write!(f, ")"),
@@ -100,28 +125,41 @@ impl std::fmt::Display for Token {
             Token::Colon => write!(f, ":"),
             Token::ColonColon => write!(f, "::"),
             Token::Semi => write!(f, ";"),
+            Token::VirtualSemi => write!(f, "<;>"),
             Token::FatArrow => write!(f, "=>"),
             Token::Dollar => write!(f, "$"),
             Token::Comma => write!(f, ","),
             Token::RightArrow => write!(f, "->"),
             Token::Dot => write!(f, "."),
             Token::DotDot => write!(f, ".."),
+            Token::DotDotEq => write!(f, "..="),
             Token::Tilde => write!(f, "~"),
+            Token::At => write!(f, "@"),
+            Token::Hash => write!(f, "#"),
 
             Token::Char(chr) => write!(f, "'{chr}'"),
             Token::Str(string) => write!(f, "\"{string}\""),
+            Token::Byte(byte) => write!(f, "b'{}'", byte.escape_ascii()),
+            Token::ByteStr(bytes) => {
+                write!(f, "b\"{}\"", bytes.escape_ascii())
+            }
             Token::Ident(ident) => write!(f, "[{ident}]"),
+            Token::Label(label) => write!(f, "'{label}"),
             Token::Comment(true, cmt) => write!(f, "{cmt}"),
             Token::Comment(false, cmt) => write!(f, "// {cmt}"),
+            Token::Whitespace(ws) => write!(f, "\"{}\"", ws.escape_debug()),
             Token::Number(base, num) => write!(f, "Num({num}, base={base})"),
 
             Token::Plus => write!(f, "+"),
             Token::Minus => write!(f, "-"),
             Token::Star => write!(f, "*"),
+            Token::StarStar => write!(f, "**"),
             Token::Slash => write!(f, "/"),
             Token::Percent => write!(f, "%"),
             Token::Ampersand => write!(f, "&"),
+            Token::AmpAmp => write!(f, "&&"),
             Token::Bar => write!(f, "|"),
+            Token::BarBar => write!(f, "||"),
             Token::Hat => write!(f, "^"),
             Token::Greater => write!(f, ">"),
             Token::GreaterEq => write!(f, ">="),
@@ -1,4 +1,18 @@
-#![feature(let_chains)]
+#![no_std]
 
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod ast;
+pub mod attributes;
+pub mod captures;
+pub mod eval;
+pub mod generics;
 pub mod lexer;
-pub mod state;
+pub mod macros;
+pub mod modules;
+pub mod parser;
+pub mod resolve;
+pub mod syntax;
+pub mod traits;
@@ -0,0 +1,77 @@
+use alloc::string::String;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MacroError {
+    /// A call to a macro which isn't declared in the file
+    Unknown(String),
+    /// A call which none of the rules of its macro match
+    NoRuleMatched(String),
+    /// A fragment of a kind which doesn't exist, like `$a:foo`
+    UnknownFragment(String),
+    /// A `$` in a rule which isn't followed by a name or `(`, or a name in a
+    /// matcher without the kind of its fragment
+    ExpectedMetavar,
+    /// A `$( .. )` in a rule which isn't followed by `*` or `+`
+    ExpectedRepeat,
+    /// One name bound twice by a matcher
+    DuplicateVar(String),
+    /// A name in the body of a rule which its matcher doesn't bind
+    UnboundVar(String),
+    /// A name bound inside of `$( .. )` which is used outside of one
+    Repeated(String),
+    /// A `$( .. )` in the body of a rule which doesn't use any repeated name,
+    /// or uses names repeated a different number of times
+    RepeatMismatch,
+    /// A macro which kept expanding into calls, more than
+    /// [`EXPANSION_LIMIT`](super::EXPANSION_LIMIT) deep
+    TooDeep(String),
+    /// A macro which made the file expand more than
+    /// [`MAX_EXPANSIONS`](super::MAX_EXPANSIONS) calls, or into more than
+    /// [`MAX_TOKENS`](super::MAX_TOKENS) tokens
+    TooLarge(String),
+}
+
+impl core::fmt::Display for MacroError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Unknown(name) => write!(f, "no macro named `{name}`"),
+            Self::NoRuleMatched(name) => {
+                write!(f, "no rule of `{name}!` matches this call")
+            }
+            Self::UnknownFragment(kind) => {
+                write!(f, "`{kind}` is not a kind of fragment")
+            }
+            Self::ExpectedMetavar => write!(
+                f,
+                "expected `$name:kind` in a matcher, `$name` in a body, or \
+                 `$(`"
+            ),
+            Self::ExpectedRepeat => {
+                write!(f, "expected `*` or `+` after `$( .. )`")
+            }
+            Self::DuplicateVar(name) => {
+                write!(f, "`${name}` is bound more than once")
+            }
+            Self::UnboundVar(name) => {
+                write!(f, "`${name}` is not bound by the matcher")
+            }
+            Self::Repeated(name) => write!(
+                f,
+                "`${name}` is repeated, so it has to be used inside of `$( .. )`"
+            ),
+            Self::RepeatMismatch => write!(
+                f,
+                "`$( .. )` has to use names repeated the same number of times"
+            ),
+            Self::TooDeep(name) => {
+                write!(f, "`{name}!` expands too deeply, it may never stop")
+            }
+            Self::TooLarge(name) => {
+                write!(f, "`{name}!` expands into too much code, it may never stop")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MacroError {}
@@ -0,0 +1,886 @@
+//! Declarative macros, which are expanded before a file is parsed.
+//!
+//! A macro is declared at the top level of a file, with rules made of a
+//! matcher and a body:
+//!
+//! ```text
+//! macro max {
+//!     ($a:expr, $b:expr) => { if $a > $b { $a } else { $b } }
+//! }
+//! ```
+//!
+//! A call like `max!(x, 1)` is replaced with the body of the first rule that
+//! matches the tokens between its delimiters. `$name:kind` in a matcher
+//! matches a fragment of that kind, one of `expr`, `ident`, `ty`, `pat`,
+//! `block`, `literal` or `tt`, and `$name` in the body is replaced with it.
+//! `$( .. ) sep *` matches what is inside of it any number of times, or at
+//! least once with `+`, and repeats the same in the body. Macros work on
+//! tokens, so the parser only sees what calls expand into. That is expanded
+//! again, so macros can call each other and themselves, up to
+//! [`EXPANSION_LIMIT`] calls deep. A file can expand at most
+//! [`MAX_EXPANSIONS`] calls into at most [`MAX_TOKENS`] tokens in total, which
+//! stops a macro calling itself more than once from growing without end.
+//!
+//! Macros are hygienic: a variable that the body of a macro declares with
+//! `let`, `for`, a lambda parameter or a pattern is renamed, so that it can't
+//! see or hide a variable with the same name passed in from the call. A body
+//! which is a single expression is put between parentheses, so that it keeps
+//! its precedence wherever it is called, and a body made of statements is put
+//! in a block when it is called in the middle of an expression. Every token
+//! that comes from a body remembers the expansion it came from, so that an
+//! error in it can list the macros it was expanded from. Its span is the span
+//! of the call.
+//!
+//! Only calls in expressions are parsed, and a macro is only known in the
+//! file that declares it. Calls which can't be expanded are reported and
+//! left for the parser, which keeps them as [`ExprKind::MacroCall`].
+//!
+//! [`ExprKind::MacroCall`]: crate::ast::ExprKind::MacroCall
+
+pub mod errors;
+pub mod rules;
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    format,
+    string::String,
+    vec,
+    vec::Vec,
+};
+
+use crate::{
+    ast::{
+        visit::{
+            walk_expr, walk_field_init, walk_field_pat, walk_lambda_param,
+            walk_let, walk_pat, Visitor,
+        },
+        Expr, ExprKind, FieldInit, FieldPat, Ident, LambdaParam, Let, Pat,
+        PatKind,
+    },
+    lexer::{
+        kind::TokenKind,
+        position::{Span, Spanned},
+        tokens::Token,
+    },
+    parser::{
+        errors::ParseError,
+        state::{joined, Parser},
+    },
+};
+
+use self::{
+    errors::MacroError,
+    rules::{compile, group_end, match_all, transcribe, Rule},
+};
+
+/// How many calls can be expanded inside of each other, which stops a macro
+/// that always calls itself. Every call in an expression is wrapped in
+/// parenthesis, so this is kept below the parser's
+/// [`MAX_DEPTH`](crate::parser::state::MAX_DEPTH), letting the expansion be
+/// reported rather than how deeply it nests.
+pub const EXPANSION_LIMIT: usize = 32;
+
+/// How many calls a file can expand, which stops a macro that calls itself
+/// more than once, since the number of calls doubles with each level.
+pub const MAX_EXPANSIONS: usize = 4096;
+
+/// How many tokens the calls in a file can expand into, which stops a macro
+/// that passes itself ever more tokens.
+pub const MAX_TOKENS: usize = 1 << 16;
+
+/// A token being expanded, along with what the parser needs to know about it
+/// besides its span.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tok {
+    pub token: Spanned<Token>,
+    /// Whether the token starts right where the one before it ended
+    pub joined: bool,
+    /// The expansion the token came from, if any
+    pub origin: Option<usize>,
+}
+
+/// A macro call which was expanded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expansion {
+    pub name: String,
+    /// The span of the name of the macro where it is declared
+    pub def: Span,
+    /// The expansion that the call came from, if any
+    pub parent: Option<usize>,
+}
+
+/// The tokens of a file with every macro call expanded.
+#[derive(Debug)]
+pub struct Expanded {
+    pub tokens: Vec<Spanned<Token>>,
+    /// Whether each token starts right where the one before it ended
+    pub joined: Vec<bool>,
+    /// The expansion that each token came from, if any
+    pub origins: Vec<Option<usize>>,
+    pub expansions: Vec<Expansion>,
+    pub errors: Vec<Spanned<ParseError>>,
+}
+
+/// Expand every macro call in `tokens`, which come from
+/// [`lex`](crate::parser::state::lex) and end with [`Token::Eof`].
+pub fn expand(tokens: Vec<Spanned<Token>>) -> Expanded {
+    let flags = joined(&tokens);
+    let mut expander = Expander::default();
+    expander.declare(&tokens, &flags);
+
+    let input = tokens
+        .into_iter()
+        .zip(flags)
+        .map(|(token, joined)| Tok {
+            token,
+            joined,
+            origin: None,
+        })
+        .collect();
+    let place = Place {
+        start: true,
+        end: true,
+    };
+    let output = expander.expand(input, 0, place);
+
+    let mut expanded = Expanded {
+        tokens: Vec::with_capacity(output.len()),
+        joined: Vec::with_capacity(output.len()),
+        origins: Vec::with_capacity(output.len()),
+        expansions: expander.expansions,
+        errors: expander.errors,
+    };
+    for tok in output {
+        expanded.tokens.push(tok.token);
+        expanded.joined.push(tok.joined);
+        expanded.origins.push(tok.origin);
+    }
+
+    expanded
+}
+
+/// Get the macros that a token from the expansion `origin` was expanded
+/// from, innermost first, each spanning the name of its declaration.
+pub fn trace(
+    expansions: &[Expansion],
+    mut origin: Option<usize>,
+) -> Vec<Spanned<String>> {
+    let mut trace = vec![];
+
+    while let Some(expansion) = origin.and_then(|idx| expansions.get(idx)) {
+        trace.push(Spanned::new(expansion.name.clone(), expansion.def));
+        origin = expansion.parent;
+    }
+
+    trace
+}
+
+struct Macro {
+    name: Ident,
+    rules: Vec<Rule>,
+}
+
+/// Whether a statement can start where some tokens start, and end where they
+/// end, so that a call there can be replaced with statements as they are.
+#[derive(Clone, Copy)]
+struct Place {
+    start: bool,
+    end: bool,
+}
+
+#[derive(Default)]
+struct Expander {
+    macros: BTreeMap<String, Macro>,
+    expansions: Vec<Expansion>,
+    /// How many tokens the calls expanded so far expanded into
+    tokens: usize,
+    /// Whether the file expanded into too much, so that no more calls are
+    /// expanded
+    stopped: bool,
+    errors: Vec<Spanned<ParseError>>,
+}
+
+impl Expander {
+    /// Find every macro declared at the top level of the file. A broken
+    /// declaration is left out, and reported later by the parser.
+    fn declare(&mut self, tokens: &[Spanned<Token>], flags: &[bool]) {
+        let mut depth = 0usize;
+
+        for (idx, tok) in tokens.iter().enumerate() {
+            let kind = tok.data.kind();
+            if kind.closing_delimiter().is_some() {
+                depth += 1;
+            } else if kind.is_closing_delimiter() {
+                depth = depth.saturating_sub(1);
+            }
+
+            if kind != TokenKind::Macro || depth > 0 {
+                continue;
+            }
+
+            let mut parser = Parser::from_tokens(
+                tokens[idx..].to_vec(),
+                flags[idx..].to_vec(),
+            );
+            let Ok(decl) = parser.parse_macro() else {
+                continue;
+            };
+            if !parser.errors.is_empty() {
+                continue;
+            }
+
+            let mut rules = vec![];
+            for rule in &decl.rules {
+                match compile(rule) {
+                    Ok(rule) => rules.push(rule),
+                    Err(err) => self.errors.push(Spanned::new(
+                        ParseError::Macro(err.data),
+                        err.span,
+                    )),
+                }
+            }
+
+            // Like any other declaration, the first one with a name is used
+            let name = decl.name.data.clone();
+            self.macros.entry(name).or_insert(Macro {
+                name: decl.name,
+                rules,
+            });
+        }
+    }
+
+    /// Expand every call in `input`, which is `depth` expansions deep, at
+    /// `place`.
+    fn expand(
+        &mut self,
+        input: Vec<Tok>,
+        depth: usize,
+        place: Place,
+    ) -> Vec<Tok> {
+        let mut output = Vec::with_capacity(input.len());
+        let mut idx = 0;
+
+        while idx < input.len() {
+            let kind = |idx: usize| {
+                input
+                    .get(idx)
+                    .map_or(TokenKind::Eof, |tok| tok.token.data.kind())
+            };
+            let group = |open: usize| {
+                let kinds =
+                    input[open..].iter().map(|tok| tok.token.data.kind());
+                group_end(kinds).map(|end| open + end + 1)
+            };
+
+            // Declarations are left as they are for the parser
+            if kind(idx) == TokenKind::Macro
+                && kind(idx + 2) == TokenKind::LBrace
+            {
+                if let Some(end) = group(idx + 2) {
+                    output.extend_from_slice(&input[idx..end]);
+                    idx = end;
+                    continue;
+                }
+            }
+
+            if kind(idx) == TokenKind::Ident
+                && kind(idx + 1) == TokenKind::Bang
+                && kind(idx + 2).closing_delimiter().is_some()
+            {
+                if let Some(end) = group(idx + 2) {
+                    let place = Place {
+                        start: output.last().map_or(place.start, |tok| {
+                            let kind = tok.token.data.kind();
+                            kind == TokenKind::LBrace || kind.is_terminator()
+                        }),
+                        end: input.get(end).map_or(place.end, |tok| {
+                            let kind = tok.token.data.kind();
+                            matches!(kind, TokenKind::RBrace | TokenKind::Eof)
+                                || kind.is_terminator()
+                        }),
+                    };
+                    self.call(&input[idx..end], depth, place, &mut output);
+                    idx = end;
+                    continue;
+                }
+            }
+
+            output.push(input[idx].clone());
+            idx += 1;
+        }
+
+        output
+    }
+
+    /// Expand a single call at `place`, from the name of the macro to its
+    /// closing delimiter. A call which can't be expanded is reported, and
+    /// left as it is.
+    fn call(
+        &mut self,
+        call: &[Tok],
+        depth: usize,
+        place: Place,
+        output: &mut Vec<Tok>,
+    ) {
+        let name = match &call[0].token.data {
+            Token::Ident(name) => name.clone(),
+            _ => String::new(),
+        };
+        let origin = call[0].origin;
+        let span = call[0].token.span.union(call[call.len() - 1].token.span);
+        let args = &call[3..call.len() - 1];
+
+        // What is left is kept as it is, since it was already reported
+        if self.stopped {
+            output.extend_from_slice(call);
+            return;
+        }
+
+        let expanded = match self.macros.get(&name) {
+            None => {
+                let err = MacroError::Unknown(name.clone());
+                Err(Spanned::new(err, call[0].token.span))
+            }
+            Some(_) if depth >= EXPANSION_LIMIT => {
+                let err = MacroError::TooDeep(name.clone());
+                Err(Spanned::new(err, span))
+            }
+            Some(_) if self.expansions.len() >= MAX_EXPANSIONS => {
+                let err = MacroError::TooLarge(name.clone());
+                Err(Spanned::new(err, span))
+            }
+            Some(decl) => decl
+                .rules
+                .iter()
+                .find_map(|rule| Some((rule, match_all(&rule.matcher, args)?)))
+                .ok_or_else(|| {
+                    let err = MacroError::NoRuleMatched(name.clone());
+                    Spanned::new(err, span)
+                })
+                .and_then(|(rule, bindings)| {
+                    let mut body = vec![];
+                    transcribe(&rule.body, &bindings, &mut body)?;
+                    if self.tokens + body.len() > MAX_TOKENS {
+                        let err = MacroError::TooLarge(name.clone());
+                        return Err(Spanned::new(err, span));
+                    }
+                    Ok((body, decl.name.span))
+                }),
+        };
+
+        let (body, def) = match expanded {
+            Ok(expanded) => expanded,
+            Err(err) => {
+                self.report(err, origin);
+                output.extend_from_slice(call);
+                return;
+            }
+        };
+        self.tokens += body.len();
+
+        let index = self.expansions.len();
+        self.expansions.push(Expansion {
+            name,
+            def,
+            parent: origin,
+        });
+        let statements = place.start && place.end;
+        let body = rename_locals(body, index, statements);
+
+        let body = body
+            .into_iter()
+            .map(|(mut tok, from_body)| {
+                if from_body {
+                    tok.token.span = span;
+                    tok.origin = Some(index);
+                }
+                tok
+            })
+            .collect();
+        output.extend(self.expand(body, depth + 1, place));
+    }
+
+    /// Report an error in a call from the expansion `origin`, listing the
+    /// macros it came from.
+    fn report(&mut self, err: Spanned<MacroError>, origin: Option<usize>) {
+        let runaway = match err.data {
+            MacroError::TooLarge(_) => {
+                self.stopped = true;
+                true
+            }
+            MacroError::TooDeep(_) => true,
+            _ => false,
+        };
+
+        // A macro that never stops would list itself over and over, and
+        // be reported for every call it makes once it is too deep
+        let data = if runaway {
+            ParseError::Macro(err.data)
+        } else {
+            let trace = trace(&self.expansions, origin);
+            ParseError::Macro(err.data).in_macro(trace)
+        };
+        let err = Spanned::new(data, err.span);
+        if !self.errors.contains(&err) {
+            self.errors.push(err);
+        }
+    }
+}
+
+/// Rename every variable that the body of a macro declares to
+/// `name#expansion`, which can't be written in the source, so that it is
+/// apart from any variable passed in from the call. Names after a `.`,
+/// around a `::` or before the `:` of a field are fields and paths, and are
+/// left alone, and a shorthand field like the `x` of `Point { x }` is written
+/// out as `x: x#expansion`.
+///
+/// A body which is a single expression is put between parentheses, so that
+/// `2 * m!(1)` multiplies all of what `m!(1)` expands into. A body made of
+/// statements is put in a block, unless `statements` can take the place of
+/// the call as they are.
+fn rename_locals(
+    body: Vec<(Tok, bool)>,
+    expansion: usize,
+    statements: bool,
+) -> Vec<(Tok, bool)> {
+    // Line ends after the body don't make it any less of an expression
+    let end = body
+        .iter()
+        .rposition(|(tok, _)| !tok.token.data.kind().is_terminator())
+        .map_or(0, |idx| idx + 1);
+    let found = Declarations::find(&body[..end]);
+
+    let mut locals = BTreeSet::new();
+    for idx in found.decls {
+        if let Some((tok, true)) = body.get(idx) {
+            if let Token::Ident(name) = &tok.token.data {
+                locals.insert(name.clone());
+            }
+        }
+    }
+
+    let kind = |idx: Option<usize>| {
+        idx.and_then(|idx| body.get(idx))
+            .map(|(tok, _)| tok.token.data.kind())
+    };
+    let renamed: Vec<_> = (0..body.len())
+        .map(|idx| {
+            let member = matches!(
+                kind(idx.checked_sub(1)),
+                Some(TokenKind::Dot | TokenKind::ColonColon)
+            ) || kind(Some(idx + 1))
+                == Some(TokenKind::ColonColon)
+                || found.fields.contains(&idx);
+
+            match &body[idx] {
+                (tok, true) if !member => match &tok.token.data {
+                    Token::Ident(name) => locals.contains(name),
+                    _ => false,
+                },
+                _ => false,
+            }
+        })
+        .collect();
+
+    let wrap = if found.is_expr && end > 1 {
+        Some((Token::LPar, Token::RPar))
+    } else if !found.is_expr && !statements && end > 0 {
+        Some((Token::LBrace, Token::RBrace))
+    } else {
+        None
+    };
+    let inserted = |token| {
+        let tok = Tok {
+            token: Spanned::new_span(token, 0, 0),
+            joined: false,
+            origin: None,
+        };
+        (tok, true)
+    };
+
+    let mut output = Vec::with_capacity(body.len() + 2);
+    for (idx, (mut tok, from_body)) in body.into_iter().enumerate() {
+        if let Some((open, _)) = wrap.as_ref().filter(|_| idx == 0) {
+            output.push(inserted(open.clone()));
+        }
+
+        if renamed[idx] {
+            if found.shorthands.contains(&idx) {
+                output.push((tok.clone(), true));
+                output.push(inserted(Token::Colon));
+            }
+            if let Token::Ident(name) = &mut tok.token.data {
+                *name = format!("{name}#{expansion}");
+            }
+        }
+        output.push((tok, from_body));
+
+        if let Some((_, close)) = wrap.as_ref().filter(|_| idx + 1 == end) {
+            output.push(inserted(close.clone()));
+        }
+    }
+
+    output
+}
+
+/// The variables declared in the body of a macro, found by parsing it as the
+/// statements of a block. Each token is given its index as its span, so that
+/// what is found can be traced back to the token it came from.
+#[derive(Default)]
+struct Declarations {
+    /// The tokens which name a variable where it is declared
+    decls: Vec<usize>,
+    /// The tokens which are shorthand fields, like the `x` of `Point { x }`
+    shorthands: BTreeSet<usize>,
+    /// The tokens which name a field given a value or pattern, like the `x`
+    /// of `Point { x: 1 }`
+    fields: BTreeSet<usize>,
+    /// Whether the body is a single expression, rather than statements
+    is_expr: bool,
+}
+
+impl Declarations {
+    fn find(body: &[(Tok, bool)]) -> Self {
+        let token = |data, idx| Spanned::new_span(data, idx, idx + 1);
+        let mut tokens = vec![token(Token::LBrace, 0)];
+        tokens.extend(
+            body.iter()
+                .enumerate()
+                .map(|(idx, (tok, _))| token(tok.token.data.clone(), idx + 1)),
+        );
+        tokens.push(token(Token::RBrace, body.len() + 1));
+        tokens.push(token(Token::Eof, body.len() + 2));
+
+        let joined = [false]
+            .into_iter()
+            .chain(body.iter().map(|(tok, _)| tok.joined))
+            .chain([false, false])
+            .collect();
+
+        // A body which doesn't parse is reported once it is expanded, so
+        // whatever could be found is good enough
+        let mut found = Self::default();
+        let mut parser = Parser::from_tokens(tokens, joined);
+        if let Ok(block) = parser.parse_block() {
+            found.is_expr = block.data.stmts.is_empty()
+                && block.data.tail.is_some()
+                && parser.errors.is_empty();
+            found.visit_block(&block);
+        }
+
+        found
+    }
+
+    fn declare(&mut self, span: Span) {
+        self.decls.push(span.start.0 - 1);
+    }
+}
+
+impl Visitor for Declarations {
+    fn visit_let(&mut self, stmt: &Let) {
+        self.declare(stmt.name.span);
+        walk_let(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let ExprKind::For { binding, .. } = &expr.data {
+            self.declare(binding.span);
+        }
+        walk_expr(self, expr);
+    }
+
+    fn visit_field_init(&mut self, init: &FieldInit) {
+        let idx = init.data.name.span.start.0 - 1;
+        match &init.data.value {
+            Some(_) => self.fields.insert(idx),
+            None => self.shorthands.insert(idx),
+        };
+        walk_field_init(self, init);
+    }
+
+    fn visit_lambda_param(&mut self, param: &LambdaParam) {
+        self.declare(param.data.name.span);
+        walk_lambda_param(self, param);
+    }
+
+    fn visit_pat(&mut self, pat: &Pat) {
+        match &pat.data {
+            PatKind::Binding(_) => self.declare(pat.span),
+            PatKind::At { name, .. } => self.declare(name.span),
+            _ => {}
+        }
+        walk_pat(self, pat);
+    }
+
+    fn visit_field_pat(&mut self, field: &FieldPat) {
+        let name = &field.data.name;
+        let idx = name.span.start.0 - 1;
+        match &field.data.pat {
+            Some(_) => self.fields.insert(idx),
+            None => {
+                self.declare(name.span);
+                self.shorthands.insert(idx)
+            }
+        };
+        walk_field_pat(self, field);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::{ast::print::sexpr, parser::parse};
+
+    /// Parse `input`, giving back its S-expression and error messages.
+    fn expand(input: &str) -> (String, Vec<String>) {
+        let (module, errors) = parse(input);
+        let messages = errors.iter().map(|err| err.data.to_string()).collect();
+
+        (sexpr(&module), messages)
+    }
+
+    #[test]
+    fn test_expands_calls() {
+        let (module, errors) = expand(concat!(
+            "macro max { ($a:expr, $b:expr) => { if $a > $b { $a } else { $b } } }\n",
+            "let m := max!(1 + 2, b)",
+        ));
+
+        assert_eq!(errors, Vec::<String>::new());
+        assert!(
+            module.ends_with(
+                "(let m (if (> (+ 1 2) b) (block (+ 1 2)) (block b))))"
+            ),
+            "{module}"
+        );
+    }
+
+    #[test]
+    fn test_repeats_and_recursion() {
+        let (module, errors) = expand(concat!(
+            "macro sum {\n",
+            "    () => { 0 }\n",
+            "    ($first:expr $(, $rest:expr)*) => { $first + sum!($($rest),*) }\n",
+            "}\n",
+            "let s := sum!(1, 2, 3)",
+        ));
+
+        assert_eq!(errors, Vec::<String>::new());
+        // Each expansion is put between parentheses
+        assert!(module.ends_with("(let s (+ 1 (+ 2 (+ 3 0)))))"), "{module}");
+    }
+
+    #[test]
+    fn test_renames_locals() {
+        let (module, errors) = expand(concat!(
+            "macro swap { ($a:ident, $b:ident) => { let tmp = $a; $a = $b; $b = tmp } }\n",
+            "func f() := { let tmp = 1; let b = 2; swap!(tmp, b) }",
+        ));
+
+        assert_eq!(errors, Vec::<String>::new());
+        assert!(
+            module.contains("(let tmp#0 tmp) (= tmp b) (= b tmp#0)"),
+            "{module}"
+        );
+    }
+
+    #[test]
+    fn test_renames_every_binding() {
+        let (module, errors) = expand(concat!(
+            "macro lambda { ($x:expr) => { |x, y| x + y + $x } }\n",
+            "macro arm { ($x:expr) => { match $x { 0 => $x, x => x } } }\n",
+            "macro at { ($x:expr) => { match $x { n @ 1..=9 => n, _ => x } } }\n",
+            "macro field {\n",
+            "    ($x:expr) => { match $x { Point { x, y: Some(y) } => x + y } }\n",
+            "}\n",
+            "macro init { ($x:expr) => { { let x := 1; Point { x, y: $x } } } }\n",
+            "func f(x: i32) := {\n",
+            "    lambda!(x)\n",
+            "    arm!(x)\n",
+            "    at!(x)\n",
+            "    field!(x)\n",
+            "    init!(x)\n",
+            "}",
+        ));
+
+        assert_eq!(errors, Vec::<String>::new());
+        for expected in [
+            "(lambda (params x#0 y#0) (+ (+ x#0 y#0) x))",
+            "(match x (arm 0 x) (arm x#1 x#1))",
+            "(match x (arm (@ n#2 (..= 1 9)) n#2) (arm _ x))",
+            "(match x (arm (Point {(x x#3) (y (Some y#3))}) (+ x#3 y#3)))",
+            "(let x#4 1) (new Point (x x#4) (y x))",
+        ] {
+            assert!(module.contains(expected), "{expected} in {module}");
+        }
+    }
+
+    #[test]
+    fn test_bodies_keep_their_precedence() {
+        let (module, errors) = expand(concat!(
+            "macro double { ($a:expr) => { $a + $a } }\n",
+            "let a := 2 * double!(1 + 1)",
+        ));
+
+        assert_eq!(errors, Vec::<String>::new());
+        assert!(
+            module.ends_with("(let a (* 2 (+ (+ 1 1) (+ 1 1)))))"),
+            "{module}"
+        );
+    }
+
+    #[test]
+    fn test_statements_in_expressions_are_a_block() {
+        let (module, errors) = expand(concat!(
+            "macro m { ($a:expr) => { let y := $a; y } }\n",
+            "macro decl { ($n:ident) => { let $n := 1 } }\n",
+            "func f(y: i32) := {\n",
+            "    let z := 2 * m!(y) + 1\n",
+            "    m!(y)\n",
+            "    decl!(x); x\n",
+            "}",
+        ));
+
+        assert_eq!(errors, Vec::<String>::new());
+        for expected in [
+            "(let z (+ (* 2 (block (let y#0 y) y#0)) 1))",
+            "(let y#1 y) (stmt y#1) (let x 1) x",
+        ] {
+            assert!(module.contains(expected), "{expected} in {module}");
+        }
+    }
+
+    #[test]
+    fn test_unexpanded_calls() {
+        let (module, errors) = expand(concat!(
+            "macro one { () => { 1 } }\n",
+            "let a := two!()\n",
+            "let b := one!(1)",
+        ));
+
+        assert_eq!(
+            errors,
+            vec![
+                "no macro named `two`",
+                "no rule of `one!` matches this call"
+            ]
+        );
+        assert!(
+            module.ends_with("(let a (two!)) (let b (one! 1)))"),
+            "{module}"
+        );
+    }
+
+    #[test]
+    fn test_expansion_limit() {
+        let (_, errors) = expand(
+            "macro forever { () => { forever!() } }\nlet a := forever!()",
+        );
+
+        assert_eq!(
+            errors,
+            vec!["`forever!` expands too deeply, it may never stop"]
+        );
+    }
+
+    #[test]
+    fn test_expansion_limit_is_reported_once() {
+        let (_, errors) = parse(concat!(
+            "macro forever { () => { forever!() } }\n",
+            "let a := forever!()\n",
+            "let b := forever!()",
+        ));
+
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|err| err.data.trace().is_empty()));
+    }
+
+    #[test]
+    fn test_limits_total_expansions() {
+        let (_, errors) = expand(concat!(
+            "macro m { ($a:expr) => { m!($a) m!($a) } }\n",
+            "let a := m!(1)",
+        ));
+        // The first call of every expansion is expanded all the way down
+        // before the second one is
+        assert_eq!(
+            errors,
+            vec![
+                "`m!` expands too deeply, it may never stop",
+                "`m!` expands into too much code, it may never stop",
+            ]
+        );
+
+        let (_, errors) = expand(concat!(
+            "macro m { ($($a:tt)*) => { m!($($a)* $($a)*) } }\n",
+            "let a := m!(1)",
+        ));
+        assert_eq!(
+            errors,
+            vec!["`m!` expands into too much code, it may never stop"]
+        );
+    }
+
+    #[test]
+    fn test_traces_errors_in_expansions() {
+        let input = concat!(
+            "macro inner { () => { (1 +) } }\n",
+            "macro outer { () => { inner!() } }\n",
+            "let a := outer!()",
+        );
+        let (_, errors) = parse(input);
+
+        assert_eq!(errors.len(), 1);
+        let trace: Vec<_> = errors[0]
+            .data
+            .trace()
+            .iter()
+            .map(|name| {
+                (
+                    name.data.as_str(),
+                    &input[name.span.start.0..name.span.end.0],
+                )
+            })
+            .collect();
+        assert_eq!(trace, vec![("inner", "inner"), ("outer", "outer")]);
+    }
+
+    #[test]
+    fn test_traces_errors_at_the_end_of_an_expansion() {
+        let (_, errors) = parse(concat!(
+            "macro broken { () => { undefined_thing + } }\n",
+            "func f() := { broken!() }\n",
+            "let a := broken!()",
+        ));
+
+        assert_eq!(errors.len(), 2);
+        for err in errors {
+            assert_eq!(
+                err.data.to_string(),
+                "expected an expression, found `}`"
+            );
+            assert_eq!(err.data.trace().len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_reports_broken_rules() {
+        let (_, errors) = expand(concat!(
+            "macro a { ($x:foo) => { $x } }\n",
+            "macro b { ($x:expr) => { $y } }\n",
+            "macro c { ($($x:expr),*) => { $x } }\n",
+            "let c := c!(1, 2)",
+        ));
+
+        assert_eq!(
+            errors,
+            vec![
+                "`foo` is not a kind of fragment",
+                "`$y` is not bound by the matcher",
+                "`$x` is repeated, so it has to be used inside of `$( .. )`",
+            ]
+        );
+    }
+}
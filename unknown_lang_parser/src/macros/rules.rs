@@ -0,0 +1,567 @@
+//! Compiling the rules of a macro, matching calls against them, and filling
+//! in their bodies.
+
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+
+use crate::{
+    ast::MacroRule,
+    lexer::{
+        kind::TokenKind,
+        position::{Span, Spanned},
+        tokens::Token,
+    },
+    parser::state::{joined, Parser},
+};
+
+use super::{errors::MacroError, Tok};
+
+/// The kind of fragment that a name in a matcher binds, like the `expr` of
+/// `$a:expr`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Fragment {
+    Expr,
+    Ident,
+    Ty,
+    Pat,
+    Block,
+    /// A single literal, which may be a negative number
+    Literal,
+    /// A single token, or a group of tokens between delimiters
+    Tt,
+}
+
+impl Fragment {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "expr" => Self::Expr,
+            "ident" => Self::Ident,
+            "ty" => Self::Ty,
+            "pat" => Self::Pat,
+            "block" => Self::Block,
+            "literal" => Self::Literal,
+            "tt" => Self::Tt,
+            _ => return None,
+        })
+    }
+}
+
+/// Part of the matcher of a rule. Line ends in a matcher are left out, and
+/// skipped over in the call being matched.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Matcher {
+    /// A token which has to be in the call as it is
+    Token(Token),
+    /// `$name:kind`
+    Fragment { name: String, kind: Fragment },
+    /// `$( .. ) sep *`, where `+` instead of `*` has to match at least once
+    Repeat {
+        inner: Vec<Matcher>,
+        sep: Option<Token>,
+        at_least_one: bool,
+    },
+}
+
+/// Part of the body of a rule.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Transcriber {
+    Token(Tok),
+    /// `$name`, replaced with what the name was bound to
+    Var(Spanned<String>),
+    /// `$( .. ) sep *`, repeated once for each time that the names in it were
+    /// matched
+    Repeat {
+        inner: Vec<Transcriber>,
+        sep: Option<Tok>,
+        span: Span,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    pub matcher: Vec<Matcher>,
+    pub body: Vec<Transcriber>,
+}
+
+/// What a name in a matcher was bound to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Binding {
+    One {
+        tokens: Vec<Tok>,
+        kind: Fragment,
+    },
+    /// A name inside of `$( .. )`, with what it was bound to each time
+    Many(Vec<Binding>),
+}
+
+pub type Bindings = BTreeMap<String, Binding>;
+
+/// Compile a rule of a macro, checking that every name used in its body is
+/// bound by its matcher.
+pub fn compile(rule: &MacroRule) -> Result<Rule, Spanned<MacroError>> {
+    let mut names = vec![];
+    let matcher = compile_matchers(&rule.data.matcher, &mut names)?;
+
+    let body = &rule.data.body;
+    let flags = joined(body);
+    let body: Vec<_> = body
+        .iter()
+        .zip(flags)
+        .map(|(tok, joined)| Tok {
+            token: tok.clone(),
+            joined,
+            origin: None,
+        })
+        .collect();
+
+    let body = compile_body(&body, &names)?;
+    Ok(Rule { matcher, body })
+}
+
+/// Find the index of the token closing the group opened by the first of
+/// `kinds`.
+pub fn group_end(kinds: impl IntoIterator<Item = TokenKind>) -> Option<usize> {
+    let mut depth = 0;
+
+    for (idx, kind) in kinds.into_iter().enumerate() {
+        if kind.closing_delimiter().is_some() {
+            depth += 1;
+        } else if kind.is_closing_delimiter() {
+            depth -= 1;
+            if depth == 0 {
+                return Some(idx);
+            }
+        }
+    }
+
+    None
+}
+
+fn compile_matchers(
+    tokens: &[Spanned<Token>],
+    names: &mut Vec<String>,
+) -> Result<Vec<Matcher>, Spanned<MacroError>> {
+    let mut matchers = vec![];
+    let mut idx = 0;
+
+    while let Some(tok) = tokens.get(idx) {
+        idx += 1;
+
+        match &tok.data {
+            Token::VirtualSemi => {}
+            Token::Dollar => {
+                let kind = |idx: usize| tokens.get(idx).map(|tok| &tok.data);
+                match (kind(idx), kind(idx + 1), kind(idx + 2)) {
+                    (
+                        Some(Token::Ident(name)),
+                        Some(Token::Colon),
+                        Some(Token::Ident(fragment)),
+                    ) => {
+                        let span = tokens[idx + 2].span;
+                        let fragment = Fragment::from_name(fragment)
+                            .ok_or_else(|| {
+                                Spanned::new(
+                                    MacroError::UnknownFragment(
+                                        fragment.clone(),
+                                    ),
+                                    span,
+                                )
+                            })?;
+
+                        if names.contains(name) {
+                            let err = MacroError::DuplicateVar(name.clone());
+                            return Err(Spanned::new(err, tokens[idx].span));
+                        }
+                        names.push(name.clone());
+
+                        matchers.push(Matcher::Fragment {
+                            name: name.clone(),
+                            kind: fragment,
+                        });
+                        idx += 3;
+                    }
+                    (Some(Token::LPar), ..) => {
+                        let end = group_end(
+                            tokens[idx..].iter().map(|tok| tok.data.kind()),
+                        )
+                        .map_or(tokens.len(), |end| idx + end);
+                        let inner =
+                            compile_matchers(&tokens[idx + 1..end], names)?;
+
+                        idx = end + 1;
+                        let (sep, at_least_one) =
+                            repeat_op(tokens, &mut idx, tok.span)?;
+                        matchers.push(Matcher::Repeat {
+                            inner,
+                            sep: sep.map(|sep| sep.data),
+                            at_least_one,
+                        });
+                    }
+                    _ => {
+                        let err = MacroError::ExpectedMetavar;
+                        return Err(Spanned::new(err, tok.span));
+                    }
+                }
+            }
+            tok => matchers.push(Matcher::Token(tok.clone())),
+        }
+    }
+
+    Ok(matchers)
+}
+
+fn compile_body(
+    tokens: &[Tok],
+    names: &[String],
+) -> Result<Vec<Transcriber>, Spanned<MacroError>> {
+    let mut transcribers = vec![];
+    let mut idx = 0;
+
+    while let Some(tok) = tokens.get(idx) {
+        idx += 1;
+
+        if tok.token.data != Token::Dollar {
+            transcribers.push(Transcriber::Token(tok.clone()));
+            continue;
+        }
+
+        match tokens.get(idx).map(|tok| &tok.token) {
+            Some(Spanned {
+                data: Token::Ident(name),
+                span,
+            }) => {
+                if !names.contains(name) {
+                    let err = MacroError::UnboundVar(name.clone());
+                    return Err(Spanned::new(err, *span));
+                }
+
+                let span = Span::new(tok.token.span.start.0, span.end.0);
+                transcribers
+                    .push(Transcriber::Var(Spanned::new(name.clone(), span)));
+                idx += 1;
+            }
+            Some(Spanned {
+                data: Token::LPar, ..
+            }) => {
+                let end = group_end(
+                    tokens[idx..].iter().map(|tok| tok.token.data.kind()),
+                )
+                .map_or(tokens.len(), |end| idx + end);
+                let inner = compile_body(&tokens[idx + 1..end], names)?;
+
+                idx = end + 1;
+                let plain: Vec<_> =
+                    tokens.iter().map(|tok| tok.token.clone()).collect();
+                let (sep, _) = repeat_op(&plain, &mut idx, tok.token.span)?;
+                let span = tok.token.span.union(plain[idx - 1].span);
+
+                transcribers.push(Transcriber::Repeat {
+                    inner,
+                    sep: sep.map(|sep| Tok {
+                        token: sep,
+                        joined: false,
+                        origin: None,
+                    }),
+                    span,
+                });
+            }
+            _ => {
+                let err = MacroError::ExpectedMetavar;
+                return Err(Spanned::new(err, tok.token.span));
+            }
+        }
+    }
+
+    Ok(transcribers)
+}
+
+/// Parse the optional separator and the `*` or `+` after `$( .. )`, giving
+/// back the separator and whether it was `+`.
+fn repeat_op(
+    tokens: &[Spanned<Token>],
+    idx: &mut usize,
+    dollar: Span,
+) -> Result<(Option<Spanned<Token>>, bool), Spanned<MacroError>> {
+    let op = |tok: Option<&Spanned<Token>>| match tok.map(|tok| &tok.data) {
+        Some(Token::Star) => Some(false),
+        Some(Token::Plus) => Some(true),
+        _ => None,
+    };
+
+    if let Some(at_least_one) = op(tokens.get(*idx)) {
+        *idx += 1;
+        return Ok((None, at_least_one));
+    }
+
+    match (tokens.get(*idx), op(tokens.get(*idx + 1))) {
+        (Some(sep), Some(at_least_one)) => {
+            *idx += 2;
+            Ok((Some(sep.clone()), at_least_one))
+        }
+        (sep, _) => {
+            let span = sep.map_or(dollar, |sep| sep.span);
+            Err(Spanned::new(MacroError::ExpectedRepeat, span))
+        }
+    }
+}
+
+/// Match all of `input` against a matcher, giving back what each of its
+/// names was bound to.
+pub fn match_all(matchers: &[Matcher], input: &[Tok]) -> Option<Bindings> {
+    let mut bindings = BTreeMap::new();
+    let end = match_seq(matchers, input, 0, &mut bindings)?;
+
+    (skip_line_ends(input, end) == input.len()).then_some(bindings)
+}
+
+/// Match `matchers` against the start of `input[pos..]`, giving back where
+/// the match ended. Repetitions match as many times as they can, and are
+/// never backtracked into.
+fn match_seq(
+    matchers: &[Matcher],
+    input: &[Tok],
+    mut pos: usize,
+    bindings: &mut Bindings,
+) -> Option<usize> {
+    for matcher in matchers {
+        pos = skip_line_ends(input, pos);
+
+        match matcher {
+            Matcher::Token(tok) => {
+                if input.get(pos)?.token.data != *tok {
+                    return None;
+                }
+                pos += 1;
+            }
+            Matcher::Fragment { name, kind } => {
+                let len = fragment_len(*kind, &input[pos..])?;
+                let tokens = input[pos..pos + len].to_vec();
+
+                bindings.insert(
+                    name.clone(),
+                    Binding::One {
+                        tokens,
+                        kind: *kind,
+                    },
+                );
+                pos += len;
+            }
+            Matcher::Repeat {
+                inner,
+                sep,
+                at_least_one,
+            } => {
+                let mut matches = vec![];
+
+                loop {
+                    let mut start = pos;
+                    if let (Some(sep), false) = (sep, matches.is_empty()) {
+                        start = skip_line_ends(input, pos);
+                        match input.get(start) {
+                            Some(tok) if tok.token.data == *sep => start += 1,
+                            _ => break,
+                        }
+                    }
+
+                    let mut found = BTreeMap::new();
+                    match match_seq(inner, input, start, &mut found) {
+                        // Anything that matches without moving forward would
+                        // match forever
+                        Some(end) if end > pos => {
+                            matches.push(found);
+                            pos = end;
+                        }
+                        _ => break,
+                    }
+                }
+
+                if *at_least_one && matches.is_empty() {
+                    return None;
+                }
+
+                let mut names = vec![];
+                matcher_names(inner, &mut names);
+                for name in names {
+                    let each = matches
+                        .iter_mut()
+                        .filter_map(|found| found.remove(&name))
+                        .collect();
+                    bindings.insert(name, Binding::Many(each));
+                }
+            }
+        }
+    }
+
+    Some(pos)
+}
+
+fn skip_line_ends(input: &[Tok], mut pos: usize) -> usize {
+    while input.get(pos).map(|tok| &tok.token.data) == Some(&Token::VirtualSemi)
+    {
+        pos += 1;
+    }
+
+    pos
+}
+
+/// Get how many tokens from the start of `input` make up a fragment of the
+/// given kind, or `None` if it doesn't start with one.
+fn fragment_len(kind: Fragment, input: &[Tok]) -> Option<usize> {
+    let first = input.first()?.token.data.kind();
+
+    match kind {
+        Fragment::Ident => (first == TokenKind::Ident).then_some(1),
+        Fragment::Literal if first.is_literal() => Some(1),
+        Fragment::Literal => {
+            let second = input.get(1)?.token.data.kind();
+            (first == TokenKind::Minus && second == TokenKind::Number)
+                .then_some(2)
+        }
+        Fragment::Tt if first.closing_delimiter().is_some() => {
+            group_end(input.iter().map(|tok| tok.token.data.kind()))
+                .map(|end| end + 1)
+        }
+        Fragment::Tt => (!first.is_closing_delimiter()).then_some(1),
+        Fragment::Expr => parsed_len(input, Parser::parse_expr),
+        Fragment::Ty => parsed_len(input, Parser::parse_type),
+        Fragment::Pat => parsed_len(input, Parser::parse_pattern),
+        Fragment::Block => parsed_len(input, Parser::parse_block),
+    }
+}
+
+/// Get how many tokens from the start of `input` `parse` takes without
+/// any errors.
+fn parsed_len<T, E>(
+    input: &[Tok],
+    parse: impl FnOnce(&mut Parser) -> Result<T, E>,
+) -> Option<usize> {
+    let end = input.last().map_or(0, |tok| tok.token.span.end.0);
+    let mut tokens: Vec<_> =
+        input.iter().map(|tok| tok.token.clone()).collect();
+    tokens.push(Spanned::new_span(Token::Eof, end, end));
+    let joined = input.iter().map(|tok| tok.joined).chain([false]).collect();
+
+    let mut parser = Parser::from_tokens(tokens.clone(), joined);
+    parse(&mut parser).ok()?;
+
+    // A token split in two, like the `>` taken from `>=`, would be lost
+    let len = parser.position();
+    let whole = parser.peek().data == tokens[len].data;
+    (parser.errors.is_empty() && len > 0 && whole).then_some(len)
+}
+
+fn matcher_names(matchers: &[Matcher], names: &mut Vec<String>) {
+    for matcher in matchers {
+        match matcher {
+            Matcher::Token(_) => {}
+            Matcher::Fragment { name, .. } => names.push(name.clone()),
+            Matcher::Repeat { inner, .. } => matcher_names(inner, names),
+        }
+    }
+}
+
+fn transcriber_names(transcribers: &[Transcriber], names: &mut Vec<String>) {
+    for transcriber in transcribers {
+        match transcriber {
+            Transcriber::Token(_) => {}
+            Transcriber::Var(name) => names.push(name.data.clone()),
+            Transcriber::Repeat { inner, .. } => {
+                transcriber_names(inner, names)
+            }
+        }
+    }
+}
+
+/// Fill in the body of a rule with what its names were bound to. Each token
+/// is given back along with whether it came from the body, rather than from
+/// the call. An expression made of more than one token is put between
+/// parentheses, so that `$a * 2` with `$a` bound to `1 + 1` gives `(1 + 1) *
+/// 2`.
+pub fn transcribe(
+    body: &[Transcriber],
+    bindings: &Bindings,
+    out: &mut Vec<(Tok, bool)>,
+) -> Result<(), Spanned<MacroError>> {
+    for transcriber in body {
+        match transcriber {
+            Transcriber::Token(tok) => out.push((tok.clone(), true)),
+            Transcriber::Var(name) => match bindings.get(&name.data) {
+                Some(Binding::One { tokens, kind }) => {
+                    let wrap = *kind == Fragment::Expr && tokens.len() > 1;
+                    let paren = |token, span| Tok {
+                        token: Spanned::new(token, span),
+                        joined: false,
+                        origin: tokens[0].origin,
+                    };
+
+                    let span = tokens[0]
+                        .token
+                        .span
+                        .union(tokens[tokens.len() - 1].token.span);
+                    if wrap {
+                        out.push((paren(Token::LPar, span), false));
+                    }
+                    out.extend(tokens.iter().map(|tok| (tok.clone(), false)));
+                    if wrap {
+                        out.push((paren(Token::RPar, span), false));
+                    }
+                }
+                Some(Binding::Many(_)) => {
+                    let err = MacroError::Repeated(name.data.clone());
+                    return Err(Spanned::new(err, name.span));
+                }
+                None => {
+                    let err = MacroError::UnboundVar(name.data.clone());
+                    return Err(Spanned::new(err, name.span));
+                }
+            },
+            Transcriber::Repeat { inner, sep, span } => {
+                let mut names = vec![];
+                transcriber_names(inner, &mut names);
+
+                let counts: Vec<_> = names
+                    .iter()
+                    .filter_map(|name| match bindings.get(name) {
+                        Some(Binding::Many(each)) => Some(each.len()),
+                        _ => None,
+                    })
+                    .collect();
+                let count = match counts.first() {
+                    Some(&count) if counts.iter().all(|&c| c == count) => count,
+                    _ => {
+                        let err = MacroError::RepeatMismatch;
+                        return Err(Spanned::new(err, *span));
+                    }
+                };
+
+                // Everything repeated is left out, so that it isn't cloned
+                // each time around
+                let mut rest = bindings.clone();
+                let repeated: Vec<_> = names
+                    .iter()
+                    .filter_map(|name| match rest.remove_entry(name)? {
+                        (name, Binding::Many(all)) => Some((name, all)),
+                        (name, one) => {
+                            rest.insert(name, one);
+                            None
+                        }
+                    })
+                    .collect();
+
+                for idx in 0..count {
+                    if let (Some(sep), true) = (sep, idx > 0) {
+                        out.push((sep.clone(), true));
+                    }
+
+                    let mut each = rest.clone();
+                    for (name, all) in &repeated {
+                        each.insert(name.clone(), all[idx].clone());
+                    }
+
+                    transcribe(inner, &each, out)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
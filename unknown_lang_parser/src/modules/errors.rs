@@ -0,0 +1,51 @@
+use alloc::{string::String, vec::Vec};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ModuleError {
+    /// An import like `import shapes::circle`, where none of the `candidates`
+    /// files that the module could be in exist
+    NotFound {
+        path: String,
+        candidates: Vec<String>,
+    },
+    /// An import of a module which is still being loaded, because it imports
+    /// the module with the import. The `chain` goes from the module being
+    /// imported, through each import, and back to itself.
+    Cycle(Vec<String>),
+    /// Two imports in one module which give the same name
+    DuplicateImport(String),
+}
+
+impl core::fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotFound { path, candidates } => {
+                write!(f, "no module named `{path}`, looked for ")?;
+                for (idx, candidate) in candidates.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, " or ")?;
+                    }
+                    write!(f, "`{candidate}`")?;
+                }
+                Ok(())
+            }
+            Self::Cycle(chain) => {
+                write!(f, "modules import each other in a cycle: ")?;
+                for (idx, file) in chain.iter().enumerate() {
+                    match idx {
+                        0 => write!(f, "`{file}`")?,
+                        1 => write!(f, " imports `{file}`")?,
+                        _ => write!(f, ", which imports `{file}`")?,
+                    }
+                }
+                Ok(())
+            }
+            Self::DuplicateImport(name) => {
+                write!(f, "`{name}` is imported more than once")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ModuleError {}
@@ -0,0 +1,443 @@
+//! Loading a program which is split across files, by following the imports
+//! from its entry file.
+//!
+//! `import shapes` looks for `shapes.ukl` next to the file with the import,
+//! or for `shapes/mod.ukl` when the module is a directory. Every segment
+//! before the last is a directory, so `import shapes::circle` looks for
+//! `shapes/circle.ukl` or `shapes/circle/mod.ukl`. The files inside of a
+//! directory import each other by name, since they are next to each other.
+//!
+//! Reading the files is left to a [`Loader`], so that the modules can come
+//! from anywhere. Each file is only loaded once, however many modules import
+//! it. Modules which import each other, even through other modules, are
+//! reported as a cycle.
+
+pub mod errors;
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::{
+    ast::{path_to_string, ItemKind, Module, Path},
+    lexer::{
+        position::{Span, Spanned},
+        tokens::Token,
+    },
+    parser::{errors::ParseError, parse, parse_lexed},
+};
+
+use self::errors::ModuleError;
+
+/// A module of a [`ModuleGraph`], by its position in the graph.
+pub type ModuleId = usize;
+
+/// Where the files of a program are read from.
+pub trait Loader {
+    type Error;
+
+    /// Read the file at `path`, which is relative to the directory of the
+    /// entry file and separated by `/`. Gives back `None` if there is no file
+    /// at `path`.
+    fn read(&mut self, path: &str) -> Result<Option<String>, Self::Error>;
+}
+
+/// A single file of a program, along with the modules it imports.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadedModule {
+    /// Where the file was read from, relative to the directory of the entry
+    /// file
+    pub path: String,
+    pub source: String,
+    pub ast: Module,
+    pub parse_errors: Vec<Spanned<ParseError>>,
+    /// The module that each import refers to, by the name it is given
+    pub imports: BTreeMap<String, ModuleId>,
+    /// Every import which couldn't be loaded
+    pub errors: Vec<Spanned<ModuleError>>,
+}
+
+/// Every module of a program, starting from its entry file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModuleGraph {
+    /// The modules in the order they were loaded, with the entry file first
+    pub modules: Vec<LoadedModule>,
+}
+
+impl ModuleGraph {
+    pub fn entry(&self) -> &LoadedModule {
+        &self.modules[0]
+    }
+
+    /// Checks if any module failed to parse, or has an import which couldn't
+    /// be loaded.
+    pub fn has_errors(&self) -> bool {
+        self.modules.iter().any(|module| {
+            !module.parse_errors.is_empty() || !module.errors.is_empty()
+        })
+    }
+}
+
+/// Load the entry file at `path` with the given `source`, and every module
+/// that it imports with `loader`. Problems with the modules themselves are
+/// kept in the graph, and only a failure to read a file stops the loading.
+pub fn load<L: Loader>(
+    path: &str,
+    source: String,
+    loader: &mut L,
+) -> Result<ModuleGraph, L::Error> {
+    let mut graph = GraphLoader::new(loader);
+    graph.add(path.to_string(), source)?;
+
+    Ok(ModuleGraph {
+        modules: graph.modules,
+    })
+}
+
+/// Load a program like [`load`], from an entry file whose `source` was
+/// already lexed into `tokens` by [`lex_all`](crate::lexer::lex_all).
+pub fn load_lexed<L: Loader>(
+    path: &str,
+    source: String,
+    tokens: Vec<Spanned<Token>>,
+    loader: &mut L,
+) -> Result<ModuleGraph, L::Error> {
+    let mut graph = GraphLoader::new(loader);
+    let parsed = parse_lexed(tokens, source.len());
+    graph.insert(path.to_string(), source, parsed)?;
+
+    Ok(ModuleGraph {
+        modules: graph.modules,
+    })
+}
+
+struct GraphLoader<'a, L> {
+    loader: &'a mut L,
+    modules: Vec<LoadedModule>,
+    by_path: BTreeMap<String, ModuleId>,
+    /// The modules which are still loading their imports, each imported by
+    /// the one before it
+    stack: Vec<ModuleId>,
+}
+
+impl<'a, L: Loader> GraphLoader<'a, L> {
+    fn new(loader: &'a mut L) -> Self {
+        Self {
+            loader,
+            modules: vec![],
+            by_path: BTreeMap::new(),
+            stack: vec![],
+        }
+    }
+
+    /// Parse a module, and load everything it imports.
+    fn add(
+        &mut self,
+        path: String,
+        source: String,
+    ) -> Result<ModuleId, L::Error> {
+        let parsed = parse(&source);
+        self.insert(path, source, parsed)
+    }
+
+    /// Add a module which was already parsed, and load everything it
+    /// imports.
+    fn insert(
+        &mut self,
+        path: String,
+        source: String,
+        (ast, parse_errors): (Module, Vec<Spanned<ParseError>>),
+    ) -> Result<ModuleId, L::Error> {
+        let imports: Vec<Path> = ast
+            .items
+            .iter()
+            .filter_map(|item| match &item.data {
+                ItemKind::Import(import) => Some(import.path.clone()),
+                _ => None,
+            })
+            .collect();
+
+        let id = self.modules.len();
+        self.by_path.insert(path.clone(), id);
+        self.modules.push(LoadedModule {
+            path,
+            source,
+            ast,
+            parse_errors,
+            imports: BTreeMap::new(),
+            errors: vec![],
+        });
+
+        self.stack.push(id);
+        for import in imports {
+            self.import(id, import)?;
+        }
+        self.stack.pop();
+
+        Ok(id)
+    }
+
+    /// Load the module that `from` imports with `path`, and give it a name in
+    /// `from`.
+    fn import(&mut self, from: ModuleId, path: Path) -> Result<(), L::Error> {
+        let Some(name) = path.data.last() else {
+            return Ok(());
+        };
+        if self.modules[from].imports.contains_key(&name.data) {
+            let err = ModuleError::DuplicateImport(name.data.clone());
+            self.error(from, err, path.span);
+            return Ok(());
+        }
+
+        let dir = parent_dir(&self.modules[from].path);
+        let segments: Vec<&str> = path
+            .data
+            .iter()
+            .map(|segment| segment.data.as_str())
+            .collect();
+        let relative = join(dir, &segments.join("/"));
+        let candidates =
+            vec![format!("{relative}.ukl"), join(&relative, "mod.ukl")];
+
+        let mut found = None;
+        for candidate in &candidates {
+            if let Some(&id) = self.by_path.get(candidate) {
+                found = Some(id);
+                break;
+            }
+
+            if let Some(source) = self.loader.read(candidate)? {
+                found = Some(self.add(candidate.clone(), source)?);
+                break;
+            }
+        }
+
+        let Some(id) = found else {
+            let err = ModuleError::NotFound {
+                path: path_to_string(&path.data),
+                candidates,
+            };
+            self.error(from, err, path.span);
+            return Ok(());
+        };
+
+        // A module which hasn't finished loading imports the module that is
+        // importing it, either directly or through the modules in between
+        if let Some(start) = self.stack.iter().position(|&other| other == id) {
+            let mut chain: Vec<String> = self.stack[start..]
+                .iter()
+                .map(|&other| self.modules[other].path.clone())
+                .collect();
+            chain.push(self.modules[id].path.clone());
+
+            self.error(from, ModuleError::Cycle(chain), path.span);
+            return Ok(());
+        }
+
+        self.modules[from].imports.insert(name.data.clone(), id);
+        Ok(())
+    }
+
+    fn error(&mut self, module: ModuleId, err: ModuleError, span: Span) {
+        self.modules[module].errors.push(Spanned::new(err, span));
+    }
+}
+
+/// Get the directory that the file at `path` is in, which is empty for a
+/// file next to the entry file.
+fn parent_dir(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(idx) => &path[..idx],
+        None => "",
+    }
+}
+
+fn join(dir: &str, path: &str) -> String {
+    match dir.is_empty() {
+        true => path.to_string(),
+        false => format!("{dir}/{path}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Files kept in memory, by their path.
+    struct Files(BTreeMap<&'static str, &'static str>);
+
+    impl Loader for Files {
+        type Error = ();
+
+        fn read(&mut self, path: &str) -> Result<Option<String>, ()> {
+            Ok(self.0.get(path).map(|source| source.to_string()))
+        }
+    }
+
+    /// Load a program from `main.ukl`, with the other `files` next to it.
+    fn load_files(
+        main: &str,
+        files: &[(&'static str, &'static str)],
+    ) -> ModuleGraph {
+        let mut files = Files(files.iter().copied().collect());
+        load("main.ukl", main.to_string(), &mut files).unwrap()
+    }
+
+    fn paths(graph: &ModuleGraph) -> Vec<&str> {
+        graph
+            .modules
+            .iter()
+            .map(|module| module.path.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_loads_imports() {
+        let graph = load_files(
+            "import shapes\nimport lines::straight",
+            &[
+                ("shapes/mod.ukl", "import circle"),
+                ("shapes/circle.ukl", ""),
+                ("lines/straight.ukl", ""),
+            ],
+        );
+        assert!(!graph.has_errors());
+        assert_eq!(
+            paths(&graph),
+            vec![
+                "main.ukl",
+                "shapes/mod.ukl",
+                "shapes/circle.ukl",
+                "lines/straight.ukl",
+            ]
+        );
+
+        // Each import is named by the last segment of its path
+        let imports: Vec<_> = graph
+            .entry()
+            .imports
+            .iter()
+            .map(|(name, &id)| (name.as_str(), id))
+            .collect();
+        assert_eq!(imports, vec![("shapes", 1), ("straight", 3)]);
+        assert_eq!(graph.modules[1].imports["circle"], 2);
+    }
+
+    #[test]
+    fn test_loads_from_lexed_tokens() {
+        let main = "// shapes\nimport shapes\nfunc f() := { m!(1) }";
+        let files = [("shapes.ukl", "pub func area() := 1")];
+        let tokens = crate::lexer::lex_all(main).unwrap();
+
+        let mut loader = Files(files.iter().copied().collect());
+        let graph =
+            load_lexed("main.ukl", main.to_string(), tokens, &mut loader)
+                .unwrap();
+
+        assert_eq!(graph, load_files(main, &files));
+    }
+
+    #[test]
+    fn test_files_are_loaded_once() {
+        let graph = load_files(
+            "import a\nimport b",
+            &[("a.ukl", "import c"), ("b.ukl", "import c"), ("c.ukl", "")],
+        );
+        assert!(!graph.has_errors());
+        assert_eq!(paths(&graph), vec!["main.ukl", "a.ukl", "c.ukl", "b.ukl"]);
+        assert_eq!(graph.modules[3].imports["c"], 2);
+    }
+
+    #[test]
+    fn test_missing_modules() {
+        let graph = load_files("import shapes::circle", &[]);
+
+        assert_eq!(
+            graph.entry().errors,
+            vec![Spanned::new(
+                ModuleError::NotFound {
+                    path: "shapes::circle".into(),
+                    candidates: vec![
+                        "shapes/circle.ukl".into(),
+                        "shapes/circle/mod.ukl".into(),
+                    ],
+                },
+                Span::new(7, 21)
+            )]
+        );
+        assert!(graph.entry().imports.is_empty());
+    }
+
+    #[test]
+    fn test_import_cycles() {
+        let graph = load_files(
+            "import a",
+            &[("a.ukl", "import b"), ("b.ukl", "\nimport main")],
+        );
+
+        let b = &graph.modules[2];
+        assert_eq!(
+            b.errors,
+            vec![Spanned::new(
+                ModuleError::Cycle(vec![
+                    "main.ukl".into(),
+                    "a.ukl".into(),
+                    "b.ukl".into(),
+                    "main.ukl".into(),
+                ]),
+                Span::new(8, 12)
+            )]
+        );
+        assert_eq!(
+            b.errors[0].data.to_string(),
+            "modules import each other in a cycle: `main.ukl` imports \
+             `a.ukl`, which imports `b.ukl`, which imports `main.ukl`"
+        );
+
+        // Only the import which closes the cycle is an error
+        assert!(graph.modules[0].errors.is_empty());
+        assert!(graph.modules[1].errors.is_empty());
+    }
+
+    #[test]
+    fn test_importing_itself() {
+        let graph = load_files("", &[]);
+        assert!(!graph.has_errors());
+
+        let graph = load_files("import main", &[]);
+        assert_eq!(
+            graph.entry().errors[0].data,
+            ModuleError::Cycle(vec!["main.ukl".into(), "main.ukl".into()])
+        );
+    }
+
+    #[test]
+    fn test_duplicate_imports() {
+        let graph = load_files(
+            "import circle\nimport shapes::circle",
+            &[("circle.ukl", ""), ("shapes/circle.ukl", "")],
+        );
+
+        assert_eq!(
+            graph.entry().errors,
+            vec![Spanned::new(
+                ModuleError::DuplicateImport("circle".into()),
+                Span::new(21, 35)
+            )]
+        );
+        assert_eq!(paths(&graph), vec!["main.ukl", "circle.ukl"]);
+    }
+
+    #[test]
+    fn test_parse_errors_are_kept() {
+        let graph = load_files("import a", &[("a.ukl", "func (")]);
+
+        assert!(graph.has_errors());
+        assert!(graph.entry().parse_errors.is_empty());
+        assert_eq!(graph.modules[1].parse_errors.len(), 1);
+    }
+}
@@ -0,0 +1,187 @@
+//! Handles parsing attributes, like `#[test]` before an item or statement,
+//! or `#![allow(unknown_attributes)]` at the start of a file or block. Which
+//! attributes exist, and what they can be put on, is checked later by the
+//! [`attributes`](crate::attributes) module.
+
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    ast::{Attribute, AttributeKind},
+    lexer::{kind::TokenKind, position::Spanned, tokens::Token},
+};
+
+use super::{errors::ParseError, state::Parser, PResult};
+
+impl Parser {
+    /// Parse the outer attributes before an item or statement. An inner
+    /// attribute can't be here, so it is reported and left out.
+    pub fn parse_outer_attrs(&mut self) -> PResult<Vec<Attribute>> {
+        let mut attrs = vec![];
+
+        while self.at(TokenKind::Hash) {
+            let attr = self.parse_attribute()?;
+            if attr.data.inner {
+                self.report(Spanned::new(
+                    ParseError::InnerAttribute,
+                    attr.span,
+                ));
+            } else {
+                attrs.push(attr);
+            }
+
+            // An attribute is usually on a line of its own
+            self.skip_terminators();
+        }
+
+        Ok(attrs)
+    }
+
+    /// Parse the inner attributes at the start of a file or block. Any which
+    /// fail to parse are reported and skipped.
+    pub fn parse_inner_attrs(&mut self) -> Vec<Attribute> {
+        let mut attrs = vec![];
+
+        loop {
+            self.skip_terminators();
+            if !self.at(TokenKind::Hash) || self.nth_kind(1) != TokenKind::Bang
+            {
+                return attrs;
+            }
+
+            match self.parse_attribute() {
+                Ok(attr) => attrs.push(attr),
+                Err(err) => {
+                    self.report(err);
+                    self.synchronize();
+                }
+            }
+        }
+    }
+
+    /// Parse a single attribute, like `#[allow(unused)]` or `#![test]`. The
+    /// tokens after its name can be anything, as long as every group in them
+    /// is closed.
+    fn parse_attribute(&mut self) -> PResult<Attribute> {
+        let start = self.expect(TokenKind::Hash)?.span;
+        let inner = self.eat(TokenKind::Bang).is_some();
+
+        // The group is checked first, so that a missing `[` or name is
+        // reported where it should be
+        if !self.at(TokenKind::LBracket) || self.nth_kind(1) != TokenKind::Ident
+        {
+            self.expect(TokenKind::LBracket)?;
+            self.expect_ident()?;
+        }
+
+        let (mut args, _) = self.parse_token_group()?;
+        let name = args.remove(0);
+        let name = match name.data {
+            Token::Ident(text) => Spanned::new(text, name.span),
+            tok => unreachable!("{tok} is not an identifier"),
+        };
+
+        Ok(Spanned::new(
+            AttributeKind { inner, name, args },
+            self.span_from(start),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::{
+        ast::{print::sexpr, ItemKind, StmtKind},
+        lexer::position::Span,
+        parser::parse,
+    };
+
+    #[test]
+    fn test_outer_attributes() {
+        let (module, errors) = parse(concat!(
+            "#[test]\n",
+            "#[allow(unused, dead)] pub func f() := {\n",
+            "    #[deprecated = \"no\"]\n",
+            "    let a := 1\n",
+            "    a\n",
+            "}",
+        ));
+        assert_eq!(errors, vec![]);
+
+        let item = &module.items[0];
+        assert!(matches!(item.data, ItemKind::Func(_)));
+        // The span of an item leaves out its attributes
+        assert_eq!(item.span, Span::new(31, 96));
+
+        let attrs = &item.attrs;
+        assert_eq!(attrs.len(), 2);
+        assert_eq!(attrs[0].span, Span::new(0, 7));
+        assert_eq!(attrs[1].data.name.data, "allow");
+        assert!(!attrs[1].data.inner);
+        assert_eq!(attrs[1].data.args.len(), 5);
+
+        let ItemKind::Func(func) = &item.data else {
+            unreachable!()
+        };
+        let stmt = &func.body.data.stmts[0];
+        assert!(matches!(stmt.data, StmtKind::Let(_)));
+        assert_eq!(stmt.attrs[0].data.name.data, "deprecated");
+    }
+
+    #[test]
+    fn test_inner_attributes() {
+        let (module, errors) = parse(concat!(
+            "// The file\n",
+            "#![allow(unknown_attributes)]\n",
+            "#![a] func f() := { #![b]\n #![c] }",
+        ));
+
+        assert_eq!(errors, vec![]);
+        assert_eq!(
+            sexpr(&module),
+            "(module #![allow(unknown_attributes)] #![a] \
+             (func f (params) (block #![b] #![c])))"
+        );
+    }
+
+    #[test]
+    fn test_misplaced_attributes() {
+        let (module, errors) = parse(concat!(
+            "func f() := {}\n",
+            "#![a] func g() := {\n",
+            "    let a := 1\n",
+            "    #[b] a\n",
+            "}\n",
+            "#[c]",
+        ));
+        let messages: Vec<_> =
+            errors.iter().map(|err| err.data.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "an inner attribute can only be at the start of a file or \
+                 block",
+                "attributes can't be put on the value at the end of a block",
+                "expected an item, found end of file",
+            ]
+        );
+        assert_eq!(errors[0].span, Span::new(15, 20));
+        assert_eq!(errors[1].span, Span::new(54, 58));
+        // The misplaced inner attribute is left out, but `g` still parses
+        assert!(module.items[1].attrs.is_empty());
+        assert!(matches!(module.items[1].data, ItemKind::Func(_)));
+    }
+
+    #[test]
+    fn test_attributes_need_a_name() {
+        for input in ["#[] func f() := {}", "#[1] func f() := {}", "#(a) = 1"] {
+            let (module, errors) = parse(input);
+
+            assert_eq!(errors.len(), 1, "{input}");
+            assert!(matches!(module.items[0].data, ItemKind::Error), "{input}");
+        }
+    }
+}
@@ -0,0 +1,498 @@
+//! Handles parsing control flow, like `if`, `match`, loops, `break` and
+//! `continue`.
+
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use crate::{
+    ast::{Arm, ArmKind, ExprKind, Ident, StmtKind},
+    lexer::{kind::TokenKind, position::Spanned, tokens::Token},
+};
+
+use super::{state::Parser, PResult};
+
+impl Parser {
+    /// Parse an `if`, along with any `else if` and `else` branches after it.
+    pub fn parse_if(&mut self) -> PResult<ExprKind> {
+        self.expect(TokenKind::If)?;
+        let cond = Box::new(self.parse_expr_before_block()?);
+        let then = self.parse_block()?;
+
+        if self.eat(TokenKind::Else).is_none() {
+            return Ok(ExprKind::If {
+                cond,
+                then,
+                else_branch: None,
+            });
+        }
+
+        let else_branch = if self.at(TokenKind::If) {
+            let start = self.peek().span;
+            let kind = self.parse_if()?;
+
+            Spanned::new(kind, self.span_from(start))
+        } else {
+            let block = self.parse_block()?;
+            let span = block.span;
+
+            Spanned::new(ExprKind::Block(block), span)
+        };
+
+        Ok(ExprKind::If {
+            cond,
+            then,
+            else_branch: Some(Box::new(else_branch)),
+        })
+    }
+
+    /// Parse a `match` and its arms. Arms are separated by commas or by the
+    /// end of a line, and the comma can also be left off after an arm ending
+    /// in a block. Arms which fail to parse are reported and left out.
+    pub fn parse_match(&mut self) -> PResult<ExprKind> {
+        self.expect(TokenKind::Match)?;
+        let scrutinee = Box::new(self.parse_expr_before_block()?);
+        self.expect(TokenKind::LBrace)?;
+
+        let arms = self.with_struct_literals(true, Self::parse_arms);
+
+        if let Err(err) = self.expect(TokenKind::RBrace) {
+            self.report(err);
+        }
+
+        Ok(ExprKind::Match { scrutinee, arms })
+    }
+
+    /// Parse the arms of a `match`, up to the closing `}`.
+    fn parse_arms(&mut self) -> Vec<Arm> {
+        let mut arms = vec![];
+
+        loop {
+            self.skip_terminators();
+            if self.at(TokenKind::RBrace)
+                || self.at(TokenKind::Func)
+                || self.at(TokenKind::Eof)
+            {
+                break;
+            }
+
+            let result = self.parse_arm().and_then(|arm| {
+                let ends_in_block = matches!(
+                    arm.data.body.data,
+                    ExprKind::Block(_)
+                        | ExprKind::If { .. }
+                        | ExprKind::While { .. }
+                        | ExprKind::Loop { .. }
+                        | ExprKind::For { .. }
+                        | ExprKind::Match { .. }
+                );
+                arms.push(arm);
+
+                if self.eat(TokenKind::Comma).is_none()
+                    && !ends_in_block
+                    && !self.at(TokenKind::RBrace)
+                    && !self.peek_kind().is_terminator()
+                {
+                    self.expect_one_of(&[TokenKind::Comma, TokenKind::RBrace])?;
+                }
+
+                Ok(())
+            });
+
+            if let Err(err) = result {
+                self.report(err);
+                self.synchronize();
+            }
+        }
+
+        arms
+    }
+
+    /// Parse a single arm of a `match`, like `n if n > 0 => n`.
+    fn parse_arm(&mut self) -> PResult<Arm> {
+        let start = self.peek().span;
+        let pat = self.parse_pattern()?;
+
+        let guard = match self.eat(TokenKind::If) {
+            Some(_) => Some(self.parse_expr()?),
+            None => None,
+        };
+
+        self.expect(TokenKind::FatArrow)?;
+        let body = self.parse_expr()?;
+
+        Ok(Spanned::new(
+            ArmKind { pat, guard, body },
+            self.span_from(start),
+        ))
+    }
+
+    /// Parse a `while`, `loop` or `for` loop, which may have a label in front
+    /// of it like `'outer: loop {}`.
+    pub fn parse_loop(&mut self) -> PResult<ExprKind> {
+        let label = self.parse_label();
+        if label.is_some() {
+            self.expect(TokenKind::Colon)?;
+        }
+
+        let tok = self.expect_one_of(&[
+            TokenKind::While,
+            TokenKind::Loop,
+            TokenKind::For,
+        ])?;
+
+        let kind = match tok.data.kind() {
+            TokenKind::While => ExprKind::While {
+                label,
+                cond: Box::new(self.parse_expr_before_block()?),
+                body: self.parse_block()?,
+            },
+            TokenKind::Loop => ExprKind::Loop {
+                label,
+                body: self.parse_block()?,
+            },
+            _ => {
+                let binding = self.expect_ident()?;
+                self.expect(TokenKind::In)?;
+
+                ExprKind::For {
+                    label,
+                    binding,
+                    iter: Box::new(self.parse_expr_before_block()?),
+                    body: self.parse_block()?,
+                }
+            }
+        };
+
+        Ok(kind)
+    }
+
+    /// Parse a `break`, which may have a label and a value to give back from
+    /// the loop, like `break 'outer 1`.
+    pub fn parse_break(&mut self) -> PResult<StmtKind> {
+        self.expect(TokenKind::Break)?;
+        let label = self.parse_label();
+
+        let value = if self.peek_kind().can_start_expression() {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+
+        Ok(StmtKind::Break { label, value })
+    }
+
+    /// Parse a `continue`, which may have a label.
+    pub fn parse_continue(&mut self) -> PResult<StmtKind> {
+        self.expect(TokenKind::Continue)?;
+
+        Ok(StmtKind::Continue {
+            label: self.parse_label(),
+        })
+    }
+
+    /// Consume a label like `'outer` if there is one, without the `'`.
+    fn parse_label(&mut self) -> Option<Ident> {
+        let tok = self.eat(TokenKind::Label)?;
+
+        match tok.data {
+            Token::Label(name) => Some(Spanned::new(name, tok.span)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{BinOp, Block, Expr, PatKind},
+        lexer::{position::Span, state::Lexer},
+        parser::errors::ParseError,
+    };
+
+    /// Parse the body of a function, failing if there were any errors.
+    fn block(input: &str) -> Block {
+        let mut parser = Parser::new(Lexer::new(input));
+        let block = parser.parse_block().unwrap();
+
+        assert_eq!(parser.errors, Vec::new(), "{input}");
+        block
+    }
+
+    fn tail(input: &str) -> Expr {
+        *block(input).data.tail.unwrap()
+    }
+
+    #[test]
+    fn test_if_else_chain() {
+        let expr = tail("{ if a { 1 } else if b { 2 } else { 3 } }");
+        assert_eq!(expr.span, Span::new(2, 39));
+
+        let ExprKind::If {
+            cond, else_branch, ..
+        } = expr.data
+        else {
+            panic!("expected an if");
+        };
+        assert_eq!(cond.data, ExprKind::Ident("a".into()));
+
+        let else_if = else_branch.unwrap();
+        assert_eq!(else_if.span, Span::new(18, 39));
+
+        let ExprKind::If { else_branch, .. } = else_if.data else {
+            panic!("expected an else if");
+        };
+        assert!(matches!(else_branch.unwrap().data, ExprKind::Block(_)));
+    }
+
+    #[test]
+    fn test_if_is_an_expression() {
+        let stmts = block("{ let a := if b { 1 } else { 2 } + 3 }").data.stmts;
+
+        let StmtKind::Let(stmt) = &stmts[0].data else {
+            panic!("expected a let");
+        };
+        let ExprKind::Binary { op, lhs, .. } = &stmt.value.data else {
+            panic!("expected a binary expression");
+        };
+        assert_eq!(*op, BinOp::Add);
+        assert!(matches!(lhs.data, ExprKind::If { .. }));
+    }
+
+    #[test]
+    fn test_else_on_the_next_line() {
+        let block = block("{\n    if a {\n        b()\n    }\n    else {\n        c()\n    }\n    d()\n}");
+
+        assert_eq!(block.data.stmts.len(), 1);
+        assert!(block.data.tail.is_some());
+    }
+
+    #[test]
+    fn test_loops() {
+        let expr = tail("{ while i < n { i += 1 } }");
+        let ExprKind::While { label, cond, body } = expr.data else {
+            panic!("expected a while");
+        };
+        assert_eq!(label, None);
+        assert!(matches!(cond.data, ExprKind::Binary { .. }));
+        assert_eq!(body.data.stmts.len(), 1);
+
+        let expr = tail("{ loop { break } }");
+        let ExprKind::Loop { body, .. } = expr.data else {
+            panic!("expected a loop");
+        };
+        assert_eq!(
+            body.data.stmts[0].data,
+            StmtKind::Break {
+                label: None,
+                value: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_for_over_ranges() {
+        let expr = tail("{ for i in 0..n { } }");
+        let ExprKind::For { binding, iter, .. } = expr.data else {
+            panic!("expected a for");
+        };
+        assert_eq!(binding.data, "i");
+        assert_eq!(iter.span, Span::new(11, 15));
+        assert!(matches!(
+            iter.data,
+            ExprKind::Range {
+                inclusive: false,
+                ..
+            }
+        ));
+
+        let expr = tail("{ for i in a + 1..=b * 2 {} }");
+        let ExprKind::For { iter, .. } = expr.data else {
+            panic!("expected a for");
+        };
+        let ExprKind::Range {
+            start,
+            end,
+            inclusive,
+        } = iter.data
+        else {
+            panic!("expected a range");
+        };
+        assert!(inclusive);
+        assert!(matches!(start.data, ExprKind::Binary { .. }));
+        assert!(matches!(end.data, ExprKind::Binary { .. }));
+    }
+
+    #[test]
+    fn test_labels() {
+        let expr = tail(
+            "{ 'outer: loop {\n    'inner: for x in 0..3 {\n        \
+            continue 'outer\n    }\n    break 'outer x\n} }",
+        );
+        let ExprKind::Loop { label, body } = expr.data else {
+            panic!("expected a loop");
+        };
+        let label = label.unwrap();
+        assert_eq!(label.data, "outer");
+        assert_eq!(label.span, Span::new(2, 8));
+
+        let StmtKind::Expr(inner) = &body.data.stmts[0].data else {
+            panic!("expected a statement");
+        };
+        let ExprKind::For {
+            label,
+            body: inner_body,
+            ..
+        } = &inner.data
+        else {
+            panic!("expected a for");
+        };
+        assert_eq!(label.as_ref().unwrap().data, "inner");
+        assert!(matches!(
+            &inner_body.data.stmts[0].data,
+            StmtKind::Continue { label: Some(label) } if label.data == "outer"
+        ));
+
+        let StmtKind::Break { label, value } = &body.data.stmts[1].data else {
+            panic!("expected a break");
+        };
+        assert_eq!(label.as_ref().unwrap().data, "outer");
+        assert_eq!(value.as_ref().unwrap().data, ExprKind::Ident("x".into()));
+    }
+
+    #[test]
+    fn test_label_needs_a_loop() {
+        let mut parser = Parser::new(Lexer::new("{ 'a: if b {} }"));
+        parser.parse_block().unwrap();
+
+        assert_eq!(
+            parser.errors[0].data,
+            ParseError::Expected {
+                expected: vec![
+                    TokenKind::While,
+                    TokenKind::Loop,
+                    TokenKind::For
+                ],
+                found: TokenKind::If,
+            }
+        );
+    }
+
+    #[test]
+    fn test_no_struct_literals_before_blocks() {
+        for input in [
+            "{ if a { b } }",
+            "{ while a.b { c } }",
+            "{ for i in 0..n { c } }",
+            "{ match a { _ => b } }",
+        ] {
+            let expr = tail(input);
+            assert!(
+                !matches!(expr.data, ExprKind::StructLit { .. }),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_struct_literals_within_delimiters() {
+        let expr = tail(
+            "{ if (P { x: 1 }).x == f(P { x }) { { P { x } } } \
+            else { P { x } } }",
+        );
+
+        let ExprKind::If {
+            cond, else_branch, ..
+        } = expr.data
+        else {
+            panic!("expected an if");
+        };
+        assert!(matches!(cond.data, ExprKind::Binary { .. }));
+
+        let ExprKind::Block(block) = else_branch.unwrap().data else {
+            panic!("expected an else block");
+        };
+        assert!(matches!(
+            block.data.tail.unwrap().data,
+            ExprKind::StructLit { .. }
+        ));
+    }
+
+    #[test]
+    fn test_struct_literals_in_match_arms() {
+        let expr = tail("{ if match a { _ => P { x } }.x { b } }");
+        assert!(matches!(expr.data, ExprKind::If { .. }));
+    }
+
+    #[test]
+    fn test_match_arms_and_guards() {
+        let expr = tail("{ match a { 0 => b, n if n > 1 => n * 2, _ => c } }");
+        assert_eq!(expr.span, Span::new(2, 49));
+
+        let ExprKind::Match { scrutinee, arms } = expr.data else {
+            panic!("expected a match");
+        };
+        assert_eq!(scrutinee.data, ExprKind::Ident("a".into()));
+        assert_eq!(arms.len(), 3);
+
+        let arm = &arms[1];
+        assert_eq!(arm.span, Span::new(20, 39));
+        assert_eq!(arm.data.pat.data, PatKind::Binding("n".into()));
+        assert!(matches!(
+            arm.data.guard.as_ref().unwrap().data,
+            ExprKind::Binary { op: BinOp::Gt, .. }
+        ));
+        assert!(arms[0].data.guard.is_none());
+    }
+
+    #[test]
+    fn test_match_arms_on_separate_lines() {
+        let expr = tail(
+            "{\n    match a {\n        0 => { b }\n        1 => { c } \
+            2 => d\n        _ => e,\n    }\n}",
+        );
+
+        let ExprKind::Match { arms, .. } = expr.data else {
+            panic!("expected a match");
+        };
+        assert_eq!(arms.len(), 4);
+        assert!(matches!(arms[1].data.body.data, ExprKind::Block(_)));
+    }
+
+    #[test]
+    fn test_match_arms_need_separators() {
+        let mut parser =
+            Parser::new(Lexer::new("{ match a { 0 => b 1 => c } }"));
+        parser.parse_block().unwrap();
+
+        assert_eq!(
+            parser.errors[0].data,
+            ParseError::Expected {
+                expected: vec![TokenKind::RBrace, TokenKind::Comma],
+                found: TokenKind::Number,
+            }
+        );
+    }
+
+    #[test]
+    fn test_broken_arms_are_left_out() {
+        let mut parser = Parser::new(Lexer::new(
+            "{\n    match a {\n        0 => b\n        1 c\n        _ => d\n    \
+            }\n}",
+        ));
+        let block = parser.parse_block().unwrap();
+
+        assert_eq!(
+            parser.errors[0].data,
+            ParseError::Expected {
+                expected: vec![TokenKind::FatArrow, TokenKind::If],
+                found: TokenKind::Ident,
+            }
+        );
+
+        let ExprKind::Match { arms, .. } = block.data.tail.unwrap().data else {
+            panic!("expected a match");
+        };
+        assert_eq!(arms.len(), 2);
+        assert_eq!(arms[1].data.pat.data, PatKind::Wild);
+    }
+}
@@ -0,0 +1,198 @@
+//! Handles parsing enum declarations, and the paths with `::` which name
+//! their variants or the items of other modules.
+
+use alloc::vec;
+
+use crate::{
+    ast::{Enum, Ident, Path, Variant, VariantFields, VariantKind},
+    lexer::{kind::TokenKind, position::Spanned},
+};
+
+use super::{state::Parser, PResult};
+
+impl Parser {
+    /// Parse an enum declaration, like
+    /// `enum Shape { Circle(f64), Rect { w: f64, h: f64 }, Empty }`.
+    pub fn parse_enum(&mut self) -> PResult<Enum> {
+        self.expect(TokenKind::Enum)?;
+        let name = self.expect_ident()?;
+        let generics = self.parse_type_params();
+
+        self.expect(TokenKind::LBrace)?;
+        let variants = self.parse_list(TokenKind::RBrace, Self::parse_variant);
+
+        Ok(Enum {
+            public: false,
+            name,
+            generics,
+            variants,
+        })
+    }
+
+    /// Parse a single variant, which holds either nothing, values in order,
+    /// or values by name. Any variant can be given a discriminant after `=`,
+    /// like `Flag = 0b100`.
+    fn parse_variant(&mut self) -> PResult<Variant> {
+        let name = self.expect_ident()?;
+        let start = name.span;
+
+        let fields = match self.peek_kind() {
+            TokenKind::LPar => {
+                self.bump();
+                let tys = self.parse_list(TokenKind::RPar, Self::parse_type);
+                VariantFields::Tuple(tys)
+            }
+            TokenKind::LBrace => {
+                self.bump();
+                let fields =
+                    self.parse_list(TokenKind::RBrace, Self::parse_field_decl);
+                VariantFields::Named(fields)
+            }
+            _ => VariantFields::Unit,
+        };
+
+        let discriminant = match self.eat(TokenKind::Eq) {
+            Some(_) => Some(self.parse_expr()?),
+            None => None,
+        };
+
+        Ok(Spanned::new(
+            VariantKind {
+                name,
+                fields,
+                discriminant,
+            },
+            self.span_from(start),
+        ))
+    }
+
+    /// Parse the rest of a path after its `first` segment, like the
+    /// `::Circle` of `Shape::Circle`. The path is just `first` if there is no
+    /// `::` after it.
+    pub fn parse_path(&mut self, first: Ident) -> PResult<Path> {
+        let start = first.span;
+        let mut segments = vec![first];
+
+        // Any name could be followed by `::`, so it is left out of the
+        // expected tokens to keep errors after a name short. A `::` followed
+        // by `<` gives type arguments instead, which aren't part of the path.
+        while self.at(TokenKind::ColonColon)
+            && self.nth_kind(1) != TokenKind::Less
+        {
+            self.bump();
+            segments.push(self.expect_ident()?);
+        }
+
+        Ok(Spanned::new(segments, self.span_from(start)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::{
+        ast::{ExprKind, ItemKind, Literal, TyKind},
+        lexer::{position::Span, state::Lexer},
+        parser::{errors::ParseError, parse},
+    };
+
+    /// Parse a single enum declaration, failing if there were any errors.
+    fn enum_decl(input: &str) -> Enum {
+        let (mut module, errors) = parse(input);
+        assert_eq!(errors, vec![], "{input}");
+
+        match module.items.remove(0).data {
+            ItemKind::Enum(decl) => decl,
+            item => panic!("expected an enum, found {item:?}"),
+        }
+    }
+
+    #[test]
+    fn test_enum_declaration() {
+        let decl = enum_decl(
+            "enum Shape {\n    Circle(f64),\n    Rect { w: f64, h: f64 },\n    \
+             Empty\n}",
+        );
+        assert_eq!(decl.name.data, "Shape");
+
+        let [circle, rect, empty] = &decl.variants[..] else {
+            panic!("expected three variants");
+        };
+
+        assert_eq!(circle.span, Span::new(17, 28));
+        let VariantFields::Tuple(tys) = &circle.data.fields else {
+            panic!("expected values in order");
+        };
+        assert_eq!(tys[0].data, TyKind::Named("f64".into()));
+
+        let VariantFields::Named(fields) = &rect.data.fields else {
+            panic!("expected values by name");
+        };
+        let names: Vec<_> = fields
+            .iter()
+            .map(|field| field.data.name.data.as_str())
+            .collect();
+        assert_eq!(names, vec!["w", "h"]);
+
+        assert_eq!(empty.data.name.data, "Empty");
+        assert_eq!(empty.data.fields, VariantFields::Unit);
+    }
+
+    #[test]
+    fn test_discriminants() {
+        let decl = enum_decl("enum Flags { A = 1, B, Flag = 0b100, C = -0x2 }");
+
+        let discriminants: Vec<_> = decl
+            .variants
+            .iter()
+            .map(|variant| {
+                variant.data.discriminant.as_ref().map(|expr| &expr.data)
+            })
+            .collect();
+
+        assert_eq!(
+            discriminants[2],
+            Some(&ExprKind::Literal(Literal::Number {
+                base: 2,
+                digits: "100".into(),
+                negative: false,
+            }))
+        );
+        assert_eq!(discriminants[1], None);
+        assert!(discriminants[3].is_some());
+    }
+
+    #[test]
+    fn test_empty_enum() {
+        assert!(enum_decl("enum Never {}").variants.is_empty());
+    }
+
+    #[test]
+    fn test_paths() {
+        let mut parser = Parser::new(Lexer::new("a::b::c d"));
+        let first = parser.expect_ident().unwrap();
+        let path = parser.parse_path(first).unwrap();
+
+        assert_eq!(path.span, Span::new(0, 7));
+        let names: Vec<_> =
+            path.data.iter().map(|name| name.data.as_str()).collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_path_needs_a_name_after_colons() {
+        let mut parser = Parser::new(Lexer::new("Shape::(1)"));
+        let first = parser.expect_ident().unwrap();
+        let err = parser.parse_path(first).unwrap_err();
+
+        assert_eq!(
+            err.data,
+            ParseError::Expected {
+                expected: vec![TokenKind::Ident],
+                found: TokenKind::LPar,
+            }
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use alloc::{boxed::Box, string::String, vec::Vec};
+
+use crate::{
+    lexer::{errors::LexingError, kind::TokenKind, position::Spanned},
+    macros::errors::MacroError,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    /// The input failed to lex
+    Lexing(LexingError),
+    /// A token was found where one of the `expected` tokens should have been
+    Expected {
+        expected: Vec<TokenKind>,
+        found: TokenKind,
+    },
+    ExpectedType(TokenKind),
+    ExpectedExpression(TokenKind),
+    ExpectedPattern(TokenKind),
+    ExpectedItem(TokenKind),
+    /// Comparisons like `a < b < c` have to be written with parenthesis
+    ChainedComparison,
+    /// An inner attribute, like `#![test]`, which isn't at the start of a
+    /// file or block
+    InnerAttribute,
+    /// Attributes before the expression at the end of a block, which is its
+    /// value rather than a statement
+    AttributesOnTail,
+    /// Expressions, types or patterns nested in each other more than
+    /// [`MAX_DEPTH`](super::state::MAX_DEPTH) levels deep
+    TooDeep,
+    /// A macro call which couldn't be expanded
+    Macro(MacroError),
+    /// An error in the tokens that a macro call expanded into, with the name
+    /// of every macro it was expanded from, innermost first, each spanning
+    /// the name of its declaration
+    InMacro {
+        error: Box<ParseError>,
+        trace: Vec<Spanned<String>>,
+    },
+}
+
+impl ParseError {
+    /// Wrap this error with the macros it was expanded from, if there are
+    /// any.
+    pub fn in_macro(self, trace: Vec<Spanned<String>>) -> Self {
+        if trace.is_empty() {
+            return self;
+        }
+
+        Self::InMacro {
+            error: Box::new(self),
+            trace,
+        }
+    }
+
+    /// Get the macros that this error was expanded from, innermost first.
+    pub fn trace(&self) -> &[Spanned<String>] {
+        match self {
+            Self::InMacro { trace, .. } => trace,
+            _ => &[],
+        }
+    }
+}
+
+impl core::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Lexing(err) => write!(f, "{err}"),
+            Self::Expected { expected, found } => {
+                write!(f, "expected ")?;
+                if expected.len() > 1 {
+                    write!(f, "one of ")?;
+                }
+
+                for (idx, kind) in expected.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{kind}")?;
+                }
+
+                write!(f, " found {found}")
+            }
+            Self::ExpectedType(found) => {
+                write!(f, "expected a type, found {found}")
+            }
+            Self::ExpectedExpression(found) => {
+                write!(f, "expected an expression, found {found}")
+            }
+            Self::ExpectedPattern(found) => {
+                write!(f, "expected a pattern, found {found}")
+            }
+            Self::ExpectedItem(found) => {
+                write!(f, "expected an item, found {found}")
+            }
+            Self::ChainedComparison => {
+                write!(f, "comparison operators cannot be chained")
+            }
+            Self::InnerAttribute => write!(
+                f,
+                "an inner attribute can only be at the start of a file or block"
+            ),
+            Self::AttributesOnTail => write!(
+                f,
+                "attributes can't be put on the value at the end of a block"
+            ),
+            Self::TooDeep => write!(
+                f,
+                "this is nested more than {} levels deep",
+                super::state::MAX_DEPTH
+            ),
+            Self::Macro(err) => write!(f, "{err}"),
+            Self::InMacro { error, .. } => write!(f, "{error}"),
+        }
+    }
+}
+
+impl From<LexingError> for ParseError {
+    fn from(err: LexingError) -> Self {
+        Self::Lexing(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
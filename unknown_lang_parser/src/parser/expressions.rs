@@ -0,0 +1,648 @@
+//! Handles parsing expressions.
+//!
+//! Expressions are parsed with a Pratt parser. Every operator gets a binding
+//! power from its level in [`TokenKind::binary_precedence`], and an operator
+//! only takes an operand on its right while the next operator binds less
+//! tightly than it does. Assignment is left to the statement parser, so it is
+//! never part of an expression.
+//!
+//! Calls and field access come after their operand rather than between two,
+//! so they are handled in the same loop as binary operators with a binding
+//! power of their own.
+
+use alloc::boxed::Box;
+
+use crate::{
+    ast::{BinOp, Expr, ExprKind, Ident, Literal, UnaryOp},
+    lexer::{
+        kind::{Assoc, Precedence, TokenKind},
+        position::Spanned,
+        tokens::Token,
+    },
+};
+
+use super::{errors::ParseError, state::Parser, PResult};
+
+/// Get the binding powers on the left and right of an operator. Operators
+/// which group to the left bind slightly tighter on their right, so that
+/// `a - b - c` stops before the second `-`.
+fn binding_power(prec: Precedence) -> (u8, u8) {
+    let power = prec.level * 2;
+
+    match prec.assoc {
+        Assoc::Right => (power, power),
+        Assoc::Left | Assoc::None => (power, power + 1),
+    }
+}
+
+impl Parser {
+    /// Parse an expression. A range like `a..b` binds more loosely than
+    /// any operator, and can't be chained.
+    pub fn parse_expr(&mut self) -> PResult<Expr> {
+        let start = self.parse_expr_bp(0)?;
+
+        let inclusive = match self.peek_kind() {
+            TokenKind::DotDot => false,
+            TokenKind::DotDotEq => true,
+            _ => return Ok(start),
+        };
+
+        self.bump();
+        let end = self.parse_expr_bp(0)?;
+
+        let span = start.span.union(end.span);
+        Ok(Spanned::new(
+            ExprKind::Range {
+                start: Box::new(start),
+                end: Box::new(end),
+                inclusive,
+            },
+            span,
+        ))
+    }
+
+    /// Parse an expression which is followed by a block, like the condition
+    /// of an `if`. A struct literal isn't allowed here unless it is within
+    /// delimiters, so that the `{` in `if a { }` starts the block.
+    pub fn parse_expr_before_block(&mut self) -> PResult<Expr> {
+        self.with_struct_literals(false, Self::parse_expr)
+    }
+
+    /// Parse an expression made of operators which bind at least as tightly
+    /// as `min_power`.
+    fn parse_expr_bp(&mut self, min_power: u8) -> PResult<Expr> {
+        self.nested(|parser| parser.parse_operators(min_power))
+    }
+
+    /// Parse the operators of [`Self::parse_expr_bp`], once it has checked
+    /// how deeply nested the expression is.
+    fn parse_operators(&mut self, min_power: u8) -> PResult<Expr> {
+        let mut lhs = self.parse_prefix()?;
+
+        loop {
+            let kind = self.peek_kind();
+
+            if kind == TokenKind::LPar {
+                if Precedence::CALL * 2 < min_power {
+                    break;
+                }
+
+                lhs = self.parse_call(lhs)?;
+                continue;
+            }
+
+            if kind == TokenKind::Dot {
+                if Precedence::FIELD * 2 < min_power {
+                    break;
+                }
+
+                lhs = self.parse_field(lhs)?;
+                continue;
+            }
+
+            let (op, prec) = match (binary_op(kind), kind.binary_precedence()) {
+                (Some(op), Some(prec)) => (op, prec),
+                _ => break,
+            };
+
+            let (left_power, right_power) = binding_power(prec);
+            if left_power < min_power {
+                break;
+            }
+
+            self.bump();
+            let rhs = self.parse_expr_bp(right_power)?;
+
+            if prec.assoc == Assoc::None
+                && self.peek_kind().binary_precedence() == Some(prec)
+            {
+                return Err(self.error(ParseError::ChainedComparison));
+            }
+
+            let span = lhs.span.union(rhs.span);
+            lhs = Spanned::new(
+                ExprKind::Binary {
+                    op,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+                span,
+            );
+        }
+
+        Ok(lhs)
+    }
+
+    /// Parse an expression which may start with a prefix operator, like `-a`.
+    fn parse_prefix(&mut self) -> PResult<Expr> {
+        let start = self.peek().span;
+
+        let Some(prec) = self.peek_kind().prefix_precedence() else {
+            return self.parse_primary();
+        };
+
+        let op = match self.bump().data {
+            Token::Minus => UnaryOp::Neg,
+            Token::Bang => UnaryOp::Not,
+            Token::Tilde => UnaryOp::BitNot,
+            tok => unreachable!("{tok} is not a prefix operator"),
+        };
+        let mut operand = self.parse_expr_bp(binding_power(prec).1)?;
+        let span = self.span_from(start);
+
+        // The lexer leaves the sign off of numbers, so `-5` is folded back
+        // into a single negative number here.
+        if let (
+            UnaryOp::Neg,
+            ExprKind::Literal(Literal::Number { negative, .. }),
+        ) = (op, &mut operand.data)
+        {
+            if !*negative {
+                *negative = true;
+                return Ok(Spanned::new(operand.data, span));
+            }
+        }
+
+        Ok(Spanned::new(
+            ExprKind::Unary {
+                op,
+                operand: Box::new(operand),
+            },
+            span,
+        ))
+    }
+
+    /// Parse the arguments of a call to `callee`, like `(1, 2)`.
+    fn parse_call(&mut self, callee: Expr) -> PResult<Expr> {
+        self.expect(TokenKind::LPar)?;
+        let args = self.with_struct_literals(true, |p| {
+            p.parse_list(TokenKind::RPar, Self::parse_expr)
+        });
+
+        let span = self.span_from(callee.span);
+        Ok(Spanned::new(
+            ExprKind::Call {
+                callee: Box::new(callee),
+                args,
+            },
+            span,
+        ))
+    }
+
+    /// Parse access to a field of `base`, like the `.x` of `point.x`.
+    fn parse_field(&mut self, base: Expr) -> PResult<Expr> {
+        self.expect(TokenKind::Dot)?;
+        let field = self.expect_ident()?;
+
+        let span = base.span.union(field.span);
+        Ok(Spanned::new(
+            ExprKind::Field {
+                base: Box::new(base),
+                field,
+            },
+            span,
+        ))
+    }
+
+    /// Parse a literal, a name or path, a struct literal, an expression in
+    /// parenthesis or a block, a lambda, or one of the control flow
+    /// expressions like `if` and `match`. A missing expression is reported, and
+    /// left as [`ExprKind::Error`] without consuming anything.
+    fn parse_primary(&mut self) -> PResult<Expr> {
+        let start = self.peek().span;
+
+        let kind = match self.peek_kind() {
+            // A call to a macro which wasn't expanded, like `max!(a, b)`
+            TokenKind::Ident
+                if self.nth_kind(1) == TokenKind::Bang
+                    && self.nth_kind(2).closing_delimiter().is_some() =>
+            {
+                let name = self.expect_ident()?;
+                self.parse_macro_call(name)?
+            }
+            TokenKind::Ident => {
+                let name = self.expect_ident()?;
+                self.parse_path_or_struct_lit(name)?
+            }
+            TokenKind::LPar => {
+                self.bump();
+                let inner =
+                    self.with_struct_literals(true, Self::parse_expr)?;
+                self.expect(TokenKind::RPar)?;
+
+                ExprKind::Paren(Box::new(inner))
+            }
+            TokenKind::LBrace => ExprKind::Block(self.parse_block()?),
+            TokenKind::If => self.parse_if()?,
+            TokenKind::While
+            | TokenKind::Loop
+            | TokenKind::For
+            | TokenKind::Label => self.parse_loop()?,
+            TokenKind::Match => self.parse_match()?,
+            TokenKind::Bar | TokenKind::BarBar => self.parse_lambda()?,
+            kind if kind.is_literal() => {
+                ExprKind::Literal(self.parse_literal())
+            }
+            kind => {
+                let err = self.error(ParseError::ExpectedExpression(kind));
+                self.report(err);
+
+                let start = start.start.0;
+                return Ok(Spanned::new_span(ExprKind::Error, start, start));
+            }
+        };
+
+        Ok(Spanned::new(kind, self.span_from(start)))
+    }
+
+    /// Parse what comes after a name in an expression, which is the rest of
+    /// its path, followed by the fields when it is a struct literal or the
+    /// type arguments when it is a generic function.
+    fn parse_path_or_struct_lit(&mut self, name: Ident) -> PResult<ExprKind> {
+        let mut path = self.parse_path(name)?;
+
+        if self.at(TokenKind::ColonColon) {
+            self.parse_instantiation(path)
+        } else if self.at(TokenKind::LBrace) && self.struct_literals_allowed() {
+            self.parse_struct_lit(path)
+        } else if path.data.len() > 1 {
+            Ok(ExprKind::Path(path.data))
+        } else {
+            Ok(ExprKind::Ident(path.data.remove(0).data))
+        }
+    }
+
+    /// Consume the current token, which has to be a literal, and turn it into
+    /// a [`Literal`].
+    pub fn parse_literal(&mut self) -> Literal {
+        match self.bump().data {
+            Token::Number(base, digits) => Literal::Number {
+                base,
+                digits,
+                negative: false,
+            },
+            Token::Str(string) => Literal::Str(string),
+            Token::Char(chr) => Literal::Char(chr),
+            Token::Byte(byte) => Literal::Byte(byte),
+            Token::ByteStr(bytes) => Literal::ByteStr(bytes),
+            tok => unreachable!("{tok} is not a literal"),
+        }
+    }
+}
+
+/// Get the binary operator for a token kind, if it is one.
+fn binary_op(kind: TokenKind) -> Option<BinOp> {
+    let op = match kind {
+        TokenKind::Plus => BinOp::Add,
+        TokenKind::Minus => BinOp::Sub,
+        TokenKind::Star => BinOp::Mul,
+        TokenKind::Slash => BinOp::Div,
+        TokenKind::Percent => BinOp::Rem,
+        TokenKind::Ampersand => BinOp::BitAnd,
+        TokenKind::Bar => BinOp::BitOr,
+        TokenKind::Hat => BinOp::BitXor,
+        TokenKind::LessLess => BinOp::Shl,
+        TokenKind::GreaterGreater => BinOp::Shr,
+        TokenKind::EqEq => BinOp::Eq,
+        TokenKind::BangEq => BinOp::Ne,
+        TokenKind::Less => BinOp::Lt,
+        TokenKind::LessEq => BinOp::Le,
+        TokenKind::Greater => BinOp::Gt,
+        TokenKind::GreaterEq => BinOp::Ge,
+        TokenKind::AmpAmp => BinOp::And,
+        TokenKind::BarBar => BinOp::Or,
+        TokenKind::StarStar => BinOp::Compose,
+        _ => return None,
+    };
+
+    Some(op)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::{
+        lexer::{position::Span, state::Lexer},
+        parser::state::MAX_DEPTH,
+    };
+
+    /// Parse an expression, giving back the first error if there were any.
+    fn expr(input: &str) -> PResult<Expr> {
+        let mut parser = Parser::new(Lexer::new(input));
+        let result = parser.parse_expr();
+
+        match parser.errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => result,
+        }
+    }
+
+    fn binary(expr: &Expr) -> (BinOp, &Expr, &Expr) {
+        match &expr.data {
+            ExprKind::Binary { op, lhs, rhs } => (*op, lhs, rhs),
+            kind => panic!("expected a binary expression, found {kind:?}"),
+        }
+    }
+
+    #[test]
+    fn test_precedence() {
+        let expr = expr("a + b * c").unwrap();
+        let (op, _, rhs) = binary(&expr);
+
+        assert_eq!(op, BinOp::Add);
+        assert_eq!(binary(rhs).0, BinOp::Mul);
+    }
+
+    #[test]
+    fn test_left_associative() {
+        let expr = expr("a - b - c").unwrap();
+        let (op, lhs, _) = binary(&expr);
+
+        assert_eq!(op, BinOp::Sub);
+        assert_eq!(binary(lhs).0, BinOp::Sub);
+        assert_eq!(expr.span, Span::new(0, 9));
+    }
+
+    #[test]
+    fn test_chained_comparison() {
+        let err = expr("a < b < c").unwrap_err();
+        assert_eq!(err.data, ParseError::ChainedComparison);
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested =
+            |depth: usize| "(".repeat(depth) + "1" + &")".repeat(depth);
+
+        expr(&nested(MAX_DEPTH - 1)).unwrap();
+
+        for depth in [MAX_DEPTH, 100_000] {
+            let err = expr(&nested(depth)).unwrap_err();
+            assert_eq!(err.data, ParseError::TooDeep);
+            assert_eq!(err.span, Span::new(MAX_DEPTH, MAX_DEPTH + 1));
+        }
+
+        let err = expr(&"-".repeat(100_000)).unwrap_err();
+        assert_eq!(err.data, ParseError::TooDeep);
+    }
+
+    #[test]
+    fn test_calls() {
+        let expr = expr("add(0x15, 0b101001)(1)").unwrap();
+
+        let ExprKind::Call { callee, args } = &expr.data else {
+            panic!("expected a call");
+        };
+        assert_eq!(args.len(), 1);
+        assert_eq!(expr.span, Span::new(0, 22));
+
+        let ExprKind::Call { args, .. } = &callee.data else {
+            panic!("expected a call");
+        };
+        assert_eq!(
+            args[0].data,
+            ExprKind::Literal(Literal::Number {
+                base: 16,
+                digits: "15".to_string(),
+                negative: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parens() {
+        let expr = expr("(a + b) * c").unwrap();
+        let (op, lhs, _) = binary(&expr);
+
+        assert_eq!(op, BinOp::Mul);
+        assert!(matches!(lhs.data, ExprKind::Paren(_)));
+        assert_eq!(lhs.span, Span::new(0, 7));
+    }
+
+    #[test]
+    fn test_missing_expression() {
+        let err = expr("a + )").unwrap_err();
+        assert_eq!(err.data, ParseError::ExpectedExpression(TokenKind::RPar));
+        assert_eq!(err.span, Span::new(4, 5));
+    }
+
+    fn callee(expr: &Expr) -> &Expr {
+        match &expr.data {
+            ExprKind::Call { callee, .. } => callee,
+            kind => panic!("expected a call, found {kind:?}"),
+        }
+    }
+
+    fn number(digits: &str, negative: bool) -> ExprKind {
+        ExprKind::Literal(Literal::Number {
+            base: 10,
+            digits: digits.to_string(),
+            negative,
+        })
+    }
+
+    #[test]
+    fn test_composition_binds_tighter_than_calls() {
+        for input in [
+            "addOne ** add (2, 3)",
+            "addOne**add(3,4)",
+            "( addOne ** add ) (2, 3)",
+        ] {
+            let expr = expr(input).unwrap();
+            let mut composed = callee(&expr);
+
+            if let ExprKind::Paren(inner) = &composed.data {
+                composed = inner;
+            }
+            assert_eq!(binary(composed).0, BinOp::Compose, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_composition_is_right_associative() {
+        let expr = expr("f ** g ** h").unwrap();
+        let (op, lhs, rhs) = binary(&expr);
+
+        assert_eq!(op, BinOp::Compose);
+        assert_eq!(lhs.data, ExprKind::Ident("f".to_string()));
+        assert_eq!(binary(rhs).0, BinOp::Compose);
+    }
+
+    #[test]
+    fn test_calls_on_results() {
+        let expr = expr("f(x) ** g").unwrap();
+        let (op, lhs, _) = binary(&expr);
+
+        assert_eq!(op, BinOp::Compose);
+        assert!(matches!(lhs.data, ExprKind::Call { .. }));
+    }
+
+    /// Get the base and the name of a field access.
+    fn field(expr: &Expr) -> (&Expr, &str) {
+        match &expr.data {
+            ExprKind::Field { base, field } => (base, &field.data),
+            kind => panic!("expected a field access, found {kind:?}"),
+        }
+    }
+
+    #[test]
+    fn test_field_chains() {
+        let expr = expr("a.b(c).d").unwrap();
+        assert_eq!(expr.span, Span::new(0, 8));
+
+        let (base, name) = field(&expr);
+        assert_eq!(name, "d");
+
+        let ExprKind::Call { callee, .. } = &base.data else {
+            panic!("expected a call");
+        };
+        assert_eq!(field(callee).1, "b");
+    }
+
+    #[test]
+    fn test_fields_bind_tighter_than_composition() {
+        let expr = expr("a.f ** b.g (x)").unwrap();
+        let (op, lhs, rhs) = binary(callee(&expr));
+
+        assert_eq!(op, BinOp::Compose);
+        assert_eq!(field(lhs).1, "f");
+        assert_eq!(field(rhs).1, "g");
+    }
+
+    #[test]
+    fn test_fields_bind_tighter_than_prefix() {
+        let expr = expr("-a.b").unwrap();
+
+        let ExprKind::Unary { operand, .. } = &expr.data else {
+            panic!("expected a unary expression");
+        };
+        assert_eq!(field(operand).1, "b");
+    }
+
+    #[test]
+    fn test_field_of_struct_literal() {
+        let expr = expr("Point { x: 1 }.x").unwrap();
+        let (base, _) = field(&expr);
+
+        assert!(matches!(base.data, ExprKind::StructLit { .. }));
+    }
+
+    #[test]
+    fn test_negative_literals() {
+        let expr_ = expr("-5 - 3").unwrap();
+        let (op, lhs, rhs) = binary(&expr_);
+
+        assert_eq!(op, BinOp::Sub);
+        assert_eq!(lhs.data, number("5", true));
+        assert_eq!(lhs.span, Span::new(0, 2));
+        assert_eq!(rhs.data, number("3", false));
+
+        // Only the innermost minus is folded
+        let expr_ = expr("--5").unwrap();
+        let ExprKind::Unary { op, operand } = &expr_.data else {
+            panic!("expected a unary expression");
+        };
+        assert_eq!(*op, UnaryOp::Neg);
+        assert_eq!(operand.data, number("5", true));
+    }
+
+    #[test]
+    fn test_unary_operators() {
+        let expr_ = expr("!a && ~b * -c").unwrap();
+        let (op, lhs, rhs) = binary(&expr_);
+
+        assert_eq!(op, BinOp::And);
+        assert!(matches!(
+            lhs.data,
+            ExprKind::Unary {
+                op: UnaryOp::Not,
+                ..
+            }
+        ));
+
+        let (op, lhs, rhs) = binary(rhs);
+        assert_eq!(op, BinOp::Mul);
+        assert!(matches!(
+            lhs.data,
+            ExprKind::Unary {
+                op: UnaryOp::BitNot,
+                ..
+            }
+        ));
+        assert!(matches!(
+            rhs.data,
+            ExprKind::Unary {
+                op: UnaryOp::Neg,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_negated_call() {
+        let expr = expr("-f(1)").unwrap();
+
+        let ExprKind::Unary { operand, .. } = &expr.data else {
+            panic!("expected a unary expression");
+        };
+        assert!(matches!(operand.data, ExprKind::Call { .. }));
+    }
+
+    #[test]
+    fn test_logical_and_comparison_precedence() {
+        let expr = expr("a < b || c == d && e | f").unwrap();
+        let (op, lhs, rhs) = binary(&expr);
+
+        assert_eq!(op, BinOp::Or);
+        assert_eq!(binary(lhs).0, BinOp::Lt);
+
+        let (op, lhs, rhs) = binary(rhs);
+        assert_eq!(op, BinOp::And);
+        assert_eq!(binary(lhs).0, BinOp::Eq);
+        assert_eq!(binary(rhs).0, BinOp::BitOr);
+    }
+
+    #[test]
+    fn test_shifts_and_bitwise() {
+        let expr = expr("a & b << 1 + c ^ d").unwrap();
+        let (op, lhs, _) = binary(&expr);
+
+        assert_eq!(op, BinOp::BitXor);
+        let (op, _, rhs) = binary(lhs);
+        assert_eq!(op, BinOp::BitAnd);
+        let (op, _, rhs) = binary(rhs);
+        assert_eq!(op, BinOp::Shl);
+        assert_eq!(binary(rhs).0, BinOp::Add);
+    }
+
+    #[test]
+    fn test_missing_operand_is_an_error_node() {
+        let mut parser = Parser::new(Lexer::new("f(a +, b)"));
+        let expr = parser.parse_expr().unwrap();
+
+        let ExprKind::Call { args, .. } = &expr.data else {
+            panic!("expected a call");
+        };
+        assert_eq!(args.len(), 2);
+        assert_eq!(binary(&args[0]).2.data, ExprKind::Error);
+        assert_eq!(parser.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_recovers_within_arguments() {
+        let mut parser = Parser::new(Lexer::new("f(a b (c, d), e)"));
+        let expr = parser.parse_expr().unwrap();
+
+        let ExprKind::Call { args, .. } = &expr.data else {
+            panic!("expected a call");
+        };
+        assert_eq!(args.len(), 2);
+        assert_eq!(args[1].data, ExprKind::Ident("e".to_string()));
+        assert_eq!(
+            parser.errors[0].data.to_string(),
+            "expected one of `)`, `,` found identifier"
+        );
+    }
+}
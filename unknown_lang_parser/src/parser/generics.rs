@@ -0,0 +1,123 @@
+//! Handles parsing the type parameters of generic declarations, like the
+//! `<T>` of `func id<T>(x: T) : T`, and the type arguments given to a generic
+//! function in an expression, like `id::<u8>`. The traits that a type
+//! parameter is bounded by, like `T: Display + Debug`, are in `traits.rs`.
+//!
+//! In an expression the type arguments come after `::`, since `id<u8>(x)`
+//! would otherwise be read as the comparisons `id < u8 > (x)`.
+
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    ast::{ExprKind, Path, TypeParam, TypeParamKind},
+    lexer::{kind::TokenKind, position::Spanned},
+};
+
+use super::{state::Parser, PResult};
+
+impl Parser {
+    /// Parse the type parameters after the name of a declaration, which are
+    /// left off when it isn't generic.
+    pub fn parse_type_params(&mut self) -> Vec<TypeParam> {
+        if self.eat(TokenKind::Less).is_none() {
+            return vec![];
+        }
+
+        self.parse_list(TokenKind::Greater, Self::parse_type_param)
+    }
+
+    fn parse_type_param(&mut self) -> PResult<TypeParam> {
+        let name = self.expect_ident()?;
+        let start = name.span;
+
+        let mut bounds = vec![];
+        if self.eat(TokenKind::Colon).is_some() {
+            bounds.push(self.parse_trait_ref()?);
+            while self.eat(TokenKind::Plus).is_some() {
+                bounds.push(self.parse_trait_ref()?);
+            }
+        }
+
+        let span = self.span_from(start);
+        Ok(Spanned::new(TypeParamKind { name, bounds }, span))
+    }
+
+    /// Parse the type arguments given to the generic function at `path`,
+    /// from the `::` before the `<`, like the `::<u8>` of `id::<u8>`.
+    pub fn parse_instantiation(&mut self, path: Path) -> PResult<ExprKind> {
+        self.expect(TokenKind::ColonColon)?;
+        self.expect(TokenKind::Less)?;
+        let args = self.parse_type_args()?;
+
+        Ok(ExprKind::Instantiate { path, args })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::{
+        ast::{print::sexpr, ItemKind},
+        lexer::position::Span,
+        parser::parse,
+    };
+
+    #[test]
+    fn test_generic_declarations() {
+        let (module, errors) = parse(concat!(
+            "func id<T>(x: T) : T = { x }\n",
+            "struct Pair<A, B,> { a: A, b: B }\n",
+            "enum Option<T> { Some(T), None }\n",
+            "func f<>() := {}",
+        ));
+        assert_eq!(errors, vec![]);
+        assert_eq!(
+            sexpr(&module),
+            "(module \
+             (func id (generics T) (params (x T)) T (block x)) \
+             (struct Pair (generics A B) (fields (a A) (b B))) \
+             (enum Option (generics T) (variants (Some T) None)) \
+             (func f (params) (block)))"
+        );
+
+        let ItemKind::Func(func) = &module.items[0].data else {
+            panic!("expected a function");
+        };
+        assert_eq!(func.generics[0].span, Span::new(8, 9));
+    }
+
+    #[test]
+    fn test_instantiation() {
+        let (module, errors) = parse(concat!(
+            "let a := id::<u8>(1)\n",
+            "let b := convert::<Option<u8>, usize>\n",
+            "let c := Option::Some::<u8>(1)\n",
+            "let d := id < u8",
+        ));
+        assert_eq!(errors, vec![]);
+        assert_eq!(
+            sexpr(&module),
+            "(module \
+             (let a (call (inst id u8) 1)) \
+             (let b (inst convert (generic Option u8) usize)) \
+             (let c (call (inst Option::Some u8) 1)) \
+             (let d (< id u8)))"
+        );
+    }
+
+    #[test]
+    fn test_broken_type_params() {
+        let (module, errors) = parse("func f<T = u8>() := {}");
+
+        assert_eq!(
+            sexpr(&module),
+            "(module (func f (generics T) (params) (block)))"
+        );
+        assert_eq!(
+            errors[0].data.to_string(),
+            "expected one of `:`, `,`, `>` found `=`"
+        );
+    }
+}
@@ -0,0 +1,398 @@
+//! Handles parsing the top level of a file, imports, and function
+//! declarations. Struct declarations are in `structs.rs`, enum declarations
+//! are in `enums.rs`, traits and their impls are in `traits.rs`, macros are
+//! in `macros.rs`, and the attributes before items are in `attributes.rs`.
+
+use alloc::vec;
+
+use crate::{
+    ast::{
+        Enum, Func, Import, Item, ItemKind, Module, Param, ParamKind, Struct,
+        Trait, TyKind,
+    },
+    lexer::{kind::TokenKind, position::Spanned},
+};
+
+use super::{errors::ParseError, state::Parser, PResult};
+
+impl Parser {
+    /// Parse a whole file. Items which fail to parse are reported, and left
+    /// in the module as [`ItemKind::Error`].
+    pub fn parse_module(&mut self) -> Module {
+        let attrs = self.parse_inner_attrs();
+        let mut items = vec![];
+
+        loop {
+            self.skip_terminators();
+            if self.at(TokenKind::Eof) {
+                break;
+            }
+
+            let start = self.peek().span;
+            match self.parse_item() {
+                Ok(item) => items.push(item),
+                Err(err) => {
+                    self.report(err);
+                    self.synchronize();
+
+                    // There is no block to close at the top level, so a
+                    // stray `}` is skipped too
+                    if self.at(TokenKind::RBrace) {
+                        self.bump();
+                    }
+
+                    let span = self.span_from(start);
+                    items.push(Item::new(ItemKind::Error, span));
+                }
+            }
+        }
+
+        Module { attrs, items }
+    }
+
+    fn parse_item(&mut self) -> PResult<Item> {
+        let attrs = self.parse_outer_attrs()?;
+        let start = self.peek().span;
+
+        // Only declarations can be used from other modules, so nothing else
+        // can be made `pub`
+        let public = self.eat(TokenKind::Pub).is_some();
+        if public {
+            let decls = [
+                TokenKind::Func,
+                TokenKind::Struct,
+                TokenKind::Enum,
+                TokenKind::Trait,
+            ];
+            if !decls.contains(&self.peek_kind()) {
+                self.expect_one_of(&decls)?;
+            }
+        }
+
+        let kind = match self.peek_kind() {
+            TokenKind::Func => ItemKind::Func(Func {
+                public,
+                ..self.parse_func()?
+            }),
+            TokenKind::Struct => ItemKind::Struct(Struct {
+                public,
+                ..self.parse_struct()?
+            }),
+            TokenKind::Enum => ItemKind::Enum(Enum {
+                public,
+                ..self.parse_enum()?
+            }),
+            TokenKind::Trait => ItemKind::Trait(Trait {
+                public,
+                ..self.parse_trait()?
+            }),
+            TokenKind::Impl => ItemKind::Impl(self.parse_impl()?),
+            TokenKind::Macro => ItemKind::Macro(self.parse_macro()?),
+            TokenKind::Let => {
+                let stmt = self.parse_let()?;
+                self.expect_terminator()?;
+                ItemKind::Let(stmt)
+            }
+            TokenKind::Import => {
+                let import = self.parse_import()?;
+                self.expect_terminator()?;
+                ItemKind::Import(import)
+            }
+            kind => return Err(self.error(ParseError::ExpectedItem(kind))),
+        };
+
+        Ok(Item {
+            attrs,
+            data: kind,
+            span: self.span_from(start),
+        })
+    }
+
+    /// Parse a function declaration, either with a return type:
+    ///
+    /// `func add(a: usize, b: usize) : usize = { a + b }`
+    ///
+    /// or without one:
+    ///
+    /// `func main() := { }`
+    pub fn parse_func(&mut self) -> PResult<Func> {
+        self.expect(TokenKind::Func)?;
+        let name = self.expect_ident()?;
+        let generics = self.parse_type_params();
+
+        self.expect(TokenKind::LPar)?;
+        let params = self.parse_list(TokenKind::RPar, Self::parse_param);
+
+        let ret = self.parse_annotation()?;
+        let body = self.parse_block()?;
+
+        Ok(Func {
+            public: false,
+            name,
+            generics,
+            params,
+            ret,
+            body,
+        })
+    }
+
+    /// Parse an import of another module, like `import shapes::circle`.
+    pub fn parse_import(&mut self) -> PResult<Import> {
+        self.expect(TokenKind::Import)?;
+        let name = self.expect_ident()?;
+        let path = self.parse_path(name)?;
+
+        Ok(Import { path })
+    }
+
+    /// Consume the end of an item which isn't closed by a `}`.
+    pub fn expect_terminator(&mut self) -> PResult<()> {
+        self.expect_one_of(&[
+            TokenKind::Semi,
+            TokenKind::VirtualSemi,
+            TokenKind::Eof,
+        ])?;
+        Ok(())
+    }
+
+    pub fn parse_param(&mut self) -> PResult<Param> {
+        let name = self.expect_ident()?;
+
+        // The type of a method's `self` can be left off
+        if name.data == "self" && !self.at(TokenKind::Colon) {
+            let ty = Spanned::new(TyKind::Named("Self".into()), name.span);
+            let span = name.span;
+            return Ok(Spanned::new(ParamKind { name, ty }, span));
+        }
+
+        self.expect(TokenKind::Colon)?;
+        let ty = self.parse_type()?;
+
+        let span = name.span.union(ty.span);
+        Ok(Spanned::new(ParamKind { name, ty }, span))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec::Vec};
+
+    use super::*;
+    use crate::{
+        ast::TyKind,
+        lexer::{position::Span, state::Lexer},
+    };
+
+    /// Parse a module, only giving it back if there were no errors.
+    fn parse(input: &str) -> Result<Module, Vec<Spanned<ParseError>>> {
+        let mut parser = Parser::new(Lexer::new(input));
+        let module = parser.parse_module();
+
+        match parser.errors.is_empty() {
+            true => Ok(module),
+            false => Err(parser.errors),
+        }
+    }
+
+    fn parse_with_errors(input: &str) -> (Module, Vec<ParseError>) {
+        let mut parser = Parser::new(Lexer::new(input));
+        let module = parser.parse_module();
+        let errors = parser.errors.into_iter().map(|err| err.data).collect();
+
+        (module, errors)
+    }
+
+    #[test]
+    fn test_func_with_return_type() {
+        let module =
+            parse("func add(a: usize, b: usize) : usize = { a }").unwrap();
+
+        let ItemKind::Func(func) = &module.items[0].data else {
+            panic!("expected a function");
+        };
+        assert_eq!(func.name.data, "add");
+        assert_eq!(func.params.len(), 2);
+        assert_eq!(func.params[1].data.name.data, "b");
+        assert_eq!(
+            func.ret.as_ref().map(|ty| &ty.data),
+            Some(&TyKind::Named("usize".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_func_without_return_type() {
+        let module = parse("func main() := {\n\n}").unwrap();
+
+        let ItemKind::Func(func) = &module.items[0].data else {
+            panic!("expected a function");
+        };
+        assert!(func.params.is_empty());
+        assert_eq!(func.ret, None);
+        assert_eq!(module.items[0].span, Span::new(0, 19));
+    }
+
+    #[test]
+    fn test_trailing_comma_in_params() {
+        let module = parse("func f(a: u8,) := {}").unwrap();
+        assert_eq!(module.items.len(), 1);
+    }
+
+    #[test]
+    fn test_mut_param() {
+        let module = parse("func f(a : mut usize) := {}").unwrap();
+
+        let ItemKind::Func(func) = &module.items[0].data else {
+            panic!("expected a function");
+        };
+        assert!(matches!(func.params[0].data.ty.data, TyKind::Mut(_)));
+    }
+
+    #[test]
+    fn test_unclosed_param_list() {
+        let err =
+            &parse("func addOne(a : usize =) : usize = {}").unwrap_err()[0];
+
+        assert_eq!(
+            err.data,
+            ParseError::Expected {
+                expected: vec![TokenKind::RPar, TokenKind::Comma],
+                found: TokenKind::Eq,
+            }
+        );
+        assert_eq!(err.data.to_string(), "expected one of `)`, `,` found `=`");
+    }
+
+    #[test]
+    fn test_items_on_separate_lines() {
+        let module = parse("let a := 1\nfunc f() := {}\nlet b := 2").unwrap();
+        assert_eq!(module.items.len(), 3);
+    }
+
+    #[test]
+    fn test_statement_at_top_level() {
+        let err = &parse("a + b").unwrap_err()[0];
+        assert_eq!(err.data, ParseError::ExpectedItem(TokenKind::Ident));
+    }
+
+    #[test]
+    fn test_imports() {
+        let module = parse("import shapes::circle\nimport lines;").unwrap();
+        assert_eq!(module.items.len(), 2);
+
+        let ItemKind::Import(import) = &module.items[0].data else {
+            panic!("expected an import");
+        };
+        assert_eq!(import.path.span, Span::new(7, 21));
+        assert_eq!(import.path.data[1].data, "circle");
+        assert_eq!(module.items[1].span, Span::new(22, 35));
+    }
+
+    #[test]
+    fn test_pub_items() {
+        let module = parse(
+            "pub func f() := {}\nfunc g() := {}\npub struct P {}\n\
+             pub enum E {}",
+        )
+        .unwrap();
+
+        let public: Vec<_> = module
+            .items
+            .iter()
+            .map(|item| match &item.data {
+                ItemKind::Func(func) => func.public,
+                ItemKind::Struct(decl) => decl.public,
+                ItemKind::Enum(decl) => decl.public,
+                _ => panic!("expected a declaration"),
+            })
+            .collect();
+        assert_eq!(public, vec![true, false, true, true]);
+
+        // The span of the item covers its `pub`
+        assert_eq!(module.items[0].span, Span::new(0, 18));
+    }
+
+    #[test]
+    fn test_only_declarations_can_be_pub() {
+        let err = &parse("pub let a := 1").unwrap_err()[0];
+        assert_eq!(
+            err.data,
+            ParseError::Expected {
+                expected: vec![
+                    TokenKind::Func,
+                    TokenKind::Struct,
+                    TokenKind::Enum,
+                    TokenKind::Trait,
+                ],
+                found: TokenKind::Let,
+            }
+        );
+
+        let err = &parse("pub import shapes").unwrap_err()[0];
+        assert!(matches!(
+            err.data,
+            ParseError::Expected {
+                found: TokenKind::Import,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_recovers_within_param_list() {
+        let (module, errors) =
+            parse_with_errors("func addOne(a : usize =, b: u8) : usize = {}");
+
+        assert_eq!(errors.len(), 1);
+        let ItemKind::Func(func) = &module.items[0].data else {
+            panic!("expected a function");
+        };
+        assert_eq!(func.params.len(), 2);
+        assert_eq!(
+            func.ret.as_ref().map(|ty| ty.span),
+            Some(Span::new(34, 39))
+        );
+    }
+
+    #[test]
+    fn test_missing_return_type() {
+        let (module, errors) =
+            parse_with_errors("func main() : = {\n    let a := 1\n}");
+
+        assert_eq!(errors, vec![ParseError::ExpectedType(TokenKind::Eq)]);
+        let ItemKind::Func(func) = &module.items[0].data else {
+            panic!("expected a function");
+        };
+        assert_eq!(func.ret.as_ref().map(|ty| &ty.data), Some(&TyKind::Error));
+        assert_eq!(func.body.data.stmts.len(), 1);
+    }
+
+    #[test]
+    fn test_error_items_skip_to_next_func() {
+        let (module, errors) = parse_with_errors(
+            "func a() := { }\n\
+             func b(, ) : u8 = { }\n\
+             }\n\
+             ) ( let\n\
+             func c() := { }",
+        );
+
+        assert_eq!(
+            errors,
+            vec![
+                ParseError::Expected {
+                    expected: vec![TokenKind::RPar, TokenKind::Ident],
+                    found: TokenKind::Comma,
+                },
+                ParseError::ExpectedItem(TokenKind::RBrace),
+                ParseError::ExpectedItem(TokenKind::RPar),
+            ]
+        );
+
+        let kinds: Vec<_> = module
+            .items
+            .iter()
+            .map(|item| matches!(item.data, ItemKind::Error))
+            .collect();
+        assert_eq!(kinds, vec![false, false, true, true, false]);
+    }
+}
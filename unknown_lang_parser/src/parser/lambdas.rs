@@ -0,0 +1,175 @@
+//! Handles parsing lambdas, like `|a, b: usize| a + b`.
+
+use alloc::{boxed::Box, vec};
+
+use crate::{
+    ast::{ExprKind, LambdaParam, LambdaParamKind},
+    lexer::{kind::TokenKind, position::Spanned},
+};
+
+use super::{state::Parser, PResult};
+
+impl Parser {
+    /// Parse a lambda, starting from its `|`. `||` is a lambda without any
+    /// parameters. The body takes everything up to the end of the expression,
+    /// so `|x| x * 2` is a lambda returning `x * 2`. When the return type is
+    /// given after `->`, the body has to be a block.
+    pub fn parse_lambda(&mut self) -> PResult<ExprKind> {
+        let params = if self.eat(TokenKind::BarBar).is_some() {
+            vec![]
+        } else {
+            self.expect(TokenKind::Bar)?;
+            self.parse_list(TokenKind::Bar, Self::parse_lambda_param)
+        };
+
+        if self.eat(TokenKind::RightArrow).is_none() {
+            let body = Box::new(self.parse_expr()?);
+            return Ok(ExprKind::Lambda {
+                params,
+                ret: None,
+                body,
+            });
+        }
+
+        let ret = Some(self.parse_type()?);
+        let block = self.parse_block()?;
+        let span = block.span;
+
+        Ok(ExprKind::Lambda {
+            params,
+            ret,
+            body: Box::new(Spanned::new(ExprKind::Block(block), span)),
+        })
+    }
+
+    /// Parse a single parameter of a lambda, like `a` or `b: mut usize`.
+    fn parse_lambda_param(&mut self) -> PResult<LambdaParam> {
+        let name = self.expect_ident()?;
+        let ty = match self.eat(TokenKind::Colon) {
+            Some(_) => Some(self.parse_type()?),
+            None => None,
+        };
+
+        let span = self.span_from(name.span);
+        Ok(Spanned::new(LambdaParamKind { name, ty }, span))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::{
+        ast::{print::sexpr, BinOp, Expr, TyKind},
+        lexer::{position::Span, state::Lexer},
+        parser::{errors::ParseError, parse},
+    };
+
+    /// Parse an expression, giving back the first error if there were any.
+    fn expr(input: &str) -> PResult<Expr> {
+        let mut parser = Parser::new(Lexer::new(input));
+        let result = parser.parse_expr();
+
+        match parser.errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => result,
+        }
+    }
+
+    #[test]
+    fn test_lambdas() {
+        let expr = expr("|a, b: mut usize| a + b").unwrap();
+        assert_eq!(expr.span, Span::new(0, 23));
+
+        let ExprKind::Lambda { params, ret, body } = &expr.data else {
+            panic!("expected a lambda");
+        };
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[0].data.ty, None);
+        assert_eq!(params[1].span, Span::new(4, 16));
+        assert!(matches!(
+            params[1].data.ty.as_ref().unwrap().data,
+            TyKind::Mut(_)
+        ));
+        assert_eq!(*ret, None);
+        assert!(matches!(body.data, ExprKind::Binary { op: BinOp::Add, .. }));
+    }
+
+    #[test]
+    fn test_lambdas_without_params() {
+        for input in ["|| 1", "| | 1", "|,| 1"] {
+            let result = expr(input);
+
+            match input {
+                "|,| 1" => assert!(result.is_err(), "{input}"),
+                _ => {
+                    let ExprKind::Lambda { params, .. } = result.unwrap().data
+                    else {
+                        panic!("expected a lambda for {input}");
+                    };
+                    assert!(params.is_empty(), "{input}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_return_types() {
+        let (module, errors) =
+            parse("let f := |x| -> usize { x }\nlet g := |x| -> usize x");
+        assert_eq!(
+            sexpr(&module),
+            "(module (let f (lambda (params x) usize (block x))) error)"
+        );
+
+        // The body has to be a block once the return type is given
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].data,
+            ParseError::Expected {
+                expected: vec![TokenKind::LBrace],
+                found: TokenKind::Ident,
+            }
+        );
+    }
+
+    #[test]
+    fn test_lambdas_take_the_rest_of_the_expression() {
+        let (module, errors) = parse(concat!(
+            "let a := addOne ** |x| x * 2\n",
+            "let b := f(|x| x, || g(1))\n",
+            "let c := match a { _ => |y| y }",
+        ));
+        assert!(errors.is_empty());
+        assert_eq!(
+            sexpr(&module),
+            "(module \
+             (let a (** addOne (lambda (params x) (* x 2)))) \
+             (let b (call f (lambda (params x) x) \
+             (lambda (params) (call g 1)))) \
+             (let c (match a (arm _ (lambda (params y) y)))))"
+        );
+    }
+
+    #[test]
+    fn test_returning_lambdas() {
+        let (module, errors) =
+            parse("func f() := {\n    return |x| |y| x + y\n}");
+        assert!(errors.is_empty());
+        assert_eq!(
+            sexpr(&module),
+            "(module (func f (params) (block (return \
+             (lambda (params x) (lambda (params y) (+ x y)))))))"
+        );
+    }
+
+    #[test]
+    fn test_broken_params() {
+        let err = expr("|a b| a").unwrap_err();
+        assert_eq!(
+            err.data.to_string(),
+            "expected one of `:`, `,`, `|` found identifier"
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Handles parsing macro declarations, and calls to macros which are still
+//! in the input. Calls are normally replaced by what they expand to before
+//! the file is parsed, see the [`macros`](crate::macros) module.
+
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    ast::{ExprKind, Ident, Macro, MacroRule, MacroRuleKind},
+    lexer::{
+        kind::TokenKind,
+        position::{Span, Spanned},
+        tokens::Token,
+    },
+};
+
+use super::{state::Parser, PResult};
+
+/// The keywords which start an item other than `let`, which ends a macro
+/// declaration that was never closed.
+const ITEM_KEYWORDS: &[TokenKind] = &[
+    TokenKind::Pub,
+    TokenKind::Func,
+    TokenKind::Struct,
+    TokenKind::Enum,
+    TokenKind::Trait,
+    TokenKind::Impl,
+    TokenKind::Macro,
+    TokenKind::Import,
+];
+
+impl Parser {
+    /// Parse a macro declaration, with its rules between braces:
+    ///
+    /// ```text
+    /// macro max {
+    ///     ($a:expr, $b:expr) => { if $a > $b { $a } else { $b } }
+    /// }
+    /// ```
+    ///
+    /// Rules are separated by line ends, `;` or `,`.
+    pub fn parse_macro(&mut self) -> PResult<Macro> {
+        self.expect(TokenKind::Macro)?;
+        let name = self.expect_ident()?;
+        self.expect(TokenKind::LBrace)?;
+
+        let mut rules = vec![];
+        loop {
+            while self.peek_kind().is_terminator() || self.at(TokenKind::Comma)
+            {
+                self.bump();
+            }
+            // A rule can't start with an item keyword, so the macro wasn't
+            // closed, and the items after it are left for the module
+            let kind = self.peek_kind();
+            if matches!(kind, TokenKind::RBrace | TokenKind::Eof)
+                || ITEM_KEYWORDS.contains(&kind)
+            {
+                break;
+            }
+
+            let start = self.position();
+            match self.parse_macro_rule() {
+                Ok(rule) => rules.push(rule),
+                Err(err) => {
+                    self.report(err);
+                    self.synchronize();
+
+                    if self.position() == start {
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.expect(TokenKind::RBrace)?;
+        Ok(Macro { name, rules })
+    }
+
+    /// Parse a single rule of a macro, like `($a:expr) => { $a + 1 }`.
+    fn parse_macro_rule(&mut self) -> PResult<MacroRule> {
+        let (matcher, start) = self.parse_token_group()?;
+        self.expect(TokenKind::FatArrow)?;
+        let (body, _) = self.parse_token_group()?;
+        let span = self.span_from(start);
+
+        if !self.at(TokenKind::RBrace) {
+            self.expect_one_of(&[
+                TokenKind::Semi,
+                TokenKind::VirtualSemi,
+                TokenKind::Comma,
+            ])?;
+        }
+
+        Ok(Spanned::new(MacroRuleKind { matcher, body }, span))
+    }
+
+    /// Parse a group of tokens between `()`, `[]` or `{}`, giving back the
+    /// tokens inside of it along with the span of the whole group. Every
+    /// group nested inside of it has to be closed by the matching delimiter.
+    pub fn parse_token_group(
+        &mut self,
+    ) -> PResult<(Vec<Spanned<Token>>, Span)> {
+        let open = self.expect_one_of(&[
+            TokenKind::LPar,
+            TokenKind::LBracket,
+            TokenKind::LBrace,
+        ])?;
+        let mut closers: Vec<_> =
+            open.data.kind().closing_delimiter().into_iter().collect();
+        let mut tokens = vec![];
+
+        loop {
+            let kind = self.peek_kind();
+            if let Some(close) = kind.closing_delimiter() {
+                closers.push(close);
+            } else if kind.is_closing_delimiter() || kind == TokenKind::Eof {
+                let close = closers.pop().unwrap_or(TokenKind::RPar);
+                if kind != close {
+                    self.expect(close)?;
+                }
+
+                if closers.is_empty() {
+                    self.bump();
+                    break;
+                }
+            }
+
+            tokens.push(self.bump());
+        }
+
+        Ok((tokens, self.span_from(open.span)))
+    }
+
+    /// Parse what comes after the name of a macro which wasn't expanded,
+    /// like the `!(a, b)` of `max!(a, b)`.
+    pub fn parse_macro_call(&mut self, name: Ident) -> PResult<ExprKind> {
+        self.expect(TokenKind::Bang)?;
+        let (args, _) = self.parse_token_group()?;
+
+        Ok(ExprKind::MacroCall { name, args })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{print::sexpr, ItemKind},
+        lexer::state::Lexer,
+        parser::{errors::ParseError, parse},
+    };
+
+    #[test]
+    fn test_macros() {
+        let mut parser = Parser::new(Lexer::new(concat!(
+            "macro pair {\n",
+            "    ($a:expr) => { ($a, $a) }\n",
+            "    ($a:expr, $b:expr) => { ($a, $b) },\n",
+            "}",
+        )));
+        let module = parser.parse_module();
+        assert!(parser.errors.is_empty(), "{:?}", parser.errors);
+
+        let ItemKind::Macro(decl) = &module.items[0].data else {
+            panic!("expected a macro");
+        };
+        assert_eq!(decl.name.data, "pair");
+        assert_eq!(decl.rules.len(), 2);
+        assert_eq!(decl.rules[0].span, Span::new(17, 42));
+        assert_eq!(decl.rules[1].data.matcher.len(), 9);
+        assert_eq!(decl.rules[1].data.body.len(), 7);
+    }
+
+    #[test]
+    fn test_unclosed_macros() {
+        let (module, errors) =
+            parse("macro p { ( ) => { 1 }\nfunc main() := {}");
+
+        assert_eq!(
+            errors.iter().map(|err| &err.data).collect::<Vec<_>>(),
+            vec![&ParseError::Expected {
+                expected: vec![TokenKind::RBrace],
+                found: TokenKind::Func,
+            }]
+        );
+        assert!(matches!(module.items[0].data, ItemKind::Error));
+        assert!(matches!(module.items[1].data, ItemKind::Func(_)));
+    }
+
+    #[test]
+    fn test_unexpanded_calls() {
+        let mut parser = Parser::new(Lexer::new("let a := max!(f(1), [2])"));
+        let module = parser.parse_module();
+
+        assert!(parser.errors.is_empty(), "{:?}", parser.errors);
+        assert_eq!(sexpr(&module), "(module (let a (max! f(1), [2])))");
+    }
+
+    #[test]
+    fn test_mismatched_delimiters() {
+        let mut parser = Parser::new(Lexer::new("let a := max!(f(1], 2)"));
+        parser.parse_module();
+
+        assert_eq!(
+            parser.errors[0].data,
+            ParseError::Expected {
+                expected: vec![TokenKind::RPar],
+                found: TokenKind::RBracket,
+            }
+        );
+        assert_eq!(parser.errors[0].span, Span::new(17, 18));
+    }
+}
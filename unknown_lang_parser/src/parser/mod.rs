@@ -0,0 +1,87 @@
+//! Turning tokens into an [`ast::Module`](crate::ast::Module).
+//!
+//! The parser is a hand written recursive descent parser. Like the lexer, the
+//! [`Parser`](state::Parser) struct lives in `state.rs`, and each kind of
+//! syntax adds its parsing functions to it from its own file.
+//!
+//! When something fails to parse, the error is recorded and the parser skips
+//! ahead to the end of the statement, a closing `}`, or the next `func`, and
+//! carries on from there. Whatever was skipped is left in the AST as an error
+//! node.
+
+pub mod attributes;
+pub mod control;
+pub mod enums;
+pub mod errors;
+pub mod expressions;
+pub mod generics;
+pub mod items;
+pub mod lambdas;
+pub mod macros;
+pub mod patterns;
+pub mod state;
+pub mod statements;
+pub mod structs;
+pub mod traits;
+pub mod types;
+
+use alloc::vec::Vec;
+
+use crate::{
+    ast::Module,
+    lexer::{
+        errors::LexingError, position::Spanned, state::Lexer, tokens::Token,
+    },
+    macros::expand,
+};
+
+use self::{
+    errors::ParseError,
+    state::{lex, Parser},
+};
+
+type PResult<T> = Result<T, Spanned<ParseError>>;
+
+/// Parse a whole file, after expanding its macros. The parser recovers from
+/// errors, so this always gives back a module, with error nodes in place of
+/// anything that failed to parse, along with every error that was found.
+pub fn parse(input: &str) -> (Module, Vec<Spanned<ParseError>>) {
+    let (tokens, lex_error) = lex(Lexer::new(input));
+    parse_tokens(tokens, lex_error)
+}
+
+/// Parse a file `len` bytes long like [`parse`], from the `tokens` that
+/// [`lex_all`](crate::lexer::lex_all) already found in it, so that it isn't
+/// lexed again.
+pub fn parse_lexed(
+    tokens: Vec<Spanned<Token>>,
+    len: usize,
+) -> (Module, Vec<Spanned<ParseError>>) {
+    let mut tokens: Vec<_> = tokens
+        .into_iter()
+        .filter(|tok| {
+            !matches!(tok.data, Token::Comment(_, _) | Token::Whitespace(_))
+        })
+        .collect();
+    tokens.push(Spanned::new_span(Token::Eof, len, len));
+
+    parse_tokens(tokens, None)
+}
+
+/// Parse the meaningful `tokens` of a file, which end with [`Token::Eof`]
+/// where lexing stopped.
+fn parse_tokens(
+    tokens: Vec<Spanned<Token>>,
+    lex_error: Option<Spanned<LexingError>>,
+) -> (Module, Vec<Spanned<ParseError>>) {
+    let expanded = expand(tokens);
+
+    let mut parser = Parser::from_expanded(expanded, lex_error);
+    let module = parser.parse_module();
+
+    // Errors from expanding macros come first, but may be anywhere
+    let mut errors = parser.errors;
+    errors.sort_by_key(|err| err.span.start.0);
+
+    (module, errors)
+}
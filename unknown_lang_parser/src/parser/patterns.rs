@@ -0,0 +1,384 @@
+//! Handles parsing the patterns in the arms of a `match`.
+
+use alloc::{boxed::Box, vec};
+
+use crate::{
+    ast::{FieldPat, FieldPatKind, Literal, Pat, PatKind, Path},
+    lexer::{kind::TokenKind, position::Spanned},
+};
+
+use super::{errors::ParseError, state::Parser, PResult};
+
+impl Parser {
+    /// Parse a pattern, like `0 | 1`, `(a, _)`, `Shape::Circle(r)` or
+    /// `n @ 1..=9`. A missing
+    /// pattern is reported, and left as [`PatKind::Error`] without consuming
+    /// anything.
+    pub fn parse_pattern(&mut self) -> PResult<Pat> {
+        let first = self.parse_single_pattern()?;
+        if !self.at(TokenKind::Bar) {
+            return Ok(first);
+        }
+
+        let mut alts = vec![first];
+        while self.eat(TokenKind::Bar).is_some() {
+            alts.push(self.parse_single_pattern()?);
+        }
+
+        let span = alts[0].span.union(alts[alts.len() - 1].span);
+        Ok(Spanned::new(PatKind::Or(alts), span))
+    }
+
+    /// Parse a pattern which isn't split up with `|`. An `@` binds tighter
+    /// than `|`, so `n @ 1 | 2` is `(n @ 1) | 2`.
+    fn parse_single_pattern(&mut self) -> PResult<Pat> {
+        self.nested(Self::parse_pattern_kind)
+    }
+
+    /// Parse a pattern, once [`Self::parse_single_pattern`] has checked how
+    /// deeply nested it is.
+    fn parse_pattern_kind(&mut self) -> PResult<Pat> {
+        let start = self.peek().span;
+
+        let kind = match self.peek_kind() {
+            TokenKind::Ident => self.parse_name_pattern()?,
+            TokenKind::LPar => self.parse_paren_pattern()?,
+            TokenKind::Minus => return self.parse_range_pattern(),
+            kind if kind.is_literal() => return self.parse_range_pattern(),
+            kind => {
+                let err = self.error(ParseError::ExpectedPattern(kind));
+                self.report(err);
+
+                let start = start.start.0;
+                return Ok(Spanned::new_span(PatKind::Error, start, start));
+            }
+        };
+
+        Ok(Spanned::new(kind, self.span_from(start)))
+    }
+
+    /// Parse a pattern starting with a name. This is a binding when it is
+    /// just the name, and otherwise a path to a struct or enum variant, which
+    /// may be followed by patterns for its fields.
+    fn parse_name_pattern(&mut self) -> PResult<PatKind> {
+        let name = self.expect_ident()?;
+
+        if name.data == "_" {
+            return Ok(PatKind::Wild);
+        } else if self.eat(TokenKind::At).is_some() {
+            let pat = Box::new(self.parse_single_pattern()?);
+            return Ok(PatKind::At { name, pat });
+        }
+
+        let mut path = self.parse_path(name)?;
+
+        match self.peek_kind() {
+            TokenKind::LPar => {
+                self.bump();
+                let elems =
+                    self.parse_list(TokenKind::RPar, Self::parse_pattern);
+                Ok(PatKind::TupleStruct { path, elems })
+            }
+            TokenKind::LBrace => self.parse_struct_pattern(path),
+            _ if path.data.len() > 1 => Ok(PatKind::Path(path.data)),
+            _ => Ok(PatKind::Binding(path.data.remove(0).data)),
+        }
+    }
+
+    /// Parse the fields of a struct pattern after its `path`, like
+    /// `{ w, h: 0.0, .. }`. The `..` allowing other fields has to come last.
+    fn parse_struct_pattern(&mut self, path: Path) -> PResult<PatKind> {
+        self.expect(TokenKind::LBrace)?;
+
+        let mut fields = vec![];
+        let mut rest = false;
+
+        while self.eat(TokenKind::RBrace).is_none() {
+            if self.eat(TokenKind::DotDot).is_some() {
+                rest = true;
+                self.expect(TokenKind::RBrace)?;
+                break;
+            }
+
+            fields.push(self.parse_field_pattern()?);
+
+            // Either another field, or the end of the pattern
+            if !self.at(TokenKind::RBrace) {
+                self.expect_one_of(&[TokenKind::RBrace, TokenKind::Comma])?;
+            }
+        }
+
+        Ok(PatKind::Struct { path, fields, rest })
+    }
+
+    /// Parse a field in a struct pattern, either `h: 0.0` or the shorthand
+    /// `w`.
+    fn parse_field_pattern(&mut self) -> PResult<FieldPat> {
+        let name = self.expect_ident()?;
+        let start = name.span;
+
+        let pat = match self.eat(TokenKind::Colon) {
+            Some(_) => Some(self.parse_pattern()?),
+            None => None,
+        };
+
+        Ok(Spanned::new(
+            FieldPatKind { name, pat },
+            self.span_from(start),
+        ))
+    }
+
+    /// Parse a literal pattern, which is the start of a range when it is
+    /// followed by `..` or `..=`.
+    fn parse_range_pattern(&mut self) -> PResult<Pat> {
+        let start = self.parse_literal_pattern()?;
+
+        let inclusive = match self.peek_kind() {
+            TokenKind::DotDot => false,
+            TokenKind::DotDotEq => true,
+            _ => return Ok(start),
+        };
+
+        self.bump();
+        let end = self.parse_literal_pattern()?;
+
+        let span = start.span.union(end.span);
+        Ok(Spanned::new(
+            PatKind::Range {
+                start: Box::new(start),
+                end: Box::new(end),
+                inclusive,
+            },
+            span,
+        ))
+    }
+
+    /// Parse a literal in a pattern, where a number may have a `-` in front
+    /// of it.
+    fn parse_literal_pattern(&mut self) -> PResult<Pat> {
+        let start = self.peek().span;
+        let negative = self.eat(TokenKind::Minus).is_some();
+
+        let kind = self.peek_kind();
+        if kind != TokenKind::Number && (negative || !kind.is_literal()) {
+            return Err(self.error(ParseError::ExpectedPattern(kind)));
+        }
+
+        let mut literal = self.parse_literal();
+        if let Literal::Number { negative: neg, .. } = &mut literal {
+            *neg = negative;
+        }
+
+        Ok(Spanned::new(
+            PatKind::Literal(literal),
+            self.span_from(start),
+        ))
+    }
+
+    /// Parse a pattern starting with `(`. Like types, this is a tuple when it
+    /// is empty or has a comma in it, and otherwise just a pattern in
+    /// parenthesis.
+    fn parse_paren_pattern(&mut self) -> PResult<PatKind> {
+        self.expect(TokenKind::LPar)?;
+
+        let mut elems = vec![];
+        let mut is_tuple = true;
+
+        while self.eat(TokenKind::RPar).is_none() {
+            elems.push(self.parse_pattern()?);
+
+            if self.eat(TokenKind::Comma).is_none() {
+                self.expect(TokenKind::RPar)?;
+                is_tuple = elems.len() != 1;
+                break;
+            }
+        }
+
+        match elems.pop() {
+            Some(inner) if !is_tuple => Ok(PatKind::Paren(Box::new(inner))),
+            inner => {
+                elems.extend(inner);
+                Ok(PatKind::Tuple(elems))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::lexer::{position::Span, state::Lexer};
+
+    /// Parse a pattern, failing if there were any errors.
+    fn pattern(input: &str) -> Pat {
+        let mut parser = Parser::new(Lexer::new(input));
+        let pat = parser.parse_pattern().unwrap();
+
+        assert_eq!(parser.errors, vec![], "{input}");
+        pat
+    }
+
+    fn number(base: u8, digits: &str, negative: bool) -> PatKind {
+        PatKind::Literal(Literal::Number {
+            base,
+            digits: digits.to_string(),
+            negative,
+        })
+    }
+
+    #[test]
+    fn test_literals_in_any_base() {
+        assert_eq!(pattern("0x1f").data, number(16, "1f", false));
+        assert_eq!(pattern("0(3)12").data, number(3, "12", false));
+        assert_eq!(pattern("'a'").data, PatKind::Literal(Literal::Char('a')));
+
+        let pat = pattern("-0b11");
+        assert_eq!(pat.data, number(2, "11", true));
+        assert_eq!(pat.span, Span::new(0, 5));
+    }
+
+    #[test]
+    fn test_wildcards_and_bindings() {
+        assert_eq!(pattern("_").data, PatKind::Wild);
+        assert_eq!(pattern("_a").data, PatKind::Binding("_a".into()));
+    }
+
+    #[test]
+    fn test_ranges() {
+        let pat = pattern("'a'..='z'");
+        let PatKind::Range {
+            start,
+            end,
+            inclusive,
+        } = pat.data
+        else {
+            panic!("expected a range");
+        };
+        assert!(inclusive);
+        assert_eq!(start.data, PatKind::Literal(Literal::Char('a')));
+        assert_eq!(end.span, Span::new(6, 9));
+
+        assert!(matches!(
+            pattern("-10..0").data,
+            PatKind::Range {
+                inclusive: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_range_ends_must_be_literals() {
+        let mut parser = Parser::new(Lexer::new("0..n"));
+        let err = parser.parse_pattern().unwrap_err();
+
+        assert_eq!(err.data, ParseError::ExpectedPattern(TokenKind::Ident));
+        assert_eq!(err.span, Span::new(3, 4));
+    }
+
+    #[test]
+    fn test_tuples_and_parens() {
+        let PatKind::Tuple(elems) = pattern("(a, (b,), (c))").data else {
+            panic!("expected a tuple");
+        };
+        assert_eq!(elems.len(), 3);
+        assert!(
+            matches!(&elems[1].data, PatKind::Tuple(inner) if inner.len() == 1)
+        );
+        assert!(matches!(elems[2].data, PatKind::Paren(_)));
+
+        assert_eq!(pattern("()").data, PatKind::Tuple(vec![]));
+    }
+
+    #[test]
+    fn test_or_patterns() {
+        let pat = pattern("0 | 1 | n @ (2 | 3)");
+        assert_eq!(pat.span, Span::new(0, 19));
+
+        let PatKind::Or(alts) = pat.data else {
+            panic!("expected an or-pattern");
+        };
+        assert_eq!(alts.len(), 3);
+
+        let PatKind::At { name, pat } = &alts[2].data else {
+            panic!("expected an @ binding");
+        };
+        assert_eq!(name.data, "n");
+        assert!(matches!(pat.data, PatKind::Paren(_)));
+    }
+
+    #[test]
+    fn test_at_binds_tighter_than_or() {
+        let PatKind::Or(alts) = pattern("n @ 1 | 2").data else {
+            panic!("expected an or-pattern");
+        };
+        assert!(matches!(alts[0].data, PatKind::At { .. }));
+        assert_eq!(alts[1].data, number(10, "2", false));
+    }
+
+    #[test]
+    fn test_variant_patterns() {
+        let pat = pattern("Shape::Circle(r)");
+        assert_eq!(pat.span, Span::new(0, 16));
+
+        let PatKind::TupleStruct { path, elems } = pat.data else {
+            panic!("expected a tuple variant");
+        };
+        assert_eq!(path.span, Span::new(0, 13));
+        assert_eq!(elems[0].data, PatKind::Binding("r".into()));
+
+        let PatKind::Path(segments) = pattern("Shape::Empty").data else {
+            panic!("expected a path");
+        };
+        assert_eq!(segments[1].data, "Empty");
+    }
+
+    #[test]
+    fn test_struct_patterns() {
+        let pat = pattern("Shape::Rect { w, h: 0 | 1, .. }");
+
+        let PatKind::Struct { fields, rest, .. } = pat.data else {
+            panic!("expected a struct pattern");
+        };
+        assert!(rest);
+        assert_eq!(fields[0].data.pat, None);
+        assert_eq!(fields[1].span, Span::new(17, 25));
+        assert!(matches!(
+            fields[1].data.pat.as_ref().unwrap().data,
+            PatKind::Or(_)
+        ));
+
+        assert!(matches!(
+            pattern("Point {}").data,
+            PatKind::Struct { rest: false, .. }
+        ));
+    }
+
+    #[test]
+    fn test_rest_has_to_come_last() {
+        let mut parser = Parser::new(Lexer::new("Point { .., x }"));
+        let err = parser.parse_pattern().unwrap_err();
+
+        assert_eq!(
+            err.data,
+            ParseError::Expected {
+                expected: vec![TokenKind::RBrace],
+                found: TokenKind::Comma,
+            }
+        );
+    }
+
+    #[test]
+    fn test_missing_pattern() {
+        let mut parser = Parser::new(Lexer::new("=> a"));
+        let pat = parser.parse_pattern().unwrap();
+
+        assert_eq!(pat.data, PatKind::Error);
+        assert_eq!(
+            parser.errors[0].data,
+            ParseError::ExpectedPattern(TokenKind::FatArrow)
+        );
+    }
+}
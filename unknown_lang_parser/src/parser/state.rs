@@ -0,0 +1,530 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    lexer::{
+        errors::LexingError,
+        kind::TokenKind,
+        position::{Span, Spanned},
+        state::Lexer,
+        tokens::Token,
+    },
+    macros::{self, Expanded, Expansion},
+};
+
+use super::{errors::ParseError, PResult};
+
+/// How deeply expressions, types and patterns can be nested in each other.
+/// Each level is parsed by recursing, so without a limit deeply nested input
+/// would overflow the stack.
+pub const MAX_DEPTH: usize = 64;
+
+#[derive(Debug)]
+pub struct Parser {
+    /// Every meaningful token in the input, always ending with
+    /// [`Token::Eof`]
+    tokens: Vec<Spanned<Token>>,
+    /// Whether each token starts right where the one before it ended. This
+    /// is kept apart from the spans, since the tokens that a macro expands
+    /// into all have the span of its call.
+    joined: Vec<bool>,
+    /// The macro expansion that each token came from, if any, as an index
+    /// into `expansions`
+    origins: Vec<Option<usize>>,
+    expansions: Vec<Expansion>,
+    /// Index of the current token
+    pos: usize,
+    /// Where the last consumed token ended
+    prev_end: usize,
+    /// If lexing failed, the tokens stop where the error was found, and this
+    /// is reported once the parser gets there.
+    lex_error: Option<Spanned<LexingError>>,
+    /// Every kind of token that was checked for since the last token was
+    /// consumed, to list in the error if none of them are found
+    expected: Vec<TokenKind>,
+    /// Set while parsing an expression which is followed by a block, like the
+    /// condition of an `if`, where `a {` starts the block rather than a
+    /// struct literal
+    no_struct_literals: bool,
+    /// How many expressions, types and patterns the current one is nested in
+    depth: usize,
+    /// The errors found so far, which the parser has recovered from
+    pub errors: Vec<Spanned<ParseError>>,
+}
+
+impl Parser {
+    /// Create a parser over all of the tokens from the `lexer`. Comments and
+    /// whitespace are dropped, since they don't have any meaning to the
+    /// parser.
+    pub fn new(lexer: Lexer) -> Self {
+        let (tokens, lex_error) = lex(lexer);
+        let joined = joined(&tokens);
+
+        Self {
+            lex_error,
+            ..Self::from_tokens(tokens, joined)
+        }
+    }
+
+    /// Create a parser over `tokens`, which have to end with [`Token::Eof`],
+    /// with `joined` saying which of them start right where the one before
+    /// ended.
+    pub fn from_tokens(tokens: Vec<Spanned<Token>>, joined: Vec<bool>) -> Self {
+        let origins = vec![None; tokens.len()];
+
+        Self {
+            tokens,
+            joined,
+            origins,
+            expansions: vec![],
+            pos: 0,
+            prev_end: 0,
+            lex_error: None,
+            expected: vec![],
+            no_struct_literals: false,
+            depth: 0,
+            errors: vec![],
+        }
+    }
+
+    /// Create a parser over a file with its macros expanded. Errors found
+    /// while expanding are kept, and errors in the tokens that a macro
+    /// expanded into list the macros they came from.
+    pub fn from_expanded(
+        expanded: Expanded,
+        lex_error: Option<Spanned<LexingError>>,
+    ) -> Self {
+        Self {
+            origins: expanded.origins,
+            expansions: expanded.expansions,
+            lex_error,
+            errors: expanded.errors,
+            ..Self::from_tokens(expanded.tokens, expanded.joined)
+        }
+    }
+
+    /// Get the index of the current token.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Get the current token.
+    pub fn peek(&self) -> &Spanned<Token> {
+        &self.tokens[self.pos]
+    }
+
+    /// Get the kind of the current token.
+    pub fn peek_kind(&self) -> TokenKind {
+        self.peek().data.kind()
+    }
+
+    /// Get the kind of the token `n` tokens after the current one.
+    pub fn nth_kind(&self, n: usize) -> TokenKind {
+        let idx = (self.pos + n).min(self.tokens.len() - 1);
+        self.tokens[idx].data.kind()
+    }
+
+    /// Checks if the current token is of the given kind.
+    pub fn at(&self, kind: TokenKind) -> bool {
+        self.peek_kind() == kind
+    }
+
+    /// Move on to the next token, returning the current one. This never moves
+    /// past the end of file token.
+    pub fn bump(&mut self) -> Spanned<Token> {
+        let tok = self.peek().clone();
+
+        if tok.data != Token::Eof {
+            self.pos += 1;
+            self.prev_end = tok.span.end.0;
+            self.expected.clear();
+        }
+
+        tok
+    }
+
+    /// Consume the current token if it is of the given kind. Otherwise the
+    /// kind is remembered, in case an error is reported for this token.
+    pub fn eat(&mut self, kind: TokenKind) -> Option<Spanned<Token>> {
+        if self.at(kind) {
+            return Some(self.bump());
+        }
+
+        self.expected.push(kind);
+        None
+    }
+
+    /// Like [`eat`](Self::eat), but also takes the first character of a
+    /// token which starts with `kind`, like the first `>` of the `>>` closing
+    /// `Option<Option<T>>`. The rest of the token is left as the current one.
+    pub fn eat_split(&mut self, kind: TokenKind) -> Option<Span> {
+        let rest = match (kind, &self.peek().data) {
+            (TokenKind::Greater, Token::GreaterGreater) => Token::Greater,
+            (TokenKind::Greater, Token::GreaterEq) => Token::Eq,
+            (TokenKind::Ampersand, Token::AmpAmp) => Token::Ampersand,
+            _ => return self.eat(kind).map(|tok| tok.span),
+        };
+
+        let tok = &mut self.tokens[self.pos];
+        let split = tok.span.start.0 + 1;
+        let span = Span::new(tok.span.start.0, split);
+        *tok = Spanned::new_span(rest, split, tok.span.end.0);
+        self.joined[self.pos] = true;
+
+        self.prev_end = split;
+        self.expected.clear();
+
+        Some(span)
+    }
+
+    /// Like [`expect`](Self::expect), but splits tokens the same way as
+    /// [`eat_split`](Self::eat_split).
+    pub fn expect_split(&mut self, kind: TokenKind) -> PResult<Span> {
+        match self.eat_split(kind) {
+            Some(span) => Ok(span),
+            None => self.expect(kind).map(|tok| tok.span),
+        }
+    }
+
+    /// Consume the current token, which has to be of the given kind.
+    pub fn expect(&mut self, kind: TokenKind) -> PResult<Spanned<Token>> {
+        self.expect_one_of(&[kind])
+    }
+
+    /// Consume the current token, which has to be one of the given kinds.
+    pub fn expect_one_of(
+        &mut self,
+        kinds: &[TokenKind],
+    ) -> PResult<Spanned<Token>> {
+        if kinds.contains(&self.peek_kind()) {
+            return Ok(self.bump());
+        }
+
+        // Include everything else that could have come here, so the error
+        // lists all of the options
+        let mut expected = self.expected.clone();
+        expected.extend_from_slice(kinds);
+        expected.sort();
+        expected.dedup();
+
+        Err(self.error(ParseError::Expected {
+            expected,
+            found: self.peek_kind(),
+        }))
+    }
+
+    /// Checks if a name followed by `{` can start a struct literal here.
+    pub fn struct_literals_allowed(&self) -> bool {
+        !self.no_struct_literals
+    }
+
+    /// Run `parse` with struct literals allowed or not, putting back what was
+    /// allowed before once it is done. They are allowed again within any
+    /// delimiters, like in `if (Point { x: 1 }).x == 1 { }`.
+    pub fn with_struct_literals<T>(
+        &mut self,
+        allowed: bool,
+        parse: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let outer = core::mem::replace(&mut self.no_struct_literals, !allowed);
+        let result = parse(self);
+        self.no_struct_literals = outer;
+
+        result
+    }
+
+    /// Run `parse` one level deeper, failing instead if that is more than
+    /// [`MAX_DEPTH`] levels.
+    pub fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> PResult<T>,
+    ) -> PResult<T> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error(ParseError::TooDeep));
+        }
+
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+
+        result
+    }
+
+    /// Skip over any statement terminators.
+    pub fn skip_terminators(&mut self) {
+        while self.peek_kind().is_terminator() {
+            self.bump();
+        }
+    }
+
+    /// Checks if the current token starts right where the last one ended,
+    /// like the `=` in `:=`.
+    pub fn is_joined(&self) -> bool {
+        self.joined[self.pos]
+    }
+
+    /// Get the span from the start of `start` to the end of the last
+    /// consumed token.
+    pub fn span_from(&self, start: Span) -> Span {
+        Span::new(start.start.0, self.prev_end.max(start.start.0))
+    }
+
+    /// Create an error at the current token. If the input stopped early
+    /// because it failed to lex, that error is reported instead. When the
+    /// token came from a macro, or is right after the end of what a macro
+    /// expanded into, the error lists the macros it was expanded from.
+    pub fn error(&self, err: ParseError) -> Spanned<ParseError> {
+        if self.at(TokenKind::Eof) {
+            if let Some(lex_err) = &self.lex_error {
+                return Spanned::new(lex_err.data.clone().into(), lex_err.span);
+            }
+        }
+
+        // A body like `a +` is only found to be broken at the token after it
+        let origin = self.origins[self.pos].or_else(|| {
+            let prev = self.pos.checked_sub(1)?;
+            self.origins[prev]
+        });
+        let trace = macros::trace(&self.expansions, origin);
+        Spanned::new(err.in_macro(trace), self.peek().span)
+    }
+
+    /// Record an error and carry on parsing. An error at the same place as
+    /// the last one is usually caused by it, so it is left out.
+    pub fn report(&mut self, err: Spanned<ParseError>) {
+        if let Some(last) = self.errors.last() {
+            if last.span.start == err.span.start {
+                return;
+            }
+        }
+
+        self.errors.push(err);
+    }
+
+    /// Skip ahead to where parsing can carry on after an error. This is just
+    /// past the end of the statement, right before a `}` closing the current
+    /// block, or right before the next `func`. Any blocks opened along the
+    /// way are skipped over entirely.
+    pub fn synchronize(&mut self) {
+        let mut depth = 0;
+
+        loop {
+            match self.peek_kind() {
+                TokenKind::Eof | TokenKind::Func => return,
+                TokenKind::RBrace if depth == 0 => return,
+                TokenKind::RBrace => depth -= 1,
+                TokenKind::LBrace => depth += 1,
+                kind if kind.is_terminator() && depth == 0 => {
+                    self.bump();
+                    return;
+                }
+                _ => {}
+            }
+
+            self.bump();
+        }
+    }
+
+    /// Skip the rest of a broken item in a comma separated list, which ends
+    /// with `close`. Returns true if there is another item in the list, and
+    /// false once the list is over, either because `close` was consumed or
+    /// because the list was never closed.
+    pub fn skip_list_item(&mut self, close: TokenKind) -> bool {
+        let mut depth = 0;
+
+        loop {
+            match self.peek_kind() {
+                TokenKind::Comma if depth == 0 => {
+                    self.bump();
+                    return true;
+                }
+                kind if kind == close && depth == 0 => {
+                    self.bump();
+                    return false;
+                }
+                TokenKind::LPar | TokenKind::LBracket => depth += 1,
+                TokenKind::RPar | TokenKind::RBracket if depth > 0 => {
+                    depth -= 1
+                }
+                // The list can't carry on past any of these
+                TokenKind::LBrace
+                | TokenKind::RBrace
+                | TokenKind::Func
+                | TokenKind::Eof => return false,
+                kind if kind.is_terminator() => return false,
+                _ => {}
+            }
+
+            self.bump();
+        }
+    }
+
+    /// Parse a comma separated list of items, which may end with a trailing
+    /// comma, up to and including the `close` token. The opening token has to
+    /// be consumed already. Broken items are reported and left out.
+    pub fn parse_list<T>(
+        &mut self,
+        close: TokenKind,
+        mut parse_item: impl FnMut(&mut Self) -> PResult<T>,
+    ) -> Vec<T> {
+        let mut items = vec![];
+
+        while self.eat(close).is_none() {
+            let result = parse_item(self).and_then(|item| {
+                items.push(item);
+
+                // Either another item, or the end of the list
+                if !self.at(close) {
+                    self.expect_one_of(&[close, TokenKind::Comma])?;
+                }
+
+                Ok(())
+            });
+
+            if let Err(err) = result {
+                self.report(err);
+
+                if !self.skip_list_item(close) {
+                    break;
+                }
+            }
+        }
+
+        items
+    }
+}
+
+/// Lex all of the meaningful tokens from the `lexer`, always ending with
+/// [`Token::Eof`]. Comments and whitespace are dropped, since they don't have
+/// any meaning to the parser. If lexing fails, the tokens stop where the
+/// error was found, and the error is given back with them.
+pub fn lex(
+    mut lexer: Lexer,
+) -> (Vec<Spanned<Token>>, Option<Spanned<LexingError>>) {
+    let mut tokens = vec![];
+
+    loop {
+        match lexer.lex_spanned() {
+            Ok(tok) if tok.data == Token::Eof => {
+                tokens.push(tok);
+                return (tokens, None);
+            }
+            Ok(Spanned {
+                data: Token::Comment(_, _) | Token::Whitespace(_),
+                ..
+            }) => {}
+            Ok(tok) => tokens.push(tok),
+            Err(err) => {
+                let start = err.span.start.0;
+                tokens.push(Spanned::new_span(Token::Eof, start, start));
+                return (tokens, Some(err));
+            }
+        }
+    }
+}
+
+/// Check which of `tokens` start right where the one before them ended, like
+/// the `=` in `:=`. The first token counts as joined if it starts the input.
+pub fn joined(tokens: &[Spanned<Token>]) -> Vec<bool> {
+    let mut prev_end = 0;
+
+    tokens
+        .iter()
+        .map(|tok| {
+            let joined = tok.span.start.0 == prev_end;
+            prev_end = tok.span.end.0;
+            joined
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comments_are_skipped() {
+        let parser = Parser::new(Lexer::new("/* a */ b // c"));
+
+        assert_eq!(parser.tokens.len(), 2);
+        assert_eq!(parser.peek_kind(), TokenKind::Ident);
+    }
+
+    #[test]
+    fn test_bump_stops_at_eof() {
+        let mut parser = Parser::new(Lexer::new("a"));
+
+        assert_eq!(parser.bump().data.kind(), TokenKind::Ident);
+        assert_eq!(parser.bump().data, Token::Eof);
+        assert_eq!(parser.bump().data, Token::Eof);
+    }
+
+    #[test]
+    fn test_expected_set_includes_eaten_kinds() {
+        let mut parser = Parser::new(Lexer::new("a"));
+
+        assert!(parser.eat(TokenKind::Comma).is_none());
+        let err = parser.expect(TokenKind::RPar).unwrap_err();
+        assert_eq!(
+            err.data,
+            ParseError::Expected {
+                expected: vec![TokenKind::RPar, TokenKind::Comma],
+                found: TokenKind::Ident,
+            }
+        );
+
+        // Consuming a token starts a new set
+        parser.bump();
+        let err = parser.expect(TokenKind::Semi).unwrap_err();
+        assert_eq!(
+            err.data,
+            ParseError::Expected {
+                expected: vec![TokenKind::Semi],
+                found: TokenKind::Eof,
+            }
+        );
+    }
+
+    #[test]
+    fn test_synchronize_skips_blocks() {
+        let mut parser = Parser::new(Lexer::new(
+            "a { b; c }
+ d; e",
+        ));
+        parser.synchronize();
+
+        assert_eq!(parser.peek().data, Token::Ident("d".into()));
+    }
+
+    #[test]
+    fn test_synchronize_stops_at_func_and_brace() {
+        let mut parser = Parser::new(Lexer::new("a b } c"));
+        parser.synchronize();
+        assert_eq!(parser.peek().data, Token::RBrace);
+
+        let mut parser = Parser::new(Lexer::new("a ( b func c"));
+        parser.synchronize();
+        assert_eq!(parser.peek_kind(), TokenKind::Func);
+    }
+
+    #[test]
+    fn test_repeated_errors_are_reported_once() {
+        let mut parser = Parser::new(Lexer::new("a"));
+
+        let err = parser.expect(TokenKind::Semi).unwrap_err();
+        parser.report(err);
+        let err = parser.expect(TokenKind::Comma).unwrap_err();
+        parser.report(err);
+
+        assert_eq!(parser.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_lexing_errors_reported_at_end() {
+        let mut parser = Parser::new(Lexer::new("a \"unclosed"));
+        parser.bump();
+
+        let err = parser.expect(TokenKind::Semi).unwrap_err();
+        assert_eq!(err.data, ParseError::Lexing(LexingError::UnclosedString));
+        assert_eq!(err.span, Span::new(2, 11));
+    }
+}
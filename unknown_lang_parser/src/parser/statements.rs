@@ -0,0 +1,322 @@
+//! Handles parsing blocks and the statements within them.
+
+use alloc::{boxed::Box, vec};
+
+use crate::{
+    ast::{AssignOp, Block, BlockKind, Let, Stmt, StmtKind},
+    lexer::{kind::TokenKind, position::Spanned},
+};
+
+use super::{errors::ParseError, state::Parser, PResult};
+
+impl Parser {
+    /// Parse a block of statements, like `{ let a := 1; a + 1 }`. An
+    /// expression right before the closing `}` becomes the tail of the block.
+    /// Statements which fail to parse are reported, and left in the block as
+    /// [`StmtKind::Error`].
+    pub fn parse_block(&mut self) -> PResult<Block> {
+        self.with_struct_literals(true, Self::parse_block_contents)
+    }
+
+    fn parse_block_contents(&mut self) -> PResult<Block> {
+        let start = self.expect(TokenKind::LBrace)?.span;
+        let attrs = self.parse_inner_attrs();
+        let mut stmts = vec![];
+        let mut tail = None;
+
+        loop {
+            self.skip_terminators();
+            // A `func` can't be inside of a block, so this one was most
+            // likely left unclosed
+            if self.at(TokenKind::RBrace)
+                || self.at(TokenKind::Func)
+                || self.at(TokenKind::Eof)
+            {
+                break;
+            }
+
+            let stmt_start = self.peek().span;
+            let result = self.parse_stmt().and_then(|stmt| {
+                match stmt.data {
+                    StmtKind::Expr(expr) if self.at(TokenKind::RBrace) => {
+                        if let Some(attr) = stmt.attrs.first() {
+                            let err = ParseError::AttributesOnTail;
+                            self.report(Spanned::new(err, attr.span));
+                        }
+                        tail = Some(Box::new(expr));
+                    }
+                    _ => {
+                        self.expect_stmt_end()?;
+                        stmts.push(stmt);
+                    }
+                }
+
+                Ok(())
+            });
+
+            if let Err(err) = result {
+                self.report(err);
+                self.synchronize();
+
+                let span = self.span_from(stmt_start);
+                stmts.push(Stmt::new(StmtKind::Error, span));
+            }
+        }
+
+        if let Err(err) = self.expect(TokenKind::RBrace) {
+            self.report(err);
+        }
+
+        Ok(Spanned::new(
+            BlockKind { attrs, stmts, tail },
+            self.span_from(start),
+        ))
+    }
+
+    /// Parse a single statement, without its terminator.
+    pub fn parse_stmt(&mut self) -> PResult<Stmt> {
+        let attrs = self.parse_outer_attrs()?;
+        let start = self.peek().span;
+
+        let kind = match self.peek_kind() {
+            TokenKind::Let => StmtKind::Let(self.parse_let()?),
+            TokenKind::Return => {
+                self.bump();
+
+                let value = if self.peek_kind().can_start_expression() {
+                    Some(self.parse_expr()?)
+                } else {
+                    None
+                };
+
+                StmtKind::Return(value)
+            }
+            TokenKind::Break => self.parse_break()?,
+            TokenKind::Continue => self.parse_continue()?,
+            _ => {
+                let expr = self.parse_expr()?;
+
+                let op = match self.peek_kind() {
+                    TokenKind::Eq => AssignOp::Assign,
+                    TokenKind::PlusEq => AssignOp::AddAssign,
+                    _ => {
+                        let span = expr.span;
+                        return Ok(Stmt {
+                            attrs,
+                            data: StmtKind::Expr(expr),
+                            span,
+                        });
+                    }
+                };
+
+                self.bump();
+                let value = self.parse_expr()?;
+
+                StmtKind::Assign {
+                    target: expr,
+                    op,
+                    value,
+                }
+            }
+        };
+
+        Ok(Stmt {
+            attrs,
+            data: kind,
+            span: self.span_from(start),
+        })
+    }
+
+    /// Parse a variable declaration, like `let a := 1` or
+    /// `let a : usize = 1`.
+    pub fn parse_let(&mut self) -> PResult<Let> {
+        self.expect(TokenKind::Let)?;
+        let name = self.expect_ident()?;
+        let ty = self.parse_annotation()?;
+        let value = self.parse_expr()?;
+
+        Ok(Let { name, ty, value })
+    }
+
+    /// Check that a statement is finished. This is either a `;`, a newline
+    /// which was turned into one, or the end of the block.
+    fn expect_stmt_end(&mut self) -> PResult<()> {
+        if self.peek_kind().is_terminator() {
+            self.bump();
+            return Ok(());
+        }
+
+        if self.at(TokenKind::RBrace) {
+            return Ok(());
+        }
+
+        Err(self.error(ParseError::Expected {
+            expected: vec![
+                TokenKind::RBrace,
+                TokenKind::Semi,
+                TokenKind::VirtualSemi,
+            ],
+            found: self.peek_kind(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec::Vec};
+
+    use super::*;
+    use crate::{
+        ast::{ExprKind, TyKind},
+        lexer::state::Lexer,
+    };
+
+    /// Parse a block, only giving it back if there were no errors.
+    fn block(input: &str) -> Result<Block, Spanned<ParseError>> {
+        let (block, mut errors) = block_with_errors(input);
+
+        match errors.is_empty() {
+            true => Ok(block),
+            false => Err(errors.remove(0)),
+        }
+    }
+
+    fn block_with_errors(input: &str) -> (Block, Vec<Spanned<ParseError>>) {
+        let mut parser = Parser::new(Lexer::new(input));
+        let block = parser.parse_block().unwrap();
+
+        (block, parser.errors)
+    }
+
+    #[test]
+    fn test_block_with_tail() {
+        let block = block("{\n    a + b\n}").unwrap();
+
+        assert!(block.data.stmts.is_empty());
+        assert!(matches!(
+            block.data.tail.map(|tail| tail.data),
+            Some(ExprKind::Binary { .. })
+        ));
+    }
+
+    #[test]
+    fn test_statements_end_at_newlines() {
+        let block = block("{\n    let a := 1\n    let b : i32 = 2\n}").unwrap();
+
+        assert_eq!(block.data.stmts.len(), 2);
+        assert_eq!(block.data.tail, None);
+
+        let StmtKind::Let(stmt) = &block.data.stmts[1].data else {
+            panic!("expected a let statement");
+        };
+        assert_eq!(stmt.name.data, "b");
+        assert_eq!(
+            stmt.ty.as_ref().map(|ty| &ty.data),
+            Some(&TyKind::Named("i32".into()))
+        );
+    }
+
+    #[test]
+    fn test_return_and_assignments() {
+        let block = block("{ a += b; res = f(1); return a + 1; }").unwrap();
+        let stmts = &block.data.stmts;
+
+        assert!(matches!(
+            stmts[0].data,
+            StmtKind::Assign {
+                op: AssignOp::AddAssign,
+                ..
+            }
+        ));
+        assert!(matches!(
+            stmts[1].data,
+            StmtKind::Assign {
+                op: AssignOp::Assign,
+                ..
+            }
+        ));
+        assert!(matches!(stmts[2].data, StmtKind::Return(Some(_))));
+        assert_eq!(block.data.tail, None);
+    }
+
+    #[test]
+    fn test_bare_return() {
+        let block = block("{ return }").unwrap();
+        assert!(matches!(block.data.stmts[0].data, StmtKind::Return(None)));
+    }
+
+    #[test]
+    fn test_missing_terminator() {
+        let err = block("{ let a := 1 let b := 2 }").unwrap_err();
+
+        assert_eq!(
+            err.data.to_string(),
+            "expected one of `}`, `;`, newline found `let`"
+        );
+    }
+
+    #[test]
+    fn test_unclosed_block() {
+        let err = block("{ a;").unwrap_err();
+
+        assert_eq!(
+            err.data,
+            ParseError::Expected {
+                expected: vec![TokenKind::RBrace],
+                found: TokenKind::Eof,
+            }
+        );
+    }
+
+    #[test]
+    fn test_recovers_at_statement_boundaries() {
+        let (block, errors) = block_with_errors(
+            "{\n    let a := )\n    let b := 2;\n    c = ;\n    d\n}",
+        );
+
+        let errors: Vec<_> = errors.into_iter().map(|err| err.data).collect();
+        assert_eq!(
+            errors,
+            vec![
+                ParseError::ExpectedExpression(TokenKind::RPar),
+                ParseError::ExpectedExpression(TokenKind::Semi),
+            ]
+        );
+
+        // The first statement is skipped up to the newline, while the
+        // assignment keeps an error node in place of its value
+        let stmts = &block.data.stmts;
+        assert_eq!(stmts[0].data, StmtKind::Error);
+        assert!(matches!(stmts[1].data, StmtKind::Let(_)));
+        assert!(matches!(
+            &stmts[2].data,
+            StmtKind::Assign { value, .. } if value.data == ExprKind::Error
+        ));
+        assert!(block.data.tail.is_some());
+    }
+
+    #[test]
+    fn test_unclosed_block_before_func() {
+        let (block, errors) = block_with_errors("{\n    a\nfunc");
+
+        assert_eq!(block.data.tail, None);
+        assert_eq!(block.data.stmts.len(), 1);
+        assert_eq!(
+            errors[0].data,
+            ParseError::Expected {
+                expected: vec![TokenKind::RBrace],
+                found: TokenKind::Func,
+            }
+        );
+    }
+
+    #[test]
+    fn test_skips_nested_blocks() {
+        let (block, errors) = block_with_errors("{ a b { c; d }\n e; f }");
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(block.data.stmts.len(), 2);
+        assert_eq!(block.data.stmts[0].data, StmtKind::Error);
+        assert!(block.data.tail.is_some());
+    }
+}
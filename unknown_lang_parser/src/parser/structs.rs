@@ -0,0 +1,204 @@
+//! Handles parsing struct declarations and struct literals.
+
+use alloc::{boxed::Box, vec};
+
+use crate::{
+    ast::{ExprKind, Field, FieldInit, FieldInitKind, FieldKind, Path, Struct},
+    lexer::{kind::TokenKind, position::Spanned},
+};
+
+use super::{state::Parser, PResult};
+
+impl Parser {
+    /// Parse a struct declaration, like `struct Point { x: i32, y: i32 }`.
+    pub fn parse_struct(&mut self) -> PResult<Struct> {
+        self.expect(TokenKind::Struct)?;
+        let name = self.expect_ident()?;
+        let generics = self.parse_type_params();
+
+        self.expect(TokenKind::LBrace)?;
+        let fields = self.parse_list(TokenKind::RBrace, Self::parse_field_decl);
+
+        Ok(Struct {
+            public: false,
+            name,
+            generics,
+            fields,
+        })
+    }
+
+    /// Parse a single field of a struct declaration, or of an enum variant
+    /// holding values by name, like `x: i32`.
+    pub fn parse_field_decl(&mut self) -> PResult<Field> {
+        let name = self.expect_ident()?;
+        self.expect(TokenKind::Colon)?;
+        let ty = self.parse_type()?;
+
+        let span = name.span.union(ty.span);
+        Ok(Spanned::new(FieldKind { name, ty }, span))
+    }
+
+    /// Parse the fields of a struct literal after its `path`, like
+    /// `{ x: 1, y, ..base }`. The `..base` giving the rest of the fields has
+    /// to come last.
+    pub fn parse_struct_lit(&mut self, path: Path) -> PResult<ExprKind> {
+        self.expect(TokenKind::LBrace)?;
+
+        self.with_struct_literals(true, |p| {
+            let mut fields = vec![];
+            let mut base = None;
+
+            while p.eat(TokenKind::RBrace).is_none() {
+                if p.eat(TokenKind::DotDot).is_some() {
+                    base = Some(Box::new(p.parse_expr()?));
+                    p.expect(TokenKind::RBrace)?;
+                    break;
+                }
+
+                fields.push(p.parse_field_init()?);
+
+                // Either another field, or the end of the literal
+                if !p.at(TokenKind::RBrace) {
+                    p.expect_one_of(&[TokenKind::RBrace, TokenKind::Comma])?;
+                }
+            }
+
+            Ok(ExprKind::StructLit { path, fields, base })
+        })
+    }
+
+    /// Parse a field in a struct literal, either `x: 1` or the shorthand `x`.
+    fn parse_field_init(&mut self) -> PResult<FieldInit> {
+        let name = self.expect_ident()?;
+        let start = name.span;
+
+        let value = match self.eat(TokenKind::Colon) {
+            Some(_) => Some(self.parse_expr()?),
+            None => None,
+        };
+
+        Ok(Spanned::new(
+            FieldInitKind { name, value },
+            self.span_from(start),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{Expr, ItemKind, TyKind},
+        lexer::{position::Span, state::Lexer},
+        parser::{errors::ParseError, parse},
+    };
+
+    /// Parse an expression, failing if there were any errors.
+    fn expr(input: &str) -> Expr {
+        let mut parser = Parser::new(Lexer::new(input));
+        let expr = parser.parse_expr().unwrap();
+
+        assert_eq!(parser.errors, vec![], "{input}");
+        expr
+    }
+
+    #[test]
+    fn test_struct_declaration() {
+        let (module, errors) =
+            parse("struct Point {\n    x: i32,\n    y: &mut i32,\n}");
+        assert_eq!(errors, vec![]);
+
+        let ItemKind::Struct(decl) = &module.items[0].data else {
+            panic!("expected a struct");
+        };
+        assert_eq!(decl.name.data, "Point");
+        assert_eq!(decl.fields.len(), 2);
+        assert_eq!(decl.fields[0].span, Span::new(19, 25));
+        assert!(matches!(decl.fields[1].data.ty.data, TyKind::Ref { .. }));
+    }
+
+    #[test]
+    fn test_empty_struct() {
+        let (module, errors) = parse("struct Unit {}");
+        assert_eq!(errors, vec![]);
+
+        let ItemKind::Struct(decl) = &module.items[0].data else {
+            panic!("expected a struct");
+        };
+        assert!(decl.fields.is_empty());
+    }
+
+    #[test]
+    fn test_struct_literal() {
+        let lit = expr("Point { x: 1, y }");
+        assert_eq!(lit.span, Span::new(0, 17));
+
+        let ExprKind::StructLit { path, fields, base } = lit.data else {
+            panic!("expected a struct literal");
+        };
+        assert_eq!(path.data[0].data, "Point");
+        assert_eq!(base, None);
+        assert!(fields[0].data.value.is_some());
+
+        // The shorthand only has a name
+        assert_eq!(fields[1].data.name.data, "y");
+        assert_eq!(fields[1].data.value, None);
+        assert_eq!(fields[1].span, Span::new(14, 15));
+    }
+
+    #[test]
+    fn test_functional_update() {
+        let lit = expr("Point { x: 1, ..origin }");
+
+        let ExprKind::StructLit { fields, base, .. } = lit.data else {
+            panic!("expected a struct literal");
+        };
+        assert_eq!(fields.len(), 1);
+        assert_eq!(base.unwrap().data, ExprKind::Ident("origin".into()));
+
+        assert!(matches!(
+            expr("Point { ..origin }").data,
+            ExprKind::StructLit { .. }
+        ));
+    }
+
+    #[test]
+    fn test_variant_literal() {
+        let lit = expr("Shape::Rect { w: 1, h: 2 }");
+
+        let ExprKind::StructLit { path, fields, .. } = lit.data else {
+            panic!("expected a struct literal");
+        };
+        assert_eq!(path.span, Span::new(0, 11));
+        assert_eq!(path.data[1].data, "Rect");
+        assert_eq!(fields.len(), 2);
+    }
+
+    #[test]
+    fn test_base_has_to_come_last() {
+        let mut parser = Parser::new(Lexer::new("Point { ..origin, x: 1 }"));
+        let err = parser.parse_expr().unwrap_err();
+
+        assert_eq!(
+            err.data,
+            ParseError::Expected {
+                expected: vec![TokenKind::RBrace],
+                found: TokenKind::Comma,
+            }
+        );
+    }
+
+    #[test]
+    fn test_fields_need_commas() {
+        let mut parser = Parser::new(Lexer::new("Point { x: 1 y: 2 }"));
+        let err = parser.parse_expr().unwrap_err();
+
+        assert_eq!(
+            err.data,
+            ParseError::Expected {
+                expected: vec![TokenKind::RBrace, TokenKind::Comma],
+                found: TokenKind::Ident,
+            }
+        );
+    }
+}
@@ -0,0 +1,230 @@
+//! Handles parsing trait declarations, the impls of traits for types, and
+//! the traits named in bounds like the `Display` of `T: Display`.
+
+use alloc::vec;
+
+use crate::{
+    ast::{Impl, Trait, TraitMethod, TraitMethodKind, TraitRef, TraitRefKind},
+    lexer::{kind::TokenKind, position::Spanned},
+};
+
+use super::{state::Parser, PResult};
+
+impl Parser {
+    /// Parse a trait declaration, like
+    /// `trait Display { func show(self) : &str }`.
+    pub fn parse_trait(&mut self) -> PResult<Trait> {
+        self.expect(TokenKind::Trait)?;
+        let name = self.expect_ident()?;
+        let generics = self.parse_type_params();
+
+        let mut methods = vec![];
+        self.parse_methods(|p| {
+            methods.push(p.parse_trait_method()?);
+            Ok(())
+        })?;
+
+        Ok(Trait {
+            public: false,
+            name,
+            generics,
+            methods,
+        })
+    }
+
+    /// Parse a method of a trait. One without a default body ends after its
+    /// return type, or after its parameters when it doesn't return anything:
+    ///
+    /// `func show(self) : &str`
+    ///
+    /// while one with a default body is written like any other function:
+    ///
+    /// `func print(self) := { .. }`
+    fn parse_trait_method(&mut self) -> PResult<TraitMethod> {
+        let start = self.expect(TokenKind::Func)?.span;
+        let name = self.expect_ident()?;
+        let generics = self.parse_type_params();
+
+        self.expect(TokenKind::LPar)?;
+        let params = self.parse_list(TokenKind::RPar, Self::parse_param);
+
+        let mut ret = None;
+        let mut default = None;
+        if self.eat(TokenKind::Colon).is_some() {
+            // `:=` is lexed as two tokens, like in any other annotation
+            if self.at(TokenKind::Eq) && self.is_joined() {
+                self.bump();
+                default = Some(self.parse_block()?);
+            } else {
+                ret = Some(self.parse_type()?);
+            }
+        }
+
+        if default.is_none() && self.eat(TokenKind::Eq).is_some() {
+            default = Some(self.parse_block()?);
+        }
+
+        // The terminator after a method without a body isn't part of it
+        let span = self.span_from(start);
+        if default.is_none() && !self.at(TokenKind::RBrace) {
+            self.expect_terminator()?;
+        }
+
+        let method = TraitMethodKind {
+            name,
+            generics,
+            params,
+            ret,
+            default,
+        };
+        Ok(Spanned::new(method, span))
+    }
+
+    /// Parse an impl of a trait for a type, like
+    /// `impl<T> Display for Pair<T> { func show(self) : &str = { .. } }`.
+    pub fn parse_impl(&mut self) -> PResult<Impl> {
+        self.expect(TokenKind::Impl)?;
+        let generics = self.parse_type_params();
+        let trait_ref = self.parse_trait_ref()?;
+
+        self.expect(TokenKind::For)?;
+        let self_ty = self.parse_type()?;
+
+        let mut methods = vec![];
+        self.parse_methods(|p| {
+            let start = p.peek().span;
+            let func = p.parse_func()?;
+            methods.push(Spanned::new(func, p.span_from(start)));
+            Ok(())
+        })?;
+
+        Ok(Impl {
+            generics,
+            trait_ref,
+            self_ty,
+            methods,
+        })
+    }
+
+    /// Parse the methods between the braces of a trait or an impl, with
+    /// `parse_method` parsing each one. A broken method is reported and
+    /// skipped, and parsing carries on from the next `func`.
+    fn parse_methods(
+        &mut self,
+        mut parse_method: impl FnMut(&mut Self) -> PResult<()>,
+    ) -> PResult<()> {
+        self.expect(TokenKind::LBrace)?;
+
+        loop {
+            self.skip_terminators();
+            if self.at(TokenKind::RBrace) || self.at(TokenKind::Eof) {
+                break;
+            }
+
+            if let Err(err) = parse_method(self) {
+                self.report(err);
+                self.synchronize();
+            }
+        }
+
+        self.expect(TokenKind::RBrace)?;
+        Ok(())
+    }
+
+    /// Parse a trait named in a bound or an impl, along with any type
+    /// arguments, like `Add<u8>` or `shapes::Display`.
+    pub fn parse_trait_ref(&mut self) -> PResult<TraitRef> {
+        let name = self.expect_ident()?;
+        let path = self.parse_path(name)?;
+        let start = path.span;
+
+        let args = match self.eat(TokenKind::Less) {
+            Some(_) => self.parse_type_args()?,
+            None => vec![],
+        };
+
+        let span = self.span_from(start);
+        Ok(Spanned::new(TraitRefKind { path, args }, span))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::{
+        ast::{print::sexpr, ItemKind},
+        lexer::position::Span,
+        parser::parse,
+    };
+
+    #[test]
+    fn test_traits() {
+        let (module, errors) = parse(concat!(
+            "pub trait Display {\n",
+            "    func show(self) : &str\n",
+            "    func reset(self);\n",
+            "    func print(self) := { self.show() }\n",
+            "}\n",
+            "trait Add<Rhs> { func add(self, other: Rhs) : Self }",
+        ));
+        assert_eq!(errors, vec![]);
+        assert_eq!(
+            sexpr(&module),
+            "(module \
+             (pub (trait Display \
+             (method show (params (self Self)) (ref str)) \
+             (method reset (params (self Self))) \
+             (method print (params (self Self)) \
+             (block (call (. self show)))))) \
+             (trait Add (generics Rhs) \
+             (method add (params (self Self) (other Rhs)) Self)))"
+        );
+
+        let ItemKind::Trait(decl) = &module.items[0].data else {
+            panic!("expected a trait");
+        };
+        assert!(decl.public);
+        assert_eq!(decl.methods[0].span, Span::new(24, 46));
+    }
+
+    #[test]
+    fn test_impls_and_bounds() {
+        let (module, errors) = parse(concat!(
+            "impl<T: Display + Add<T>> Display for Pair<T> {\n",
+            "    func show(self) : &str = { \"pair\" }\n",
+            "}\n",
+            "impl shapes::Display for u8 {}\n",
+            "func show_all<T: Display>(x: T) := {}",
+        ));
+        assert_eq!(errors, vec![]);
+        assert_eq!(
+            sexpr(&module),
+            "(module \
+             (impl (generics (T Display (Add T))) Display (generic Pair T) \
+             (func show (params (self Self)) (ref str) (block \"pair\"))) \
+             (impl shapes::Display u8) \
+             (func show_all (generics (T Display)) (params (x T)) (block)))"
+        );
+    }
+
+    #[test]
+    fn test_broken_methods_are_skipped() {
+        let (module, errors) = parse(concat!(
+            "trait Display {\n",
+            "    func show(self) : &str = \n",
+            "    func print(self) := {}\n",
+            "}\n",
+            "func main() := {}",
+        ));
+
+        assert_eq!(errors[0].data.to_string(), "expected `{` found `func`");
+        assert_eq!(
+            sexpr(&module),
+            "(module \
+             (trait Display (method print (params (self Self)) (block))) \
+             (func main (params) (block)))"
+        );
+    }
+}
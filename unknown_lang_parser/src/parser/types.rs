@@ -0,0 +1,371 @@
+//! Handles parsing types, and the `: T =` annotations in front of values.
+
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+
+use crate::{
+    ast::{Ident, Ty, TyKind},
+    lexer::{kind::TokenKind, position::Spanned, tokens::Token},
+};
+
+use super::{errors::ParseError, state::Parser, PResult};
+
+impl Parser {
+    /// Parse a type, like `usize`, `&mut [u8]` or `(A, B) -> C`. A missing
+    /// type is reported, and left as [`TyKind::Error`] without consuming
+    /// anything.
+    pub fn parse_type(&mut self) -> PResult<Ty> {
+        self.nested(Self::parse_type_kind)
+    }
+
+    /// Parse a type, once [`Self::parse_type`] has checked how deeply nested
+    /// it is.
+    fn parse_type_kind(&mut self) -> PResult<Ty> {
+        let start = self.peek().span;
+
+        let kind = match self.peek_kind() {
+            TokenKind::Mut => {
+                self.bump();
+                TyKind::Mut(Box::new(self.parse_type()?))
+            }
+            TokenKind::Ident => {
+                let name = self.expect_ident()?;
+
+                if self.at(TokenKind::Less) {
+                    self.bump();
+                    let args = self.parse_type_args()?;
+
+                    TyKind::Generic { name, args }
+                } else if self.at(TokenKind::ColonColon) {
+                    TyKind::Path(self.parse_path(name)?.data)
+                } else {
+                    TyKind::Named(name.data)
+                }
+            }
+            TokenKind::LBracket => {
+                self.bump();
+                let elem = Box::new(self.parse_type()?);
+
+                if self.eat(TokenKind::Semi).is_some() {
+                    let len = Box::new(self.parse_expr()?);
+                    self.expect(TokenKind::RBracket)?;
+
+                    TyKind::Array { elem, len }
+                } else {
+                    self.expect(TokenKind::RBracket)?;
+                    TyKind::Slice(elem)
+                }
+            }
+            TokenKind::LPar => self.parse_paren_type()?,
+            // `&&T` is a reference to a reference
+            TokenKind::Ampersand | TokenKind::AmpAmp => {
+                self.eat_split(TokenKind::Ampersand);
+                let mutable = self.eat(TokenKind::Mut).is_some();
+
+                TyKind::Ref {
+                    mutable,
+                    ty: Box::new(self.parse_type()?),
+                }
+            }
+            kind => {
+                let err = self.error(ParseError::ExpectedType(kind));
+                self.report(err);
+
+                let start = self.peek().span.start.0;
+                return Ok(Spanned::new_span(TyKind::Error, start, start));
+            }
+        };
+
+        Ok(Spanned::new(kind, self.span_from(start)))
+    }
+
+    /// Parse the type arguments after the `<` of a generic type, up to and
+    /// including the closing `>`. A `>>` closes two lists at once, so it is
+    /// split in half.
+    pub fn parse_type_args(&mut self) -> PResult<Vec<Ty>> {
+        let mut args = vec![];
+
+        while self.eat_split(TokenKind::Greater).is_none() {
+            args.push(self.parse_type()?);
+
+            if self.eat(TokenKind::Comma).is_none() {
+                self.expect_split(TokenKind::Greater)?;
+                break;
+            }
+        }
+
+        Ok(args)
+    }
+
+    /// Parse a type starting with `(`. This is a tuple when it is empty or
+    /// has a comma in it, and otherwise just a type in parenthesis. Either is
+    /// a function type when followed by `->`.
+    fn parse_paren_type(&mut self) -> PResult<TyKind> {
+        self.expect(TokenKind::LPar)?;
+
+        let mut elems = vec![];
+        let mut is_tuple = true;
+
+        while self.eat(TokenKind::RPar).is_none() {
+            elems.push(self.parse_type()?);
+
+            if self.eat(TokenKind::Comma).is_none() {
+                self.expect(TokenKind::RPar)?;
+                is_tuple = elems.len() != 1;
+                break;
+            }
+        }
+
+        if self.at(TokenKind::RightArrow) {
+            self.bump();
+            let ret = Box::new(self.parse_type()?);
+
+            return Ok(TyKind::Func { params: elems, ret });
+        }
+
+        match elems.pop() {
+            Some(inner) if !is_tuple => Ok(TyKind::Paren(Box::new(inner))),
+            inner => {
+                elems.extend(inner);
+                Ok(TyKind::Tuple(elems))
+            }
+        }
+    }
+
+    /// Parse the optional type given to a value before its `=`, either
+    /// `: T =`, or `:=` and `=` when the type is left off.
+    pub fn parse_annotation(&mut self) -> PResult<Option<Ty>> {
+        if self.eat(TokenKind::Colon).is_none() {
+            self.expect_one_of(&[TokenKind::Colon, TokenKind::Eq])?;
+            return Ok(None);
+        }
+
+        // `:=` is lexed as two tokens, so it has to be written without any
+        // space between them. `: =` is missing its type.
+        if self.at(TokenKind::Eq) && self.is_joined() {
+            self.bump();
+            return Ok(None);
+        }
+
+        let ty = self.parse_type()?;
+        self.expect(TokenKind::Eq)?;
+
+        Ok(Some(ty))
+    }
+
+    /// Consume an identifier which isn't a keyword.
+    pub fn expect_ident(&mut self) -> PResult<Ident> {
+        let tok = self.expect(TokenKind::Ident)?;
+
+        let name = match tok.data {
+            Token::Ident(name) => name,
+            _ => String::new(),
+        };
+
+        Ok(Spanned::new(name, tok.span))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::lexer::{position::Span, state::Lexer};
+
+    fn annotation(input: &str) -> PResult<Option<Ty>> {
+        let mut parser = Parser::new(Lexer::new(input));
+        let result = parser.parse_annotation();
+
+        // The first error is the one that caused any others
+        match parser.errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => result,
+        }
+    }
+
+    #[test]
+    fn test_joined_colon_eq_has_no_type() {
+        assert_eq!(annotation(":= 1"), Ok(None));
+        assert_eq!(annotation("= 1"), Ok(None));
+    }
+
+    #[test]
+    fn test_annotation_with_type() {
+        let ty = annotation(": i32 = 1").unwrap().unwrap();
+        assert_eq!(ty.data, TyKind::Named("i32".into()));
+    }
+
+    #[test]
+    fn test_spaced_colon_eq_is_missing_type() {
+        let err = annotation(": = 1").unwrap_err();
+        assert_eq!(err.data, ParseError::ExpectedType(TokenKind::Eq));
+        assert_eq!(err.span, Span::new(2, 3));
+    }
+
+    #[test]
+    fn test_missing_type_is_an_error_node() {
+        let mut parser = Parser::new(Lexer::new(": = 1"));
+        let ty = parser.parse_annotation().unwrap().unwrap();
+
+        assert_eq!(ty.data, TyKind::Error);
+        assert!(ty.span.is_empty());
+        assert_eq!(parser.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_keyword_is_not_a_type() {
+        let err = annotation(": let = 1").unwrap_err();
+        assert_eq!(err.data, ParseError::ExpectedType(TokenKind::Let));
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let input = "&mut ".repeat(100_000) + "u8";
+        let err = Parser::new(Lexer::new(&input)).parse_type().unwrap_err();
+        assert_eq!(err.data, ParseError::TooDeep);
+    }
+
+    fn ty(input: &str) -> Ty {
+        let mut parser = Parser::new(Lexer::new(input));
+        let ty = parser.parse_type().unwrap();
+
+        assert_eq!(parser.errors, vec![], "{input}");
+        assert_eq!(parser.peek_kind(), TokenKind::Eof, "{input}");
+        ty
+    }
+
+    fn named(name: &str) -> TyKind {
+        TyKind::Named(name.into())
+    }
+
+    #[test]
+    fn test_arrays_and_slices() {
+        let TyKind::Array { elem, len } = ty("[u8; 0x10]").data else {
+            panic!("expected an array");
+        };
+        assert_eq!(elem.data, named("u8"));
+        assert_eq!(len.span, Span::new(5, 9));
+
+        let TyKind::Slice(elem) = ty("[[u8; 4]]").data else {
+            panic!("expected a slice");
+        };
+        assert!(matches!(elem.data, TyKind::Array { .. }));
+    }
+
+    #[test]
+    fn test_tuples_and_parens() {
+        assert_eq!(ty("()").data, TyKind::Tuple(vec![]));
+
+        let TyKind::Paren(inner) = ty("(u8)").data else {
+            panic!("expected parenthesis");
+        };
+        assert_eq!(inner.data, named("u8"));
+
+        let TyKind::Tuple(elems) = ty("(u8,)").data else {
+            panic!("expected a tuple");
+        };
+        assert_eq!(elems.len(), 1);
+
+        let TyKind::Tuple(elems) = ty("(u8, (), i32)").data else {
+            panic!("expected a tuple");
+        };
+        assert_eq!(elems[1].data, TyKind::Tuple(vec![]));
+        assert_eq!(elems[2].span, Span::new(9, 12));
+    }
+
+    #[test]
+    fn test_function_types() {
+        let TyKind::Func { params, ret } = ty("(u8, i32) -> void").data else {
+            panic!("expected a function type");
+        };
+        assert_eq!(params.len(), 2);
+        assert_eq!(ret.data, named("void"));
+
+        // The return type is itself a function type
+        let TyKind::Func { params, ret } = ty("() -> (u8) -> u8").data else {
+            panic!("expected a function type");
+        };
+        assert_eq!(params, vec![]);
+        assert!(matches!(ret.data, TyKind::Func { .. }));
+        assert_eq!(ret.span, Span::new(6, 16));
+    }
+
+    #[test]
+    fn test_references() {
+        let TyKind::Ref { mutable, ty: inner } = ty("&mut [u8]").data else {
+            panic!("expected a reference");
+        };
+        assert!(mutable);
+        assert!(matches!(inner.data, TyKind::Slice(_)));
+
+        // `&&` is split into two references
+        let TyKind::Ref { mutable, ty: inner } = ty("&&u8").data else {
+            panic!("expected a reference");
+        };
+        assert!(!mutable);
+        assert_eq!(inner.span, Span::new(1, 4));
+        assert!(matches!(inner.data, TyKind::Ref { mutable: false, .. }));
+    }
+
+    #[test]
+    fn test_generics() {
+        let TyKind::Generic { name, args } = ty("Result<u8, &str>").data else {
+            panic!("expected a generic type");
+        };
+        assert_eq!(name.data, "Result");
+        assert_eq!(args.len(), 2);
+
+        // `>>` closes both lists
+        let outer = ty("Option<Option<u8>>");
+        assert_eq!(outer.span, Span::new(0, 18));
+
+        let TyKind::Generic { args, .. } = outer.data else {
+            panic!("expected a generic type");
+        };
+        assert_eq!(args[0].span, Span::new(7, 17));
+    }
+
+    #[test]
+    fn test_paths() {
+        let path = ty("shapes::Circle");
+        assert_eq!(path.span, Span::new(0, 14));
+
+        let TyKind::Path(segments) = path.data else {
+            panic!("expected a path");
+        };
+        assert_eq!(segments[0].data, "shapes");
+        assert_eq!(segments[1].span, Span::new(8, 14));
+
+        // A lone name is still just a name
+        assert!(matches!(ty("Circle").data, TyKind::Named(_)));
+    }
+
+    #[test]
+    fn test_generic_before_joined_eq() {
+        // `>=` is split into the closing `>` and the `=` of the annotation
+        let ty = annotation(": Option<u8>= 1").unwrap().unwrap();
+        assert!(matches!(ty.data, TyKind::Generic { .. }));
+        assert_eq!(ty.span, Span::new(2, 12));
+    }
+
+    #[test]
+    fn test_unclosed_types() {
+        let err = annotation(": [u8; 4 = 1").unwrap_err();
+        assert_eq!(
+            err.data,
+            ParseError::Expected {
+                expected: vec![TokenKind::RBracket],
+                found: TokenKind::Eq
+            }
+        );
+
+        let err = annotation(": Option<u8 = 1").unwrap_err();
+        assert_eq!(
+            err.data,
+            ParseError::Expected {
+                expected: vec![TokenKind::Comma, TokenKind::Greater],
+                found: TokenKind::Eq
+            }
+        );
+    }
+}
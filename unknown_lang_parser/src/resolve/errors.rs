@@ -0,0 +1,88 @@
+use alloc::string::String;
+
+use super::{DefKind, VariantShape};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResolveError {
+    /// A path like `Shape::Circle`, where `Shape` isn't an enum
+    UnknownEnum(String),
+    /// A path like `Shape::Oval`, where `Shape` has no variant `Oval`
+    UnknownVariant { enum_name: String, variant: String },
+    /// A path like `shapes::Oval`, where the module imported as `shapes`
+    /// has no item named `Oval`
+    UnknownItem { module: String, name: String },
+    /// An item of another module which wasn't declared with `pub`
+    Private(String),
+    /// A variant used as if it held a different `shape` of values, like
+    /// `Shape::Empty(1)`
+    WrongShape { path: String, shape: VariantShape },
+    /// A path naming something which can't be used where it is, like a
+    /// function used as a type
+    WrongKind {
+        path: String,
+        expected: &'static str,
+        found: DefKind,
+    },
+    /// Two enums with the same name
+    DuplicateEnum(String),
+    /// Two variants with the same name in one enum
+    DuplicateVariant(String),
+    /// Two variants of one enum with the same discriminant
+    DuplicateDiscriminant { variant: String, value: i128 },
+    /// A discriminant which isn't a number that fits in an `i128`
+    InvalidDiscriminant(String),
+}
+
+impl core::fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnknownEnum(name) => write!(f, "no enum named `{name}`"),
+            Self::UnknownVariant { enum_name, variant } => {
+                write!(f, "`{enum_name}` has no variant named `{variant}`")
+            }
+            Self::UnknownItem { module, name } => {
+                write!(f, "module `{module}` has no item named `{name}`")
+            }
+            Self::Private(path) => write!(
+                f,
+                "`{path}` isn't `pub`, so it can only be used in its own module"
+            ),
+            Self::WrongShape { path, shape } => match shape {
+                VariantShape::Unit => {
+                    write!(f, "`{path}` does not hold any values")
+                }
+                VariantShape::Tuple => {
+                    write!(
+                        f,
+                        "`{path}` holds values in order, like `{path}(..)`"
+                    )
+                }
+                VariantShape::Named => write!(
+                    f,
+                    "`{path}` holds values by name, like `{path} {{ .. }}`"
+                ),
+            },
+            Self::WrongKind {
+                path,
+                expected,
+                found,
+            } => write!(f, "expected {expected}, but `{path}` is {found}"),
+            Self::DuplicateEnum(name) => {
+                write!(f, "enum `{name}` is declared more than once")
+            }
+            Self::DuplicateVariant(name) => {
+                write!(f, "variant `{name}` is declared more than once")
+            }
+            Self::DuplicateDiscriminant { variant, value } => {
+                write!(f, "variant `{variant}` reuses the discriminant {value}")
+            }
+            Self::InvalidDiscriminant(variant) => write!(
+                f,
+                "the discriminant of `{variant}` has to be a whole number"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ResolveError {}
@@ -0,0 +1,888 @@
+//! Resolving the paths written with `::`, like `Shape::Circle` or
+//! `shapes::area`, to the enum variants and items that they name.
+//!
+//! Every item in every module is collected before any path is looked up, so
+//! a path can name something declared further down the file. Each variant is
+//! given its discriminant along the way, which is either written after its
+//! `=`, or one more than the variant before it.
+//!
+//! A path starting with the name of an import goes into that module, where
+//! it can only name items declared with `pub`. Names without any `::`, like
+//! the `Point` of `Point { x: 1 }`, aren't looked up here.
+
+pub mod errors;
+
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+
+use crate::{
+    ast::{
+        path_to_string,
+        visit::{walk_expr, walk_pat, walk_trait_ref, walk_ty, Visitor},
+        Enum, Expr, ExprKind, Ident, ItemKind, Module, Pat, PatKind, TraitRef,
+        Ty, TyKind, UnaryOp, VariantFields,
+    },
+    lexer::position::{Span, Spanned},
+    modules::{ModuleGraph, ModuleId},
+};
+
+use self::errors::ResolveError;
+
+/// The values that an enum variant holds, without their types.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VariantShape {
+    /// No values, like `Empty`
+    Unit,
+    /// Values in order, like `Circle(f64)`
+    Tuple,
+    /// Values by name, like `Rect { w: f64, h: f64 }`
+    Named,
+}
+
+impl From<&VariantFields> for VariantShape {
+    fn from(fields: &VariantFields) -> Self {
+        match fields {
+            VariantFields::Unit => VariantShape::Unit,
+            VariantFields::Tuple(_) => VariantShape::Tuple,
+            VariantFields::Named(_) => VariantShape::Named,
+        }
+    }
+}
+
+/// What kind of thing a path names.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DefKind {
+    Func,
+    Struct,
+    Enum,
+    Variant,
+    Trait,
+}
+
+impl core::fmt::Display for DefKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Func => write!(f, "a function"),
+            Self::Struct => write!(f, "a struct"),
+            Self::Enum => write!(f, "an enum"),
+            Self::Variant => write!(f, "a variant"),
+            Self::Trait => write!(f, "a trait"),
+        }
+    }
+}
+
+/// A function, struct, enum or trait declared at the top level of a module.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ItemDef {
+    pub kind: DefKind,
+    /// Whether the item was declared with `pub`, so other modules can use it
+    pub public: bool,
+}
+
+/// An enum declared in a module.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnumDef {
+    pub variants: Vec<VariantDef>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VariantDef {
+    pub name: String,
+    pub shape: VariantShape,
+    pub discriminant: i128,
+}
+
+/// A variant of an enum, by its position in the declaration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VariantRef {
+    /// The module that the enum is declared in
+    pub module: ModuleId,
+    pub enum_name: String,
+    pub index: usize,
+}
+
+/// What a path resolved to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Def {
+    /// An item declared at the top level of a module
+    Item {
+        module: ModuleId,
+        name: String,
+    },
+    Variant(VariantRef),
+}
+
+/// What the paths of every module resolved to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Resolution {
+    /// The modules in the same order as they were given
+    pub modules: Vec<ModuleResolution>,
+}
+
+impl Resolution {
+    /// Get the declaration of the variant that `variant` refers to.
+    pub fn variant(&self, variant: &VariantRef) -> Option<&VariantDef> {
+        let module = self.modules.get(variant.module)?;
+        let decl = module.enums.get(&variant.enum_name)?;
+        decl.variants.get(variant.index)
+    }
+
+    /// Checks if any path in any module couldn't be resolved.
+    pub fn has_errors(&self) -> bool {
+        self.modules.iter().any(|module| !module.errors.is_empty())
+    }
+}
+
+/// What one module declares, and what each of its paths resolved to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModuleResolution {
+    /// Every function, struct, enum and trait declared in the module, by name
+    pub items: BTreeMap<String, ItemDef>,
+    /// Every enum declared in the module, by name
+    pub enums: BTreeMap<String, EnumDef>,
+    /// What each path resolved to, in the order they appear
+    pub paths: Vec<Spanned<Def>>,
+    /// Every path which couldn't be resolved, along with any problems with
+    /// the enums themselves
+    pub errors: Vec<Spanned<ResolveError>>,
+}
+
+/// Resolve every path in a `module` on its own. Its imports aren't followed,
+/// so a path into another module is an error, and
+/// [`resolve_graph`] is needed for a program with more than one module.
+pub fn resolve(module: &Module) -> Resolution {
+    resolve_modules(&[(module, &BTreeMap::new())])
+}
+
+/// Resolve every path in every module of `graph`, following the imports
+/// between them.
+pub fn resolve_graph(graph: &ModuleGraph) -> Resolution {
+    let modules: Vec<_> = graph
+        .modules
+        .iter()
+        .map(|module| (&module.ast, &module.imports))
+        .collect();
+
+    resolve_modules(&modules)
+}
+
+fn resolve_modules(
+    modules: &[(&Module, &BTreeMap<String, ModuleId>)],
+) -> Resolution {
+    let mut resolution = Resolution {
+        modules: vec![ModuleResolution::default(); modules.len()],
+    };
+
+    for (id, (module, _)) in modules.iter().enumerate() {
+        let declared = &mut resolution.modules[id];
+
+        for item in &module.items {
+            let (name, kind) = match &item.data {
+                ItemKind::Func(func) => (&func.name, DefKind::Func),
+                ItemKind::Struct(decl) => (&decl.name, DefKind::Struct),
+                ItemKind::Trait(decl) => (&decl.name, DefKind::Trait),
+                ItemKind::Enum(decl) => {
+                    declare_enum(declared, decl);
+                    (&decl.name, DefKind::Enum)
+                }
+                _ => continue,
+            };
+
+            // The first item with a name is the one it refers to
+            let public = item.data.is_public();
+            declared
+                .items
+                .entry(name.data.clone())
+                .or_insert(ItemDef { kind, public });
+        }
+    }
+
+    for (id, (module, imports)) in modules.iter().enumerate() {
+        let mut resolver = Resolver {
+            resolution: &mut resolution,
+            module: id,
+            imports,
+        };
+        resolver.visit_module(module);
+    }
+
+    resolution
+}
+
+/// Add an enum to the module it is declared in, giving each of its variants a
+/// discriminant.
+fn declare_enum(module: &mut ModuleResolution, decl: &Enum) {
+    let mut error = |err, span| module.errors.push(Spanned::new(err, span));
+
+    let name = &decl.name.data;
+    if module.enums.contains_key(name) {
+        return error(
+            ResolveError::DuplicateEnum(name.clone()),
+            decl.name.span,
+        );
+    }
+
+    let mut variants: Vec<VariantDef> = vec![];
+    let mut next = 0;
+
+    for variant in &decl.variants {
+        let name = &variant.data.name;
+        if variants.iter().any(|other| other.name == name.data) {
+            error(ResolveError::DuplicateVariant(name.data.clone()), name.span);
+            continue;
+        }
+
+        let discriminant = match &variant.data.discriminant {
+            Some(expr) => discriminant_value(expr).unwrap_or_else(|| {
+                let err = ResolveError::InvalidDiscriminant(name.data.clone());
+                error(err, expr.span);
+                next
+            }),
+            None => next,
+        };
+
+        if variants
+            .iter()
+            .any(|other| other.discriminant == discriminant)
+        {
+            let err = ResolveError::DuplicateDiscriminant {
+                variant: name.data.clone(),
+                value: discriminant,
+            };
+            error(err, variant.span);
+        }
+
+        next = discriminant.wrapping_add(1);
+        variants.push(VariantDef {
+            name: name.data.clone(),
+            shape: VariantShape::from(&variant.data.fields),
+            discriminant,
+        });
+    }
+
+    module.enums.insert(name.clone(), EnumDef { variants });
+}
+
+/// Where a path is used, which decides what it is allowed to name.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Use {
+    /// A path used on its own as a value
+    Value,
+    /// The function being called
+    Callee,
+    /// The path in front of the fields of a struct literal
+    StructLit,
+    /// A path used on its own as a pattern
+    Pattern,
+    /// The path in front of `(..)` in a pattern
+    TuplePattern,
+    /// The path in front of `{..}` in a pattern
+    StructPattern,
+    Type,
+    /// A trait named in a bound or an impl
+    Trait,
+}
+
+impl Use {
+    /// Get the kinds of thing which can be used here.
+    fn kinds(self) -> &'static [DefKind] {
+        match self {
+            Use::Value | Use::Callee => &[DefKind::Func, DefKind::Variant],
+            Use::StructLit | Use::StructPattern => {
+                &[DefKind::Struct, DefKind::Variant]
+            }
+            Use::Pattern | Use::TuplePattern => &[DefKind::Variant],
+            Use::Type => &[DefKind::Struct, DefKind::Enum],
+            Use::Trait => &[DefKind::Trait],
+        }
+    }
+
+    /// Get the shapes of variant which can be used here. A variant without
+    /// values can be used on its own, and one with values in order can be
+    /// passed around like a function.
+    fn shapes(self) -> &'static [VariantShape] {
+        match self {
+            Use::Value => &[VariantShape::Unit, VariantShape::Tuple],
+            Use::Callee | Use::TuplePattern => &[VariantShape::Tuple],
+            Use::StructLit | Use::StructPattern => &[VariantShape::Named],
+            Use::Pattern => &[VariantShape::Unit],
+            Use::Type | Use::Trait => &[],
+        }
+    }
+
+    /// Describe what should have been used here.
+    fn expected(self) -> &'static str {
+        match self {
+            Use::Value => "a value",
+            Use::Callee => "a function",
+            Use::StructLit | Use::StructPattern => "a struct",
+            Use::Pattern | Use::TuplePattern => "a variant",
+            Use::Type => "a type",
+            Use::Trait => "a trait",
+        }
+    }
+}
+
+struct Resolver<'a> {
+    resolution: &'a mut Resolution,
+    /// The module that the paths are in
+    module: ModuleId,
+    imports: &'a BTreeMap<String, ModuleId>,
+}
+
+impl Resolver<'_> {
+    fn error(&mut self, err: ResolveError, span: Span) {
+        let module = &mut self.resolution.modules[self.module];
+        module.errors.push(Spanned::new(err, span));
+    }
+
+    /// Look up what `segments` names, and check that it can be used where it
+    /// is.
+    fn resolve_path(&mut self, segments: &[Ident], span: Span, used: Use) {
+        if segments.len() < 2 {
+            return;
+        }
+
+        // A path through an import carries on in the imported module
+        let (module, rest) = match self.imports.get(&segments[0].data) {
+            Some(&module) => (module, &segments[1..]),
+            None => (self.module, segments),
+        };
+
+        let def = match rest {
+            [item] => self.resolve_item(&segments[0], module, item),
+            [prefix @ .., variant] => {
+                let enum_path = &segments[..segments.len() - 1];
+                self.resolve_variant(module, enum_path, prefix, variant)
+            }
+            [] => None,
+        };
+        let Some((def, kind)) = def else {
+            return;
+        };
+
+        let path = path_to_string(segments);
+        if !used.kinds().contains(&kind) {
+            let err = ResolveError::WrongKind {
+                path,
+                expected: used.expected(),
+                found: kind,
+            };
+            self.error(err, span);
+        } else if let Def::Variant(variant) = &def {
+            let shape = self.resolution.variant(variant).unwrap().shape;
+            if !used.shapes().contains(&shape) {
+                self.error(ResolveError::WrongShape { path, shape }, span);
+            }
+        }
+
+        // The path still names what it names, even when it's used wrong
+        let module = &mut self.resolution.modules[self.module];
+        module.paths.push(Spanned::new(def, span));
+    }
+
+    /// Look up an `item` of another module, which was imported as `import`.
+    fn resolve_item(
+        &mut self,
+        import: &Ident,
+        module: ModuleId,
+        item: &Ident,
+    ) -> Option<(Def, DefKind)> {
+        let Some(&decl) = self.resolution.modules[module].items.get(&item.data)
+        else {
+            let err = ResolveError::UnknownItem {
+                module: import.data.clone(),
+                name: item.data.clone(),
+            };
+            self.error(err, item.span);
+            return None;
+        };
+
+        if !decl.public {
+            let path = [import.clone(), item.clone()];
+            let err = ResolveError::Private(path_to_string(&path));
+            self.error(err, import.span.union(item.span));
+        }
+
+        let def = Def::Item {
+            module,
+            name: item.data.clone(),
+        };
+        Some((def, decl.kind))
+    }
+
+    /// Look up the `variant` of the enum named `name` in `module`, which is
+    /// written as `enum_path`.
+    fn resolve_variant(
+        &mut self,
+        module: ModuleId,
+        enum_path: &[Ident],
+        name: &[Ident],
+        variant: &Ident,
+    ) -> Option<(Def, DefKind)> {
+        let enum_name = path_to_string(name);
+        let declared = &self.resolution.modules[module];
+        let enum_span =
+            enum_path[0].span.union(enum_path[enum_path.len() - 1].span);
+
+        let Some(decl) = declared.enums.get(&enum_name) else {
+            let err = ResolveError::UnknownEnum(path_to_string(enum_path));
+            self.error(err, enum_span);
+            return None;
+        };
+
+        let index = decl
+            .variants
+            .iter()
+            .position(|other| other.name == variant.data);
+        let public = declared.items[&enum_name].public;
+
+        let Some(index) = index else {
+            let err = ResolveError::UnknownVariant {
+                enum_name: path_to_string(enum_path),
+                variant: variant.data.clone(),
+            };
+            self.error(err, variant.span);
+            return None;
+        };
+
+        if module != self.module && !public {
+            let err = ResolveError::Private(path_to_string(enum_path));
+            self.error(err, enum_span);
+        }
+
+        let def = Def::Variant(VariantRef {
+            module,
+            enum_name,
+            index,
+        });
+        Some((def, DefKind::Variant))
+    }
+}
+
+impl Visitor for Resolver<'_> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        match &expr.data {
+            ExprKind::Path(segments) => {
+                self.resolve_path(segments, expr.span, Use::Value)
+            }
+            ExprKind::Instantiate { path, .. } => {
+                self.resolve_path(&path.data, path.span, Use::Value)
+            }
+            ExprKind::Call { callee, args } => {
+                let path = match &callee.data {
+                    ExprKind::Path(segments) => Some((segments, callee.span)),
+                    ExprKind::Instantiate { path, .. } => {
+                        Some((&path.data, path.span))
+                    }
+                    _ => None,
+                };
+
+                if let Some((segments, span)) = path {
+                    self.resolve_path(segments, span, Use::Callee);
+
+                    // The type arguments still need resolving
+                    if let ExprKind::Instantiate { args: tys, .. } =
+                        &callee.data
+                    {
+                        tys.iter().for_each(|ty| self.visit_ty(ty));
+                    }
+                    args.iter().for_each(|arg| self.visit_expr(arg));
+                    return;
+                }
+            }
+            ExprKind::StructLit { path, .. } => {
+                self.resolve_path(&path.data, path.span, Use::StructLit)
+            }
+            _ => {}
+        }
+
+        walk_expr(self, expr);
+    }
+
+    fn visit_pat(&mut self, pat: &Pat) {
+        match &pat.data {
+            PatKind::Path(segments) => {
+                self.resolve_path(segments, pat.span, Use::Pattern)
+            }
+            PatKind::TupleStruct { path, .. } => {
+                self.resolve_path(&path.data, path.span, Use::TuplePattern)
+            }
+            PatKind::Struct { path, .. } => {
+                self.resolve_path(&path.data, path.span, Use::StructPattern)
+            }
+            _ => {}
+        }
+
+        walk_pat(self, pat);
+    }
+
+    fn visit_ty(&mut self, ty: &Ty) {
+        if let TyKind::Path(segments) = &ty.data {
+            self.resolve_path(segments, ty.span, Use::Type);
+        }
+
+        walk_ty(self, ty);
+    }
+
+    fn visit_trait_ref(&mut self, trait_ref: &TraitRef) {
+        let path = &trait_ref.data.path;
+        self.resolve_path(&path.data, path.span, Use::Trait);
+
+        walk_trait_ref(self, trait_ref);
+    }
+}
+
+/// Get the value of a discriminant, which has to be a whole number.
+fn discriminant_value(expr: &Expr) -> Option<i128> {
+    match &expr.data {
+        ExprKind::Literal(literal) => literal.as_integer(),
+        ExprKind::Unary {
+            op: UnaryOp::Neg,
+            operand,
+        } => discriminant_value(operand)?.checked_neg(),
+        ExprKind::Paren(inner) => discriminant_value(inner),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::{
+        modules::{load, Loader},
+        parser::parse,
+    };
+
+    /// Resolve `input` as a module on its own, failing if it didn't parse.
+    fn resolved(input: &str) -> ModuleResolution {
+        let (module, errors) = parse(input);
+        assert_eq!(errors, vec![], "{input}");
+
+        resolve(&module).modules.remove(0)
+    }
+
+    /// Get the enum and index of each variant that a path resolved to.
+    fn variants(resolution: &ModuleResolution) -> Vec<(&str, usize)> {
+        resolution
+            .paths
+            .iter()
+            .map(|path| match &path.data {
+                Def::Variant(variant) => {
+                    (variant.enum_name.as_str(), variant.index)
+                }
+                def => panic!("expected a variant, found {def:?}"),
+            })
+            .collect()
+    }
+
+    const SHAPE: &str =
+        "enum Shape {\n    Circle(i32),\n    Rect { w: i32, h: i32 },\n    \
+         Empty,\n}\n";
+
+    #[test]
+    fn test_resolves_variants() {
+        let input = [
+            SHAPE,
+            "func area(s: Shape) : i32 = {\n",
+            "    match s {\n",
+            "        Shape::Circle(r) => r * r * 3,\n",
+            "        Shape::Rect { w, h } => w * h,\n",
+            "        Shape::Empty => 0,\n",
+            "    }\n",
+            "}\n",
+            "let a := area(Shape::Circle(1))\n",
+            "let b := Shape::Rect { w: 1, h: 2 }\n",
+            "let c := Shape::Empty\n",
+        ]
+        .concat();
+        let (module, _) = parse(&input);
+        let program = resolve(&module);
+        let resolution = &program.modules[0];
+        assert_eq!(resolution.errors, vec![]);
+
+        assert_eq!(
+            variants(resolution),
+            vec![
+                ("Shape", 0),
+                ("Shape", 1),
+                ("Shape", 2),
+                ("Shape", 0),
+                ("Shape", 1),
+                ("Shape", 2),
+            ]
+        );
+
+        let circle = &resolution.paths[3];
+        assert_eq!(
+            &input[circle.span.start.0..circle.span.end.0],
+            "Shape::Circle"
+        );
+
+        let Def::Variant(circle) = &circle.data else {
+            panic!("expected a variant");
+        };
+        assert_eq!(program.variant(circle).unwrap().shape, VariantShape::Tuple);
+    }
+
+    #[test]
+    fn test_enums_can_be_used_before_they_are_declared() {
+        let resolution =
+            resolved("let a := Color::Red\nenum Color { Red, Green }");
+
+        assert_eq!(resolution.errors, vec![]);
+        assert_eq!(resolution.paths.len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_paths() {
+        let input =
+            [SHAPE, "let a := Shape::Oval\nlet b := Color::Red"].concat();
+        let resolution = resolved(&input);
+
+        let oval = input.find("Oval").unwrap();
+        let color = input.find("Color").unwrap();
+        assert_eq!(
+            resolution.errors,
+            vec![
+                Spanned::new(
+                    ResolveError::UnknownVariant {
+                        enum_name: "Shape".into(),
+                        variant: "Oval".into(),
+                    },
+                    Span::new(oval, oval + 4)
+                ),
+                Spanned::new(
+                    ResolveError::UnknownEnum("Color".into()),
+                    Span::new(color, color + 5)
+                ),
+            ]
+        );
+        assert!(resolution.paths.is_empty());
+    }
+
+    #[test]
+    fn test_variants_used_with_the_wrong_shape() {
+        let input = [
+            SHAPE,
+            "let a := Shape::Empty(1)\n",
+            "let b := Shape::Circle { r: 1 }\n",
+            "let c := Shape::Rect\n",
+            "let d := match a { Shape::Circle => 0, Shape::Rect(w, h) => 1 }\n",
+            "let e := Shape::Circle\n",
+        ]
+        .concat();
+        let resolution = resolved(&input);
+
+        let shapes: Vec<_> = resolution
+            .errors
+            .iter()
+            .map(|err| match &err.data {
+                ResolveError::WrongShape { path, shape } => {
+                    (path.as_str(), *shape)
+                }
+                err => panic!("expected a wrong shape, found {err:?}"),
+            })
+            .collect();
+        assert_eq!(
+            shapes,
+            vec![
+                ("Shape::Empty", VariantShape::Unit),
+                ("Shape::Circle", VariantShape::Tuple),
+                ("Shape::Rect", VariantShape::Named),
+                ("Shape::Circle", VariantShape::Tuple),
+                ("Shape::Rect", VariantShape::Named),
+            ]
+        );
+
+        // The paths still resolve, even when they are used wrong
+        assert_eq!(resolution.paths.len(), 6);
+    }
+
+    #[test]
+    fn test_discriminants_in_any_base() {
+        let resolution = resolved(
+            "enum Flags { A, B = 0x10, C, D = 0b100, E = 0(3)12, F = -1, \
+             G = 1_000 }",
+        );
+        assert_eq!(resolution.errors, vec![]);
+
+        let discriminants: Vec<_> = resolution.enums["Flags"]
+            .variants
+            .iter()
+            .map(|variant| variant.discriminant)
+            .collect();
+        assert_eq!(discriminants, vec![0, 16, 17, 4, 5, -1, 1000]);
+    }
+
+    #[test]
+    fn test_broken_enums() {
+        let resolution =
+            resolved("enum E { A = 1, B = 0b1, A, C = 'c' }\nenum E { D }");
+
+        let errors: Vec<_> =
+            resolution.errors.iter().map(|err| &err.data).collect();
+        assert_eq!(
+            errors,
+            vec![
+                &ResolveError::DuplicateDiscriminant {
+                    variant: "B".into(),
+                    value: 1,
+                },
+                &ResolveError::DuplicateVariant("A".into()),
+                &ResolveError::InvalidDiscriminant("C".into()),
+                &ResolveError::DuplicateEnum("E".into()),
+            ]
+        );
+
+        // The first declaration is the one that is kept
+        assert_eq!(resolution.enums["E"].variants.len(), 3);
+    }
+
+    /// The files of a program, by their path.
+    struct Files(&'static [(&'static str, &'static str)]);
+
+    impl Loader for Files {
+        type Error = ();
+
+        fn read(&mut self, path: &str) -> Result<Option<String>, ()> {
+            let file = self.0.iter().find(|(name, _)| *name == path);
+            Ok(file.map(|(_, source)| source.to_string()))
+        }
+    }
+
+    const SHAPES: &str = "pub enum Shape { Circle(i32), Empty }\n\
+                          pub func area(s: Shape) : i32 = { 0 }\n\
+                          pub struct Point { x: i32 }\n\
+                          enum Hidden { A }\n\
+                          func helper() := {}\n\
+                          pub func id<T>(x: T) : T = { x }\n\
+                          pub trait Draw { func draw(self) }";
+
+    /// Resolve a program, with `main` importing `shapes` from [`SHAPES`].
+    fn resolved_program(main: &str) -> Resolution {
+        let mut files = Files(&[("shapes.ukl", SHAPES)]);
+        let graph = load("main.ukl", main.to_string(), &mut files).unwrap();
+        assert!(!graph.has_errors(), "{main}");
+
+        resolve_graph(&graph)
+    }
+
+    #[test]
+    fn test_paths_into_imported_modules() {
+        let resolution = resolved_program(
+            "import shapes\n\
+             func f(s: shapes::Shape) : i32 = {\n\
+                 shapes::area(shapes::Shape::Circle(1))\n\
+             }\n\
+             let p := shapes::Point { x: 1 }\n\
+             let e := match p { shapes::Shape::Empty => 0, _ => 1 }\n\
+             let i := shapes::id::<shapes::Point>(p)\n\
+             impl shapes::Draw for shapes::Point {}",
+        );
+        assert!(!resolution.has_errors());
+
+        let main = &resolution.modules[0];
+        let item = |name: &str| Def::Item {
+            module: 1,
+            name: name.into(),
+        };
+        let variant = |index| {
+            Def::Variant(VariantRef {
+                module: 1,
+                enum_name: "Shape".into(),
+                index,
+            })
+        };
+        let defs: Vec<_> = main.paths.iter().map(|path| &path.data).collect();
+        assert_eq!(
+            defs,
+            vec![
+                &item("Shape"),
+                &item("area"),
+                &variant(0),
+                &item("Point"),
+                &variant(1),
+                &item("id"),
+                &item("Point"),
+                &item("Draw"),
+                &item("Point"),
+            ]
+        );
+
+        // The imported module is resolved on its own too
+        assert_eq!(resolution.modules[1].items["area"].kind, DefKind::Func);
+    }
+
+    #[test]
+    fn test_private_items() {
+        let input = "import shapes\n\
+                     let a := shapes::helper()\n\
+                     let b := shapes::Hidden::A";
+        let resolution = resolved_program(input);
+
+        let helper = input.find("shapes::helper").unwrap();
+        let hidden = input.find("shapes::Hidden").unwrap();
+        assert_eq!(
+            resolution.modules[0].errors,
+            vec![
+                Spanned::new(
+                    ResolveError::Private("shapes::helper".into()),
+                    Span::new(helper, helper + 14)
+                ),
+                Spanned::new(
+                    ResolveError::Private("shapes::Hidden".into()),
+                    Span::new(hidden, hidden + 14)
+                ),
+            ]
+        );
+        assert_eq!(
+            resolution.modules[0].errors[0].data.to_string(),
+            "`shapes::helper` isn't `pub`, so it can only be used in its own \
+             module"
+        );
+    }
+
+    #[test]
+    fn test_unknown_items_and_wrong_kinds() {
+        let resolution = resolved_program(
+            "import shapes\n\
+             let a := shapes::oval()\n\
+             let b : shapes::area = shapes::Point\n\
+             let c := shapes::Shape::Oval\n\
+             let d : shapes::Shape::Empty = shapes::area\n\
+             func e<T: shapes::Point>(x: T) := {}\n\
+             let f : shapes::Draw = 1",
+        );
+
+        let errors: Vec<_> = resolution.modules[0]
+            .errors
+            .iter()
+            .map(|err| err.data.to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "module `shapes` has no item named `oval`",
+                "expected a type, but `shapes::area` is a function",
+                "expected a value, but `shapes::Point` is a struct",
+                "`shapes::Shape` has no variant named `Oval`",
+                "expected a type, but `shapes::Shape::Empty` is a variant",
+                "expected a trait, but `shapes::Point` is a struct",
+                "expected a type, but `shapes::Draw` is a trait",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_imports_are_not_followed_on_their_own() {
+        let resolution =
+            resolved("import shapes\nlet a := shapes::Shape::Empty");
+
+        assert_eq!(
+            resolution.errors[0].data,
+            ResolveError::UnknownEnum("shapes::Shape".into())
+        );
+    }
+}
@@ -0,0 +1,601 @@
+//! Building the green tree for a file.
+//!
+//! The file is parsed as usual, and then lexed again keeping whitespace, so
+//! that the tokens cover every byte. The nodes of the syntax tree come from
+//! the spans of the AST, and every token goes into the innermost node that
+//! covers it. Tokens which no node covers, like the `,` between parameters or
+//! a `;` after a statement, go into the node around them.
+
+use alloc::vec::Vec;
+
+use super::{green::GreenBuilder, kind::SyntaxKind};
+use crate::{
+    ast::{
+        Arm, Attribute, Block, Enum, Expr, ExprKind, Field, FieldInit,
+        FieldPat, Func, Ident, Impl, Item, ItemKind, LambdaParam, Let, Module,
+        Param, Pat, PatKind, Path, Stmt, StmtKind, Struct, Trait, TraitMethod,
+        TraitRef, Ty, TyKind, TypeParam, Variant, VariantFields,
+    },
+    lexer::{
+        position::{Span, Spanned},
+        state::Lexer,
+        tokens::Token,
+    },
+};
+
+/// Lex every byte of `input`, including whitespace and comments. Anything
+/// that fails to lex becomes a [`SyntaxKind::ErrorToken`], and lexing carries
+/// on after it.
+pub fn lex_lossless(input: &str) -> Vec<Spanned<SyntaxKind>> {
+    let mut lexer = Lexer::with_trivia(input);
+    let mut tokens = Vec::new();
+
+    loop {
+        match lexer.lex_spanned() {
+            Ok(tok) if tok.data == Token::Eof => return tokens,
+            Ok(tok) => {
+                tokens.push(Spanned::new(tok.data.kind().into(), tok.span))
+            }
+            Err(err) => {
+                // Make sure that lexing moves forward
+                if err.span.is_empty() && lexer.next_char().is_none() {
+                    return tokens;
+                }
+
+                let span = Span::new(err.span.start.0, lexer.pos);
+                tokens.push(Spanned::new(SyntaxKind::ErrorToken, span));
+            }
+        }
+    }
+}
+
+/// Places the tokens of a file into the nodes of its AST.
+pub struct TreeBuilder<'a> {
+    input: &'a str,
+    tokens: Vec<Spanned<SyntaxKind>>,
+    /// The next token which hasn't been added to the tree
+    next: usize,
+    builder: GreenBuilder,
+}
+
+impl<'a> TreeBuilder<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            tokens: lex_lossless(input),
+            next: 0,
+            builder: GreenBuilder::new(),
+        }
+    }
+
+    /// Build the tree for a whole file.
+    pub fn build(mut self, module: &Module) -> super::green::GreenNode {
+        let span = Span::new(0, self.input.len());
+        self.node(SyntaxKind::SourceFile, span, |b| {
+            b.attributes(&module.attrs);
+            module.items.iter().for_each(|item| b.item(item));
+        });
+
+        self.builder.finish()
+    }
+
+    /// Add every token which starts before `pos` to the current node.
+    fn tokens_before(&mut self, pos: usize) {
+        while let Some(tok) = self.tokens.get(self.next) {
+            if tok.span.start.0 >= pos {
+                break;
+            }
+
+            let text = &self.input[tok.span.start.0..tok.span.end.0];
+            self.builder.token(tok.data, text);
+            self.next += 1;
+        }
+    }
+
+    /// Add a node covering `span`, with `children` adding the nodes inside
+    /// of it.
+    fn node(
+        &mut self,
+        kind: SyntaxKind,
+        span: Span,
+        children: impl FnOnce(&mut Self),
+    ) {
+        self.tokens_before(span.start.0);
+        self.builder.start_node(kind);
+        children(self);
+        self.tokens_before(span.end.0);
+        self.builder.finish_node();
+    }
+
+    /// Add a node which only has tokens in it.
+    fn leaf(&mut self, kind: SyntaxKind, span: Span) {
+        self.node(kind, span, |_| {});
+    }
+
+    fn attributes(&mut self, attrs: &[Attribute]) {
+        for attr in attrs {
+            self.node(SyntaxKind::Attribute, attr.span, |b| {
+                b.name(&attr.data.name);
+            });
+        }
+    }
+
+    fn item(&mut self, item: &Item) {
+        self.attributes(&item.attrs);
+        match &item.data {
+            ItemKind::Func(func) => self.func(func, item.span),
+            ItemKind::Let(stmt) => self.let_decl(stmt, item.span),
+            ItemKind::Struct(decl) => self.struct_decl(decl, item.span),
+            ItemKind::Enum(decl) => self.enum_decl(decl, item.span),
+            ItemKind::Trait(decl) => self.trait_decl(decl, item.span),
+            ItemKind::Impl(decl) => self.impl_decl(decl, item.span),
+            ItemKind::Import(import) => {
+                self.node(SyntaxKind::ImportDecl, item.span, |b| {
+                    b.path(&import.path);
+                })
+            }
+            ItemKind::Macro(decl) => {
+                self.node(SyntaxKind::MacroDecl, item.span, |b| {
+                    b.name(&decl.name);
+                    decl.rules.iter().for_each(|rule| {
+                        b.leaf(SyntaxKind::MacroRule, rule.span)
+                    });
+                })
+            }
+            ItemKind::Error => self.leaf(SyntaxKind::Error, item.span),
+        }
+    }
+
+    fn name(&mut self, name: &Ident) {
+        self.leaf(SyntaxKind::Name, name.span);
+    }
+
+    fn label(&mut self, label: &Option<Ident>) {
+        if let Some(label) = label {
+            self.leaf(SyntaxKind::Label, label.span);
+        }
+    }
+
+    fn func(&mut self, func: &Func, span: Span) {
+        self.node(SyntaxKind::FuncDecl, span, |b| {
+            b.name(&func.name);
+            func.generics.iter().for_each(|param| b.type_param(param));
+            func.params.iter().for_each(|param| b.param(param));
+
+            if let Some(ret) = &func.ret {
+                b.node(SyntaxKind::RetType, ret.span, |b| b.ty(ret));
+            }
+
+            b.block(&func.body);
+        });
+    }
+
+    fn type_param(&mut self, param: &TypeParam) {
+        self.node(SyntaxKind::TypeParam, param.span, |b| {
+            b.name(&param.data.name);
+            let bounds = &param.data.bounds;
+            bounds.iter().for_each(|bound| b.trait_ref(bound));
+        });
+    }
+
+    fn trait_ref(&mut self, trait_ref: &TraitRef) {
+        self.node(SyntaxKind::TraitRef, trait_ref.span, |b| {
+            b.path(&trait_ref.data.path);
+            trait_ref.data.args.iter().for_each(|arg| b.ty(arg));
+        });
+    }
+
+    fn param(&mut self, param: &Param) {
+        self.node(SyntaxKind::Param, param.span, |b| {
+            b.name(&param.data.name);
+            b.ty(&param.data.ty);
+        });
+    }
+
+    fn struct_decl(&mut self, decl: &Struct, span: Span) {
+        self.node(SyntaxKind::StructDecl, span, |b| {
+            b.name(&decl.name);
+            decl.generics.iter().for_each(|param| b.type_param(param));
+            decl.fields.iter().for_each(|field| b.field(field));
+        });
+    }
+
+    fn field(&mut self, field: &Field) {
+        self.node(SyntaxKind::FieldDecl, field.span, |b| {
+            b.name(&field.data.name);
+            b.ty(&field.data.ty);
+        });
+    }
+
+    fn enum_decl(&mut self, decl: &Enum, span: Span) {
+        self.node(SyntaxKind::EnumDecl, span, |b| {
+            b.name(&decl.name);
+            decl.generics.iter().for_each(|param| b.type_param(param));
+            decl.variants.iter().for_each(|variant| b.variant(variant));
+        });
+    }
+
+    fn trait_decl(&mut self, decl: &Trait, span: Span) {
+        self.node(SyntaxKind::TraitDecl, span, |b| {
+            b.name(&decl.name);
+            decl.generics.iter().for_each(|param| b.type_param(param));
+            decl.methods
+                .iter()
+                .for_each(|method| b.trait_method(method));
+        });
+    }
+
+    fn trait_method(&mut self, method: &TraitMethod) {
+        self.node(SyntaxKind::TraitMethod, method.span, |b| {
+            b.name(&method.data.name);
+            let generics = &method.data.generics;
+            generics.iter().for_each(|param| b.type_param(param));
+            method.data.params.iter().for_each(|param| b.param(param));
+
+            if let Some(ret) = &method.data.ret {
+                b.node(SyntaxKind::RetType, ret.span, |b| b.ty(ret));
+            }
+            if let Some(default) = &method.data.default {
+                b.block(default);
+            }
+        });
+    }
+
+    fn impl_decl(&mut self, decl: &Impl, span: Span) {
+        self.node(SyntaxKind::ImplDecl, span, |b| {
+            decl.generics.iter().for_each(|param| b.type_param(param));
+            b.trait_ref(&decl.trait_ref);
+            b.ty(&decl.self_ty);
+
+            for method in &decl.methods {
+                b.func(&method.data, method.span);
+            }
+        });
+    }
+
+    fn variant(&mut self, variant: &Variant) {
+        self.node(SyntaxKind::Variant, variant.span, |b| {
+            b.name(&variant.data.name);
+
+            match &variant.data.fields {
+                VariantFields::Unit => {}
+                VariantFields::Tuple(tys) => tys.iter().for_each(|ty| b.ty(ty)),
+                VariantFields::Named(fields) => {
+                    fields.iter().for_each(|field| b.field(field));
+                }
+            }
+
+            let discriminant = &variant.data.discriminant;
+            discriminant.iter().for_each(|expr| b.expr(expr));
+        });
+    }
+
+    fn path(&mut self, path: &Path) {
+        self.leaf(SyntaxKind::Path, path.span);
+    }
+
+    fn ty(&mut self, ty: &Ty) {
+        match &ty.data {
+            TyKind::Named(_) => self.leaf(SyntaxKind::NamedType, ty.span),
+            TyKind::Path(_) => self.leaf(SyntaxKind::PathType, ty.span),
+            TyKind::Mut(inner) => {
+                self.node(SyntaxKind::MutType, ty.span, |b| b.ty(inner))
+            }
+            TyKind::Generic { args, .. } => {
+                self.node(SyntaxKind::GenericType, ty.span, |b| {
+                    args.iter().for_each(|arg| b.ty(arg));
+                })
+            }
+            TyKind::Array { elem, len } => {
+                self.node(SyntaxKind::ArrayType, ty.span, |b| {
+                    b.ty(elem);
+                    b.expr(len);
+                })
+            }
+            TyKind::Slice(elem) => {
+                self.node(SyntaxKind::SliceType, ty.span, |b| b.ty(elem))
+            }
+            TyKind::Tuple(elems) => {
+                self.node(SyntaxKind::TupleType, ty.span, |b| {
+                    elems.iter().for_each(|elem| b.ty(elem));
+                })
+            }
+            TyKind::Func { params, ret } => {
+                self.node(SyntaxKind::FuncType, ty.span, |b| {
+                    params.iter().for_each(|param| b.ty(param));
+                    b.node(SyntaxKind::RetType, ret.span, |b| b.ty(ret));
+                })
+            }
+            TyKind::Ref { ty: inner, .. } => {
+                self.node(SyntaxKind::RefType, ty.span, |b| b.ty(inner))
+            }
+            TyKind::Paren(inner) => {
+                self.node(SyntaxKind::ParenType, ty.span, |b| b.ty(inner))
+            }
+            TyKind::Error => self.leaf(SyntaxKind::Error, ty.span),
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.node(SyntaxKind::Block, block.span, |b| {
+            b.attributes(&block.data.attrs);
+            block.data.stmts.iter().for_each(|stmt| b.stmt(stmt));
+
+            if let Some(tail) = &block.data.tail {
+                b.expr(tail);
+            }
+        });
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        self.attributes(&stmt.attrs);
+        let span = stmt.span;
+
+        match &stmt.data {
+            StmtKind::Let(let_stmt) => self.let_decl(let_stmt, span),
+            StmtKind::Return(value) => {
+                self.node(SyntaxKind::ReturnStmt, span, |b| {
+                    value.iter().for_each(|value| b.expr(value));
+                })
+            }
+            StmtKind::Assign { target, value, .. } => {
+                self.node(SyntaxKind::AssignStmt, span, |b| {
+                    b.expr(target);
+                    b.expr(value);
+                })
+            }
+            StmtKind::Break { label, value } => {
+                self.node(SyntaxKind::BreakStmt, span, |b| {
+                    b.label(label);
+                    value.iter().for_each(|value| b.expr(value));
+                })
+            }
+            StmtKind::Continue { label } => {
+                self.node(SyntaxKind::ContinueStmt, span, |b| b.label(label))
+            }
+            StmtKind::Expr(expr) => {
+                self.node(SyntaxKind::ExprStmt, span, |b| b.expr(expr))
+            }
+            StmtKind::Error => self.leaf(SyntaxKind::Error, span),
+        }
+    }
+
+    fn let_decl(&mut self, stmt: &Let, span: Span) {
+        self.node(SyntaxKind::LetDecl, span, |b| {
+            b.name(&stmt.name);
+
+            if let Some(ty) = &stmt.ty {
+                b.ty(ty);
+            }
+
+            b.expr(&stmt.value);
+        });
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        let span = expr.span;
+
+        match &expr.data {
+            ExprKind::Literal(_) => self.leaf(SyntaxKind::Literal, span),
+            ExprKind::Ident(_) => self.leaf(SyntaxKind::NameRef, span),
+            ExprKind::Path(_) => self.leaf(SyntaxKind::PathExpr, span),
+            ExprKind::Instantiate { path, args } => {
+                self.node(SyntaxKind::InstantiateExpr, span, |b| {
+                    b.path(path);
+                    args.iter().for_each(|arg| b.ty(arg));
+                })
+            }
+            ExprKind::Unary { operand, .. } => {
+                self.node(SyntaxKind::UnaryExpr, span, |b| b.expr(operand))
+            }
+            ExprKind::Binary { lhs, rhs, .. } => {
+                self.node(SyntaxKind::BinaryExpr, span, |b| {
+                    b.expr(lhs);
+                    b.expr(rhs);
+                })
+            }
+            ExprKind::Call { callee, args } => {
+                self.node(SyntaxKind::CallExpr, span, |b| {
+                    b.expr(callee);
+                    args.iter().for_each(|arg| b.expr(arg));
+                })
+            }
+            ExprKind::Field { base, .. } => {
+                self.node(SyntaxKind::FieldExpr, span, |b| b.expr(base))
+            }
+            ExprKind::StructLit { path, fields, base } => {
+                self.node(SyntaxKind::StructExpr, span, |b| {
+                    b.path(path);
+                    fields.iter().for_each(|init| b.field_init(init));
+                    base.iter().for_each(|base| b.expr(base));
+                })
+            }
+            ExprKind::Paren(inner) => {
+                self.node(SyntaxKind::ParenExpr, span, |b| b.expr(inner))
+            }
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::If {
+                cond,
+                then,
+                else_branch,
+            } => self.node(SyntaxKind::IfExpr, span, |b| {
+                b.expr(cond);
+                b.block(then);
+                else_branch
+                    .iter()
+                    .for_each(|else_branch| b.expr(else_branch));
+            }),
+            ExprKind::While { label, cond, body } => {
+                self.node(SyntaxKind::WhileExpr, span, |b| {
+                    b.label(label);
+                    b.expr(cond);
+                    b.block(body);
+                })
+            }
+            ExprKind::Loop { label, body } => {
+                self.node(SyntaxKind::LoopExpr, span, |b| {
+                    b.label(label);
+                    b.block(body);
+                })
+            }
+            ExprKind::For {
+                label,
+                binding,
+                iter,
+                body,
+            } => self.node(SyntaxKind::ForExpr, span, |b| {
+                b.label(label);
+                b.name(binding);
+                b.expr(iter);
+                b.block(body);
+            }),
+            ExprKind::Range { start, end, .. } => {
+                self.node(SyntaxKind::RangeExpr, span, |b| {
+                    b.expr(start);
+                    b.expr(end);
+                })
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                self.node(SyntaxKind::MatchExpr, span, |b| {
+                    b.expr(scrutinee);
+                    arms.iter().for_each(|arm| b.arm(arm));
+                })
+            }
+            ExprKind::Lambda { params, ret, body } => {
+                self.node(SyntaxKind::LambdaExpr, span, |b| {
+                    params.iter().for_each(|param| b.lambda_param(param));
+
+                    if let Some(ret) = ret {
+                        b.node(SyntaxKind::RetType, ret.span, |b| b.ty(ret));
+                    }
+
+                    b.expr(body);
+                })
+            }
+            ExprKind::MacroCall { .. } => {
+                self.leaf(SyntaxKind::MacroCall, span)
+            }
+            ExprKind::Error => self.leaf(SyntaxKind::Error, span),
+        }
+    }
+
+    fn field_init(&mut self, init: &FieldInit) {
+        self.node(SyntaxKind::FieldInit, init.span, |b| {
+            init.data.value.iter().for_each(|value| b.expr(value));
+        });
+    }
+
+    fn lambda_param(&mut self, param: &LambdaParam) {
+        self.node(SyntaxKind::Param, param.span, |b| {
+            b.name(&param.data.name);
+            param.data.ty.iter().for_each(|ty| b.ty(ty));
+        });
+    }
+
+    fn arm(&mut self, arm: &Arm) {
+        self.node(SyntaxKind::MatchArm, arm.span, |b| {
+            b.pat(&arm.data.pat);
+            arm.data.guard.iter().for_each(|guard| b.expr(guard));
+            b.expr(&arm.data.body);
+        });
+    }
+
+    fn pat(&mut self, pat: &Pat) {
+        let span = pat.span;
+
+        match &pat.data {
+            PatKind::Wild => self.leaf(SyntaxKind::WildcardPat, span),
+            PatKind::Binding(_) => self.leaf(SyntaxKind::BindingPat, span),
+            PatKind::Path(_) => self.leaf(SyntaxKind::PathPat, span),
+            PatKind::TupleStruct { path, elems } => {
+                self.node(SyntaxKind::TupleStructPat, span, |b| {
+                    b.path(path);
+                    elems.iter().for_each(|elem| b.pat(elem));
+                })
+            }
+            PatKind::Struct { path, fields, .. } => {
+                self.node(SyntaxKind::StructPat, span, |b| {
+                    b.path(path);
+                    fields.iter().for_each(|field| b.field_pat(field));
+                })
+            }
+            PatKind::Literal(_) => self.leaf(SyntaxKind::LiteralPat, span),
+            PatKind::Range { start, end, .. } => {
+                self.node(SyntaxKind::RangePat, span, |b| {
+                    b.pat(start);
+                    b.pat(end);
+                })
+            }
+            PatKind::Tuple(elems) => {
+                self.node(SyntaxKind::TuplePat, span, |b| {
+                    elems.iter().for_each(|elem| b.pat(elem));
+                })
+            }
+            PatKind::Paren(inner) => {
+                self.node(SyntaxKind::ParenPat, span, |b| b.pat(inner))
+            }
+            PatKind::Or(alts) => self.node(SyntaxKind::OrPat, span, |b| {
+                alts.iter().for_each(|alt| b.pat(alt));
+            }),
+            PatKind::At { name, pat } => {
+                self.node(SyntaxKind::AtPat, span, |b| {
+                    b.name(name);
+                    b.pat(pat);
+                })
+            }
+            PatKind::Error => self.leaf(SyntaxKind::Error, span),
+        }
+    }
+
+    fn field_pat(&mut self, field: &FieldPat) {
+        self.node(SyntaxKind::FieldPat, field.span, |b| {
+            field.data.pat.iter().for_each(|pat| b.pat(pat));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec};
+
+    use super::*;
+    use crate::lexer::kind::TokenKind;
+
+    #[test]
+    fn test_lossless_tokens_cover_input() {
+        let input = "let a := 1 /* b */\n  + 2\nc ` \"d";
+        let tokens = lex_lossless(input);
+
+        let text: String = tokens
+            .iter()
+            .map(|tok| &input[tok.span.start.0..tok.span.end.0])
+            .collect();
+        assert_eq!(text, input);
+
+        let errors: Vec<_> = tokens
+            .iter()
+            .filter(|tok| tok.data == SyntaxKind::ErrorToken)
+            .map(|tok| &input[tok.span.start.0..tok.span.end.0])
+            .collect();
+        assert_eq!(errors, vec!["`", "\"d"]);
+    }
+
+    #[test]
+    fn test_newlines_keep_virtual_semicolons() {
+        let kinds: Vec<_> = lex_lossless("a\n\n  b")
+            .into_iter()
+            .map(|tok| tok.data)
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Ident.into(),
+                TokenKind::VirtualSemi.into(),
+                TokenKind::Whitespace.into(),
+                TokenKind::Whitespace.into(),
+                TokenKind::Ident.into(),
+            ]
+        );
+    }
+}
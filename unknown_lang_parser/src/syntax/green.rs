@@ -0,0 +1,206 @@
+//! The green tree, which holds the text of the file.
+//!
+//! Green nodes don't know where they are in the file, only how long they
+//! are, so they are immutable and identical subtrees can be shared. Positions
+//! and parents are worked out by the red tree in [`red`](super::red) as it is
+//! walked.
+
+use alloc::{string::String, sync::Arc, vec::Vec};
+
+use super::kind::SyntaxKind;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    text_len: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        let text_len = children.iter().map(GreenElement::text_len).sum();
+
+        Self {
+            kind,
+            text_len,
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    /// The length of the text of this node, in bytes.
+    pub fn text_len(&self) -> usize {
+        self.text_len
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+
+    /// Write the text of every token in this node to `out`.
+    pub fn write_text(&self, out: &mut String) {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => node.write_text(out),
+                GreenElement::Token(tok) => out.push_str(tok.text()),
+            }
+        }
+    }
+}
+
+impl core::fmt::Display for GreenNode {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let mut text = String::with_capacity(self.text_len);
+        self.write_text(&mut text);
+
+        write!(f, "{text}")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GreenToken {
+    kind: SyntaxKind,
+    text: String,
+}
+
+impl GreenToken {
+    pub fn new(kind: SyntaxKind, text: String) -> Self {
+        Self { kind, text }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    /// The text of this token, exactly as it was written.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn text_len(&self) -> usize {
+        self.text.len()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GreenElement {
+    Node(Arc<GreenNode>),
+    Token(Arc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            GreenElement::Node(node) => node.kind(),
+            GreenElement::Token(tok) => tok.kind(),
+        }
+    }
+
+    pub fn text_len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.text_len(),
+            GreenElement::Token(tok) => tok.text_len(),
+        }
+    }
+}
+
+/// Builds a green tree from the top down, one node or token at a time.
+#[derive(Debug, Default)]
+pub struct GreenBuilder {
+    /// The nodes which have been started but not finished, along with where
+    /// their children start in `children`
+    parents: Vec<(SyntaxKind, usize)>,
+    /// The children of every unfinished node, innermost last
+    children: Vec<GreenElement>,
+}
+
+impl GreenBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a new node, which everything added until the matching
+    /// [`finish_node`](Self::finish_node) is a child of.
+    pub fn start_node(&mut self, kind: SyntaxKind) {
+        self.parents.push((kind, self.children.len()));
+    }
+
+    /// Add a token to the current node.
+    pub fn token(&mut self, kind: SyntaxKind, text: &str) {
+        let tok = GreenToken::new(kind, text.into());
+        self.children.push(GreenElement::Token(Arc::new(tok)));
+    }
+
+    /// Finish the current node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no node to finish.
+    pub fn finish_node(&mut self) {
+        let (kind, first) = self.parents.pop().expect("no node to finish");
+        let children = self.children.split_off(first);
+
+        let node = GreenNode::new(kind, children);
+        self.children.push(GreenElement::Node(Arc::new(node)));
+    }
+
+    /// Finish building, giving back the root node.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless every node has been finished, leaving a single node at
+    /// the root.
+    pub fn finish(mut self) -> GreenNode {
+        assert!(self.parents.is_empty(), "unfinished nodes");
+
+        match self.children.pop() {
+            Some(GreenElement::Node(node)) if self.children.is_empty() => {
+                Arc::unwrap_or_clone(node)
+            }
+            _ => panic!("expected a single root node"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec};
+
+    use super::*;
+    use crate::lexer::kind::TokenKind;
+
+    #[test]
+    fn test_builder() {
+        let mut builder = GreenBuilder::new();
+        builder.start_node(SyntaxKind::BinaryExpr);
+        builder.start_node(SyntaxKind::NameRef);
+        builder.token(TokenKind::Ident.into(), "a");
+        builder.finish_node();
+        builder.token(TokenKind::Whitespace.into(), " ");
+        builder.token(TokenKind::Plus.into(), "+");
+        builder.finish_node();
+
+        let node = builder.finish();
+        assert_eq!(node.kind(), SyntaxKind::BinaryExpr);
+        assert_eq!(node.text_len(), 3);
+        assert_eq!(node.children().len(), 3);
+        assert_eq!(node.to_string(), "a +");
+    }
+
+    #[test]
+    fn test_shared_subtrees_are_equal() {
+        let leaf = |text: &str| {
+            let tok = GreenToken::new(TokenKind::Ident.into(), text.into());
+            GreenNode::new(
+                SyntaxKind::NameRef,
+                vec![GreenElement::Token(Arc::new(tok))],
+            )
+        };
+
+        assert_eq!(leaf("a"), leaf("a"));
+        assert_ne!(leaf("a"), leaf("b"));
+    }
+}
@@ -0,0 +1,188 @@
+use crate::lexer::kind::TokenKind;
+
+/// The kind of a node or token in the syntax tree.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    /// A token, including whitespace and comments
+    Token(TokenKind),
+    /// Input which failed to lex
+    ErrorToken,
+
+    /// A whole file
+    SourceFile,
+    FuncDecl,
+    /// A parameter of a function or a lambda, where the type of a lambda
+    /// parameter may be left off
+    Param,
+    /// A type parameter of a generic function or type, like the `T` of
+    /// `func id<T>(x: T) : T`
+    TypeParam,
+    /// The return type of a function, after the `:` or `->`
+    RetType,
+    /// A variable declaration, either at the top level or in a block
+    LetDecl,
+    StructDecl,
+    /// A field of a struct declaration, like `x: i32`
+    FieldDecl,
+    EnumDecl,
+    /// A variant of an enum declaration, with its discriminant after its
+    /// values if it has one
+    Variant,
+    TraitDecl,
+    /// A method of a trait, with its default body last if it has one
+    TraitMethod,
+    /// `impl Display for Point { .. }`, with the trait in a
+    /// [`SyntaxKind::TraitRef`] before the type it is implemented for
+    ImplDecl,
+    /// A trait named in a bound or an impl, like `Add<u8>`, with its path in
+    /// a [`SyntaxKind::Path`]
+    TraitRef,
+    /// `import shapes::circle`, with the module in a [`SyntaxKind::Path`]
+    ImportDecl,
+    /// `macro max { .. }`, with the tokens of each rule left as they are
+    MacroDecl,
+    /// A rule of a macro, like `($a:expr) => { $a }`
+    MacroRule,
+    /// An attribute, like `#[allow(unused)]`, with its name in a
+    /// [`SyntaxKind::Name`] and the tokens after it left as they are. It is
+    /// before the node of what it is on, rather than inside of it.
+    Attribute,
+    /// A name being declared, like a function or a parameter
+    Name,
+    /// The label of a loop, or the label that a `break` or `continue` goes to
+    Label,
+    /// The path naming a struct, variant or module, like `Shape::Circle`
+    Path,
+
+    NamedType,
+    /// A type with more than one segment, like `shapes::Circle`
+    PathType,
+    MutType,
+    /// `Option<T>`
+    GenericType,
+    ArrayType,
+    SliceType,
+    TupleType,
+    /// `(A, B) -> C`, with the return type in a [`SyntaxKind::RetType`]
+    FuncType,
+    RefType,
+    ParenType,
+
+    Block,
+    ReturnStmt,
+    AssignStmt,
+    ExprStmt,
+    BreakStmt,
+    ContinueStmt,
+
+    Literal,
+    /// A name used in an expression
+    NameRef,
+    /// A path with more than one segment used in an expression
+    PathExpr,
+    /// `id::<u8>`, with the path in a [`SyntaxKind::Path`] before the types
+    InstantiateExpr,
+    UnaryExpr,
+    BinaryExpr,
+    CallExpr,
+    /// `base.field`, with the name of the field left as a token
+    FieldExpr,
+    /// A struct literal, with its [`SyntaxKind::Path`] first and `..base`
+    /// left as tokens around its expression
+    StructExpr,
+    /// A field in a struct literal, which is only a name for the shorthand
+    FieldInit,
+    ParenExpr,
+    IfExpr,
+    WhileExpr,
+    LoopExpr,
+    ForExpr,
+    RangeExpr,
+    MatchExpr,
+    /// An arm of a `match`, with the guard before the body if there is one
+    MatchArm,
+    /// `|a, b| a + b`, with the return type in a [`SyntaxKind::RetType`]
+    LambdaExpr,
+    /// `max!(a, b)`, with the tokens between the delimiters left as they are
+    MacroCall,
+
+    /// `_`
+    WildcardPat,
+    BindingPat,
+    LiteralPat,
+    RangePat,
+    TuplePat,
+    ParenPat,
+    /// `a | b`
+    OrPat,
+    /// `name @ pat`, with the name in a [`SyntaxKind::Name`]
+    AtPat,
+    /// A path with more than one segment used as a pattern
+    PathPat,
+    /// `Shape::Circle(r)`, with the path in a [`SyntaxKind::Path`]
+    TupleStructPat,
+    /// `Shape::Rect { w, .. }`, with the path in a [`SyntaxKind::Path`]
+    StructPat,
+    /// A field in a struct pattern, which is only a name for the shorthand
+    FieldPat,
+
+    /// Anything which failed to parse
+    Error,
+}
+
+impl SyntaxKind {
+    /// Checks if this is the kind of a token, rather than a node.
+    pub fn is_token(self) -> bool {
+        matches!(self, SyntaxKind::Token(_) | SyntaxKind::ErrorToken)
+    }
+
+    /// Checks if this is a token without any meaning to the parser, which is
+    /// either whitespace or a comment.
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Token(TokenKind::Whitespace | TokenKind::Comment)
+        )
+    }
+
+    /// Checks if this is the kind of a type node.
+    pub fn is_type(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::NamedType
+                | SyntaxKind::PathType
+                | SyntaxKind::MutType
+                | SyntaxKind::GenericType
+                | SyntaxKind::ArrayType
+                | SyntaxKind::SliceType
+                | SyntaxKind::TupleType
+                | SyntaxKind::FuncType
+                | SyntaxKind::RefType
+                | SyntaxKind::ParenType
+        )
+    }
+
+    /// Checks if this is the kind of a pattern node.
+    pub fn is_pattern(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::WildcardPat
+                | SyntaxKind::BindingPat
+                | SyntaxKind::LiteralPat
+                | SyntaxKind::RangePat
+                | SyntaxKind::TuplePat
+                | SyntaxKind::ParenPat
+                | SyntaxKind::OrPat
+                | SyntaxKind::AtPat
+                | SyntaxKind::PathPat
+                | SyntaxKind::TupleStructPat
+                | SyntaxKind::StructPat
+        )
+    }
+}
+
+impl From<TokenKind> for SyntaxKind {
+    fn from(kind: TokenKind) -> Self {
+        SyntaxKind::Token(kind)
+    }
+}
@@ -0,0 +1,140 @@
+//! A lossless syntax tree, which keeps every byte of the file.
+//!
+//! The [`ast`](crate::ast) drops anything without meaning, like whitespace,
+//! comments and parenthesis. Tools like a formatter need all of it, so this
+//! tree is made up of every token of the file, grouped into nodes. Printing
+//! the tree gives back the file exactly, even when it has errors in it.
+//!
+//! The tree is built in two layers. The [`green`] tree is immutable and only
+//! knows the text of each node, while the [`red`] tree is created on top of
+//! it as it is walked, and knows the parent and position of every node. The
+//! types in [`nodes`] give typed accessors over the red tree.
+
+pub mod build;
+pub mod green;
+pub mod kind;
+pub mod nodes;
+pub mod red;
+
+use alloc::{sync::Arc, vec::Vec};
+
+use crate::{
+    lexer::{position::Spanned, state::Lexer},
+    parser::{errors::ParseError, state::Parser},
+};
+
+use self::{
+    build::TreeBuilder,
+    green::GreenNode,
+    nodes::{AstNode, SourceFile},
+    red::SyntaxNode,
+};
+
+/// The result of parsing a file into a syntax tree.
+#[derive(Clone, Debug)]
+pub struct Parse {
+    green: Arc<GreenNode>,
+    /// Every error that was found while parsing
+    pub errors: Vec<Spanned<ParseError>>,
+}
+
+impl Parse {
+    /// Get the root of the tree.
+    pub fn syntax(&self) -> SyntaxNode {
+        SyntaxNode::new_root(self.green.clone())
+    }
+
+    /// Get the root of the tree, with typed accessors.
+    pub fn tree(&self) -> SourceFile {
+        SourceFile::cast(self.syntax()).expect("the root is a source file")
+    }
+}
+
+/// Parse a whole file into a syntax tree.
+pub fn parse(input: &str) -> Parse {
+    let mut parser = Parser::new(Lexer::new(input));
+    let module = parser.parse_module();
+    let green = TreeBuilder::new(input).build(&module);
+
+    Parse {
+        green: Arc::new(green),
+        errors: parser.errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::syntax::kind::SyntaxKind;
+
+    #[test]
+    fn test_round_trip() {
+        for input in [
+            "",
+            "  \n// only a comment",
+            "func f(a : usize ,) := {\n    return ( a ** g ) (1, -2)\n}\n",
+            "let a := 0x15; /* done */ \r\n",
+            "func f(a: =) : = { let b = ; c d }\n} func",
+            "let s := \"unclosed\n\nfunc g() := {}",
+            "let ü := 'ü' @ b\"x\"",
+            "let a : &&mut Option<Vec<(u8, )>>= b\nlet c : [ u8 ; 2 ] = d",
+        ] {
+            let parse = parse(input);
+            assert_eq!(parse.syntax().to_string(), input);
+            assert_eq!(parse.syntax().text_range().end.0, input.len());
+        }
+    }
+
+    #[test]
+    fn test_tokens_go_in_innermost_node() {
+        let parse = parse("let a := (b + 1) // c\n");
+        let root = parse.syntax();
+
+        assert_eq!(
+            root.debug_tree(),
+            [
+                "SourceFile@0..22",
+                "  LetDecl@0..16",
+                "    Let@0..3 \"let\"",
+                "    Whitespace@3..4 \" \"",
+                "    Name@4..5",
+                "      Ident@4..5 \"a\"",
+                "    Whitespace@5..6 \" \"",
+                "    Colon@6..7 \":\"",
+                "    Eq@7..8 \"=\"",
+                "    Whitespace@8..9 \" \"",
+                "    ParenExpr@9..16",
+                "      LPar@9..10 \"(\"",
+                "      BinaryExpr@10..15",
+                "        NameRef@10..11",
+                "          Ident@10..11 \"b\"",
+                "        Whitespace@11..12 \" \"",
+                "        Plus@12..13 \"+\"",
+                "        Whitespace@13..14 \" \"",
+                "        Literal@14..15",
+                "          Number@14..15 \"1\"",
+                "      RPar@15..16 \")\"",
+                "  Whitespace@16..17 \" \"",
+                "  Comment@17..21 \"// c\"",
+                "  Whitespace@21..22 \"\\n\"",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_errors_are_kept() {
+        let parse = parse("func f() := { a b }");
+
+        assert_eq!(parse.errors.len(), 1);
+        let error = parse
+            .syntax()
+            .descendants()
+            .find(|node| node.kind() == SyntaxKind::Error)
+            .unwrap();
+        assert_eq!(error.text(), "a b");
+    }
+}
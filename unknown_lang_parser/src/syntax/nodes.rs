@@ -0,0 +1,221 @@
+//! Typed views over the nodes of the syntax tree.
+//!
+//! Each type wraps a [`SyntaxNode`] of a single kind, with accessors for the
+//! parts of it, like [`FuncDecl::params`]. Accessors give back `None` when a
+//! part is missing, which happens when the file has errors in it.
+
+use alloc::string::String;
+
+use super::{kind::SyntaxKind, red::SyntaxNode};
+use crate::lexer::kind::TokenKind;
+
+/// A typed view over a [`SyntaxNode`].
+pub trait AstNode: Sized {
+    /// Wrap `node`, if it is of the right kind.
+    fn cast(node: SyntaxNode) -> Option<Self>;
+
+    fn syntax(&self) -> &SyntaxNode;
+}
+
+/// Declare a typed view over nodes of one kind.
+macro_rules! ast_node {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[derive(Clone, Debug, PartialEq, Eq)]
+        pub struct $name(SyntaxNode);
+
+        impl AstNode for $name {
+            fn cast(node: SyntaxNode) -> Option<Self> {
+                match node.kind() {
+                    SyntaxKind::$name => Some(Self(node)),
+                    _ => None,
+                }
+            }
+
+            fn syntax(&self) -> &SyntaxNode {
+                &self.0
+            }
+        }
+    };
+}
+
+/// Get the first child of `node` which is an `N`.
+fn child<N: AstNode>(node: &SyntaxNode) -> Option<N> {
+    node.children().find_map(N::cast)
+}
+
+/// Get every child of `node` which is an `N`.
+fn children<N: AstNode>(node: &SyntaxNode) -> impl Iterator<Item = N> {
+    node.children().filter_map(N::cast)
+}
+
+ast_node!(
+    /// A whole file
+    SourceFile
+);
+
+impl SourceFile {
+    pub fn funcs(&self) -> impl Iterator<Item = FuncDecl> {
+        children(&self.0)
+    }
+
+    /// Get the variables declared at the top level of the file.
+    pub fn lets(&self) -> impl Iterator<Item = LetDecl> {
+        children(&self.0)
+    }
+}
+
+ast_node!(
+    /// `func name(params) : ret = { body }`
+    FuncDecl
+);
+
+impl FuncDecl {
+    pub fn name(&self) -> Option<Name> {
+        child(&self.0)
+    }
+
+    pub fn params(&self) -> impl Iterator<Item = Param> {
+        children(&self.0)
+    }
+
+    pub fn ret_type(&self) -> Option<RetType> {
+        child(&self.0)
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        child(&self.0)
+    }
+}
+
+ast_node!(
+    /// `name : type`
+    Param
+);
+
+impl Param {
+    pub fn name(&self) -> Option<Name> {
+        child(&self.0)
+    }
+
+    pub fn ty(&self) -> Option<SyntaxNode> {
+        self.0.children().find(|node| node.kind().is_type())
+    }
+}
+
+ast_node!(
+    /// The type after the `:` of a function
+    RetType
+);
+
+impl RetType {
+    pub fn ty(&self) -> Option<SyntaxNode> {
+        self.0.children().find(|node| node.kind().is_type())
+    }
+}
+
+ast_node!(
+    /// `let name : type = value`, or `let name := value`
+    LetDecl
+);
+
+impl LetDecl {
+    pub fn name(&self) -> Option<Name> {
+        child(&self.0)
+    }
+
+    pub fn ty(&self) -> Option<SyntaxNode> {
+        self.0.children().find(|node| node.kind().is_type())
+    }
+
+    /// The value given to the variable, which is always the last node.
+    pub fn value(&self) -> Option<SyntaxNode> {
+        self.0
+            .children()
+            .last()
+            .filter(|node| node.kind() != SyntaxKind::Name)
+    }
+}
+
+ast_node!(
+    /// A name being declared
+    Name
+);
+
+impl Name {
+    /// The name, without any trivia around it.
+    pub fn text(&self) -> String {
+        self.0
+            .child_tokens()
+            .find(|tok| tok.kind() == TokenKind::Ident.into())
+            .map_or_else(String::new, |tok| tok.text().into())
+    }
+}
+
+ast_node!(
+    /// `{ statements }`
+    Block
+);
+
+impl Block {
+    /// Every statement in the block, including the expression at the end of
+    /// it.
+    pub fn statements(&self) -> impl Iterator<Item = SyntaxNode> {
+        self.0.children()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use super::*;
+    use crate::syntax::parse;
+
+    #[test]
+    fn test_func_accessors() {
+        let file =
+            parse("func add(a: usize, b : mut u8) : usize = {\n    a + b\n}")
+                .tree();
+        let func = file.funcs().next().unwrap();
+
+        assert_eq!(func.name().unwrap().text(), "add");
+
+        let params: Vec<_> = func
+            .params()
+            .map(|param| {
+                (param.name().unwrap().text(), param.ty().unwrap().text())
+            })
+            .collect();
+        assert_eq!(
+            params,
+            vec![("a".into(), "usize".into()), ("b".into(), "mut u8".into())]
+        );
+
+        let ret = func.ret_type().unwrap().ty().unwrap();
+        assert_eq!(ret.kind(), SyntaxKind::NamedType);
+        assert_eq!(func.body().unwrap().statements().count(), 1);
+    }
+
+    #[test]
+    fn test_let_accessors() {
+        let file = parse("let a : i32 = 1 + 2\nlet b := c").tree();
+        let lets: Vec<_> = file.lets().collect();
+
+        assert_eq!(lets[0].ty().unwrap().text(), "i32");
+        assert_eq!(lets[0].value().unwrap().text(), "1 + 2");
+        assert_eq!(lets[1].ty(), None);
+        assert_eq!(lets[1].value().unwrap().kind(), SyntaxKind::NameRef);
+    }
+
+    #[test]
+    fn test_missing_parts() {
+        let file = parse("func f(a: ) := {}").tree();
+        let func = file.funcs().next().unwrap();
+        let param = func.params().next().unwrap();
+
+        assert_eq!(param.name().unwrap().text(), "a");
+        assert_eq!(param.ty(), None);
+        assert_eq!(func.ret_type(), None);
+    }
+}
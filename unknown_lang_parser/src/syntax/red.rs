@@ -0,0 +1,346 @@
+//! The red tree, a view over the green tree with positions and parents.
+//!
+//! A [`SyntaxNode`] is created for the root, and every other node is created
+//! as the tree is walked, keeping a pointer to its parent along with where it
+//! starts in the file. Cloning a node only clones a pointer.
+
+use alloc::{format, string::String, sync::Arc, vec::Vec};
+use core::fmt::Write;
+
+use super::{
+    green::{GreenElement, GreenNode, GreenToken},
+    kind::SyntaxKind,
+};
+use crate::lexer::position::Span;
+
+#[derive(Clone)]
+pub struct SyntaxNode(Arc<NodeData>);
+
+struct NodeData {
+    green: Arc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    /// Where this node starts in the file
+    offset: usize,
+}
+
+impl SyntaxNode {
+    /// Create the root of a tree.
+    pub fn new_root(green: Arc<GreenNode>) -> Self {
+        Self(Arc::new(NodeData {
+            green,
+            parent: None,
+            offset: 0,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind()
+    }
+
+    pub fn green(&self) -> &Arc<GreenNode> {
+        &self.0.green
+    }
+
+    /// The range of the file that this node covers.
+    pub fn text_range(&self) -> Span {
+        let start = self.0.offset;
+        Span::new(start, start + self.0.green.text_len())
+    }
+
+    /// The text of this node, exactly as it was written, including any
+    /// whitespace and comments within it.
+    pub fn text(&self) -> String {
+        let mut text = String::with_capacity(self.0.green.text_len());
+        self.0.green.write_text(&mut text);
+        text
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    /// Iterate over this node and every node above it, up to the root.
+    pub fn ancestors(&self) -> impl Iterator<Item = SyntaxNode> {
+        core::iter::successors(Some(self.clone()), SyntaxNode::parent)
+    }
+
+    /// Get every child of this node, both nodes and tokens, in order.
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        let mut children = Vec::with_capacity(self.0.green.children().len());
+
+        for child in self.0.green.children() {
+            children.push(match child {
+                GreenElement::Node(green) => {
+                    SyntaxElement::Node(Self(Arc::new(NodeData {
+                        green: green.clone(),
+                        parent: Some(self.clone()),
+                        offset,
+                    })))
+                }
+                GreenElement::Token(green) => {
+                    SyntaxElement::Token(SyntaxToken {
+                        green: green.clone(),
+                        parent: self.clone(),
+                        offset,
+                    })
+                }
+            });
+
+            offset += child.text_len();
+        }
+
+        children
+    }
+
+    /// Get every child node of this node, skipping over tokens.
+    pub fn children(&self) -> impl Iterator<Item = SyntaxNode> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(SyntaxElement::into_node)
+    }
+
+    /// Get the tokens directly within this node, skipping over nodes.
+    pub fn child_tokens(&self) -> impl Iterator<Item = SyntaxToken> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(SyntaxElement::into_token)
+    }
+
+    /// Iterate over this node and every node within it, in the order they
+    /// appear in the file.
+    pub fn descendants(&self) -> impl Iterator<Item = SyntaxNode> {
+        let mut stack = Vec::from([self.clone()]);
+
+        core::iter::from_fn(move || {
+            let node = stack.pop()?;
+            let len = stack.len();
+            stack.extend(node.children());
+            stack[len..].reverse();
+
+            Some(node)
+        })
+    }
+
+    /// Find the innermost node which covers all of `range`.
+    pub fn covering_node(&self, range: Span) -> SyntaxNode {
+        let mut node = self.clone();
+
+        'outer: loop {
+            for child in node.children() {
+                let span = child.text_range();
+
+                if span.start <= range.start && range.end <= span.end {
+                    node = child;
+                    continue 'outer;
+                }
+            }
+
+            return node;
+        }
+    }
+
+    /// Print the structure of the tree under this node, with one node or
+    /// token on every line.
+    pub fn debug_tree(&self) -> String {
+        let mut out = String::new();
+        self.write_debug_tree(0, &mut out);
+        out
+    }
+
+    fn write_debug_tree(&self, depth: usize, out: &mut String) {
+        let _ = writeln!(out, "{:indent$}{self:?}", "", indent = depth * 2);
+
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node) => {
+                    node.write_debug_tree(depth + 1, out)
+                }
+                SyntaxElement::Token(tok) => {
+                    let indent = (depth + 1) * 2;
+                    let _ = writeln!(out, "{:indent$}{tok:?}", "");
+                }
+            }
+        }
+    }
+}
+
+impl PartialEq for SyntaxNode {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0.green, &other.0.green)
+            && self.0.offset == other.0.offset
+    }
+}
+
+impl Eq for SyntaxNode {}
+
+impl core::fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let span = self.text_range();
+        write!(f, "{:?}@{}..{}", self.kind(), span.start.0, span.end.0)
+    }
+}
+
+impl core::fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}", self.0.green)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct SyntaxToken {
+    green: Arc<GreenToken>,
+    parent: SyntaxNode,
+    offset: usize,
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind()
+    }
+
+    pub fn text(&self) -> &str {
+        self.green.text()
+    }
+
+    pub fn text_range(&self) -> Span {
+        Span::new(self.offset, self.offset + self.green.text_len())
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+}
+
+impl core::fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let span = self.text_range();
+        let kind = match self.kind() {
+            SyntaxKind::Token(kind) => format!("{kind:?}"),
+            kind => format!("{kind:?}"),
+        };
+
+        write!(
+            f,
+            "{kind}@{}..{} {:?}",
+            span.start.0,
+            span.end.0,
+            self.text()
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            SyntaxElement::Node(node) => node.kind(),
+            SyntaxElement::Token(tok) => tok.kind(),
+        }
+    }
+
+    pub fn text_range(&self) -> Span {
+        match self {
+            SyntaxElement::Node(node) => node.text_range(),
+            SyntaxElement::Token(tok) => tok.text_range(),
+        }
+    }
+
+    pub fn into_node(self) -> Option<SyntaxNode> {
+        match self {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        }
+    }
+
+    pub fn into_token(self) -> Option<SyntaxToken> {
+        match self {
+            SyntaxElement::Node(_) => None,
+            SyntaxElement::Token(tok) => Some(tok),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::{lexer::kind::TokenKind, syntax::green::GreenBuilder};
+
+    /// Build the tree for `f(a)`.
+    fn call() -> SyntaxNode {
+        let mut builder = GreenBuilder::new();
+        builder.start_node(SyntaxKind::CallExpr);
+        builder.start_node(SyntaxKind::NameRef);
+        builder.token(TokenKind::Ident.into(), "f");
+        builder.finish_node();
+        builder.token(TokenKind::LPar.into(), "(");
+        builder.start_node(SyntaxKind::NameRef);
+        builder.token(TokenKind::Ident.into(), "a");
+        builder.finish_node();
+        builder.token(TokenKind::RPar.into(), ")");
+        builder.finish_node();
+
+        SyntaxNode::new_root(Arc::new(builder.finish()))
+    }
+
+    #[test]
+    fn test_ranges_and_parents() {
+        let root = call();
+        let args: Vec<_> = root.children().collect();
+
+        assert_eq!(args.len(), 2);
+        assert_eq!(args[1].text_range(), Span::new(2, 3));
+        assert_eq!(args[1].text(), "a");
+        assert_eq!(args[1].parent(), Some(root.clone()));
+        assert_eq!(args[1].ancestors().count(), 2);
+
+        let tokens: Vec<_> =
+            root.child_tokens().map(|tok| tok.kind()).collect();
+        assert_eq!(
+            tokens,
+            vec![TokenKind::LPar.into(), TokenKind::RPar.into()]
+        );
+    }
+
+    #[test]
+    fn test_descendants_in_order() {
+        let kinds: Vec<_> =
+            call().descendants().map(|node| node.kind()).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                SyntaxKind::CallExpr,
+                SyntaxKind::NameRef,
+                SyntaxKind::NameRef
+            ]
+        );
+    }
+
+    #[test]
+    fn test_covering_node() {
+        let root = call();
+
+        let node = root.covering_node(Span::new(2, 3));
+        assert_eq!(node.kind(), SyntaxKind::NameRef);
+        assert_eq!(node.text(), "a");
+        assert_eq!(root.covering_node(Span::new(1, 3)), root);
+    }
+
+    #[test]
+    fn test_debug_tree() {
+        assert_eq!(
+            call().debug_tree(),
+            "CallExpr@0..4\n  NameRef@0..1\n    Ident@0..1 \"f\"\n  \
+            LPar@1..2 \"(\"\n  NameRef@2..3\n    Ident@2..3 \"a\"\n  \
+            RPar@3..4 \")\"\n"
+        );
+    }
+}
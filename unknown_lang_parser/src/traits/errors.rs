@@ -0,0 +1,106 @@
+use alloc::{string::String, vec::Vec};
+
+use crate::generics::types::Type;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TraitError {
+    /// A bound or impl naming a trait which isn't declared
+    UnknownTrait(String),
+    /// An impl for a type which isn't declared, like `impl Show for Unknown`
+    UnknownType(String),
+    /// A trait given the wrong number of type arguments, like `Add<u8, u8>`
+    /// for `trait Add<Rhs>`
+    WrongArgCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    /// An impl which doesn't give every method of its trait that has no
+    /// default body
+    MissingMethods {
+        trait_name: String,
+        methods: Vec<String>,
+    },
+    /// A method in an impl which its trait doesn't have
+    NotInTrait { trait_name: String, method: String },
+    /// A method in an impl which takes a different number of parameters than
+    /// in its trait
+    ParamCount {
+        method: String,
+        expected: usize,
+        found: usize,
+    },
+    /// Two methods with the same name in one trait or impl
+    DuplicateMethod(String),
+    /// Two impls of one trait which could both be for the same type, like
+    /// `impl<T> Display for T` and `impl Display for u8`
+    Overlap { trait_name: String, ty: Type },
+    /// An impl of a trait from another module for a type which isn't
+    /// declared in this module either
+    Orphan { trait_name: String, ty: Type },
+}
+
+impl core::fmt::Display for TraitError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnknownTrait(name) => write!(f, "no trait named `{name}`"),
+            Self::UnknownType(name) => write!(f, "no type named `{name}`"),
+            Self::WrongArgCount {
+                name,
+                expected,
+                found,
+            } => {
+                let s = if *expected == 1 { "" } else { "s" };
+                write!(
+                    f,
+                    "`{name}` takes {expected} type argument{s}, but \
+                     {found} were given"
+                )
+            }
+            Self::MissingMethods {
+                trait_name,
+                methods,
+            } => {
+                write!(f, "missing methods of `{trait_name}`: ")?;
+                for (idx, method) in methods.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "`{method}`")?;
+                }
+                Ok(())
+            }
+            Self::NotInTrait { trait_name, method } => {
+                write!(f, "`{method}` isn't a method of `{trait_name}`")
+            }
+            Self::ParamCount {
+                method,
+                expected,
+                found,
+            } => {
+                let s = if *expected == 1 { "" } else { "s" };
+                write!(
+                    f,
+                    "`{method}` takes {expected} parameter{s} in its trait, \
+                     but {found} here"
+                )
+            }
+            Self::DuplicateMethod(name) => {
+                write!(f, "`{name}` is declared more than once")
+            }
+            Self::Overlap { trait_name, ty } => write!(
+                f,
+                "`{trait_name}` is already implemented for `{ty}` by another \
+                 impl"
+            ),
+            Self::Orphan { trait_name, ty } => write!(
+                f,
+                "`{trait_name}` is from another module, so it can only be \
+                 implemented here for a type declared here, not `{ty}`"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TraitError {}
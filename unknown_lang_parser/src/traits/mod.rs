@@ -0,0 +1,479 @@
+//! Checking that traits and their impls fit together.
+//!
+//! Every trait named in a bound or an impl has to be declared, and be given
+//! as many type arguments as it has type parameters. An impl has to give
+//! every method of its trait that has no default body, taking as many
+//! parameters as in the trait, and nothing else.
+//!
+//! Which impl a method call uses is decided by the type it is called on, so
+//! no two impls of a trait may be for types which could be the same. An impl
+//! of a trait from another module has to be for a type declared in this
+//! module, since the module of the trait could have an impl for it as well.
+//! The type of an impl has to be declared too, or be built in.
+//!
+//! Method calls aren't dispatched while the program runs. Instead,
+//! [`monomorphize`](crate::generics::monomorphize) decides which impl every
+//! method call uses, giving the instances of their methods that the
+//! [`eval`](crate::eval) backend calls.
+//!
+//! Traits from other modules can't be seen here, so only how they are
+//! implemented is checked, and not their methods.
+
+pub mod errors;
+
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+
+use crate::{
+    ast::{
+        path_to_string,
+        visit::{walk_trait_ref, walk_ty, Visitor},
+        Ident, Impl, ItemKind, Module, Trait, TraitRef, Ty, TyKind, TypeParam,
+    },
+    generics::types::{is_builtin, Type, Vars},
+    lexer::position::{Span, Spanned},
+};
+
+use self::errors::TraitError;
+
+/// Check every trait and impl in `module`.
+pub fn check(module: &Module) -> Vec<Spanned<TraitError>> {
+    let mut traits = BTreeMap::new();
+    let mut types = vec![];
+    for item in &module.items {
+        match &item.data {
+            ItemKind::Trait(decl) => {
+                traits.entry(decl.name.data.clone()).or_insert(decl);
+            }
+            ItemKind::Struct(decl) => types.push(decl.name.data.clone()),
+            ItemKind::Enum(decl) => types.push(decl.name.data.clone()),
+            _ => {}
+        }
+    }
+
+    let mut checker = Checker {
+        traits: &traits,
+        errors: vec![],
+    };
+    checker.visit_module(module);
+
+    let mut impls: Vec<(&Impl, Vec<String>)> = vec![];
+    for item in &module.items {
+        match &item.data {
+            ItemKind::Trait(decl) => checker.check_trait(decl),
+            ItemKind::Impl(imp) => {
+                let generics = names(&imp.generics);
+                // An impl which can't be here can't overlap with others
+                if checker.check_impl(imp, &generics, &types) {
+                    checker.check_overlap(imp, &generics, &impls);
+                    impls.push((imp, generics));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    checker.errors
+}
+
+fn names(generics: &[TypeParam]) -> Vec<String> {
+    generics
+        .iter()
+        .map(|param| param.data.name.data.clone())
+        .collect()
+}
+
+struct Checker<'a> {
+    /// The traits declared in the module, by name
+    traits: &'a BTreeMap<String, &'a Trait>,
+    errors: Vec<Spanned<TraitError>>,
+}
+
+impl Checker<'_> {
+    fn error(&mut self, err: TraitError, span: Span) {
+        self.errors.push(Spanned::new(err, span));
+    }
+
+    fn check_trait(&mut self, decl: &Trait) {
+        let names = decl.methods.iter().map(|method| &method.data.name);
+        self.check_duplicates(names);
+    }
+
+    /// Check that the methods of an impl are those of its trait, and that
+    /// the trait can be implemented for its type here, giving back whether
+    /// it can be.
+    fn check_impl(
+        &mut self,
+        imp: &Impl,
+        generics: &[String],
+        types: &[String],
+    ) -> bool {
+        let trait_ref = &imp.trait_ref;
+        let trait_name = path_to_string(&trait_ref.data.path.data);
+        self.check_duplicates(
+            imp.methods.iter().map(|method| &method.data.name),
+        );
+
+        let mut unknown = UnknownTypes {
+            generics,
+            types,
+            found: vec![],
+        };
+        unknown.visit_ty(&imp.self_ty);
+        // An impl for a type that isn't there can't be used or overlap
+        let known = unknown.found.is_empty();
+        for name in unknown.found {
+            self.error(TraitError::UnknownType(name.data), name.span);
+        }
+
+        let Some(decl) = self.traits.get(&trait_name).copied() else {
+            if trait_ref.data.path.data.len() > 1 {
+                let ty = Type::from_ty(&imp.self_ty, generics);
+                if known && !is_local(&ty, types) {
+                    let err = TraitError::Orphan { trait_name, ty };
+                    self.error(err, trait_ref.span.union(imp.self_ty.span));
+                    return false;
+                }
+            }
+            return known;
+        };
+
+        for method in &imp.methods {
+            let name = &method.data.name;
+            let found = decl
+                .methods
+                .iter()
+                .find(|other| other.data.name.data == name.data);
+
+            let Some(declared) = found else {
+                let err = TraitError::NotInTrait {
+                    trait_name: trait_name.clone(),
+                    method: name.data.clone(),
+                };
+                self.error(err, name.span);
+                continue;
+            };
+
+            let expected = declared.data.params.len();
+            let found = method.data.params.len();
+            if expected != found {
+                let err = TraitError::ParamCount {
+                    method: name.data.clone(),
+                    expected,
+                    found,
+                };
+                self.error(err, name.span);
+            }
+        }
+
+        let missing: Vec<_> = decl
+            .methods
+            .iter()
+            .filter(|method| method.data.default.is_none())
+            .map(|method| &method.data.name.data)
+            .filter(|name| {
+                !imp.methods
+                    .iter()
+                    .any(|method| method.data.name.data == **name)
+            })
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            let err = TraitError::MissingMethods {
+                trait_name,
+                methods: missing,
+            };
+            self.error(err, trait_ref.span);
+        }
+
+        known
+    }
+
+    /// Check that an impl isn't for a type which one of the `earlier` impls
+    /// of the same trait could be for too.
+    fn check_overlap(
+        &mut self,
+        imp: &Impl,
+        generics: &[String],
+        earlier: &[(&Impl, Vec<String>)],
+    ) {
+        let trait_name = path_to_string(&imp.trait_ref.data.path.data);
+
+        for (other, other_generics) in earlier {
+            let other_name = path_to_string(&other.trait_ref.data.path.data);
+            if other_name != trait_name {
+                continue;
+            }
+
+            // Both impls are given fresh type arguments, so that their type
+            // parameters don't get mixed up when they share a name
+            let mut vars = Vars::default();
+            let (ty, args) = instantiate(imp, generics, &mut vars);
+            let (other_ty, other_args) =
+                instantiate(other, other_generics, &mut vars);
+
+            if vars.unify(&ty, &other_ty) && vars.unify_all(&args, &other_args)
+            {
+                let err = TraitError::Overlap {
+                    trait_name,
+                    ty: vars.finish(&ty),
+                };
+                let span = imp.trait_ref.span.union(imp.self_ty.span);
+                self.error(err, span);
+                return;
+            }
+        }
+    }
+
+    /// Report every name which was already used by one of the names before
+    /// it.
+    fn check_duplicates<'a>(&mut self, names: impl Iterator<Item = &'a Ident>) {
+        let mut seen: Vec<&Ident> = vec![];
+
+        for name in names {
+            if seen.iter().any(|other| other.data == name.data) {
+                let err = TraitError::DuplicateMethod(name.data.clone());
+                self.error(err, name.span);
+            }
+            seen.push(name);
+        }
+    }
+}
+
+impl Visitor for Checker<'_> {
+    /// Check that a trait named without any `::` is declared in the module,
+    /// with the right number of type arguments. Paths into other modules
+    /// are checked while resolving them.
+    fn visit_trait_ref(&mut self, trait_ref: &TraitRef) {
+        let path = &trait_ref.data.path.data;
+        if let [name] = path.as_slice() {
+            match self.traits.get(&name.data) {
+                Some(decl)
+                    if decl.generics.len() != trait_ref.data.args.len() =>
+                {
+                    let err = TraitError::WrongArgCount {
+                        name: name.data.clone(),
+                        expected: decl.generics.len(),
+                        found: trait_ref.data.args.len(),
+                    };
+                    self.error(err, trait_ref.span);
+                }
+                Some(_) => {}
+                None => {
+                    let err = TraitError::UnknownTrait(name.data.clone());
+                    self.error(err, trait_ref.span);
+                }
+            }
+        }
+
+        walk_trait_ref(self, trait_ref);
+    }
+}
+
+/// Finds the names of types which are neither declared in the module, built
+/// in, nor type parameters. Paths into other modules are checked while
+/// resolving them.
+struct UnknownTypes<'a> {
+    generics: &'a [String],
+    types: &'a [String],
+    found: Vec<Ident>,
+}
+
+impl Visitor for UnknownTypes<'_> {
+    fn visit_ty(&mut self, ty: &Ty) {
+        let name = match &ty.data {
+            TyKind::Named(name) => Some(name),
+            TyKind::Generic { name, .. } => Some(&name.data),
+            _ => None,
+        };
+        let unknown = name.filter(|name| {
+            !is_builtin(name)
+                && !self.generics.contains(name)
+                && !self.types.contains(name)
+        });
+        if let Some(name) = unknown {
+            self.found.push(Spanned::new(name.clone(), ty.span));
+        }
+
+        walk_ty(self, ty);
+    }
+}
+
+/// Lower the type and trait arguments of an impl, giving its type
+/// parameters fresh variables.
+fn instantiate(
+    imp: &Impl,
+    generics: &[String],
+    vars: &mut Vars,
+) -> (Type, Vec<Type>) {
+    let fresh: Vec<_> = generics.iter().map(|_| vars.fresh()).collect();
+    let lower = |ty| Type::from_ty(ty, generics).subst(generics, &fresh);
+
+    let args = imp.trait_ref.data.args.iter().map(lower).collect();
+    (lower(&imp.self_ty), args)
+}
+
+/// Checks if a type is declared in this module, as one of `types`. A
+/// reference to it counts too.
+fn is_local(ty: &Type, types: &[String]) -> bool {
+    match ty {
+        Type::Named { name, .. } => types.contains(name),
+        Type::Ref { inner, .. } => is_local(inner, types),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::parser::parse;
+
+    /// Check `input`, failing if it didn't parse.
+    fn messages(input: &str) -> Vec<String> {
+        let (module, errors) = parse(input);
+        assert_eq!(errors, vec![], "{input}");
+
+        check(&module)
+            .iter()
+            .map(|err| err.data.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_impls_fit_their_traits() {
+        let errors = messages(concat!(
+            "trait Display {\n",
+            "    func show(self) : &str\n",
+            "    func size(self) : usize\n",
+            "    func print(self) := {}\n",
+            "}\n",
+            "struct Point { x: i32 }\n",
+            "impl Display for Point {\n",
+            "    func show(self, extra: u8) : &str = { \"\" }\n",
+            "    func draw(self) := {}\n",
+            "}\n",
+            "impl Display for u8 {\n",
+            "    func show(self) : &str = { \"\" }\n",
+            "    func size(self) : usize = { 1 }\n",
+            "    func size(self) : usize = { 1 }\n",
+            "}",
+        ));
+
+        assert_eq!(
+            errors,
+            vec![
+                "`show` takes 1 parameter in its trait, but 2 here",
+                "`draw` isn't a method of `Display`",
+                "missing methods of `Display`: `size`",
+                "`size` is declared more than once",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_traits_and_arg_counts() {
+        let input = concat!(
+            "trait Add<Rhs> { func add(self, other: Rhs) : Self }\n",
+            "impl Add for u8 { func add(self, other: u8) : u8 = { 0 } }\n",
+            "func show<T: Display + Add<T, T>>(x: T) := {}\n",
+            "trait Twice { func a(self); func a(self) }",
+        );
+        let (module, _) = parse(input);
+        let errors = check(&module);
+
+        assert_eq!(
+            errors
+                .iter()
+                .map(|err| err.data.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "`Add` takes 1 type argument, but 0 were given",
+                "no trait named `Display`",
+                "`Add` takes 1 type argument, but 2 were given",
+                "`a` is declared more than once",
+            ]
+        );
+        let display = input.find("Display").unwrap();
+        assert_eq!(errors[1].span, Span::new(display, display + 7));
+    }
+
+    #[test]
+    fn test_overlapping_impls() {
+        let errors = messages(concat!(
+            "trait Show {}\n",
+            "trait Add<Rhs> {}\n",
+            "struct Pair<A, B> { a: A, b: B }\n",
+            "impl<T> Show for Pair<T, u8> {}\n",
+            "impl<T> Show for Pair<char, T> {}\n",
+            "impl Show for Pair<u8, char> {}\n",
+            "impl Add<u8> for u8 {}\n",
+            "impl Add<char> for u8 {}\n",
+            "impl<T> Add<T> for T {}",
+        ));
+
+        assert_eq!(
+            errors,
+            vec![
+                "`Show` is already implemented for `Pair<char, u8>` by \
+                 another impl",
+                "`Add` is already implemented for `u8` by another impl",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_impls_for_unknown_types() {
+        let input = concat!(
+            "trait Show {}\n",
+            "struct Pair<T> { a: T }\n",
+            "impl Show for Unknown {}\n",
+            "impl Show for Unknown {}\n",
+            "impl Show for Pair<Missing> {}\n",
+            "impl Show for (bool, &str) {}\n",
+            "impl<T> Show for Pair<T> {}",
+        );
+        let (module, _) = parse(input);
+        let errors = check(&module);
+
+        assert_eq!(
+            errors
+                .iter()
+                .map(|err| err.data.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "no type named `Unknown`",
+                "no type named `Unknown`",
+                "no type named `Missing`",
+            ]
+        );
+        let unknown = input.find("Unknown").unwrap();
+        assert_eq!(errors[0].span, Span::new(unknown, unknown + 7));
+    }
+
+    #[test]
+    fn test_orphan_impls() {
+        let errors = messages(concat!(
+            "import shapes\n",
+            "struct Point { x: i32 }\n",
+            "impl shapes::Draw for Point {}\n",
+            "impl shapes::Draw for &Point {}\n",
+            "impl shapes::Draw for u8 {}\n",
+            "impl<T> shapes::Draw for T {}\n",
+            "impl shapes::Draw for shapes::Circle {}",
+        ));
+
+        assert_eq!(
+            errors,
+            vec![
+                "`shapes::Draw` is from another module, so it can only be \
+                 implemented here for a type declared here, not `u8`",
+                "`shapes::Draw` is from another module, so it can only be \
+                 implemented here for a type declared here, not `T`",
+                "`shapes::Draw` is from another module, so it can only be \
+                 implemented here for a type declared here, not \
+                 `shapes::Circle`",
+            ]
+        );
+    }
+}
@@ -0,0 +1,30 @@
+//! Helpers shared between the integration tests and the benches, of this
+//! crate and of the CLI.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Every example file, including the modules in the directories of examples
+/// which are split across files.
+pub fn example_files() -> Vec<PathBuf> {
+    files_in(Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../examples"
+    )))
+}
+
+fn files_in(dir: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        match path.is_dir() {
+            true => files.extend(files_in(&path)),
+            false => files.push(path),
+        }
+    }
+
+    files
+}
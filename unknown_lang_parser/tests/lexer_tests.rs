@@ -1,11 +1,17 @@
-use unknown_lang_parser::lexer::{state::Lexer, tokens::Token};
+mod common;
+
+use std::fs;
+
+use unknown_lang_parser::lexer::{
+    render::unparse, state::Lexer, tokens::Token,
+};
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
     // Helper function to get all the tokens at once within the Lexer
-    fn get_toks<'a>(lexer: &mut Lexer) -> Vec<Token> {
+    fn get_toks(lexer: &mut Lexer) -> Vec<Token> {
         let mut toks: Vec<Token> = vec![];
 
         while let Ok(tok) = lexer.lex_next() {
@@ -72,4 +78,134 @@ mod tests {
 
         assert_eq!(toks, expected);
     }
+
+    #[test]
+    fn test_optional_semicolons() {
+        let input = "let res : i32 = addOne ** add (2, 3)\nlet b := 1";
+        let mut lexer = Lexer::new(input);
+
+        let toks = get_toks(&mut lexer);
+        let expected = vec![
+            Token::Ident("let".to_string()),
+            Token::Ident("res".to_string()),
+            Token::Colon,
+            Token::Ident("i32".to_string()),
+            Token::Eq,
+            Token::Ident("addOne".to_string()),
+            Token::StarStar,
+            Token::Ident("add".to_string()),
+            Token::LPar,
+            Token::Number(10, "2".to_string()),
+            Token::Comma,
+            Token::Number(10, "3".to_string()),
+            Token::RPar,
+            Token::VirtualSemi,
+            Token::Ident("let".to_string()),
+            Token::Ident("b".to_string()),
+            Token::Colon,
+            Token::Eq,
+            Token::Number(10, "1".to_string()),
+        ];
+
+        assert_eq!(toks, expected);
+    }
+
+    #[test]
+    fn test_no_semicolons_within_parens() {
+        let input = "add(\n    1,\n    2\n)\n";
+        let mut lexer = Lexer::new(input);
+
+        let toks = get_toks(&mut lexer);
+        let expected = vec![
+            Token::Ident("add".to_string()),
+            Token::LPar,
+            Token::Number(10, "1".to_string()),
+            Token::Comma,
+            Token::Number(10, "2".to_string()),
+            Token::RPar,
+        ];
+
+        assert_eq!(toks, expected);
+    }
+
+    #[test]
+    fn test_block_tail_keeps_no_semicolon() {
+        let input = "{\n    a + b\n}\nfunc";
+        let mut lexer = Lexer::new(input);
+
+        let toks = get_toks(&mut lexer);
+        let expected = vec![
+            Token::LBrace,
+            Token::Ident("a".to_string()),
+            Token::Plus,
+            Token::Ident("b".to_string()),
+            Token::RBrace,
+            Token::VirtualSemi,
+            Token::Ident("func".to_string()),
+        ];
+
+        assert_eq!(toks, expected);
+    }
+
+    #[test]
+    fn test_unparse_examples_roundtrip() {
+        for path in common::example_files() {
+            let input = fs::read_to_string(&path).unwrap();
+
+            let toks = get_toks(&mut Lexer::new(&input));
+            let output = unparse(&toks);
+
+            assert_eq!(
+                get_toks(&mut Lexer::new(&output)),
+                toks,
+                "{} did not roundtrip through:\n{output}",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_parallel_examples_same_as_lexer() {
+        use unknown_lang_parser::lexer::{
+            lex_all, parallel::lex_parallel_with,
+        };
+
+        for path in common::example_files() {
+            let input = fs::read_to_string(&path).unwrap();
+            let expected = lex_all(&input);
+
+            for chunks in 1..=16 {
+                assert_eq!(
+                    lex_parallel_with(&input, chunks),
+                    expected,
+                    "{chunks} chunks for {}",
+                    path.display()
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_stream_examples_same_as_lexer() {
+        use unknown_lang_parser::lexer::{lex_all, stream::StreamLexer};
+
+        for path in common::example_files() {
+            let input = fs::read_to_string(&path).unwrap();
+            let expected = lex_all(&input).unwrap();
+
+            for size in 1..=8 {
+                let lexer = StreamLexer::with_read_size(input.as_bytes(), size);
+                let toks: Result<Vec<_>, _> = lexer.collect();
+
+                assert_eq!(
+                    toks.unwrap(),
+                    expected,
+                    "read size {size} for {}",
+                    path.display()
+                );
+            }
+        }
+    }
 }
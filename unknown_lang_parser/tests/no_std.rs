@@ -0,0 +1,57 @@
+//! Checks that the lexer works with only `core` and `alloc`. Run this with
+//! `cargo test -p unknown_lang_parser --no-default-features --test no_std`
+//! to build the library without its `std` feature.
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::ToString, vec, vec::Vec};
+
+use unknown_lang_parser::lexer::{
+    errors::LexingError, render::unparse, state::Lexer, tokens::Token,
+};
+
+fn get_toks(input: &str) -> Result<Vec<Token>, LexingError> {
+    let mut lexer = Lexer::new(input);
+    let mut toks = vec![];
+
+    loop {
+        match lexer.lex_next()? {
+            Token::Eof => return Ok(toks),
+            tok => toks.push(tok),
+        }
+    }
+}
+
+#[test]
+fn lexes_without_std() {
+    let toks = get_toks("let a := \"str\"\nlet b := b\"bytes\";").unwrap();
+    let expected = vec![
+        Token::Ident("let".to_string()),
+        Token::Ident("a".to_string()),
+        Token::Colon,
+        Token::Eq,
+        Token::Str("str".to_string()),
+        Token::VirtualSemi,
+        Token::Ident("let".to_string()),
+        Token::Ident("b".to_string()),
+        Token::Colon,
+        Token::Eq,
+        Token::ByteStr(b"bytes".to_vec()),
+        Token::Semi,
+    ];
+
+    assert_eq!(toks, expected);
+}
+
+#[test]
+fn errors_display_without_std() {
+    let err = get_toks("\"unclosed").unwrap_err();
+    assert_eq!(err.to_string(), "unclosed string");
+}
+
+#[test]
+fn unparses_without_std() {
+    let toks = get_toks("add( 1 , 2 )").unwrap();
+    assert_eq!(unparse(&toks), "add(1,2)");
+}
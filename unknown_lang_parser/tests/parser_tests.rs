@@ -0,0 +1,148 @@
+use unknown_lang_parser::{
+    ast::{BinOp, ExprKind, ItemKind, StmtKind, TyKind},
+    lexer::{kind::TokenKind, position::Span},
+    parser::{errors::ParseError, parse},
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_example(name: &str) -> String {
+        let path = format!("{}/../examples/{name}", env!("CARGO_MANIFEST_DIR"));
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_parse_examples() {
+        for name in [
+            "attributes.ukl",
+            "bases.ukl",
+            "comments.ukl",
+            "control-flow.ukl",
+            "enums.ukl",
+            "generics.ukl",
+            "lambdas.ukl",
+            "macros.ukl",
+            "match.ukl",
+            "mut.ukl",
+            "playground.ukl",
+            "structs.ukl",
+            "traits.ukl",
+        ] {
+            let input = read_example(name);
+
+            if let Some(err) = parse(&input).1.first() {
+                panic!(
+                    "failed to parse {name}: {} at {:?}",
+                    err.data, err.span
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_bases_example() {
+        let (module, errors) = parse(&read_example("bases.ukl"));
+        assert!(errors.is_empty());
+        assert_eq!(module.items.len(), 2);
+
+        let ItemKind::Func(main) = &module.items[1].data else {
+            panic!("expected a function");
+        };
+        assert_eq!(main.name.data, "main");
+        assert_eq!(main.ret, None);
+        assert!(matches!(main.body.data.stmts[0].data, StmtKind::Let(_)));
+    }
+
+    #[test]
+    fn test_parse_function_composition_example() {
+        let input = read_example("function-composition.ukl");
+        let (module, errors) = parse(&input);
+        assert_eq!(errors.len(), 2);
+
+        assert_eq!(
+            errors[0].data,
+            ParseError::Expected {
+                expected: vec![TokenKind::RPar, TokenKind::Comma],
+                found: TokenKind::Eq,
+            }
+        );
+        let start = input.find("=)").unwrap();
+        assert_eq!(errors[0].span, Span::new(start, start + 1));
+
+        assert_eq!(errors[1].data, ParseError::ExpectedType(TokenKind::Eq));
+        let start = input.find("main() : =").unwrap() + 9;
+        assert_eq!(errors[1].span, Span::new(start, start + 1));
+
+        // Both functions are still there, with an error node in place of the
+        // missing return type
+        let funcs: Vec<_> = module
+            .items
+            .iter()
+            .map(|item| match &item.data {
+                ItemKind::Func(func) => func,
+                _ => panic!("expected a function"),
+            })
+            .collect();
+        assert_eq!(funcs.len(), 3);
+        assert_eq!(funcs[0].params.len(), 1);
+        assert_eq!(
+            funcs[2].ret.as_ref().map(|ty| &ty.data),
+            Some(&TyKind::Error)
+        );
+        assert_eq!(funcs[2].body.data.stmts.len(), 4);
+    }
+
+    #[test]
+    fn test_parse_fixed_function_composition_example() {
+        let input = read_example("function-composition.ukl")
+            .replace("addOne(a : usize =)", "addOne(a : usize)")
+            .replace("main() : =", "main() :=");
+        let (module, errors) = parse(&input);
+        assert!(errors.is_empty());
+
+        let ItemKind::Func(main) = &module.items[2].data else {
+            panic!("expected a function");
+        };
+        let stmts = &main.body.data.stmts;
+        assert_eq!(stmts.len(), 4);
+
+        // `addOne ** add (2, 3)` calls the composed function
+        let StmtKind::Let(res) = &stmts[0].data else {
+            panic!("expected a let statement");
+        };
+        let ExprKind::Call { callee, args } = &res.value.data else {
+            panic!("expected a call");
+        };
+        assert_eq!(args.len(), 2);
+        assert!(matches!(
+            callee.data,
+            ExprKind::Binary {
+                op: BinOp::Compose,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_lambdas_example() {
+        let (module, errors) = parse(&read_example("lambdas.ukl"));
+        assert!(errors.is_empty());
+
+        let ItemKind::Func(main) = &module.items[2].data else {
+            panic!("expected a function");
+        };
+        let stmts = &main.body.data.stmts;
+        assert_eq!(stmts.len(), 4);
+
+        // `addOne ** |x| x * 2` composes with a lambda
+        let StmtKind::Let(res) = &stmts[0].data else {
+            panic!("expected a let statement");
+        };
+        let ExprKind::Binary { rhs, .. } = &res.value.data else {
+            panic!("expected a composition");
+        };
+        assert!(matches!(rhs.data, ExprKind::Lambda { .. }));
+    }
+}
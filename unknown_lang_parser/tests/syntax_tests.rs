@@ -0,0 +1,67 @@
+mod common;
+
+use std::fs;
+
+use unknown_lang_parser::{
+    lexer::kind::TokenKind,
+    syntax::{kind::SyntaxKind, parse},
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_examples_round_trip() {
+        for path in common::example_files() {
+            let input = fs::read_to_string(&path).unwrap();
+            let parse = parse(&input);
+
+            assert_eq!(
+                parse.syntax().to_string(),
+                input,
+                "{} didn't round trip",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn test_function_composition_params() {
+        let input = std::fs::read_to_string(format!(
+            "{}/../examples/function-composition.ukl",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .unwrap();
+        let parse = parse(&input);
+
+        let names: Vec<_> = parse
+            .tree()
+            .funcs()
+            .map(|func| {
+                let params: Vec<_> = func
+                    .params()
+                    .map(|param| param.name().unwrap().text())
+                    .collect();
+                (func.name().unwrap().text(), params)
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("addOne".to_string(), vec!["a".to_string()]),
+                ("add".to_string(), vec!["a".to_string(), "b".to_string()]),
+                ("main".to_string(), vec![]),
+            ]
+        );
+
+        // Every comment is still in the tree, with its parent
+        let comments = parse
+            .syntax()
+            .descendants()
+            .flat_map(|node| node.child_tokens())
+            .filter(|tok| tok.kind() == SyntaxKind::Token(TokenKind::Comment))
+            .count();
+        assert_eq!(comments, 11);
+    }
+}
@@ -0,0 +1,63 @@
+use unknown_lang_parser::{
+    ast::{
+        visit::{walk_expr, Visitor},
+        Expr, ExprKind,
+    },
+    parser::parse,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts every function call, including calls to composed functions.
+    #[derive(Default)]
+    struct CallCounter {
+        calls: usize,
+    }
+
+    impl Visitor for CallCounter {
+        fn visit_expr(&mut self, expr: &Expr) {
+            if let ExprKind::Call { .. } = expr.data {
+                self.calls += 1;
+            }
+
+            walk_expr(self, expr);
+        }
+    }
+
+    fn count_calls(name: &str) -> usize {
+        let path = format!("{}/../examples/{name}", env!("CARGO_MANIFEST_DIR"));
+        let (module, _) = parse(&std::fs::read_to_string(path).unwrap());
+
+        let mut counter = CallCounter::default();
+        counter.visit_module(&module);
+        counter.calls
+    }
+
+    #[test]
+    fn test_count_calls_in_examples() {
+        let examples = [
+            ("attributes.ukl", 2),
+            ("bases.ukl", 1),
+            ("comments.ukl", 0),
+            ("control-flow.ukl", 1),
+            ("enums.ukl", 2),
+            ("generics.ukl", 3),
+            ("lambdas.ukl", 1),
+            // Only the calls that the macros expand into
+            ("macros.ukl", 2),
+            ("match.ukl", 1),
+            // This still has errors in it, but the calls are all recovered
+            ("function-composition.ukl", 3),
+            ("mut.ukl", 0),
+            ("playground.ukl", 0),
+            ("structs.ukl", 1),
+            ("traits.ukl", 5),
+        ];
+
+        for (name, calls) in examples {
+            assert_eq!(count_calls(name), calls, "calls in {name}");
+        }
+    }
+}